@@ -3,11 +3,14 @@
 #extension GL_ARB_separate_shader_objects : enable
 
 layout (binding = 0) uniform UniformBufferObject {
-    mat4 model;
     mat4 view;
     mat4 proj;
 } ubo;
 
+layout (push_constant) uniform PushConstants {
+    mat4 model;
+} object;
+
 layout (location = 0) in vec3 inPosition;
 layout (location = 1) in vec3 inColor;
 layout (location = 2) in vec2 inTexCoord;
@@ -21,7 +24,7 @@ out gl_PerVertex {
 
 void main() {
 
-    gl_Position = ubo.proj * ubo.view * ubo.model * vec4(inPosition, 1.0);
+    gl_Position = ubo.proj * ubo.view * object.model * vec4(inPosition, 1.0);
     fragColor = inColor;
     fragTexCoord = inTexCoord;
 }
\ No newline at end of file
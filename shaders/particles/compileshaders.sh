@@ -0,0 +1,5 @@
+#!/bin/sh
+
+glslangValidator -V particles.comp
+glslangValidator -V particles.frag
+glslangValidator -V particles.vert
\ No newline at end of file
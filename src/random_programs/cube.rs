@@ -1,29 +1,46 @@
 use vk_playground::{
-    utility,
-    utility::constants::*,
-    utility::debug::*,
+    utility::allocator::Allocation,
+    utility::args,
+    utility::assets::{AssetLoader, LoadProgress, TextureHandle},
+    utility::compute::Access,
+    utility::constants::MAX_FRAMES_IN_FLIGHT,
+    utility::error::{self, VkPlaygroundError},
+    utility::gpu::{self, GpuOptions},
+    utility::handles::Owned,
+    utility::headless::{self, HeadlessOptions},
+    utility::hot_reload::{HotReloadOptions, ShaderWatcher},
+    utility::pipeline::{self, PipelineBuilder},
+    utility::renderer::{Frame, RenderContext, RenderScene, Renderer},
+    utility::reflection::{self, ShaderReflection},
+    utility::shader_compiler,
     utility::share,
     utility::vkstuff,
     utility::structures::*,
-    utility::window::{ProgramProc, VulkanApp},
+    utility::uniform_ring::UniformRing,
+    utility::upload::Uploader,
+    utility::window::ProgramProc,
 };
 
 use ash::version::DeviceV1_0;
-use ash::version::InstanceV1_0;
 use ash::vk;
 use cgmath::{Deg, Matrix4, Point3, Vector3};
 use memoffset::offset_of;
 
 
-use std::ffi::CString;
-use std::path::Path;
-use std::ptr;
+use std::path::{Path, PathBuf};
 
 // constants
 const WINDOW_TITLE: &'static str = "Cube";
 const WINDOW_WIDTH: u32 = 800;
 const WINDOW_HEIGHT: u32 = 600;
 
+const SHADER_DIRECTORY: &str = "shaders/cube";
+// the GLSL sources are only needed with --hot-reload, so editing them is
+// picked up without running compileshaders.sh
+const SPIRV_SHADERS: [&str; 2] = ["shaders/cube/vert.spv", "shaders/cube/frag.spv"];
+const GLSL_SHADERS: [&str; 2] = ["shaders/cube/cube.vert", "shaders/cube/cube.frag"];
+const TEXTURE_PATH: &str = "resources/textures/container.jpg";
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct VertexV3 {
@@ -212,250 +229,133 @@ pub const RECT_TEX_COORD_INDICES_DATA: [u32; 36] = [
     20, 21, 22, 22, 23, 20,
     ];
 
-struct VulkanAppCube {
-    window: winit::window::Window,
-
-    _entry: ash::Entry,
-    instance: ash::Instance,
-    surface_loader: ash::extensions::khr::Surface,
-    surface: vk::SurfaceKHR,
-    debug_utils_loader: ash::extensions::ext::DebugUtils,
-    debug_messenger: vk::DebugUtilsMessengerEXT,
-
-    physical_device: vk::PhysicalDevice,
-    memory_properties: vk::PhysicalDeviceMemoryProperties,
+struct CubeScene {
+    // room for two sets, the one with the placeholder texture is left alone
+    // once the streamed one replaces it, as frames in flight may still use it
+    descriptor_pool: Owned<vk::DescriptorPool>,
+    descriptor_set: vk::DescriptorSet,
+    is_texture_bound: bool,
+
+    graphics_pipeline: Owned<vk::Pipeline>,
+    pipeline_layout: Owned<vk::PipelineLayout>,
+    ubo_layout: Owned<vk::DescriptorSetLayout>,
+
+    texture_sampler: Owned<vk::Sampler>,
+    texture: TextureHandle,
+    assets: AssetLoader,
+    load_progress: LoadProgress,
+
+    vertex_buffer: Owned<vk::Buffer>,
+    _vertex_buffer_memory: Allocation,
+    index_buffer: Owned<vk::Buffer>,
+    _index_buffer_memory: Allocation,
+
+    // pushed with the draw rather than kept in the uniform buffer
+    model: Matrix4<f32>,
+    uniform_transform: CameraUniformBufferObject,
+    uniform_ring: UniformRing,
+    // where update put this frame's transform
+    uniform_offset: u32,
+
+    vert_shader: PathBuf,
+    frag_shader: PathBuf,
+    // update has no context to make descriptor sets with
     device: ash::Device,
-
-    queue_family: QueueFamilyIndices,
-    graphics_queue: vk::Queue,
-    present_queue: vk::Queue,
-
-    swapchain_loader: ash::extensions::khr::Swapchain,
-    swapchain: vk::SwapchainKHR,
-    swapchain_images: Vec<vk::Image>,
-    swapchain_format: vk::Format,
-    swapchain_extent: vk::Extent2D,
-    swapchain_imageviews: Vec<vk::ImageView>,
-    swapchain_framebuffers: Vec<vk::Framebuffer>,
-
-    render_pass: vk::RenderPass,
-    ubo_layout: vk::DescriptorSetLayout,
-    pipeline_layout: vk::PipelineLayout,
-    graphics_pipeline: vk::Pipeline,
-
-    depth_image: vk::Image,
-    depth_image_view: vk::ImageView,
-    depth_image_memory: vk::DeviceMemory,
-
-    texture_image: vk::Image,
-    texture_image_view: vk::ImageView,
-    texture_sampler: vk::Sampler,
-    texture_image_memory: vk::DeviceMemory,
-
-    vertex_buffer: vk::Buffer,
-    vertex_buffer_memory: vk::DeviceMemory,
-    index_buffer: vk::Buffer,
-    index_buffer_memory: vk::DeviceMemory,
-
-    uniform_transform: UniformBufferObject,
-    uniform_buffers: Vec<vk::Buffer>,
-    uniform_buffers_memory: Vec<vk::DeviceMemory>,
-
-    descriptor_pool: vk::DescriptorPool,
-    descriptor_sets: Vec<vk::DescriptorSet>,
-
-    command_pool: vk::CommandPool,
-    command_buffers: Vec<vk::CommandBuffer>,
-
-    image_available_semaphores: Vec<vk::Semaphore>,
-    render_finished_semaphores: Vec<vk::Semaphore>,
-    in_flight_fences: Vec<vk::Fence>,
-    current_frame: usize,
-
-    is_framebuffer_resized: bool,
 }
 
-// ------------------------------------------
-// depth buffer functions, render passes and
-// graphics pipelines, oh my!
-//-------------------------------------------
-impl VulkanAppCube {
-    pub fn new(event_loop: &winit::event_loop::EventLoop<()>) -> VulkanAppCube {
-        let window =
-            utility::window::init_window(&event_loop, WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT);
-        
-        let entry = ash::Entry::new().unwrap();
-        let instance = vkstuff::vkinstance::create_instance(
-            &entry,
-            WINDOW_TITLE,
-            VALIDATION.is_enable,
-            &VALIDATION.required_validation_layers.to_vec(),
-        );
-        let surface_stuff =
-        vkstuff::vksurface::create_surface(&entry, &instance, &window, WINDOW_WIDTH, WINDOW_HEIGHT);
-        let (debug_utils_loader, debug_messenger) =
-            setup_debug_utils(VALIDATION.is_enable, &entry, &instance);
-        let physical_device =
-            vkstuff::vkdevice::pick_physical_device(&instance, &surface_stuff, &DEVICE_EXTENSIONS);
-        let physical_device_memory_properties =
-            unsafe { instance.get_physical_device_memory_properties(physical_device) };
-        let (device, queue_family) = vkstuff::vkdevice::create_logical_device(
-            &instance,
-            physical_device,
-            &VALIDATION,
-            &DEVICE_EXTENSIONS,
-            &surface_stuff,
-        );
-        let graphics_queue =
-            unsafe { device.get_device_queue(queue_family.graphics_family.unwrap(), 0) };
-        let present_queue =
-            unsafe { device.get_device_queue(queue_family.present_family.unwrap(), 0) };
-        let swapchain_stuff = vkstuff::vkswapchain::create_swapchain(
-            &instance,
-            &device,
-            physical_device,
-            &window,
-            &surface_stuff,
-            &queue_family,
-        );
-        let swapchain_imageviews = vkstuff::vkimage::create_image_views(
-            &device,
-            swapchain_stuff.swapchain_format,
-            &swapchain_stuff.swapchain_images,
-        );
-        let render_pass = VulkanAppCube::create_render_pass(
-            &instance,
-            &device, 
-            physical_device,
-            swapchain_stuff.swapchain_format);
-        let ubo_layout = vkstuff::vkdescriptor::create_descriptor_set_layout(&device);
-        let (graphics_pipeline, pipeline_layout) = VulkanAppCube::create_graphics_pipeline(
-            &device,
-            render_pass,
-            swapchain_stuff.swapchain_extent,
-            ubo_layout,
-        );
-        
-        let command_pool = vkstuff::vkcommand::create_command_pool(&device, &queue_family);
-        let (depth_image, depth_image_view, depth_image_memory) = 
-            VulkanAppCube::create_depth_resources(
-                &instance,
-                &device,
-                physical_device,
-                command_pool,
-                graphics_queue,
-                swapchain_stuff.swapchain_extent,
-                &physical_device_memory_properties,
-            );
-        let swapchain_framebuffers = VulkanAppCube::create_framebuffers(
-            &device,
-            render_pass,
-            &swapchain_imageviews,
-            depth_image_view,
-            swapchain_stuff.swapchain_extent
-        );
-        let (texture_image, texture_image_memory) = vkstuff::vkimage::create_texture_image(
-            &device,
-            command_pool,
-            graphics_queue,
-            &physical_device_memory_properties,
-            &Path::new("resources/textures/container.jpg")
-        );
-        let texture_image_view = vkstuff::vkimage::create_texture_image_view(&device, texture_image, 1);
-        let texture_sampler = vkstuff::vkimage::create_texture_sampler(&device);
-        let (vertex_buffer, vertex_buffer_memory) = vkstuff::vkbuffer::create_vertex_buffer(
-            &device,
-            &physical_device_memory_properties,
-            command_pool,
-            graphics_queue,
+impl CubeScene {
+    /// Streams the texture in unless `wait_for_assets`, for headless runs
+    /// whose first frame has to show it.
+    fn new(
+        context: &RenderContext,
+        shaders: [&str; 2],
+        wait_for_assets: bool,
+    ) -> Result<CubeScene, VkPlaygroundError> {
+        let device = context.device();
+        let swapchain_extent = context.swapchain_extent();
+        let [vert_shader, frag_shader] = shaders;
+
+        // the descriptor set layout comes from the shaders themselves, with
+        // the camera's uniform buffer fed from a ring
+        let vert_reflection =
+            ShaderReflection::from_spirv(&shader_compiler::load_spirv(Path::new(vert_shader))?)?
+                .with_dynamic_uniform_buffers();
+        let frag_reflection =
+            ShaderReflection::from_spirv(&shader_compiler::load_spirv(Path::new(frag_shader))?)?;
+        vert_reflection.check_vertex_input(&VertexV3::get_attribute_descriptions())?;
+        let ubo_layout =
+            reflection::create_descriptor_set_layouts(device, &[vert_reflection, frag_reflection])?
+                .remove(0);
+        // the texture is decoded off the main thread and drawn as a
+        // placeholder until it's uploaded, see update
+        let mut assets = AssetLoader::new(device, context.allocator(), context.upload_queues())?;
+        let texture = assets.load_texture(TEXTURE_PATH);
+        if wait_for_assets {
+            assets.finish()?;
+        }
+        let texture_sampler = vkstuff::vkimage::create_texture_sampler(device)?;
+        // the geometry goes up in one batch on the transfer queue, instead
+        // of a submit and a wait for each buffer
+        let mut uploader = Uploader::new(device, context.upload_queues())?;
+        let (vertex_buffer, vertex_buffer_memory) = uploader.upload_buffer(
+            context.allocator(),
+            vk::BufferUsageFlags::VERTEX_BUFFER,
             &RECT_TEX_COORD_VERTICES_DATA,
-        );
-        let (index_buffer, index_buffer_memory) = vkstuff::vkbuffer::create_index_buffer(
-            &device,
-            &physical_device_memory_properties,
-            command_pool,
-            graphics_queue,
+            Access::VertexInput,
+        )?;
+        let (index_buffer, index_buffer_memory) = uploader.upload_buffer(
+            context.allocator(),
+            vk::BufferUsageFlags::INDEX_BUFFER,
             &RECT_TEX_COORD_INDICES_DATA,
-        );
-        let (uniform_buffers, uniform_buffers_memory) = vkstuff::vkbuffer::create_uniform_buffers(
-            &device,
-            &physical_device_memory_properties,
-            swapchain_stuff.swapchain_images.len()
-        );
-        let descriptor_pool =
-            vkstuff::vkdescriptor::create_descriptor_pool(&device, swapchain_stuff.swapchain_images.len());
-        let descriptor_sets = vkstuff::vkdescriptor::create_descriptor_sets(
-            &device,
-            descriptor_pool,
-            ubo_layout,
-            &uniform_buffers,
-            texture_image_view,
-            texture_sampler,
-            swapchain_stuff.swapchain_images.len(),
-        );
-        let command_buffers = VulkanAppCube::create_command_buffers(
-            &device,
-            command_pool,
-            graphics_pipeline,
-            &swapchain_framebuffers,
-            render_pass,
-            swapchain_stuff.swapchain_extent,
-            vertex_buffer,
-            index_buffer,
-            pipeline_layout,
-            &descriptor_sets
-        );
-        let sync_objects = vkstuff::vksemaphore::create_sync_objects(&device, MAX_FRAMES_IN_FLIGHT);
-        
-
-        VulkanAppCube {
-            window,
-
-            // vulkan stuff
-            _entry: entry,
-            instance,
-            surface: surface_stuff.surface,
-            surface_loader: surface_stuff.surface_loader,
-            debug_utils_loader,
-            debug_messenger,
-
-            physical_device,
-            memory_properties: physical_device_memory_properties,
+            Access::IndexInput,
+        )?;
+        let upload = uploader.submit()?;
+        uploader.wait(upload)?;
+        let uniform_ring = UniformRing::new(
+            context.instance(),
+            context.physical_device(),
             device,
-
-            queue_family,
-            graphics_queue,
-            present_queue,
-
-            swapchain_loader: swapchain_stuff.swapchain_loader,
-            swapchain: swapchain_stuff.swapchain,
-            swapchain_format: swapchain_stuff.swapchain_format,
-            swapchain_images: swapchain_stuff.swapchain_images,
-            swapchain_extent: swapchain_stuff.swapchain_extent,
-            swapchain_imageviews,
-            swapchain_framebuffers,
-
-            pipeline_layout,
+            context.allocator(),
+            std::mem::size_of::<CameraUniformBufferObject>() as vk::DeviceSize,
+            MAX_FRAMES_IN_FLIGHT,
+        )?;
+        let descriptor_pool = share::v2::create_descriptor_pool(
+            device,
+            2,
+            vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+        )?;
+        let descriptor_sets = share::v2::create_descriptor_sets(
+            device,
+            descriptor_pool,
             ubo_layout,
-            render_pass,
-            graphics_pipeline,
-
-            depth_image,
-            depth_image_view,
-            depth_image_memory,
-
-            texture_image,
-            texture_image_view,
+            &vec![uniform_ring.buffer()],
+            assets.texture_view(texture),
             texture_sampler,
-            texture_image_memory,
-
-            vertex_buffer,
-            vertex_buffer_memory,
-            index_buffer,
-            index_buffer_memory,
-
-            uniform_transform: UniformBufferObject {
-                model: Matrix4::from_angle_z(Deg(90.0)),
+            vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+        )?;
+
+        Ok(CubeScene {
+            descriptor_pool: Owned::new(device, descriptor_pool),
+            descriptor_set: descriptor_sets[0],
+            is_texture_bound: assets.is_texture_ready(texture),
+
+            // made by create_pipelines once the scene exists
+            graphics_pipeline: Owned::null(device),
+            pipeline_layout: Owned::null(device),
+            ubo_layout: Owned::new(device, ubo_layout),
+
+            texture_sampler: Owned::new(device, texture_sampler),
+            texture,
+            load_progress: assets.progress(),
+            assets,
+
+            vertex_buffer: Owned::new(device, vertex_buffer),
+            _vertex_buffer_memory: vertex_buffer_memory,
+            index_buffer: Owned::new(device, index_buffer),
+            _index_buffer_memory: index_buffer_memory,
+
+            model: Matrix4::from_angle_z(Deg(90.0)),
+            uniform_transform: CameraUniformBufferObject {
                 view: Matrix4::look_at(
                     Point3::new(2.0, 2.0, 2.0),
                     Point3::new(0.0, 0.0, 0.0),
@@ -464,8 +364,7 @@ impl VulkanAppCube {
                 proj: {
                     let mut proj = cgmath::perspective(
                         Deg(45.0),
-                        swapchain_stuff.swapchain_extent.width as f32
-                            / swapchain_stuff.swapchain_extent.height as f32,
+                        swapchain_extent.width as f32 / swapchain_extent.height as f32,
                         0.1,
                         10.0,
                     );
@@ -473,870 +372,202 @@ impl VulkanAppCube {
                     proj
                 },
             },
-            uniform_buffers,
-            uniform_buffers_memory,
-
-            descriptor_pool,
-            descriptor_sets,
-
-            command_pool,
-            command_buffers,
+            uniform_ring,
+            uniform_offset: 0,
 
-            image_available_semaphores: sync_objects.image_available_semaphores,
-            render_finished_semaphores: sync_objects.render_finished_semaphores,
-            in_flight_fences: sync_objects.inflight_fences,
-            current_frame: 0,
-
-            is_framebuffer_resized: false,
-        }
-    }
-
-    // Depth buffer functions
-    fn create_depth_resources(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        physical_device: vk::PhysicalDevice,
-        _command_pool: vk::CommandPool,
-        _submit_queue: vk::Queue,
-        swapchain_extent: vk::Extent2D,
-        device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
-    ) -> (vk::Image, vk::ImageView, vk::DeviceMemory) {
-        let depth_format = VulkanAppCube::find_depth_format(instance, physical_device);
-        let (depth_image, depth_image_memory) = share::v1::create_image(
-            device,
-            swapchain_extent.width,
-            swapchain_extent.height,
-            1,
-            vk::SampleCountFlags::TYPE_1,
-            depth_format,
-            vk::ImageTiling::OPTIMAL,
-            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
-            vk::MemoryPropertyFlags::DEVICE_LOCAL,
-            device_memory_properties,
-        );
-
-        let depth_image_view = share::v1::create_image_view(
-            device,
-            depth_image,
-            depth_format,
-            vk::ImageAspectFlags::DEPTH,
-            1
-        );
-
-        (depth_image, depth_image_view, depth_image_memory)
-    }
-
-    fn find_depth_format(
-        instance: &ash::Instance,
-        physical_device: vk::PhysicalDevice,
-    ) -> vk::Format {
-        VulkanAppCube::find_supported_format(
-            instance,
-            physical_device,
-            &[
-                vk::Format::D32_SFLOAT,
-                vk::Format::D32_SFLOAT_S8_UINT,
-                vk::Format::D24_UNORM_S8_UINT,
-            ],
-            vk::ImageTiling::OPTIMAL,
-            vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
-        )
+            vert_shader: PathBuf::from(vert_shader),
+            frag_shader: PathBuf::from(frag_shader),
+            device: device.clone(),
+        })
     }
 
-    fn find_supported_format(
-        instance: &ash::Instance,
-        physical_device: vk::PhysicalDevice,
-        candidate_formats: &[vk::Format],
-        tiling: vk::ImageTiling,
-        features: vk::FormatFeatureFlags,
-    ) -> vk::Format {
-        for &format in candidate_formats.iter() {
-            let format_properties = 
-                unsafe { instance.get_physical_device_format_properties(physical_device, format) };
-            if tiling == vk::ImageTiling::LINEAR 
-                && format_properties.linear_tiling_features.contains(features)
-            {
-                return format.clone();
-            } else if tiling == vk::ImageTiling::OPTIMAL
-                && format_properties.optimal_tiling_features.contains(features)
-            {
-                return format.clone();
+    // Picks up the streamed texture once it's uploaded. Rather than
+    // rewriting the set a frame in flight may be drawing with, a fresh one
+    // is made.
+    fn update_assets(&mut self) -> Result<(), VkPlaygroundError> {
+        self.assets.update()?;
+
+        let load_progress = self.assets.progress();
+        if load_progress != self.load_progress {
+            println!(
+                "Loaded {} of {} assets ({:.0}%)",
+                load_progress.ready,
+                load_progress.requested,
+                load_progress.fraction() * 100.0
+            );
+            for (path, err) in self.assets.errors() {
+                eprintln!("Failed to load {}: {}", path.display(), err);
             }
+            self.load_progress = load_progress;
         }
 
-        panic!("Failed to find supported format!")
-    }
-
-    #[allow(dead_code)]
-    fn has_stencil_component(format: vk::Format) -> bool {
-        format == vk::Format::D32_SFLOAT_S8_UINT || format == vk::Format::D24_UNORM_S8_UINT 
-    }
-    // end of depth buffer functions
-
-    fn create_render_pass(
-        instance: &ash::Instance,
-        device: &ash::Device,
-        physical_device: vk::PhysicalDevice,
-        surface_format: vk::Format,
-    ) -> vk::RenderPass {
-        let color_attachment = vk::AttachmentDescription {
-            flags: vk::AttachmentDescriptionFlags::empty(),
-            format: surface_format,
-            samples: vk::SampleCountFlags::TYPE_1,
-            load_op : vk::AttachmentLoadOp::CLEAR,
-            store_op: vk::AttachmentStoreOp::STORE,
-            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
-            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
-            initial_layout: vk::ImageLayout::UNDEFINED,
-            final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
-        };
-
-        let depth_attachment = vk::AttachmentDescription {
-            flags: vk::AttachmentDescriptionFlags::empty(),
-            format: VulkanAppCube::find_depth_format(instance, physical_device),
-            samples: vk::SampleCountFlags::TYPE_1,
-            load_op: vk::AttachmentLoadOp::CLEAR,
-            store_op: vk::AttachmentStoreOp::DONT_CARE,
-            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
-            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
-            initial_layout: vk::ImageLayout::UNDEFINED,
-            final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
-        };
-
-        let color_attachment_ref = vk::AttachmentReference {
-            attachment: 0,
-            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-        };
-
-        let depth_attachment_ref = vk::AttachmentReference {
-            attachment: 1,
-            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
-        };
-
-        let subpasses = [vk::SubpassDescription {
-            color_attachment_count: 1,
-            p_color_attachments: &color_attachment_ref,
-            p_depth_stencil_attachment: &depth_attachment_ref,
-            flags: vk::SubpassDescriptionFlags::empty(),
-            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
-            input_attachment_count: 0,
-            p_input_attachments: ptr::null(),
-            p_resolve_attachments: ptr::null(),
-            preserve_attachment_count: 0,
-            p_preserve_attachments: ptr::null(),
-        }];
-
-        let render_pass_attachments = [color_attachment, depth_attachment];
-
-        let subpass_dependencies = [vk::SubpassDependency {
-            src_subpass: vk::SUBPASS_EXTERNAL,
-            dst_subpass: 0,
-            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-            dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-            src_access_mask: vk::AccessFlags::empty(),
-            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
-                | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-            dependency_flags: vk::DependencyFlags::empty(),
-        }];
-
-        let renderpass_create_info = vk::RenderPassCreateInfo {
-            s_type: vk::StructureType::RENDER_PASS_CREATE_INFO,
-            flags: vk::RenderPassCreateFlags::empty(),
-            p_next: ptr::null(),
-            attachment_count: render_pass_attachments.len() as u32,
-            p_attachments: render_pass_attachments.as_ptr(),
-            subpass_count: subpasses.len() as u32,
-            p_subpasses: subpasses.as_ptr(),
-            dependency_count: subpass_dependencies.len() as u32,
-            p_dependencies: subpass_dependencies.as_ptr(),
-        };
-
-        unsafe {
-            device
-                .create_render_pass(&renderpass_create_info, None)
-                .expect("Failed to create render pass!")
-        }
-    }
-
-    fn create_framebuffers(
-        device: &ash::Device,
-        render_pass: vk::RenderPass,
-        image_views: &Vec<vk::ImageView>,
-        depth_image_view: vk::ImageView,
-        swapchain_extent: vk::Extent2D,
-    ) -> Vec<vk::Framebuffer> {
-        let mut framebuffers = vec![];
-
-        for &image_view in image_views.iter() {
-            let attachments = [image_view, depth_image_view];
-
-            let framebuffer_create_info = vk::FramebufferCreateInfo {
-                s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
-                p_next: ptr::null(),
-                flags: vk::FramebufferCreateFlags::empty(),
-                render_pass,
-                attachment_count: attachments.len() as u32,
-                p_attachments: attachments.as_ptr(),
-                width: swapchain_extent.width,
-                height: swapchain_extent.height,
-                layers: 1,
-            };
-
-            let framebuffer = unsafe {
-                device
-                    .create_framebuffer(&framebuffer_create_info, None)
-                    .expect("Failed to create Framebuffer!")
-            };
-
-            framebuffers.push(framebuffer);
-        }
-
-        framebuffers
-    }
-
-    fn create_graphics_pipeline(
-        device: &ash::Device,
-        render_pass: vk::RenderPass,
-        swapchain_extent: vk::Extent2D,
-        ubo_set_layout: vk::DescriptorSetLayout,
-    ) -> (vk::Pipeline, vk::PipelineLayout) {
-        let vert_shader_module = share::create_shader_module(
-            device,
-            "shaders/cube/vert.spv",
-        );
-        let frag_shader_module = share::create_shader_module(
-            device,
-            "shaders/cube/frag.spv",
-        );
-
-        let main_function_name = CString::new("main").unwrap(); // the beginning function name in shader code.
-
-        let shader_stages = [
-            vk::PipelineShaderStageCreateInfo {
-                // Vertex Shader
-                s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
-                p_next: ptr::null(),
-                flags: vk::PipelineShaderStageCreateFlags::empty(),
-                module: vert_shader_module,
-                p_name: main_function_name.as_ptr(),
-                p_specialization_info: ptr::null(),
-                stage: vk::ShaderStageFlags::VERTEX,
-            },
-            vk::PipelineShaderStageCreateInfo {
-                // Fragment Shader
-                s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
-                p_next: ptr::null(),
-                flags: vk::PipelineShaderStageCreateFlags::empty(),
-                module: frag_shader_module,
-                p_name: main_function_name.as_ptr(),
-                p_specialization_info: ptr::null(),
-                stage: vk::ShaderStageFlags::FRAGMENT,
-            },
-        ];
-
-        let binding_description = VertexV3::get_binding_description();
-        let attribute_description = VertexV3::get_attribute_descriptions();
-
-        let vertex_input_state_create_info = vk::PipelineVertexInputStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_VERTEX_INPUT_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineVertexInputStateCreateFlags::empty(),
-            vertex_attribute_description_count: attribute_description.len() as u32,
-            p_vertex_attribute_descriptions: attribute_description.as_ptr(),
-            vertex_binding_description_count: binding_description.len() as u32,
-            p_vertex_binding_descriptions: binding_description.as_ptr(),
-        };
-        let vertex_input_assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO,
-            flags: vk::PipelineInputAssemblyStateCreateFlags::empty(),
-            p_next: ptr::null(),
-            primitive_restart_enable: vk::FALSE,
-            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
-        };
-
-        let viewports = [vk::Viewport {
-            x: 0.0,
-            y: 0.0,
-            width: swapchain_extent.width as f32,
-            height: swapchain_extent.height as f32,
-            min_depth: 0.0,
-            max_depth: 1.0,
-        }];
-
-        let scissors = [vk::Rect2D {
-            offset: vk::Offset2D { x: 0, y: 0 },
-            extent: swapchain_extent,
-        }];
-
-        let viewport_state_create_info = vk::PipelineViewportStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_VIEWPORT_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineViewportStateCreateFlags::empty(),
-            scissor_count: scissors.len() as u32,
-            p_scissors: scissors.as_ptr(),
-            viewport_count: viewports.len() as u32,
-            p_viewports: viewports.as_ptr(),
-        };
-
-        let rasterization_statue_create_info = vk::PipelineRasterizationStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineRasterizationStateCreateFlags::empty(),
-            depth_clamp_enable: vk::FALSE,
-            cull_mode: vk::CullModeFlags::BACK,
-            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
-            line_width: 1.0,
-            polygon_mode: vk::PolygonMode::FILL,
-            rasterizer_discard_enable: vk::FALSE,
-            depth_bias_clamp: 0.0,
-            depth_bias_constant_factor: 0.0,
-            depth_bias_enable: vk::FALSE,
-            depth_bias_slope_factor: 0.0,
-        };
-
-        let multisample_state_create_info = vk::PipelineMultisampleStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
-            flags: vk::PipelineMultisampleStateCreateFlags::empty(),
-            p_next: ptr::null(),
-            rasterization_samples: vk::SampleCountFlags::TYPE_1,
-            sample_shading_enable: vk::FALSE,
-            min_sample_shading: 0.0,
-            p_sample_mask: ptr::null(),
-            alpha_to_one_enable: vk::FALSE,
-            alpha_to_coverage_enable: vk::FALSE,
-        };
-
-        let stencil_state = vk::StencilOpState {
-            fail_op: vk::StencilOp::KEEP,
-            pass_op: vk::StencilOp::KEEP,
-            depth_fail_op: vk::StencilOp::KEEP,
-            compare_op: vk::CompareOp::ALWAYS,
-            compare_mask: 0,
-            write_mask: 0,
-            reference: 0,
-        };
-
-        let depth_state_create_info = vk::PipelineDepthStencilStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineDepthStencilStateCreateFlags::empty(),
-            depth_test_enable: vk::TRUE,
-            depth_write_enable: vk::TRUE,
-            depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
-            depth_bounds_test_enable: vk::FALSE,
-            stencil_test_enable: vk::FALSE,
-            front: stencil_state,
-            back: stencil_state,
-            max_depth_bounds: 1.0,
-            min_depth_bounds: 0.0,
-        };
-
-        let color_blend_attachment_states = [vk::PipelineColorBlendAttachmentState {
-            blend_enable: vk::FALSE,
-            color_write_mask: vk::ColorComponentFlags::all(),
-            src_color_blend_factor: vk::BlendFactor::ONE,
-            dst_color_blend_factor: vk::BlendFactor::ZERO,
-            color_blend_op: vk::BlendOp::ADD,
-            src_alpha_blend_factor: vk::BlendFactor::ONE,
-            dst_alpha_blend_factor: vk::BlendFactor::ZERO,
-            alpha_blend_op: vk::BlendOp::ADD,
-        }];
-
-        let color_blend_state = vk::PipelineColorBlendStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineColorBlendStateCreateFlags::empty(),
-            logic_op_enable: vk::FALSE,
-            logic_op: vk::LogicOp::COPY,
-            attachment_count: color_blend_attachment_states.len() as u32,
-            p_attachments: color_blend_attachment_states.as_ptr(),
-            blend_constants: [0.0, 0.0, 0.0, 0.0],
-        };
-
-        let set_layouts = [ubo_set_layout];
-
-        let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo {
-            s_type: vk::StructureType::PIPELINE_LAYOUT_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineLayoutCreateFlags::empty(),
-            set_layout_count: set_layouts.len() as u32,
-            p_set_layouts: set_layouts.as_ptr(),
-            push_constant_range_count: 0,
-            p_push_constant_ranges: ptr::null(),
-        };
-
-        let pipeline_layout = unsafe {
-            device
-                .create_pipeline_layout(&pipeline_layout_create_info, None)
-                .expect("Failed to create pipeline layout!")
-        };
-
-        let graphic_pipeline_create_infos = [vk::GraphicsPipelineCreateInfo {
-            s_type: vk::StructureType::GRAPHICS_PIPELINE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineCreateFlags::empty(),
-            stage_count: shader_stages.len() as u32,
-            p_stages: shader_stages.as_ptr(),
-            p_vertex_input_state: &vertex_input_state_create_info,
-            p_input_assembly_state: &vertex_input_assembly_state_info,
-            p_tessellation_state: ptr::null(),
-            p_viewport_state: &viewport_state_create_info,
-            p_rasterization_state: &rasterization_statue_create_info,
-            p_multisample_state: &multisample_state_create_info,
-            p_depth_stencil_state: &depth_state_create_info,
-            p_color_blend_state: &color_blend_state,
-            p_dynamic_state: ptr::null(),
-            layout: pipeline_layout,
-            render_pass,
-            subpass: 0,
-            base_pipeline_handle: vk::Pipeline::null(),
-            base_pipeline_index: -1,
-        }];
-
-        let graphics_pipelines = unsafe {
-            device
-                .create_graphics_pipelines(
-                    vk::PipelineCache::null(),
-                    &graphic_pipeline_create_infos,
-                    None,
-                )
-                .expect("Failed to create Graphics Pipeline!.")
-        };
-
-        unsafe {
-            device.destroy_shader_module(vert_shader_module, None);
-            device.destroy_shader_module(frag_shader_module, None);
+        if self.is_texture_bound || !self.assets.is_texture_ready(self.texture) {
+            return Ok(());
         }
-
-        (graphics_pipelines[0], pipeline_layout)
+        self.descriptor_set = share::v2::create_descriptor_sets(
+            &self.device,
+            *self.descriptor_pool,
+            *self.ubo_layout,
+            &vec![self.uniform_ring.buffer()],
+            self.assets.texture_view(self.texture),
+            *self.texture_sampler,
+            vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+        )?[0];
+        self.is_texture_bound = true;
+
+        Ok(())
     }
-
-    
-
-   
 }
 
-impl VulkanAppCube {
-    fn create_command_buffers(
-        device: &ash::Device,
-        command_pool: vk::CommandPool,
-        graphics_pipeline: vk::Pipeline,
-        framebuffers: &Vec<vk::Framebuffer>,
-        render_pass: vk::RenderPass,
-        surface_extent: vk::Extent2D,
-        vertex_buffer: vk::Buffer,
-        index_buffer: vk::Buffer,
-        pipeline_layout: vk::PipelineLayout,
-        descriptor_sets: &Vec<vk::DescriptorSet>
-    ) -> Vec<vk::CommandBuffer> {
-        let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
-            s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
-            p_next: ptr::null(),
-            command_buffer_count: framebuffers.len() as u32,
-            command_pool,
-            level: vk::CommandBufferLevel::PRIMARY,
-        };
-
-        let command_buffers = unsafe {
-            device
-                .allocate_command_buffers(&command_buffer_allocate_info)
-                .expect("Failed to allocate Command Buffers!")
-        };
-
-        for (i, &command_buffer) in command_buffers.iter().enumerate() {
-            let command_buffer_begin_info = vk::CommandBufferBeginInfo {
-                s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
-                p_next: ptr::null(),
-                p_inheritance_info: ptr::null(),
-                flags: vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
-            };
-
-            unsafe {
-                device
-                    .begin_command_buffer(command_buffer, &command_buffer_begin_info)
-                    .expect("Failed to begin recording Command Buffer at beginning!");
-            }
-
-            let clear_values = [
-                vk::ClearValue {
-                    // clear value for color buffer
-                    color: vk::ClearColorValue {
-                        float32: [0.0, 0.0, 0.0, 1.0],
-                    }
-                },
-
-                // clear value for depth buffer
-                vk::ClearValue {
-                    depth_stencil: vk::ClearDepthStencilValue {
-                        depth: 1.0,
-                        stencil: 0,
-                    },
-                },
-            ];
-
-            let render_pass_begin_info = vk::RenderPassBeginInfo {
-                s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
-                p_next: ptr::null(),
-                render_pass,
-                framebuffer: framebuffers[i],
-                render_area: vk::Rect2D {
-                    offset: vk::Offset2D { x: 0, y: 0 },
-                    extent: surface_extent,
-                },
-                clear_value_count: clear_values.len() as u32,
-                p_clear_values: clear_values.as_ptr(),
-            };
-
-            unsafe {
-                device.cmd_begin_render_pass(
-                    command_buffer,
-                    &render_pass_begin_info,
-                    vk::SubpassContents::INLINE,
-                );
-                device.cmd_bind_pipeline(
-                    command_buffer,
-                    vk::PipelineBindPoint::GRAPHICS,
-                    graphics_pipeline,
-                );
-
-                let vertex_buffers = [vertex_buffer];
-                let offsets = [0_u64];
-                let descriptor_sets_to_bind = [descriptor_sets[i]];
-
-                device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
-                device.cmd_bind_index_buffer(
-                    command_buffer,
-                    index_buffer,
-                    0,
-                    vk::IndexType::UINT32,
-                );
-                device.cmd_bind_descriptor_sets(
-                    command_buffer,
-                    vk::PipelineBindPoint::GRAPHICS,
-                    pipeline_layout,
-                    0,
-                    &descriptor_sets_to_bind,
-                    &[],
-                );
-
-                device.cmd_draw_indexed(command_buffer, 
-                    RECT_TEX_COORD_INDICES_DATA.len() as u32, 
-                    1, 
-                    0, 
-                    0, 
-                    0,
-                );
-
-                device.cmd_end_render_pass(command_buffer);
-
-                device
-                    .end_command_buffer(command_buffer)
-                    .expect("Failed to record Command Buffer at Ending!");
-            }
-        }
-
-        command_buffers 
+impl RenderScene for CubeScene {
+    fn create_pipelines(&mut self, context: &RenderContext) -> Result<(), VkPlaygroundError> {
+        let (graphics_pipeline, pipeline_layout) = PipelineBuilder::new()
+            .vertex_shader(&self.vert_shader)
+            .fragment_shader(&self.frag_shader)
+            .vertex_input(
+                &VertexV3::get_binding_description(),
+                &VertexV3::get_attribute_descriptions(),
+            )
+            .dynamic_viewport()
+            .depth_test(vk::CompareOp::LESS_OR_EQUAL, true)
+            .descriptor_set_layouts(&[*self.ubo_layout])
+            .push_constants::<ModelPushConstants>(vk::ShaderStageFlags::VERTEX)
+            .pipeline_cache(context.pipeline_cache())
+            .build(
+                context.instance(),
+                context.physical_device(),
+                context.device(),
+                context.render_pass(),
+            )?;
+        self.graphics_pipeline = Owned::new(context.device(), graphics_pipeline);
+        self.pipeline_layout = Owned::new(context.device(), pipeline_layout);
+
+        Ok(())
     }
 
-    fn update_uniform_buffer(&mut self, current_image: usize, _delta_time: f32) {
-        let ubos = [self.uniform_transform.clone()];
-
-        let buffer_size = (std::mem::size_of::<UniformBufferObject>() * ubos.len()) as u64;
-
-        unsafe {
-            let data_ptr =
-                self.device
-                    .map_memory(
-                        self.uniform_buffers_memory[current_image],
-                        0,
-                        buffer_size,
-                        vk::MemoryMapFlags::empty(),
-                    )
-                    .expect("Failed to Map Memory") as *mut UniformBufferObject;
-
-            data_ptr.copy_from_nonoverlapping(ubos.as_ptr(), ubos.len());
-
-            self.device
-                .unmap_memory(self.uniform_buffers_memory[current_image]);
+    fn reload_shaders(
+        &mut self,
+        context: &RenderContext,
+        changed: &[PathBuf],
+    ) -> Result<bool, VkPlaygroundError> {
+        if !changed.iter().any(|path| *path == self.vert_shader || *path == self.frag_shader) {
+            return Ok(false);
         }
-    }
-}
+        self.create_pipelines(context)?;
 
-impl Drop for VulkanAppCube {
-    fn drop(&mut self) {
-        unsafe {
-            for i in 0..MAX_FRAMES_IN_FLIGHT {
-                self.device
-                    .destroy_semaphore(self.image_available_semaphores[i], None);
-                self.device
-                    .destroy_semaphore(self.render_finished_semaphores[i], None);
-                self.device.destroy_fence(self.in_flight_fences[i], None);
-            }
-
-            self.cleanup_swapchain();
-
-            self.device
-                .destroy_descriptor_pool(self.descriptor_pool, None);
-
-            for i in 0..self.uniform_buffers.len() {
-                self.device.destroy_buffer(self.uniform_buffers[i], None);
-                self.device
-                    .free_memory(self.uniform_buffers_memory[i], None);
-            }
-
-            self.device.destroy_buffer(self.index_buffer, None);
-            self.device.free_memory(self.index_buffer_memory, None);
-
-            self.device.destroy_buffer(self.vertex_buffer, None);
-            self.device.free_memory(self.vertex_buffer_memory, None);
-
-            self.device.destroy_sampler(self.texture_sampler, None);
-            self.device
-                .destroy_image_view(self.texture_image_view, None);
-
-            self.device.destroy_image(self.texture_image, None);
-            self.device.free_memory(self.texture_image_memory, None);
-
-            self.device
-                .destroy_descriptor_set_layout(self.ubo_layout, None);
-
-            self.device.destroy_command_pool(self.command_pool, None);
-
-            self.device.destroy_device(None);
-            self.surface_loader.destroy_surface(self.surface, None);
-
-            if VALIDATION.is_enable {
-                self.debug_utils_loader
-                    .destroy_debug_utils_messenger(self.debug_messenger, None);
-            }
-            self.instance.destroy_instance(None);
-        }
+        Ok(true)
     }
-}
 
-impl VulkanApp for VulkanAppCube {
-    fn draw_frame(&mut self, delta_time: f32) {
-        let wait_fences = [self.in_flight_fences[self.current_frame]];
+    fn record_commands(
+        &self,
+        context: &RenderContext,
+        command_buffer: vk::CommandBuffer,
+        _frame: Frame,
+    ) {
+        let device = context.device();
 
         unsafe {
-            self.device
-                .wait_for_fences(&wait_fences, true, std::u64::MAX)
-                .expect("Failed to wait for Fence!");
-        }
-
-        let (image_index, _is_sub_optimal) = unsafe {
-            let result = self.swapchain_loader.acquire_next_image(
-                self.swapchain,
-                std::u64::MAX,
-                self.image_available_semaphores[self.current_frame],
-                vk::Fence::null(),
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                *self.graphics_pipeline,
             );
-            match result {
-                Ok(image_index) => image_index,
-                Err(vk_result) => match vk_result {
-                    vk::Result::ERROR_OUT_OF_DATE_KHR => {
-                        self.recreate_swapchain();
-                        return;
-                    }
-                    _ => panic!("Failed to acquire Swap Chain Image!"),
-                },
-            }
-        };
-
-        self.update_uniform_buffer(image_index as usize, delta_time);
-
-        let wait_semaphores = [self.image_available_semaphores[self.current_frame]];
-        let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let signal_semaphores = [self.render_finished_semaphores[self.current_frame]];
-
-        let submit_infos = [vk::SubmitInfo {
-            s_type: vk::StructureType::SUBMIT_INFO,
-            p_next: ptr::null(),
-            wait_semaphore_count: wait_semaphores.len() as u32,
-            p_wait_semaphores: wait_semaphores.as_ptr(),
-            p_wait_dst_stage_mask: wait_stages.as_ptr(),
-            command_buffer_count: 1,
-            p_command_buffers: &self.command_buffers[image_index as usize],
-            signal_semaphore_count: signal_semaphores.len() as u32,
-            p_signal_semaphores: signal_semaphores.as_ptr(),
-        }];
-
-        unsafe {
-            self.device
-                .reset_fences(&wait_fences)
-                .expect("Failed to reset Fence!");
-
-            self.device
-                .queue_submit(
-                    self.graphics_queue,
-                    &submit_infos,
-                    self.in_flight_fences[self.current_frame],
-                )
-                .expect("Failed to execute queue submit.");
-        }
-
-        let swapchains = [self.swapchain];
 
-        let present_info = vk::PresentInfoKHR {
-            s_type: vk::StructureType::PRESENT_INFO_KHR,
-            p_next: ptr::null(),
-            wait_semaphore_count: 1,
-            p_wait_semaphores: signal_semaphores.as_ptr(),
-            swapchain_count: 1,
-            p_swapchains: swapchains.as_ptr(),
-            p_image_indices: &image_index,
-            p_results: ptr::null_mut(),
-        };
-
-        let result = unsafe {
-            self.swapchain_loader
-                .queue_present(self.present_queue, &present_info)
-        };
+            let vertex_buffers = [*self.vertex_buffer];
+            let offsets = [0_u64];
+            let descriptor_sets_to_bind = [self.descriptor_set];
+            let dynamic_offsets = [self.uniform_offset];
+
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
+            device.cmd_bind_index_buffer(
+                command_buffer,
+                *self.index_buffer,
+                0,
+                vk::IndexType::UINT32,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                *self.pipeline_layout,
+                0,
+                &descriptor_sets_to_bind,
+                &dynamic_offsets,
+            );
 
-        let is_resized = match result {
-            Ok(_) => self.is_framebuffer_resized,
-            Err(vk_result) => match vk_result {
-                vk::Result::ERROR_OUT_OF_DATE_KHR | vk::Result::SUBOPTIMAL_KHR => true,
-                _ => panic!("Failed to execute queue present."),
-            },
-        };
-        if is_resized {
-            self.is_framebuffer_resized = false;
-            self.recreate_swapchain();
+            pipeline::push_constants(
+                device,
+                command_buffer,
+                *self.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX,
+                &ModelPushConstants { model: self.model },
+            );
+            device.cmd_draw_indexed(
+                command_buffer,
+                RECT_TEX_COORD_INDICES_DATA.len() as u32,
+                1,
+                0,
+                0,
+                0,
+            );
         }
-
-        self.current_frame = (self.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
     }
 
-    fn recreate_swapchain(&mut self) {
-        // parameters -------------
-        let surface_stuff = SurfaceStuff {
-            surface_loader: self.surface_loader.clone(),
-            surface: self.surface,
-            screen_width: WINDOW_WIDTH,
-            screen_height: WINDOW_HEIGHT,
-        };
-        // ------------------------
-
-        unsafe {
-            self.device
-                .device_wait_idle()
-                .expect("Failed to wait device idle!")
-        };
-        self.cleanup_swapchain();
+    fn update(&mut self, frame: Frame, _delta_time: f32) -> Result<(), VkPlaygroundError> {
+        self.update_assets()?;
 
-        let swapchain_stuff = share::create_swapchain(
-            &self.instance,
-            &self.device,
-            self.physical_device,
-            &self.window,
-            &surface_stuff,
-            &self.queue_family,
-        );
-        self.swapchain_loader = swapchain_stuff.swapchain_loader;
-        self.swapchain = swapchain_stuff.swapchain;
-        self.swapchain_images = swapchain_stuff.swapchain_images;
-        self.swapchain_format = swapchain_stuff.swapchain_format;
-        self.swapchain_extent = swapchain_stuff.swapchain_extent;
+        self.uniform_ring.begin_frame(frame.index);
+        self.uniform_offset = self.uniform_ring.push(&self.uniform_transform);
 
-        self.swapchain_imageviews = share::v1::create_image_views(
-            &self.device,
-            self.swapchain_format,
-            &self.swapchain_images,
-        );
-        self.render_pass = VulkanAppCube::create_render_pass(
-            &self.instance,
-            &self.device,
-            self.physical_device, 
-            self.swapchain_format);
-        let (graphics_pipeline, pipeline_layout) = VulkanAppCube::create_graphics_pipeline(
-            &self.device,
-            self.render_pass,
-            swapchain_stuff.swapchain_extent,
-            self.ubo_layout,
-        );
-        self.graphics_pipeline = graphics_pipeline;
-        self.pipeline_layout = pipeline_layout;
-
-        let depth_resources = VulkanAppCube::create_depth_resources(
-            &self.instance,
-            &self.device,
-            self.physical_device,
-            self.command_pool,
-            self.graphics_queue,
-            self.swapchain_extent,
-            &self.memory_properties,
-        );
-
-        self.depth_image = depth_resources.0;
-        self.depth_image_view = depth_resources.1;
-        self.depth_image_memory = depth_resources.2;
-
-        self.swapchain_framebuffers = VulkanAppCube::create_framebuffers(
-            &self.device,
-            self.render_pass,
-            &self.swapchain_imageviews,
-            self.depth_image_view,
-            self.swapchain_extent,
-        );
-        self.command_buffers = VulkanAppCube::create_command_buffers(
-            &self.device,
-            self.command_pool,
-            self.graphics_pipeline,
-            &self.swapchain_framebuffers,
-            self.render_pass,
-            self.swapchain_extent,
-            self.vertex_buffer,
-            self.index_buffer,
-            self.pipeline_layout,
-            &self.descriptor_sets,
-        );
-    }
-
-    fn cleanup_swapchain(&self) {
-        unsafe {
-            self.device.destroy_image_view(self.depth_image_view, None);
-            self.device.destroy_image(self.depth_image, None);
-            self.device.free_memory(self.depth_image_memory, None);
-
-            self.device
-                .free_command_buffers(self.command_pool, &self.command_buffers);
-            for &framebuffer in self.swapchain_framebuffers.iter() {
-                self.device.destroy_framebuffer(framebuffer, None);
-            }
-            self.device.destroy_pipeline(self.graphics_pipeline, None);
-            self.device
-                .destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.destroy_render_pass(self.render_pass, None);
-            for &image_view in self.swapchain_imageviews.iter() {
-                self.device.destroy_image_view(image_view, None);
-            }
-            self.swapchain_loader
-                .destroy_swapchain(self.swapchain, None);
-        }
-    }
-
-    fn wait_device_idle(&self) {
-        unsafe {
-            self.device
-                .device_wait_idle()
-                .expect("Failed to wait device idle!")
-        };
+        Ok(())
     }
+}
 
-    fn resize_framebuffer(&mut self) {
-        self.is_framebuffer_resized = true;
+fn main() {
+    args::check_args(&[HotReloadOptions::FLAGS]).unwrap_or_else(|err| error::exit_with(err));
+    let gpu_options = GpuOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if gpu_options.is_list_requested {
+        gpu::list_gpus(&gpu_options).unwrap_or_else(|err| error::exit_with(err));
+        return;
     }
 
-    fn window_ref(&self) -> &winit::window::Window {
-        &self.window
+    let headless_options = HeadlessOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if let Some(options) = headless_options {
+        let mut renderer = RenderContext::new_headless(WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT)
+            .and_then(|context| {
+                Renderer::new(context, |context| CubeScene::new(context, SPIRV_SHADERS, true))
+            })
+            .unwrap_or_else(|err| error::exit_with(err));
+        headless::run_headless(&mut renderer, &options)
+            .unwrap_or_else(|err| error::exit_with(err));
+        return;
     }
-}
 
-fn main() {
+    let hot_reload_options = HotReloadOptions::from_args();
+    let shaders = match hot_reload_options {
+        Some(_) => GLSL_SHADERS,
+        None => SPIRV_SHADERS,
+    };
 
     let program_proc = ProgramProc::new();   
-    let vulkan_app = VulkanAppCube::new(&program_proc.event_loop);
+    let mut renderer = RenderContext::new(
+        &program_proc.event_loop,
+        WINDOW_TITLE,
+        WINDOW_WIDTH,
+        WINDOW_HEIGHT,
+    )
+    .and_then(|context| Renderer::new(context, |context| CubeScene::new(context, shaders, false)))
+    .unwrap_or_else(|err| error::exit_with(err));
+
+    if let Some(options) = hot_reload_options {
+        let watcher = ShaderWatcher::watch_directory(Path::new(SHADER_DIRECTORY), &options)
+            .unwrap_or_else(|source| {
+                error::exit_with(VkPlaygroundError::Io {
+                    path: SHADER_DIRECTORY.into(),
+                    source,
+                })
+            });
+        renderer.watch_shaders(watcher);
+    }
 
-    program_proc.main_loop(vulkan_app);
-}
\ No newline at end of file
+    program_proc.main_loop(renderer);
+}
@@ -0,0 +1,395 @@
+use vk_playground::{
+    utility::allocator::Allocation,
+    utility::args,
+    utility::compute::{self, Access},
+    utility::error::{self, VkPlaygroundError},
+    utility::gpu::{self, GpuOptions},
+    utility::handles::{self, Owned},
+    utility::headless::{self, HeadlessOptions},
+    utility::pipeline::{self, PipelineBuilder},
+    utility::reflection::{self, ShaderReflection},
+    utility::renderer::{Frame, RenderContext, RenderScene, Renderer},
+    utility::shader_compiler,
+    utility::vkstuff::{self, vkdescriptor},
+    utility::window::ProgramProc,
+};
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use memoffset::offset_of;
+
+use std::f32::consts::PI;
+use std::path::Path;
+
+// Particles orbiting the centre of the window, moved by a compute shader
+// every frame and drawn straight out of the same storage buffer as points,
+// e.g. `cargo run --release --bin particles -- --particles 200000`
+
+const WINDOW_TITLE: &str = "Particles";
+const WINDOW_WIDTH: u32 = 800;
+const WINDOW_HEIGHT: u32 = 600;
+
+const COMPUTE_SHADER: &str = "shaders/particles/comp.spv";
+const VERTEX_SHADER: &str = "shaders/particles/vert.spv";
+const FRAGMENT_SHADER: &str = "shaders/particles/frag.spv";
+// local_size_x in particles.comp
+const WORKGROUP_SIZE: u32 = 256;
+
+const DEFAULT_PARTICLE_COUNT: usize = 65536;
+const PARTICLES_USAGE: &str = "--particles expects a particle count above 0";
+// matches GRAVITY in particles.comp, for starting velocities that orbit
+const GRAVITY: f32 = 0.5;
+
+struct ParticleOptions {
+    particle_count: usize,
+}
+
+impl ParticleOptions {
+    const FLAGS: &'static [&'static str] = &["--particles"];
+
+    fn from_args() -> Result<ParticleOptions, VkPlaygroundError> {
+        let mut options = ParticleOptions {
+            particle_count: DEFAULT_PARTICLE_COUNT,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--particles" {
+                options.particle_count = args::parse_value(args.next(), PARTICLES_USAGE)?;
+                if options.particle_count == 0 {
+                    return Err(VkPlaygroundError::Usage {
+                        context: PARTICLES_USAGE.to_owned(),
+                    });
+                }
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Laid out like `Particle` in particles.comp, std430.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+    color: [f32; 4],
+}
+
+impl Particle {
+    fn get_binding_description() -> [vk::VertexInputBindingDescription; 1] {
+        [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: std::mem::size_of::<Self>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }]
+    }
+
+    // the velocity is only for the compute shader
+    fn get_attribute_descriptions() -> [vk::VertexInputAttributeDescription; 2] {
+        [
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 0,
+                format: vk::Format::R32G32_SFLOAT,
+                offset: offset_of!(Self, position) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 1,
+                format: vk::Format::R32G32B32A32_SFLOAT,
+                offset: offset_of!(Self, color) as u32,
+            },
+        ]
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SimulationPushConstants {
+    delta_time: f32,
+    particle_count: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ViewPushConstants {
+    scale: [f32; 2],
+}
+
+/// A disc of particles, each moving on a roughly circular orbit. Seeded, so
+/// every run starts the same.
+fn initial_particles(particle_count: usize) -> Vec<Particle> {
+    // xorshift32, plenty for scattering particles
+    let mut state = 0x9E37_79B9_u32;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as f32 / u32::MAX as f32
+    };
+
+    (0..particle_count)
+        .map(|_| {
+            // sqrt spreads them evenly over the area of the disc
+            let radius = 0.1 + 0.8 * random().sqrt();
+            let angle = 2.0 * PI * random();
+            let speed = (GRAVITY / radius).sqrt() * (0.9 + 0.2 * random());
+            let (sin, cos) = angle.sin_cos();
+
+            // white hot in the middle, fading to blue at the rim
+            let heat = 1.0 - (radius - 0.1) / 0.8;
+            Particle {
+                position: [radius * cos, radius * sin],
+                velocity: [-speed * sin, speed * cos],
+                color: [heat, 0.5 * heat + 0.2, 1.0, 0.4],
+            }
+        })
+        .collect()
+}
+
+struct ParticleScene {
+    _descriptor_pool: Owned<vk::DescriptorPool>,
+    descriptor_set: vk::DescriptorSet,
+
+    compute_pipeline: Owned<vk::Pipeline>,
+    compute_pipeline_layout: Owned<vk::PipelineLayout>,
+    _compute_set_layouts: Vec<Owned<vk::DescriptorSetLayout>>,
+
+    graphics_pipeline: Owned<vk::Pipeline>,
+    pipeline_layout: Owned<vk::PipelineLayout>,
+
+    // the compute shader's storage buffer and the draw's vertex buffer
+    particle_buffer: Owned<vk::Buffer>,
+    _particle_buffer_memory: Allocation,
+    particle_count: usize,
+
+    delta_time: f32,
+}
+
+impl ParticleScene {
+    fn new(
+        context: &RenderContext,
+        particle_count: usize,
+    ) -> Result<ParticleScene, VkPlaygroundError> {
+        let device = context.device();
+
+        let (particle_buffer, particle_buffer_memory) =
+            vkstuff::vkbuffer::create_device_local_buffer(
+                device,
+                context.allocator(),
+                context.command_pool(),
+                context.graphics_queue(),
+                vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::VERTEX_BUFFER,
+                &initial_particles(particle_count),
+            )?;
+        let particle_buffer = Owned::new(device, particle_buffer);
+
+        // the set layout and push constants come from the shader itself
+        let compute_reflection = ShaderReflection::from_spirv(&shader_compiler::load_spirv(
+            Path::new(COMPUTE_SHADER),
+        )?)?;
+        let compute_bindings =
+            reflection::merge_descriptor_bindings(std::slice::from_ref(&compute_reflection))?;
+        let compute_set_layouts = handles::own_all(
+            device,
+            reflection::create_descriptor_set_layouts(
+                device,
+                std::slice::from_ref(&compute_reflection),
+            )?,
+        );
+        let (compute_pipeline, compute_pipeline_layout) = compute::create_compute_pipeline(
+            device,
+            context.pipeline_cache(),
+            Path::new(COMPUTE_SHADER),
+            &[*compute_set_layouts[0]],
+            &compute_reflection.push_constant_ranges,
+        )?;
+        let compute_pipeline = Owned::new(device, compute_pipeline);
+        let compute_pipeline_layout = Owned::new(device, compute_pipeline_layout);
+
+        let descriptor_pool = Owned::new(
+            device,
+            vkdescriptor::create_descriptor_pool_for_bindings(device, &compute_bindings, 1)?,
+        );
+        let descriptor_set = vkdescriptor::allocate_descriptor_sets(
+            device,
+            *descriptor_pool,
+            *compute_set_layouts[0],
+            1,
+        )?[0];
+        vkdescriptor::write_storage_buffer(device, descriptor_set, 0, *particle_buffer);
+
+        Ok(ParticleScene {
+            _descriptor_pool: descriptor_pool,
+            descriptor_set,
+
+            compute_pipeline,
+            compute_pipeline_layout,
+            _compute_set_layouts: compute_set_layouts,
+
+            // made by create_pipelines once the scene exists
+            graphics_pipeline: Owned::null(device),
+            pipeline_layout: Owned::null(device),
+
+            particle_buffer,
+            _particle_buffer_memory: particle_buffer_memory,
+            particle_count,
+
+            delta_time: 0.0,
+        })
+    }
+}
+
+impl RenderScene for ParticleScene {
+    fn create_pipelines(&mut self, context: &RenderContext) -> Result<(), VkPlaygroundError> {
+        let (graphics_pipeline, pipeline_layout) = PipelineBuilder::new()
+            .vertex_shader(VERTEX_SHADER)
+            .fragment_shader(FRAGMENT_SHADER)
+            .vertex_input(
+                &Particle::get_binding_description(),
+                &Particle::get_attribute_descriptions(),
+            )
+            .topology(vk::PrimitiveTopology::POINT_LIST)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .color_attachment(pipeline::additive_blend())
+            .dynamic_viewport()
+            .push_constants::<ViewPushConstants>(vk::ShaderStageFlags::VERTEX)
+            .pipeline_cache(context.pipeline_cache())
+            .build(
+                context.instance(),
+                context.physical_device(),
+                context.device(),
+                context.render_pass(),
+            )?;
+        self.graphics_pipeline = Owned::new(context.device(), graphics_pipeline);
+        self.pipeline_layout = Owned::new(context.device(), pipeline_layout);
+
+        Ok(())
+    }
+
+    fn record_compute(
+        &self,
+        context: &RenderContext,
+        command_buffer: vk::CommandBuffer,
+        _frame: Frame,
+    ) {
+        let device = context.device();
+
+        // the previous frame's draw has to be done reading before the
+        // particles move, and they have to have moved before this one's
+        compute::buffer_barrier(
+            device,
+            command_buffer,
+            *self.particle_buffer,
+            Access::VertexInput,
+            Access::ComputeReadWrite,
+        );
+        pipeline::push_constants(
+            device,
+            command_buffer,
+            *self.compute_pipeline_layout,
+            vk::ShaderStageFlags::COMPUTE,
+            &SimulationPushConstants {
+                delta_time: self.delta_time,
+                particle_count: self.particle_count as u32,
+            },
+        );
+        compute::dispatch(
+            device,
+            command_buffer,
+            *self.compute_pipeline,
+            *self.compute_pipeline_layout,
+            &[self.descriptor_set],
+            [compute::group_count(self.particle_count, WORKGROUP_SIZE), 1, 1],
+        );
+        compute::buffer_barrier(
+            device,
+            command_buffer,
+            *self.particle_buffer,
+            Access::ComputeReadWrite,
+            Access::VertexInput,
+        );
+    }
+
+    fn record_commands(
+        &self,
+        context: &RenderContext,
+        command_buffer: vk::CommandBuffer,
+        _frame: Frame,
+    ) {
+        let device = context.device();
+        let extent = context.swapchain_extent();
+        // keeps the orbits round whatever the window's shape
+        let aspect = extent.width as f32 / extent.height as f32;
+        let scale = if aspect > 1.0 {
+            [1.0 / aspect, 1.0]
+        } else {
+            [1.0, aspect]
+        };
+
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                *self.graphics_pipeline,
+            );
+
+            let vertex_buffers = [*self.particle_buffer];
+            let offsets = [0_u64];
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
+
+            pipeline::push_constants(
+                device,
+                command_buffer,
+                *self.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX,
+                &ViewPushConstants { scale },
+            );
+            device.cmd_draw(command_buffer, self.particle_count as u32, 1, 0, 0);
+        }
+    }
+
+    fn update(&mut self, _frame: Frame, delta_time: f32) -> Result<(), VkPlaygroundError> {
+        self.delta_time = delta_time;
+
+        Ok(())
+    }
+}
+
+fn main() {
+    args::check_args(&[ParticleOptions::FLAGS]).unwrap_or_else(|err| error::exit_with(err));
+    let gpu_options = GpuOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if gpu_options.is_list_requested {
+        gpu::list_gpus(&gpu_options).unwrap_or_else(|err| error::exit_with(err));
+        return;
+    }
+
+    let particle_count = ParticleOptions::from_args()
+        .unwrap_or_else(|err| error::exit_with(err))
+        .particle_count;
+    let create_scene = |context: &RenderContext| ParticleScene::new(context, particle_count);
+
+    let headless_options = HeadlessOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if let Some(options) = headless_options {
+        let mut renderer = RenderContext::new_headless(WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT)
+            .and_then(|context| Renderer::new(context, create_scene))
+            .unwrap_or_else(|err| error::exit_with(err));
+        headless::run_headless(&mut renderer, &options)
+            .unwrap_or_else(|err| error::exit_with(err));
+        return;
+    }
+
+    let program_proc = ProgramProc::new();
+    let renderer = RenderContext::new(
+        &program_proc.event_loop,
+        WINDOW_TITLE,
+        WINDOW_WIDTH,
+        WINDOW_HEIGHT,
+    )
+    .and_then(|context| Renderer::new(context, create_scene))
+    .unwrap_or_else(|err| error::exit_with(err));
+
+    program_proc.main_loop(renderer);
+}
@@ -0,0 +1,264 @@
+use vk_playground::{
+    utility::args,
+    utility::constants::MAX_FRAMES_IN_FLIGHT,
+    utility::error::{self, VkPlaygroundError},
+    utility::frame_commands,
+    utility::gpu::{self, GpuOptions},
+    utility::handles::Owned,
+    utility::pipeline::PipelineBuilder,
+    utility::renderer::{Frame, ParallelDraws, RenderContext, RenderScene, Renderer},
+};
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+// Times recording a frame of many small draws on the main thread against
+// recording it on worker threads, e.g.
+// `cargo run --release --bin recording_benchmark -- --objects 50000`
+//
+// Each object is the triangle demo's triangle scissored to its own tile of
+// a grid, so every draw is a scissor and a draw command. Nothing is
+// submitted, only the recording is timed.
+
+const WINDOW_TITLE: &str = "Recording benchmark";
+const WINDOW_WIDTH: u32 = 800;
+const WINDOW_HEIGHT: u32 = 600;
+
+const DEFAULT_OBJECT_COUNT: usize = 20000;
+const DEFAULT_FRAME_COUNT: usize = 200;
+// recorded first and not timed, while caches and allocations warm up
+const WARM_UP_FRAME_COUNT: usize = 10;
+
+const FRAMES_USAGE: &str = "--frames expects a frame count above 0";
+
+struct BenchmarkOptions {
+    object_count: usize,
+    frame_count: usize,
+    // the most worker threads to try
+    max_thread_count: usize,
+}
+
+impl BenchmarkOptions {
+    const FLAGS: &'static [&'static str] = &["--objects", "--frames", "--threads"];
+
+    fn from_args() -> Result<BenchmarkOptions, VkPlaygroundError> {
+        let mut options = BenchmarkOptions {
+            object_count: DEFAULT_OBJECT_COUNT,
+            frame_count: DEFAULT_FRAME_COUNT,
+            max_thread_count: frame_commands::default_worker_count(),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--objects" => {
+                    options.object_count =
+                        args::parse_value(args.next(), "--objects expects an object count")?;
+                }
+                "--frames" => {
+                    options.frame_count = args::parse_value(args.next(), FRAMES_USAGE)?;
+                    if options.frame_count == 0 {
+                        return Err(VkPlaygroundError::Usage {
+                            context: FRAMES_USAGE.to_owned(),
+                        });
+                    }
+                }
+                "--threads" => {
+                    options.max_thread_count =
+                        args::parse_value(args.next(), "--threads expects a thread count")?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// 0, i.e. recording on the main thread, then doubling up to the
+    /// maximum.
+    fn thread_counts(&self) -> Vec<usize> {
+        let mut thread_counts = vec![0];
+        let mut thread_count = 2;
+        while thread_count < self.max_thread_count {
+            thread_counts.push(thread_count);
+            thread_count *= 2;
+        }
+        if self.max_thread_count > 0 {
+            thread_counts.push(self.max_thread_count);
+        }
+
+        thread_counts
+    }
+}
+
+struct GridDraws {
+    graphics_pipeline: Owned<vk::Pipeline>,
+    pipeline_layout: Owned<vk::PipelineLayout>,
+
+    object_count: usize,
+    column_count: usize,
+    tile_extent: vk::Extent2D,
+}
+
+impl ParallelDraws for GridDraws {
+    fn draw_count(&self) -> usize {
+        self.object_count
+    }
+
+    fn record_draws(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        _frame: Frame,
+        draws: Range<usize>,
+    ) {
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                *self.graphics_pipeline,
+            );
+
+            for object in draws {
+                let scissors = [vk::Rect2D {
+                    offset: vk::Offset2D {
+                        x: ((object % self.column_count) as u32 * self.tile_extent.width) as i32,
+                        y: ((object / self.column_count) as u32 * self.tile_extent.height) as i32,
+                    },
+                    extent: self.tile_extent,
+                }];
+                device.cmd_set_scissor(command_buffer, 0, &scissors);
+                device.cmd_draw(command_buffer, 3, 1, 0, 0);
+            }
+        }
+    }
+}
+
+struct GridScene {
+    draws: GridDraws,
+}
+
+impl GridScene {
+    fn new(context: &RenderContext, object_count: usize) -> GridScene {
+        let extent = context.swapchain_extent();
+        let column_count = ((object_count as f64).sqrt().ceil() as usize).max(1);
+        let row_count = object_count.div_ceil(column_count).max(1);
+
+        GridScene {
+            draws: GridDraws {
+                graphics_pipeline: Owned::null(context.device()),
+                pipeline_layout: Owned::null(context.device()),
+
+                object_count,
+                column_count,
+                // tiles are at least a pixel, so with more objects than
+                // pixels the grid runs off the edge and gets clipped
+                tile_extent: vk::Extent2D {
+                    width: (extent.width / column_count as u32).max(1),
+                    height: (extent.height / row_count as u32).max(1),
+                },
+            },
+        }
+    }
+}
+
+impl RenderScene for GridScene {
+    fn create_pipelines(&mut self, context: &RenderContext) -> Result<(), VkPlaygroundError> {
+        let (graphics_pipeline, pipeline_layout) = PipelineBuilder::new()
+            .vertex_shader("shaders/triangle/vert.spv")
+            .fragment_shader("shaders/triangle/frag.spv")
+            .dynamic_viewport()
+            .front_face(vk::FrontFace::CLOCKWISE)
+            .pipeline_cache(context.pipeline_cache())
+            .build(
+                context.instance(),
+                context.physical_device(),
+                context.device(),
+                context.render_pass(),
+            )?;
+        self.draws.graphics_pipeline = Owned::new(context.device(), graphics_pipeline);
+        self.draws.pipeline_layout = Owned::new(context.device(), pipeline_layout);
+
+        Ok(())
+    }
+
+    fn parallel_draws(&self) -> Option<&dyn ParallelDraws> {
+        Some(&self.draws)
+    }
+}
+
+/// The median and mean time to record a frame.
+fn time_recording(
+    renderer: &Renderer<GridScene>,
+    frame_count: usize,
+) -> Result<(Duration, Duration), VkPlaygroundError> {
+    let mut frame_times = Vec::with_capacity(frame_count);
+
+    for i in 0..WARM_UP_FRAME_COUNT + frame_count {
+        let frame = Frame {
+            index: i % MAX_FRAMES_IN_FLIGHT,
+            image_index: 0,
+        };
+
+        let start = Instant::now();
+        renderer.record_frame(frame)?;
+        if i >= WARM_UP_FRAME_COUNT {
+            frame_times.push(start.elapsed());
+        }
+    }
+
+    frame_times.sort();
+    let median = frame_times[frame_times.len() / 2];
+    let mean = frame_times.iter().sum::<Duration>() / frame_times.len() as u32;
+
+    Ok((median, mean))
+}
+
+fn run(options: &BenchmarkOptions) -> Result<(), VkPlaygroundError> {
+    let object_count = options.object_count;
+    let context = RenderContext::new_headless(WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT)?;
+    let mut renderer = Renderer::new(context, |context| Ok(GridScene::new(context, object_count)))?;
+
+    println!(
+        "Recording {} draws, median and mean of {} frames",
+        object_count, options.frame_count
+    );
+    println!("{:>8} {:>10} {:>10} {:>8}", "threads", "median", "mean", "speedup");
+
+    let mut single_threaded_median = None;
+    for thread_count in options.thread_counts() {
+        renderer.set_recording_threads(thread_count)?;
+        let (median, mean) = time_recording(&renderer, options.frame_count)?;
+        let baseline = *single_threaded_median.get_or_insert(median);
+
+        let threads = if thread_count == 0 {
+            "main".to_owned()
+        } else {
+            thread_count.to_string()
+        };
+        println!(
+            "{:>8} {:>7.3} ms {:>7.3} ms {:>7.2}x",
+            threads,
+            median.as_secs_f64() * 1000.0,
+            mean.as_secs_f64() * 1000.0,
+            baseline.as_secs_f64() / median.as_secs_f64()
+        );
+    }
+
+    Ok(())
+}
+
+fn main() {
+    args::check_args(&[BenchmarkOptions::FLAGS]).unwrap_or_else(|err| error::exit_with(err));
+    let gpu_options = GpuOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if gpu_options.is_list_requested {
+        gpu::list_gpus(&gpu_options).unwrap_or_else(|err| error::exit_with(err));
+        return;
+    }
+
+    let options = BenchmarkOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    run(&options).unwrap_or_else(|err| error::exit_with(err));
+}
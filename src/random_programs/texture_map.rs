@@ -1,20 +1,33 @@
 use vk_playground::{
     utility,
+    utility::allocator::{Allocation, Allocator},
+    utility::args,
     utility::constants::*,
     utility::debug::*,
+    utility::error::{self, VkPlaygroundError, VkResultExt},
+    utility::extensions::ExtensionConfig,
+    utility::frame_commands::FrameCommandPools,
+    utility::gpu::{self, GpuOptions},
+    utility::handles::{
+        self, Owned, OwnedDebugMessenger, OwnedDevice, OwnedInstance, OwnedSurface,
+        OwnedSwapchain,
+    },
+    utility::headless::{self, HeadlessApp, HeadlessOptions, OffscreenTarget},
+    utility::pipeline::{self, PipelineBuilder},
+    utility::pipeline_cache::PipelineCache,
+    utility::screenshot::{self, CapturedFrame},
     utility::share,
     utility::structures::*,
+    utility::vkstuff,
     utility::window::{ProgramProc, VulkanApp},
 };
 
 use ash::version::DeviceV1_0;
-use ash::version::InstanceV1_0;
 use ash::vk;
 use cgmath::{Deg, Matrix4, Point3, Vector3};
 use memoffset::offset_of;
 
 
-use std::ffi::CString;
 use std::path::Path;
 use std::ptr;
 
@@ -91,152 +104,226 @@ pub const RECT_TEX_COORD_VERTICES_DATA: [VertexV2; 4] = [
 
 
 struct VulkanAppTextureMapping {
-    window: winit::window::Window,
+    // Fields are dropped in declaration order and the owned handles destroy
+    // themselves, so everything made from the device is declared before the
+    // device, and the device before the instance.
+    image_available_semaphores: Vec<Owned<vk::Semaphore>>,
+    render_finished_semaphores: Vec<Owned<vk::Semaphore>>,
+    in_flight_fences: Vec<Owned<vk::Fence>>,
+    current_frame: usize,
 
-    _entry: ash::Entry,
-    instance: ash::Instance,
-    surface_loader: ash::extensions::khr::Surface,
-    surface: vk::SurfaceKHR,
-    debug_utils_loader: ash::extensions::ext::DebugUtils,
-    debug_messenger: vk::DebugUtilsMessengerEXT,
+    command_pool: Owned<vk::CommandPool>,
+    // recorded again every frame, one pool per frame in flight
+    frame_commands: FrameCommandPools,
 
-    physical_device: vk::PhysicalDevice,
-    device: ash::Device,
-
-    queue_family: QueueFamilyIndices,
-    graphics_queue: vk::Queue,
-    present_queue: vk::Queue,
+    _descriptor_pool: Owned<vk::DescriptorPool>,
+    descriptor_sets: Vec<vk::DescriptorSet>,
 
-    swapchain_loader: ash::extensions::khr::Swapchain,
-    swapchain: vk::SwapchainKHR,
-    swapchain_images: Vec<vk::Image>,
-    swapchain_format: vk::Format,
-    swapchain_extent: vk::Extent2D,
-    swapchain_imageviews: Vec<vk::ImageView>,
-    swapchain_framebuffers: Vec<vk::Framebuffer>,
+    swapchain_framebuffers: Vec<Owned<vk::Framebuffer>>,
 
-    render_pass: vk::RenderPass,
-    ubo_layout: vk::DescriptorSetLayout,
-    pipeline_layout: vk::PipelineLayout,
-    graphics_pipeline: vk::Pipeline,
+    graphics_pipeline: Owned<vk::Pipeline>,
+    pipeline_layout: Owned<vk::PipelineLayout>,
+    pipeline_cache: PipelineCache,
+    ubo_layout: Owned<vk::DescriptorSetLayout>,
+    render_pass: Owned<vk::RenderPass>,
 
-    texture_image: vk::Image,
-    texture_image_view: vk::ImageView,
-    texture_sampler: vk::Sampler,
-    texture_image_memory: vk::DeviceMemory,
+    _texture_sampler: Owned<vk::Sampler>,
+    _texture_image_view: Owned<vk::ImageView>,
+    _texture_image: Owned<vk::Image>,
+    _texture_image_memory: Allocation,
 
-    vertex_buffer: vk::Buffer,
-    vertex_buffer_memory: vk::DeviceMemory,
-    index_buffer: vk::Buffer,
-    index_buffer_memory: vk::DeviceMemory,
+    vertex_buffer: Owned<vk::Buffer>,
+    _vertex_buffer_memory: Allocation,
+    index_buffer: Owned<vk::Buffer>,
+    _index_buffer_memory: Allocation,
 
     uniform_transform: UniformBufferObject,
-    uniform_buffers: Vec<vk::Buffer>,
-    uniform_buffers_memory: Vec<vk::DeviceMemory>,
+    _uniform_buffers: Vec<Owned<vk::Buffer>>,
+    uniform_buffers_memory: Vec<Allocation>,
 
-    descriptor_pool: vk::DescriptorPool,
-    descriptor_sets: Vec<vk::DescriptorSet>,
+    swapchain_imageviews: Vec<Owned<vk::ImageView>>,
+    swapchain_loader: ash::extensions::khr::Swapchain,
+    swapchain: OwnedSwapchain,
+    swapchain_images: Vec<vk::Image>,
+    swapchain_format: vk::Format,
+    swapchain_extent: vk::Extent2D,
+    swapchain_usage: vk::ImageUsageFlags,
+    offscreen_target: Option<OffscreenTarget>,
 
-    command_pool: vk::CommandPool,
-    command_buffers: Vec<vk::CommandBuffer>,
+    allocator: Allocator,
+    queue_family: QueueFamilyIndices,
+    graphics_queue: vk::Queue,
+    present_queue: vk::Queue,
+    device: OwnedDevice,
+    physical_device: vk::PhysicalDevice,
 
-    image_available_semaphores: Vec<vk::Semaphore>,
-    render_finished_semaphores: Vec<vk::Semaphore>,
-    in_flight_fences: Vec<vk::Fence>,
-    current_frame: usize,
+    surface_loader: ash::extensions::khr::Surface,
+    surface: OwnedSurface,
+    // None when rendering headless, outlives the surface made from it
+    window: Option<winit::window::Window>,
+    _debug_messenger: OwnedDebugMessenger,
+    instance: OwnedInstance,
+    _entry: ash::Entry,
 
     is_framebuffer_resized: bool,
+    is_capture_requested: bool,
+    captured_frame: Option<CapturedFrame>,
 }
 
 impl VulkanAppTextureMapping {
-    pub fn new(event_loop: &winit::event_loop::EventLoop<()>) -> VulkanAppTextureMapping {
+    pub fn new(
+        event_loop: &winit::event_loop::EventLoop<()>,
+    ) -> Result<VulkanAppTextureMapping, VkPlaygroundError> {
         let window =
-            utility::window::init_window(&event_loop, WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT);
-        
-        let entry = ash::Entry::new().unwrap();
-        let instance = share::create_instance(
-            &entry,
-            WINDOW_TITLE,
-            VALIDATION.is_enable,
-            &VALIDATION.required_validation_layers.to_vec(),
-        );
-        let surface_stuff =
-        share::create_surface(&entry, &instance, &window, WINDOW_WIDTH, WINDOW_HEIGHT);
+            utility::window::init_window(&event_loop, WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT)?;
+
+        VulkanAppTextureMapping::init(Some(window))
+    }
+
+    pub fn new_headless() -> Result<VulkanAppTextureMapping, VkPlaygroundError> {
+        VulkanAppTextureMapping::init(None)
+    }
+
+    fn init(
+        window: Option<winit::window::Window>,
+    ) -> Result<VulkanAppTextureMapping, VkPlaygroundError> {
+        let entry = vkstuff::vkinstance::load_entry()?;
+        let config = match window {
+            Some(_) => ExtensionConfig::for_window(&VALIDATION, &DEVICE_EXTENSIONS),
+            None => ExtensionConfig::for_headless(&VALIDATION),
+        };
+        let (instance, mut enabled_extensions) =
+            vkstuff::vkinstance::create_instance(&entry, WINDOW_TITLE, &config)?;
+        let instance = OwnedInstance::new(instance);
+        let surface_stuff = match &window {
+            Some(window) => share::create_surface(
+                &entry,
+                &instance,
+                window,
+                WINDOW_WIDTH,
+                WINDOW_HEIGHT,
+            )?,
+            None => headless::create_null_surface_stuff(
+                &entry,
+                &instance,
+                WINDOW_WIDTH,
+                WINDOW_HEIGHT,
+            ),
+        };
+        // only a real surface needs present support from the device
+        let present_surface = window.as_ref().map(|_| &surface_stuff);
         let (debug_utils_loader, debug_messenger) =
-            setup_debug_utils(VALIDATION.is_enable, &entry, &instance);
-        let physical_device =
-            share::pick_physical_device(&instance, &surface_stuff, &DEVICE_EXTENSIONS);
-        let physical_device_memory_properties =
-            unsafe { instance.get_physical_device_memory_properties(physical_device) };
-        let (device, queue_family) = share::create_logical_device(
+            setup_debug_utils(enabled_extensions.has_debug_utils(), &entry, &instance)?;
+        let physical_device = vkstuff::vkdevice::pick_physical_device(
+            &instance,
+            present_surface,
+            &config,
+            GpuOptions::from_args()?.selector.as_ref(),
+        )?;
+        config.select_device(&instance, physical_device, &mut enabled_extensions)?;
+        let (device, queue_family) = vkstuff::vkdevice::create_logical_device(
             &instance,
             physical_device,
-            &VALIDATION,
-            &DEVICE_EXTENSIONS,
-            &surface_stuff,
-        );
+            &enabled_extensions,
+            present_surface,
+        )?;
+        let device = OwnedDevice::new(device);
+        let allocator = Allocator::new(&instance, physical_device, &device);
         let graphics_queue =
             unsafe { device.get_device_queue(queue_family.graphics_family.unwrap(), 0) };
-        let present_queue =
-            unsafe { device.get_device_queue(queue_family.present_family.unwrap(), 0) };
-        let swapchain_stuff = share::create_swapchain(
-            &instance,
-            &device,
-            physical_device,
-            &window,
-            &surface_stuff,
-            &queue_family,
-        );
+        // headless devices have no present queue, nothing is presented anyway
+        let present_queue = match queue_family.present_family {
+            Some(present_family) => unsafe { device.get_device_queue(present_family, 0) },
+            None => graphics_queue,
+        };
+        let (swapchain_stuff, offscreen_target, final_layout) = match &window {
+            Some(window) => {
+                let swapchain_stuff = share::create_swapchain(
+                    &instance,
+                    &device,
+                    physical_device,
+                    window,
+                    &surface_stuff,
+                    &queue_family,
+                )?;
+                (swapchain_stuff, None, vk::ImageLayout::PRESENT_SRC_KHR)
+            }
+            None => {
+                let offscreen_target = headless::create_offscreen_target(
+                    &device,
+                    vk::Extent2D {
+                        width: WINDOW_WIDTH,
+                        height: WINDOW_HEIGHT,
+                    },
+                    &allocator,
+                )?;
+                let swapchain_stuff = headless::create_offscreen_swapchain_stuff(
+                    &instance,
+                    &device,
+                    &offscreen_target,
+                );
+                (swapchain_stuff, Some(offscreen_target), headless::OFFSCREEN_FINAL_LAYOUT)
+            }
+        };
         let swapchain_imageviews = share::v1::create_image_views(
             &device,
             swapchain_stuff.swapchain_format,
             &swapchain_stuff.swapchain_images,
-        );
-        let render_pass = share::v1::create_render_pass(&device, swapchain_stuff.swapchain_format);
-        let ubo_layout = VulkanAppTextureMapping::create_descriptor_set_layout(&device);
+        )?;
+        let render_pass = share::v1::create_render_pass_with_final_layout(
+            &device,
+            swapchain_stuff.swapchain_format,
+            final_layout,
+        )?;
+        let ubo_layout = VulkanAppTextureMapping::create_descriptor_set_layout(&device)?;
+        let pipeline_cache = PipelineCache::load(&instance, physical_device, &device)?;
         let (graphics_pipeline, pipeline_layout) = VulkanAppTextureMapping::create_graphics_pipeline(
+            &instance,
+            physical_device,
             &device,
             render_pass,
-            swapchain_stuff.swapchain_extent,
+            pipeline_cache.handle(),
             ubo_layout,
-        );
+        )?;
         let swapchain_framebuffers = share::v1::create_framebuffers(
             &device,
             render_pass,
             &swapchain_imageviews,
             swapchain_stuff.swapchain_extent
-        );
-        let command_pool = share::v1::create_command_pool(&device, &queue_family);
+        )?;
+        let command_pool = share::v1::create_command_pool(&device, &queue_family)?;
         let (texture_image, texture_image_memory) = share::v1::create_texture_image(
             &device,
             command_pool,
             graphics_queue,
-            &physical_device_memory_properties,
+            &allocator,
             &Path::new("resources/textures/container.jpg")
-        );
-        let texture_image_view = share::v1::create_texture_image_view(&device, texture_image, 1);
-        let texture_sampler = share::v1::create_texture_sampler(&device);
+        )?;
+        let texture_image_view = share::v1::create_texture_image_view(&device, texture_image, 1)?;
+        let texture_sampler = share::v1::create_texture_sampler(&device)?;
         let (vertex_buffer, vertex_buffer_memory) = share::v1::create_vertex_buffer(
             &device,
-            &physical_device_memory_properties,
+            &allocator,
             command_pool,
             graphics_queue,
             &RECT_TEX_COORD_VERTICES_DATA,
-        );
+        )?;
         let (index_buffer, index_buffer_memory) = share::v1::create_index_buffer(
             &device,
-            &physical_device_memory_properties,
+            &allocator,
             command_pool,
             graphics_queue,
             &RECT_INDICES_DATA,
-        );
+        )?;
         let (uniform_buffers, uniform_buffers_memory) = share::v1::create_uniform_buffers(
             &device,
-            &physical_device_memory_properties,
+            &allocator,
             swapchain_stuff.swapchain_images.len()
-        );
-        let descriptor_pool =
-            VulkanAppTextureMapping::create_descriptor_pool(&device, swapchain_stuff.swapchain_images.len());
+        )?;
+        let descriptor_pool = VulkanAppTextureMapping::create_descriptor_pool(
+            &device,
+            swapchain_stuff.swapchain_images.len(),
+        )?;
         let descriptor_sets = VulkanAppTextureMapping::create_descriptor_sets(
             &device,
             descriptor_pool,
@@ -245,62 +332,52 @@ impl VulkanAppTextureMapping {
             texture_image_view,
             texture_sampler,
             swapchain_stuff.swapchain_images.len(),
-        );
-        let command_buffers = VulkanAppTextureMapping::create_command_buffers(
+        )?;
+        let frame_commands = FrameCommandPools::new(
             &device,
-            command_pool,
-            graphics_pipeline,
-            &swapchain_framebuffers,
-            render_pass,
-            swapchain_stuff.swapchain_extent,
-            vertex_buffer,
-            index_buffer,
-            pipeline_layout,
-            &descriptor_sets
-        );
-        let sync_objects = share::v1::create_sync_objects(&device, MAX_FRAMES_IN_FLIGHT);
+            queue_family.graphics_family.unwrap(),
+            MAX_FRAMES_IN_FLIGHT,
+        )?;
+        let sync_objects = share::v1::create_sync_objects(&device, MAX_FRAMES_IN_FLIGHT)?;
         
 
-        VulkanAppTextureMapping {
+        Ok(VulkanAppTextureMapping {
             window,
 
-            // vulkan stuff
-            _entry: entry,
-            instance,
-            surface: surface_stuff.surface,
-            surface_loader: surface_stuff.surface_loader,
-            debug_utils_loader,
-            debug_messenger,
+            image_available_semaphores: handles::own_all(
+                &device,
+                sync_objects.image_available_semaphores,
+            ),
+            render_finished_semaphores: handles::own_all(
+                &device,
+                sync_objects.render_finished_semaphores,
+            ),
+            in_flight_fences: handles::own_all(&device, sync_objects.inflight_fences),
+            current_frame: 0,
 
-            physical_device,
-            device,
+            command_pool: Owned::new(&device, command_pool),
+            frame_commands,
 
-            queue_family,
-            graphics_queue,
-            present_queue,
+            _descriptor_pool: Owned::new(&device, descriptor_pool),
+            descriptor_sets,
 
-            swapchain_loader: swapchain_stuff.swapchain_loader,
-            swapchain: swapchain_stuff.swapchain,
-            swapchain_format: swapchain_stuff.swapchain_format,
-            swapchain_images: swapchain_stuff.swapchain_images,
-            swapchain_extent: swapchain_stuff.swapchain_extent,
-            swapchain_imageviews,
-            swapchain_framebuffers,
+            swapchain_framebuffers: handles::own_all(&device, swapchain_framebuffers),
 
-            pipeline_layout,
-            ubo_layout,
-            render_pass,
-            graphics_pipeline,
+            graphics_pipeline: Owned::new(&device, graphics_pipeline),
+            pipeline_layout: Owned::new(&device, pipeline_layout),
+            pipeline_cache,
+            ubo_layout: Owned::new(&device, ubo_layout),
+            render_pass: Owned::new(&device, render_pass),
 
-            texture_image,
-            texture_image_view,
-            texture_sampler,
-            texture_image_memory,
+            _texture_sampler: Owned::new(&device, texture_sampler),
+            _texture_image_view: Owned::new(&device, texture_image_view),
+            _texture_image: Owned::new(&device, texture_image),
+            _texture_image_memory: texture_image_memory,
 
-            vertex_buffer,
-            vertex_buffer_memory,
-            index_buffer,
-            index_buffer_memory,
+            vertex_buffer: Owned::new(&device, vertex_buffer),
+            _vertex_buffer_memory: vertex_buffer_memory,
+            index_buffer: Owned::new(&device, index_buffer),
+            _index_buffer_memory: index_buffer_memory,
 
             uniform_transform: UniformBufferObject {
                 model: Matrix4::from_angle_z(Deg(90.0)),
@@ -321,28 +398,45 @@ impl VulkanAppTextureMapping {
                     proj
                 },
             },
-            uniform_buffers,
+            _uniform_buffers: handles::own_all(&device, uniform_buffers),
             uniform_buffers_memory,
 
-            descriptor_pool,
-            descriptor_sets,
+            swapchain_imageviews: handles::own_all(&device, swapchain_imageviews),
+            swapchain: OwnedSwapchain::new(
+                &device,
+                &swapchain_stuff.swapchain_loader,
+                swapchain_stuff.swapchain,
+            ),
+            swapchain_loader: swapchain_stuff.swapchain_loader,
+            swapchain_images: swapchain_stuff.swapchain_images,
+            swapchain_format: swapchain_stuff.swapchain_format,
+            swapchain_extent: swapchain_stuff.swapchain_extent,
+            swapchain_usage: swapchain_stuff.swapchain_usage,
+            offscreen_target,
 
-            command_pool,
-            command_buffers,
+            allocator,
+            queue_family,
+            graphics_queue,
+            present_queue,
+            device,
+            physical_device,
 
-            image_available_semaphores: sync_objects.image_available_semaphores,
-            render_finished_semaphores: sync_objects.render_finished_semaphores,
-            in_flight_fences: sync_objects.inflight_fences,
-            current_frame: 0,
+            surface: OwnedSurface::new(&surface_stuff.surface_loader, surface_stuff.surface),
+            surface_loader: surface_stuff.surface_loader,
+            _debug_messenger: OwnedDebugMessenger::new(&debug_utils_loader, debug_messenger),
+            instance,
+            _entry: entry,
 
             is_framebuffer_resized: false,
-        }
+            is_capture_requested: false,
+            captured_frame: None,
+        })
     }
 
     fn create_descriptor_pool (
         device: &ash::Device,
         swapchain_images_size: usize,
-    ) -> vk::DescriptorPool {
+    ) -> Result<vk::DescriptorPool, VkPlaygroundError> {
         let pool_sizes = [
             vk::DescriptorPoolSize {
                 // transform descriptor pool
@@ -368,7 +462,7 @@ impl VulkanAppTextureMapping {
         unsafe {
             device
                 .create_descriptor_pool(&descriptor_pool_create_info, None)
-                .expect("Failed to create descriptor pool!")
+                .vk_context("vkCreateDescriptorPool", "Failed to create descriptor pool!")
         }
     }
 
@@ -380,7 +474,7 @@ impl VulkanAppTextureMapping {
         texture_image_view: vk::ImageView,
         texture_sampler: vk::Sampler,
         swapchain_images_size: usize,
-    ) -> Vec<vk::DescriptorSet> {
+    ) -> Result<Vec<vk::DescriptorSet>, VkPlaygroundError> {
         let mut layouts: Vec<vk::DescriptorSetLayout> = vec![];
 
         for _ in 0..swapchain_images_size {
@@ -398,7 +492,7 @@ impl VulkanAppTextureMapping {
         let descriptor_sets = unsafe {
             device
                 .allocate_descriptor_sets(&descriptor_set_allocate_info)
-                .expect("Failed to allocate descriptor sets!")
+                .vk_context("vkAllocateDescriptorSets", "Failed to allocate descriptor sets!")?
         };
 
         for (i, &descritptor_set) in descriptor_sets.iter().enumerate() {
@@ -448,10 +542,12 @@ impl VulkanAppTextureMapping {
             }
         }
 
-        descriptor_sets
+        Ok(descriptor_sets)
     }
 
-    fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+    fn create_descriptor_set_layout(
+        device: &ash::Device,
+    ) -> Result<vk::DescriptorSetLayout, VkPlaygroundError> {
         let ubo_layout_bindings = [
             vk::DescriptorSetLayoutBinding {
                 // transform uniform
@@ -482,443 +578,144 @@ impl VulkanAppTextureMapping {
         unsafe {
             device
                 .create_descriptor_set_layout(&ubo_layout_create_info, None)
-                .expect("Failed to create descriptor set layout")
+                .vk_context("vkCreateDescriptorSetLayout", "Failed to create descriptor set layout")
         }
     }
 }
 
 impl VulkanAppTextureMapping {
-    fn create_command_buffers(
-        device: &ash::Device,
-        command_pool: vk::CommandPool,
-        graphics_pipeline: vk::Pipeline,
-        framebuffers: &Vec<vk::Framebuffer>,
-        render_pass: vk::RenderPass,
-        surface_extent: vk::Extent2D,
-        vertex_buffer: vk::Buffer,
-        index_buffer: vk::Buffer,
-        pipeline_layout: vk::PipelineLayout,
-        descriptor_sets: &Vec<vk::DescriptorSet>
-    ) -> Vec<vk::CommandBuffer> {
-        let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
-            s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
-            p_next: ptr::null(),
-            command_buffer_count: framebuffers.len() as u32,
-            command_pool,
-            level: vk::CommandBufferLevel::PRIMARY,
-        };
+    /// Records drawing the quad into the framebuffer of `image_index`.
+    fn record_commands(&self, command_buffer: vk::CommandBuffer, image_index: usize) {
+        let clear_values = [vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: [0.0, 0.0, 0.0, 1.0],
+            },
+        }];
 
-        let command_buffers = unsafe {
-            device
-                .allocate_command_buffers(&command_buffer_allocate_info)
-                .expect("Failed to allocate Command Buffers!")
+        let render_pass_begin_info = vk::RenderPassBeginInfo {
+            s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+            p_next: ptr::null(),
+            render_pass: *self.render_pass,
+            framebuffer: *self.swapchain_framebuffers[image_index],
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: self.swapchain_extent,
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
         };
 
-        for (i, &command_buffer) in command_buffers.iter().enumerate() {
-            let command_buffer_begin_info = vk::CommandBufferBeginInfo {
-                s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
-                p_next: ptr::null(),
-                p_inheritance_info: ptr::null(),
-                flags: vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
-            };
-
-            unsafe {
-                device
-                    .begin_command_buffer(command_buffer, &command_buffer_begin_info)
-                    .expect("Failed to begin recording Command Buffer at beginning!");
-            }
-
-            let clear_values = [vk::ClearValue {
-                color: vk::ClearColorValue {
-                    float32: [0.0, 0.0, 0.0, 1.0],
-                },
-            }];
-
-            let render_pass_begin_info = vk::RenderPassBeginInfo {
-                s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
-                p_next: ptr::null(),
-                render_pass,
-                framebuffer: framebuffers[i],
-                render_area: vk::Rect2D {
-                    offset: vk::Offset2D { x: 0, y: 0 },
-                    extent: surface_extent,
-                },
-                clear_value_count: clear_values.len() as u32,
-                p_clear_values: clear_values.as_ptr(),
-            };
-
-            unsafe {
-                device.cmd_begin_render_pass(
-                    command_buffer,
-                    &render_pass_begin_info,
-                    vk::SubpassContents::INLINE,
-                );
-                device.cmd_bind_pipeline(
-                    command_buffer,
-                    vk::PipelineBindPoint::GRAPHICS,
-                    graphics_pipeline,
-                );
-
-                let vertex_buffers = [vertex_buffer];
-                let offsets = [0_u64];
-                let descriptor_sets_to_bind = [descriptor_sets[i]];
-
-                device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
-                device.cmd_bind_index_buffer(
-                    command_buffer,
-                    index_buffer,
-                    0,
-                    vk::IndexType::UINT32,
-                );
-                device.cmd_bind_descriptor_sets(
-                    command_buffer,
-                    vk::PipelineBindPoint::GRAPHICS,
-                    pipeline_layout,
-                    0,
-                    &descriptor_sets_to_bind,
-                    &[],
-                );
-
-                device.cmd_draw_indexed(command_buffer, RECT_INDICES_DATA.len() as u32, 1, 0, 0, 0);
+        let device: &ash::Device = &self.device;
+        unsafe {
+            device.cmd_begin_render_pass(
+                command_buffer,
+                &render_pass_begin_info,
+                vk::SubpassContents::INLINE,
+            );
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                *self.graphics_pipeline,
+            );
+            pipeline::set_viewport(device, command_buffer, self.swapchain_extent);
+
+            let vertex_buffers = [*self.vertex_buffer];
+            let offsets = [0_u64];
+            let descriptor_sets_to_bind = [self.descriptor_sets[image_index]];
+
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
+            device.cmd_bind_index_buffer(
+                command_buffer,
+                *self.index_buffer,
+                0,
+                vk::IndexType::UINT32,
+            );
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                *self.pipeline_layout,
+                0,
+                &descriptor_sets_to_bind,
+                &[],
+            );
 
-                device.cmd_end_render_pass(command_buffer);
+            device.cmd_draw_indexed(command_buffer, RECT_INDICES_DATA.len() as u32, 1, 0, 0, 0);
 
-                device
-                    .end_command_buffer(command_buffer)
-                    .expect("Failed to record Command Buffer at Ending!");
-            }
+            device.cmd_end_render_pass(command_buffer);
         }
-
-        command_buffers 
     }
 
     fn update_uniform_buffer(&mut self, current_image: usize, _delta_time: f32) {
         let ubos = [self.uniform_transform.clone()];
 
-        let buffer_size = (std::mem::size_of::<UniformBufferObject>() * ubos.len()) as u64;
-
-        unsafe {
-            let data_ptr =
-                self.device
-                    .map_memory(
-                        self.uniform_buffers_memory[current_image],
-                        0,
-                        buffer_size,
-                        vk::MemoryMapFlags::empty(),
-                    )
-                    .expect("Failed to Map Memory") as *mut UniformBufferObject;
-
-            data_ptr.copy_from_nonoverlapping(ubos.as_ptr(), ubos.len());
-
-            self.device
-                .unmap_memory(self.uniform_buffers_memory[current_image]);
-        }
+        self.uniform_buffers_memory[current_image].write(&ubos);
     }
 
     fn create_graphics_pipeline(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
         device: &ash::Device,
         render_pass: vk::RenderPass,
-        swapchain_extent: vk::Extent2D,
+        pipeline_cache: vk::PipelineCache,
         ubo_set_layout: vk::DescriptorSetLayout,
-    ) -> (vk::Pipeline, vk::PipelineLayout) {
-        let vert_shader_module = share::create_shader_module(
-            device,
-            "shaders/texture_map/vert.spv",
-        );
-        let frag_shader_module = share::create_shader_module(
-            device,
-            "shaders/texture_map/frag.spv",
-        );
-
-        let main_function_name = CString::new("main").unwrap(); // the beginning function name in shader code.
-
-        let shader_stages = [
-            vk::PipelineShaderStageCreateInfo {
-                // Vertex Shader
-                s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
-                p_next: ptr::null(),
-                flags: vk::PipelineShaderStageCreateFlags::empty(),
-                module: vert_shader_module,
-                p_name: main_function_name.as_ptr(),
-                p_specialization_info: ptr::null(),
-                stage: vk::ShaderStageFlags::VERTEX,
-            },
-            vk::PipelineShaderStageCreateInfo {
-                // Fragment Shader
-                s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
-                p_next: ptr::null(),
-                flags: vk::PipelineShaderStageCreateFlags::empty(),
-                module: frag_shader_module,
-                p_name: main_function_name.as_ptr(),
-                p_specialization_info: ptr::null(),
-                stage: vk::ShaderStageFlags::FRAGMENT,
-            },
-        ];
-
-        let binding_description = VertexV2::get_binding_description();
-        let attribute_description = VertexV2::get_attribute_descriptions();
-
-        let vertex_input_state_create_info = vk::PipelineVertexInputStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_VERTEX_INPUT_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineVertexInputStateCreateFlags::empty(),
-            vertex_attribute_description_count: attribute_description.len() as u32,
-            p_vertex_attribute_descriptions: attribute_description.as_ptr(),
-            vertex_binding_description_count: binding_description.len() as u32,
-            p_vertex_binding_descriptions: binding_description.as_ptr(),
-        };
-        let vertex_input_assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO,
-            flags: vk::PipelineInputAssemblyStateCreateFlags::empty(),
-            p_next: ptr::null(),
-            primitive_restart_enable: vk::FALSE,
-            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
-        };
-
-        let viewports = [vk::Viewport {
-            x: 0.0,
-            y: 0.0,
-            width: swapchain_extent.width as f32,
-            height: swapchain_extent.height as f32,
-            min_depth: 0.0,
-            max_depth: 1.0,
-        }];
-
-        let scissors = [vk::Rect2D {
-            offset: vk::Offset2D { x: 0, y: 0 },
-            extent: swapchain_extent,
-        }];
-
-        let viewport_state_create_info = vk::PipelineViewportStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_VIEWPORT_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineViewportStateCreateFlags::empty(),
-            scissor_count: scissors.len() as u32,
-            p_scissors: scissors.as_ptr(),
-            viewport_count: viewports.len() as u32,
-            p_viewports: viewports.as_ptr(),
-        };
-
-        let rasterization_statue_create_info = vk::PipelineRasterizationStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineRasterizationStateCreateFlags::empty(),
-            depth_clamp_enable: vk::FALSE,
-            cull_mode: vk::CullModeFlags::BACK,
-            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
-            line_width: 1.0,
-            polygon_mode: vk::PolygonMode::FILL,
-            rasterizer_discard_enable: vk::FALSE,
-            depth_bias_clamp: 0.0,
-            depth_bias_constant_factor: 0.0,
-            depth_bias_enable: vk::FALSE,
-            depth_bias_slope_factor: 0.0,
-        };
-
-        let multisample_state_create_info = vk::PipelineMultisampleStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
-            flags: vk::PipelineMultisampleStateCreateFlags::empty(),
-            p_next: ptr::null(),
-            rasterization_samples: vk::SampleCountFlags::TYPE_1,
-            sample_shading_enable: vk::FALSE,
-            min_sample_shading: 0.0,
-            p_sample_mask: ptr::null(),
-            alpha_to_one_enable: vk::FALSE,
-            alpha_to_coverage_enable: vk::FALSE,
-        };
-
-        let stencil_state = vk::StencilOpState {
-            fail_op: vk::StencilOp::KEEP,
-            pass_op: vk::StencilOp::KEEP,
-            depth_fail_op: vk::StencilOp::KEEP,
-            compare_op: vk::CompareOp::ALWAYS,
-            compare_mask: 0,
-            write_mask: 0,
-            reference: 0,
-        };
-
-        let depth_state_create_info = vk::PipelineDepthStencilStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineDepthStencilStateCreateFlags::empty(),
-            depth_test_enable: vk::FALSE,
-            depth_write_enable: vk::FALSE,
-            depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
-            depth_bounds_test_enable: vk::FALSE,
-            stencil_test_enable: vk::FALSE,
-            front: stencil_state,
-            back: stencil_state,
-            max_depth_bounds: 1.0,
-            min_depth_bounds: 0.0,
-        };
-
-        let color_blend_attachment_states = [vk::PipelineColorBlendAttachmentState {
-            blend_enable: vk::FALSE,
-            color_write_mask: vk::ColorComponentFlags::all(),
-            src_color_blend_factor: vk::BlendFactor::ONE,
-            dst_color_blend_factor: vk::BlendFactor::ZERO,
-            color_blend_op: vk::BlendOp::ADD,
-            src_alpha_blend_factor: vk::BlendFactor::ONE,
-            dst_alpha_blend_factor: vk::BlendFactor::ZERO,
-            alpha_blend_op: vk::BlendOp::ADD,
-        }];
-
-        let color_blend_state = vk::PipelineColorBlendStateCreateInfo {
-            s_type: vk::StructureType::PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineColorBlendStateCreateFlags::empty(),
-            logic_op_enable: vk::FALSE,
-            logic_op: vk::LogicOp::COPY,
-            attachment_count: color_blend_attachment_states.len() as u32,
-            p_attachments: color_blend_attachment_states.as_ptr(),
-            blend_constants: [0.0, 0.0, 0.0, 0.0],
-        };
-
-        let set_layouts = [ubo_set_layout];
-
-        let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo {
-            s_type: vk::StructureType::PIPELINE_LAYOUT_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineLayoutCreateFlags::empty(),
-            set_layout_count: set_layouts.len() as u32,
-            p_set_layouts: set_layouts.as_ptr(),
-            push_constant_range_count: 0,
-            p_push_constant_ranges: ptr::null(),
-        };
-
-        let pipeline_layout = unsafe {
-            device
-                .create_pipeline_layout(&pipeline_layout_create_info, None)
-                .expect("Failed to create pipeline layout!")
-        };
-
-        let graphic_pipeline_create_infos = [vk::GraphicsPipelineCreateInfo {
-            s_type: vk::StructureType::GRAPHICS_PIPELINE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineCreateFlags::empty(),
-            stage_count: shader_stages.len() as u32,
-            p_stages: shader_stages.as_ptr(),
-            p_vertex_input_state: &vertex_input_state_create_info,
-            p_input_assembly_state: &vertex_input_assembly_state_info,
-            p_tessellation_state: ptr::null(),
-            p_viewport_state: &viewport_state_create_info,
-            p_rasterization_state: &rasterization_statue_create_info,
-            p_multisample_state: &multisample_state_create_info,
-            p_depth_stencil_state: &depth_state_create_info,
-            p_color_blend_state: &color_blend_state,
-            p_dynamic_state: ptr::null(),
-            layout: pipeline_layout,
-            render_pass,
-            subpass: 0,
-            base_pipeline_handle: vk::Pipeline::null(),
-            base_pipeline_index: -1,
-        }];
-
-        let graphics_pipelines = unsafe {
-            device
-                .create_graphics_pipelines(
-                    vk::PipelineCache::null(),
-                    &graphic_pipeline_create_infos,
-                    None,
-                )
-                .expect("Failed to create Graphics Pipeline!.")
-        };
-
-        unsafe {
-            device.destroy_shader_module(vert_shader_module, None);
-            device.destroy_shader_module(frag_shader_module, None);
-        }
-
-        (graphics_pipelines[0], pipeline_layout)
-    }
-}
-
-impl Drop for VulkanAppTextureMapping {
-    fn drop(&mut self) {
-        unsafe {
-            for i in 0..MAX_FRAMES_IN_FLIGHT {
-                self.device
-                    .destroy_semaphore(self.image_available_semaphores[i], None);
-                self.device
-                    .destroy_semaphore(self.render_finished_semaphores[i], None);
-                self.device.destroy_fence(self.in_flight_fences[i], None);
-            }
-
-            self.cleanup_swapchain();
-
-            self.device
-                .destroy_descriptor_pool(self.descriptor_pool, None);
-
-            for i in 0..self.uniform_buffers.len() {
-                self.device.destroy_buffer(self.uniform_buffers[i], None);
-                self.device
-                    .free_memory(self.uniform_buffers_memory[i], None);
-            }
-
-            self.device.destroy_buffer(self.index_buffer, None);
-            self.device.free_memory(self.index_buffer_memory, None);
-
-            self.device.destroy_buffer(self.vertex_buffer, None);
-            self.device.free_memory(self.vertex_buffer_memory, None);
-
-            self.device.destroy_sampler(self.texture_sampler, None);
-            self.device
-                .destroy_image_view(self.texture_image_view, None);
-
-            self.device.destroy_image(self.texture_image, None);
-            self.device.free_memory(self.texture_image_memory, None);
-
-            self.device
-                .destroy_descriptor_set_layout(self.ubo_layout, None);
-
-            self.device.destroy_command_pool(self.command_pool, None);
-
-            self.device.destroy_device(None);
-            self.surface_loader.destroy_surface(self.surface, None);
-
-            if VALIDATION.is_enable {
-                self.debug_utils_loader
-                    .destroy_debug_utils_messenger(self.debug_messenger, None);
-            }
-            self.instance.destroy_instance(None);
-        }
+    ) -> Result<(vk::Pipeline, vk::PipelineLayout), VkPlaygroundError> {
+        PipelineBuilder::new()
+            .vertex_shader("shaders/texture_map/vert.spv")
+            .fragment_shader("shaders/texture_map/frag.spv")
+            .vertex_input(
+                &VertexV2::get_binding_description(),
+                &VertexV2::get_attribute_descriptions(),
+            )
+            .dynamic_viewport()
+            .descriptor_set_layouts(&[ubo_set_layout])
+            .pipeline_cache(pipeline_cache)
+            .build(instance, physical_device, device, render_pass)
     }
 }
 
 impl VulkanApp for VulkanAppTextureMapping {
-    fn draw_frame(&mut self, delta_time: f32) {
-        let wait_fences = [self.in_flight_fences[self.current_frame]];
+    fn draw_frame(&mut self, delta_time: f32) -> Result<(), VkPlaygroundError> {
+        let wait_fences = [*self.in_flight_fences[self.current_frame]];
 
         unsafe {
             self.device
                 .wait_for_fences(&wait_fences, true, std::u64::MAX)
-                .expect("Failed to wait for Fence!");
+                .vk_context("vkWaitForFences", "Failed to wait for Fence!")?;
         }
 
         let (image_index, _is_sub_optimal) = unsafe {
             let result = self.swapchain_loader.acquire_next_image(
-                self.swapchain,
+                *self.swapchain,
                 std::u64::MAX,
-                self.image_available_semaphores[self.current_frame],
+                *self.image_available_semaphores[self.current_frame],
                 vk::Fence::null(),
             );
             match result {
                 Ok(image_index) => image_index,
                 Err(vk_result) => match vk_result {
                     vk::Result::ERROR_OUT_OF_DATE_KHR => {
-                        self.recreate_swapchain();
-                        return;
+                        return self.recreate_swapchain();
+                    }
+                    _ => {
+                        return Err(vk_result)
+                            .vk_context(
+                                "vkAcquireNextImageKHR",
+                                "Failed to acquire Swap Chain Image!",
+                            );
                     }
-                    _ => panic!("Failed to acquire Swap Chain Image!"),
                 },
             }
         };
 
         self.update_uniform_buffer(image_index as usize, delta_time);
+        let command_buffer = self.frame_commands.record(self.current_frame, |command_buffer, _| {
+            self.record_commands(command_buffer, image_index as usize);
+            Ok(())
+        })?;
 
-        let wait_semaphores = [self.image_available_semaphores[self.current_frame]];
+        let wait_semaphores = [*self.image_available_semaphores[self.current_frame]];
         let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let signal_semaphores = [self.render_finished_semaphores[self.current_frame]];
+        let signal_semaphores = [*self.render_finished_semaphores[self.current_frame]];
 
         let submit_infos = [vk::SubmitInfo {
             s_type: vk::StructureType::SUBMIT_INFO,
@@ -927,7 +724,7 @@ impl VulkanApp for VulkanAppTextureMapping {
             p_wait_semaphores: wait_semaphores.as_ptr(),
             p_wait_dst_stage_mask: wait_stages.as_ptr(),
             command_buffer_count: 1,
-            p_command_buffers: &self.command_buffers[image_index as usize],
+            p_command_buffers: &command_buffer,
             signal_semaphore_count: signal_semaphores.len() as u32,
             p_signal_semaphores: signal_semaphores.as_ptr(),
         }];
@@ -935,18 +732,42 @@ impl VulkanApp for VulkanAppTextureMapping {
         unsafe {
             self.device
                 .reset_fences(&wait_fences)
-                .expect("Failed to reset Fence!");
+                .vk_context("vkResetFences", "Failed to reset Fence!")?;
 
             self.device
                 .queue_submit(
                     self.graphics_queue,
                     &submit_infos,
-                    self.in_flight_fences[self.current_frame],
+                    *self.in_flight_fences[self.current_frame],
                 )
-                .expect("Failed to execute queue submit.");
+                .vk_context("vkQueueSubmit", "Failed to execute queue submit.")?;
         }
 
-        let swapchains = [self.swapchain];
+        if self.is_capture_requested {
+            self.is_capture_requested = false;
+            // a failed capture shouldn't take the frame down with it
+            let captured_frame = screenshot::check_capture_supported(self.swapchain_usage)
+                .and_then(|()| {
+                    screenshot::capture_swapchain_image(
+                        &self.device,
+                        *self.command_pool,
+                        self.graphics_queue,
+                        &self.allocator,
+                        self.swapchain_images[image_index as usize],
+                        self.swapchain_format,
+                        self.swapchain_extent,
+                    )
+                });
+            self.captured_frame = match captured_frame {
+                Ok(frame) => Some(frame),
+                Err(err) => {
+                    eprintln!("Failed to capture frame: {}", err);
+                    None
+                }
+            };
+        }
+
+        let swapchains = [*self.swapchain];
 
         let present_info = vk::PresentInfoKHR {
             s_type: vk::StructureType::PRESENT_INFO_KHR,
@@ -968,22 +789,27 @@ impl VulkanApp for VulkanAppTextureMapping {
             Ok(_) => self.is_framebuffer_resized,
             Err(vk_result) => match vk_result {
                 vk::Result::ERROR_OUT_OF_DATE_KHR | vk::Result::SUBOPTIMAL_KHR => true,
-                _ => panic!("Failed to execute queue present."),
+                _ => {
+                    return Err(vk_result)
+                        .vk_context("vkQueuePresentKHR", "Failed to execute queue present.");
+                }
             },
         };
         if is_resized {
             self.is_framebuffer_resized = false;
-            self.recreate_swapchain();
+            self.recreate_swapchain()?;
         }
 
         self.current_frame = (self.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
+
+        Ok(())
     }
 
-    fn recreate_swapchain(&mut self) {
+    fn recreate_swapchain(&mut self) -> Result<(), VkPlaygroundError> {
         // parameters -------------
         let surface_suff = SurfaceStuff {
             surface_loader: self.surface_loader.clone(),
-            surface: self.surface,
+            surface: *self.surface,
             screen_width: WINDOW_WIDTH,
             screen_height: WINDOW_HEIGHT,
         };
@@ -992,84 +818,77 @@ impl VulkanApp for VulkanAppTextureMapping {
         unsafe {
             self.device
                 .device_wait_idle()
-                .expect("Failed to wait device idle!")
-        };
+                .vk_context("vkDeviceWaitIdle", "Failed to wait device idle!")?;
+        }
         self.cleanup_swapchain();
 
         let swapchain_stuff = share::create_swapchain(
             &self.instance,
             &self.device,
             self.physical_device,
-            &self.window,
+            self.window_ref(),
             &surface_suff,
             &self.queue_family,
+        )?;
+        self.swapchain = OwnedSwapchain::new(
+            &self.device,
+            &swapchain_stuff.swapchain_loader,
+            swapchain_stuff.swapchain,
         );
+        let is_format_changed = swapchain_stuff.swapchain_format != self.swapchain_format;
         self.swapchain_loader = swapchain_stuff.swapchain_loader;
-        self.swapchain = swapchain_stuff.swapchain;
         self.swapchain_images = swapchain_stuff.swapchain_images;
         self.swapchain_format = swapchain_stuff.swapchain_format;
         self.swapchain_extent = swapchain_stuff.swapchain_extent;
+        self.swapchain_usage = swapchain_stuff.swapchain_usage;
 
-        self.swapchain_imageviews = share::v1::create_image_views(
+        let swapchain_imageviews = share::v1::create_image_views(
             &self.device,
             self.swapchain_format,
             &self.swapchain_images,
-        );
-        self.render_pass = share::v1::create_render_pass(&self.device, self.swapchain_format);
-        let (graphics_pipeline, pipeline_layout) = VulkanAppTextureMapping::create_graphics_pipeline(
-            &self.device,
-            self.render_pass,
-            swapchain_stuff.swapchain_extent,
-            self.ubo_layout,
-        );
-        self.graphics_pipeline = graphics_pipeline;
-        self.pipeline_layout = pipeline_layout;
+        )?;
+        self.swapchain_imageviews = handles::own_all(&self.device, swapchain_imageviews);
+        // the viewport is dynamic, so only a new format (and with it a new
+        // render pass) needs a new pipeline
+        if is_format_changed {
+            let render_pass = share::v1::create_render_pass(&self.device, self.swapchain_format)?;
+            let (graphics_pipeline, pipeline_layout) =
+                VulkanAppTextureMapping::create_graphics_pipeline(
+                    &self.instance,
+                    self.physical_device,
+                    &self.device,
+                    render_pass,
+                    self.pipeline_cache.handle(),
+                    *self.ubo_layout,
+                )?;
+            self.render_pass = Owned::new(&self.device, render_pass);
+            self.graphics_pipeline = Owned::new(&self.device, graphics_pipeline);
+            self.pipeline_layout = Owned::new(&self.device, pipeline_layout);
+        }
 
-        self.swapchain_framebuffers = share::v1::create_framebuffers(
-            &self.device,
-            self.render_pass,
-            &self.swapchain_imageviews,
-            self.swapchain_extent,
-        );
-        self.command_buffers = VulkanAppTextureMapping::create_command_buffers(
+        let swapchain_framebuffers = share::v1::create_framebuffers(
             &self.device,
-            self.command_pool,
-            self.graphics_pipeline,
-            &self.swapchain_framebuffers,
-            self.render_pass,
+            *self.render_pass,
+            &handles::raw_handles(&self.swapchain_imageviews),
             self.swapchain_extent,
-            self.vertex_buffer,
-            self.index_buffer,
-            self.pipeline_layout,
-            &self.descriptor_sets,
-        );
+        )?;
+        self.swapchain_framebuffers = handles::own_all(&self.device, swapchain_framebuffers);
+
+        Ok(())
     }
 
-    fn cleanup_swapchain(&self) {
-        unsafe {
-            self.device
-                .free_command_buffers(self.command_pool, &self.command_buffers);
-            for &framebuffer in self.swapchain_framebuffers.iter() {
-                self.device.destroy_framebuffer(framebuffer, None);
-            }
-            self.device.destroy_pipeline(self.graphics_pipeline, None);
-            self.device
-                .destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.destroy_render_pass(self.render_pass, None);
-            for &image_view in self.swapchain_imageviews.iter() {
-                self.device.destroy_image_view(image_view, None);
-            }
-            self.swapchain_loader
-                .destroy_swapchain(self.swapchain, None);
-        }
+    fn cleanup_swapchain(&mut self) {
+        self.swapchain_framebuffers.clear();
+        self.swapchain_imageviews.clear();
+        self.swapchain.reset();
     }
 
-    fn wait_device_idle(&self) {
+    fn wait_device_idle(&self) -> Result<(), VkPlaygroundError> {
         unsafe {
             self.device
                 .device_wait_idle()
-                .expect("Failed to wait device idle!")
-        };
+                .vk_context("vkDeviceWaitIdle", "Failed to wait device idle!")
+        }
     }
 
     fn resize_framebuffer(&mut self) {
@@ -1077,14 +896,82 @@ impl VulkanApp for VulkanAppTextureMapping {
     }
 
     fn window_ref(&self) -> &winit::window::Window {
-        &self.window
+        self.window
+            .as_ref()
+            .expect("There is no window when rendering headless")
+    }
+
+    fn request_capture(&mut self) {
+        self.is_capture_requested = true;
+    }
+
+    fn take_captured_frame(&mut self) -> Option<CapturedFrame> {
+        self.captured_frame.take()
+    }
+}
+
+impl HeadlessApp for VulkanAppTextureMapping {
+    // there is a single offscreen image, so only one frame is ever in flight
+    fn draw_frame(&mut self, delta_time: f32) -> Result<(), VkPlaygroundError> {
+        headless::begin_frame(&self.device, *self.in_flight_fences[0])?;
+        self.update_uniform_buffer(0, delta_time);
+        let command_buffer = self.frame_commands.record(0, |command_buffer, _| {
+            self.record_commands(command_buffer, 0);
+            Ok(())
+        })?;
+        headless::submit_frame(
+            &self.device,
+            self.graphics_queue,
+            command_buffer,
+            *self.in_flight_fences[0],
+        )
+    }
+
+    fn read_frame(&self) -> Result<Vec<u8>, VkPlaygroundError> {
+        headless::read_offscreen_target(
+            &self.device,
+            *self.command_pool,
+            self.graphics_queue,
+            self.offscreen_target
+                .as_ref()
+                .expect("read_frame is only available when headless"),
+        )
+    }
+
+    fn frame_extent(&self) -> vk::Extent2D {
+        self.swapchain_extent
+    }
+
+    fn wait_device_idle(&self) -> Result<(), VkPlaygroundError> {
+        unsafe {
+            self.device
+                .device_wait_idle()
+                .vk_context("vkDeviceWaitIdle", "Failed to wait device idle!")
+        }
     }
 }
 
 fn main() {
+    args::check_args(&[]).unwrap_or_else(|err| error::exit_with(err));
+    let gpu_options = GpuOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if gpu_options.is_list_requested {
+        gpu::list_gpus(&gpu_options).unwrap_or_else(|err| error::exit_with(err));
+        return;
+    }
+
+    let headless_options = HeadlessOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if let Some(options) = headless_options {
+        let mut vulkan_app =
+            VulkanAppTextureMapping::new_headless().unwrap_or_else(|err| error::exit_with(err));
+        headless::run_headless(&mut vulkan_app, &options)
+            .unwrap_or_else(|err| error::exit_with(err));
+        return;
+    }
 
     let program_proc = ProgramProc::new();   
-    let vulkan_app = VulkanAppTextureMapping::new(&program_proc.event_loop);
+    let vulkan_app =
+        VulkanAppTextureMapping::new(&program_proc.event_loop)
+            .unwrap_or_else(|err| error::exit_with(err));
 
     program_proc.main_loop(vulkan_app);
 }
\ No newline at end of file
@@ -1,12 +1,24 @@
 use vk_playground::{
     utility,
+    utility::allocator::Allocator,
+    utility::args,
     utility::constants::*,
     utility::debug::*,
+    utility::error::{self, VkPlaygroundError, VkResultExt},
+    utility::extensions::ExtensionConfig,
+    utility::frame_commands::FrameCommandPools,
+    utility::gpu::{self, GpuOptions},
+    utility::handles::{
+        self, Owned, OwnedDebugMessenger, OwnedDevice, OwnedInstance, OwnedSurface,
+        OwnedSwapchain,
+    },
+    utility::headless::{self, HeadlessApp, HeadlessOptions, OffscreenTarget},
+    utility::pipeline_cache::PipelineCache,
     utility::share,
+    utility::vkstuff,
 };
 
 use ash::version::DeviceV1_0;
-use ash::version::InstanceV1_0;
 use ash::vk;
 use winit::event::{Event, VirtualKeyCode, ElementState, KeyboardInput, WindowEvent};
 use winit::event_loop::{EventLoop, ControlFlow};
@@ -26,70 +38,82 @@ struct SyncObjects {
 }
 
 struct VulkanApp {
-    window: winit::window::Window,
+    // Fields are dropped in declaration order and the owned handles destroy
+    // themselves, so everything made from the device is declared before the
+    // device, and the device before the instance.
+    image_available_semaphores: Vec<Owned<vk::Semaphore>>,
+    render_finished_sempahores: Vec<Owned<vk::Semaphore>>,
+    in_flight_fences: Vec<Owned<vk::Fence>>,
+    current_frame: usize,
 
-    _entry: ash::Entry,
-    instance: ash::Instance,
-    surface_loader: ash::extensions::khr::Surface,
-    surface: vk::SurfaceKHR,
-    debug_utils_loader: ash::extensions::ext::DebugUtils,
-    debug_messenger: vk::DebugUtilsMessengerEXT,
+    command_pool: Owned<vk::CommandPool>,
+    // recorded again every frame, one pool per frame in flight
+    frame_commands: FrameCommandPools,
 
-    _physical_device: vk::PhysicalDevice,
-    device: ash::Device,
-
-    graphics_queue: vk::Queue,
-    present_queue: vk::Queue,
+    swapchain_framebuffers: Vec<Owned<vk::Framebuffer>>,
+    graphics_pipeline: Owned<vk::Pipeline>,
+    _pipeline_layout: Owned<vk::PipelineLayout>,
+    _pipeline_cache: PipelineCache,
+    render_pass: Owned<vk::RenderPass>,
 
+    _swapchain_imageviews: Vec<Owned<vk::ImageView>>,
     swapchain_loader: ash::extensions::khr::Swapchain,
-    swapchain: vk::SwapchainKHR,
+    swapchain: OwnedSwapchain,
     _swapchain_images: Vec<vk::Image>,
     _swapchain_format: vk::Format,
-    _swapchain_extent: vk::Extent2D,
-    swapchain_imageviews: Vec<vk::ImageView>,
-    swapchain_framebuffers: Vec<vk::Framebuffer>,
-
-    render_pass: vk::RenderPass,
-    pipeline_layout: vk::PipelineLayout,
-    graphics_pipeline: vk::Pipeline,
+    swapchain_extent: vk::Extent2D,
+    offscreen_target: Option<OffscreenTarget>,
 
-    command_pool: vk::CommandPool,
-    command_buffers: Vec<vk::CommandBuffer>,
+    _allocator: Allocator,
+    graphics_queue: vk::Queue,
+    present_queue: vk::Queue,
+    device: OwnedDevice,
+    _physical_device: vk::PhysicalDevice,
 
-    image_available_semaphores: Vec<vk::Semaphore>,
-    render_finished_sempahores: Vec<vk::Semaphore>,
-    in_flight_fences: Vec<vk::Fence>,
-    current_frame: usize,
+    _surface: OwnedSurface,
+    // None when rendering headless, outlives the surface made from it
+    window: Option<winit::window::Window>,
+    _debug_messenger: OwnedDebugMessenger,
+    _instance: OwnedInstance,
+    _entry: ash::Entry,
 }
 
 impl VulkanApp {
-    pub fn new(event_loop: &winit::event_loop::EventLoop<()>) -> VulkanApp {
+    pub fn new(
+        event_loop: &winit::event_loop::EventLoop<()>,
+    ) -> Result<VulkanApp, VkPlaygroundError> {
         
-        let window = utility::window::init_window(event_loop, WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT);
+        let window =
+            utility::window::init_window(event_loop, WINDOW_TITLE, WINDOW_WIDTH, WINDOW_HEIGHT)?;
 
         // init vulkan stuff
-        let entry = ash::Entry::new().unwrap();
+        let entry = vkstuff::vkinstance::load_entry()?;
 
-        let instance = share::create_instance(
-            &entry,
-            WINDOW_TITLE,
-            VALIDATION.is_enable,
-            &VALIDATION.required_validation_layers.to_vec()
-        );
+        let config = ExtensionConfig::for_window(&VALIDATION, &DEVICE_EXTENSIONS);
+        let (instance, mut enabled_extensions) =
+            vkstuff::vkinstance::create_instance(&entry, WINDOW_TITLE, &config)?;
+        let instance = OwnedInstance::new(instance);
 
         let surface_stuff = 
-            share::create_surface(&entry, &instance, &window, WINDOW_WIDTH, WINDOW_HEIGHT);
+            share::create_surface(&entry, &instance, &window, WINDOW_WIDTH, WINDOW_HEIGHT)?;
         let (debug_utils_loader, debug_messenger) =
-            setup_debug_utils(VALIDATION.is_enable, &entry, &instance);
-            let physical_device =
-            share::pick_physical_device(&instance, &surface_stuff, &DEVICE_EXTENSIONS);
-        let (device, family_indices) = share::create_logical_device(
+            setup_debug_utils(enabled_extensions.has_debug_utils(), &entry, &instance)?;
+        let physical_device = vkstuff::vkdevice::pick_physical_device(
+            &instance,
+            Some(&surface_stuff),
+            &config,
+            GpuOptions::from_args()?.selector.as_ref(),
+        )?;
+        config.select_device(&instance, physical_device, &mut enabled_extensions)?;
+        let (device, family_indices) = vkstuff::vkdevice::create_logical_device(
             &instance,
             physical_device,
-            &VALIDATION,
-            &DEVICE_EXTENSIONS,
-            &surface_stuff,
-        );
+            &enabled_extensions,
+            Some(&surface_stuff),
+        )?;
+        let device = OwnedDevice::new(device);
+        // nothing is allocated when windowed
+        let allocator = Allocator::new(&instance, physical_device, &device);
         let graphics_queue =
             unsafe { device.get_device_queue(family_indices.graphics_family.unwrap(), 0) };
         let present_queue =
@@ -101,96 +125,229 @@ impl VulkanApp {
             &window,
             &surface_stuff,
             &family_indices,
-        );
+        )?;
         let swapchain_imageviews = share::v1::create_image_views(
             &device,
             swapchain_stuff.swapchain_format,
             &swapchain_stuff.swapchain_images,
-        );
-        let render_pass = VulkanApp::create_render_pass(&device, swapchain_stuff.swapchain_format);
+        )?;
+        let render_pass = VulkanApp::create_render_pass(
+            &device,
+            swapchain_stuff.swapchain_format,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+        )?;
+        let pipeline_cache = PipelineCache::load(&instance, physical_device, &device)?;
         let (graphics_pipeline, pipeline_layout) = share::v1::create_graphics_pipeline(
+            &instance,
+            physical_device,
             &device,
             render_pass,
-            swapchain_stuff.swapchain_extent,
+            pipeline_cache.handle(),
             "shaders/triangle/vert.spv",
             "shaders/triangle/frag.spv",
-        );
+        )?;
         let swapchain_framebuffers = share::v1::create_framebuffers(
             &device,
             render_pass,
             &swapchain_imageviews,
             swapchain_stuff.swapchain_extent,
-        );
-        let command_pool = share::v1::create_command_pool(&device, &family_indices);
-        let command_buffers = share::v1::create_command_buffers(
+        )?;
+        let command_pool = share::v1::create_command_pool(&device, &family_indices)?;
+        let frame_commands = FrameCommandPools::new(
             &device,
-            command_pool,
-            graphics_pipeline,
-            &swapchain_framebuffers,
-            render_pass,
-            swapchain_stuff.swapchain_extent,
-        );
-        let sync_objects = VulkanApp::create_sync_objects(&device);
+            family_indices.graphics_family.unwrap(),
+            MAX_FRAMES_IN_FLIGHT,
+        )?;
+        let sync_objects = VulkanApp::create_sync_objects(&device)?;
 
         
         
-        VulkanApp {
-            window,
-            _entry: entry,
-            instance,
-            surface: surface_stuff.surface,
-            surface_loader: surface_stuff.surface_loader,
-            debug_utils_loader,
-            debug_messenger,
+        Ok(VulkanApp {
+            image_available_semaphores: handles::own_all(
+                &device,
+                sync_objects.image_available_semaphores,
+            ),
+            render_finished_sempahores: handles::own_all(
+                &device,
+                sync_objects.render_finished_semaphores,
+            ),
+            in_flight_fences: handles::own_all(&device, sync_objects.inflight_fences),
+            current_frame: 0,
 
-            _physical_device: physical_device,
-            device,
+            command_pool: Owned::new(&device, command_pool),
+            frame_commands,
+
+            swapchain_framebuffers: handles::own_all(&device, swapchain_framebuffers),
+            graphics_pipeline: Owned::new(&device, graphics_pipeline),
+            _pipeline_layout: Owned::new(&device, pipeline_layout),
+            _pipeline_cache: pipeline_cache,
+            render_pass: Owned::new(&device, render_pass),
+
+            _swapchain_imageviews: handles::own_all(&device, swapchain_imageviews),
+            swapchain: OwnedSwapchain::new(
+                &device,
+                &swapchain_stuff.swapchain_loader,
+                swapchain_stuff.swapchain,
+            ),
+            swapchain_loader: swapchain_stuff.swapchain_loader,
+            _swapchain_images: swapchain_stuff.swapchain_images,
+            _swapchain_format: swapchain_stuff.swapchain_format,
+            swapchain_extent: swapchain_stuff.swapchain_extent,
+            offscreen_target: None,
 
+            _allocator: allocator,
             graphics_queue,
             present_queue,
+            device,
+            _physical_device: physical_device,
 
-            swapchain_loader: swapchain_stuff.swapchain_loader,
-            swapchain: swapchain_stuff.swapchain,
-            _swapchain_format: swapchain_stuff.swapchain_format,
-            _swapchain_images: swapchain_stuff.swapchain_images,
-            _swapchain_extent: swapchain_stuff.swapchain_extent,
-            swapchain_imageviews,
-            swapchain_framebuffers,
+            _surface: OwnedSurface::new(&surface_stuff.surface_loader, surface_stuff.surface),
+            window: Some(window),
+            _debug_messenger: OwnedDebugMessenger::new(&debug_utils_loader, debug_messenger),
+            _instance: instance,
+            _entry: entry,
+        })
+    }
 
-            pipeline_layout,
-            render_pass,
-            graphics_pipeline,
+    pub fn new_headless() -> Result<VulkanApp, VkPlaygroundError> {
+        let entry = vkstuff::vkinstance::load_entry()?;
 
-            command_pool,
-            command_buffers,
+        let config = ExtensionConfig::for_headless(&VALIDATION);
+        let (instance, mut enabled_extensions) =
+            vkstuff::vkinstance::create_instance(&entry, WINDOW_TITLE, &config)?;
+        let instance = OwnedInstance::new(instance);
+        let (debug_utils_loader, debug_messenger) =
+            setup_debug_utils(enabled_extensions.has_debug_utils(), &entry, &instance)?;
+        let physical_device = vkstuff::vkdevice::pick_physical_device(
+            &instance,
+            None,
+            &config,
+            GpuOptions::from_args()?.selector.as_ref(),
+        )?;
+        config.select_device(&instance, physical_device, &mut enabled_extensions)?;
+        let (device, family_indices) = vkstuff::vkdevice::create_logical_device(
+            &instance,
+            physical_device,
+            &enabled_extensions,
+            None,
+        )?;
+        let device = OwnedDevice::new(device);
+        let allocator = Allocator::new(&instance, physical_device, &device);
+        let graphics_queue =
+            unsafe { device.get_device_queue(family_indices.graphics_family.unwrap(), 0) };
 
-            image_available_semaphores: sync_objects.image_available_semaphores,
-            render_finished_sempahores: sync_objects.render_finished_semaphores,
-            in_flight_fences: sync_objects.inflight_fences,
+        let offscreen_target = headless::create_offscreen_target(
+            &device,
+            vk::Extent2D {
+                width: WINDOW_WIDTH,
+                height: WINDOW_HEIGHT,
+            },
+            &allocator,
+        )?;
+        let offscreen_images = offscreen_target.images();
+        let (offscreen_format, offscreen_extent) = (offscreen_target.format, offscreen_target.extent);
+        let swapchain_imageviews = share::v1::create_image_views(
+            &device,
+            offscreen_format,
+            &offscreen_images,
+        )?;
+        let render_pass = VulkanApp::create_render_pass(
+            &device,
+            offscreen_format,
+            headless::OFFSCREEN_FINAL_LAYOUT,
+        )?;
+        let pipeline_cache = PipelineCache::load(&instance, physical_device, &device)?;
+        let (graphics_pipeline, pipeline_layout) = share::v1::create_graphics_pipeline(
+            &instance,
+            physical_device,
+            &device,
+            render_pass,
+            pipeline_cache.handle(),
+            "shaders/triangle/vert.spv",
+            "shaders/triangle/frag.spv",
+        )?;
+        let swapchain_framebuffers = share::v1::create_framebuffers(
+            &device,
+            render_pass,
+            &swapchain_imageviews,
+            offscreen_extent,
+        )?;
+        let command_pool = share::v1::create_command_pool(&device, &family_indices)?;
+        let frame_commands = FrameCommandPools::new(
+            &device,
+            family_indices.graphics_family.unwrap(),
+            MAX_FRAMES_IN_FLIGHT,
+        )?;
+        let sync_objects = VulkanApp::create_sync_objects(&device)?;
+
+        // loaded but never called, null handles are never destroyed
+        let surface_loader = ash::extensions::khr::Surface::new(&entry, &*instance);
+        let swapchain_loader = ash::extensions::khr::Swapchain::new(&*instance, &*device);
+
+        Ok(VulkanApp {
+            image_available_semaphores: handles::own_all(
+                &device,
+                sync_objects.image_available_semaphores,
+            ),
+            render_finished_sempahores: handles::own_all(
+                &device,
+                sync_objects.render_finished_semaphores,
+            ),
+            in_flight_fences: handles::own_all(&device, sync_objects.inflight_fences),
             current_frame: 0,
-        }
+
+            command_pool: Owned::new(&device, command_pool),
+            frame_commands,
+
+            swapchain_framebuffers: handles::own_all(&device, swapchain_framebuffers),
+            graphics_pipeline: Owned::new(&device, graphics_pipeline),
+            _pipeline_layout: Owned::new(&device, pipeline_layout),
+            _pipeline_cache: pipeline_cache,
+            render_pass: Owned::new(&device, render_pass),
+
+            _swapchain_imageviews: handles::own_all(&device, swapchain_imageviews),
+            swapchain: OwnedSwapchain::new(&device, &swapchain_loader, vk::SwapchainKHR::null()),
+            swapchain_loader,
+            _swapchain_images: offscreen_images,
+            _swapchain_format: offscreen_format,
+            swapchain_extent: offscreen_extent,
+            offscreen_target: Some(offscreen_target),
+
+            _allocator: allocator,
+            graphics_queue,
+            present_queue: graphics_queue,
+            device,
+            _physical_device: physical_device,
+
+            _surface: OwnedSurface::new(&surface_loader, vk::SurfaceKHR::null()),
+            window: None,
+            _debug_messenger: OwnedDebugMessenger::new(&debug_utils_loader, debug_messenger),
+            _instance: instance,
+            _entry: entry,
+        })
     }
 
-    fn draw_frame(&mut self) {
-        let wait_fences = [self.in_flight_fences[self.current_frame]];
+    fn draw_frame(&mut self) -> Result<(), VkPlaygroundError> {
+        let wait_fences = [*self.in_flight_fences[self.current_frame]];
 
         let (image_index, _is_sub_optimal) = unsafe {
             self.device
                 .wait_for_fences(&wait_fences, true, std::u64::MAX)
-                .expect("Failed to wait for fence!");
+                .vk_context("vkWaitForFences", "Failed to wait for fence!")?;
         
             self.swapchain_loader
                 .acquire_next_image(
-                    self.swapchain,
+                    *self.swapchain,
                     std::u64::MAX, 
-                    self.image_available_semaphores[self.current_frame], 
+                    *self.image_available_semaphores[self.current_frame], 
                     vk::Fence::null())
-                .expect("Failed to acquire next image")
+                .vk_context("vkAcquireNextImageKHR", "Failed to acquire next image")?
         };
+        let command_buffer = self.record_frame(self.current_frame, image_index as usize)?;
 
-        let wait_semaphores = [self.image_available_semaphores[self.current_frame]];
+        let wait_semaphores = [*self.image_available_semaphores[self.current_frame]];
         let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-        let signal_semaphores = [self.render_finished_sempahores[self.current_frame]];
+        let signal_semaphores = [*self.render_finished_sempahores[self.current_frame]];
 
         let submit_infos = [vk::SubmitInfo{
             s_type: vk::StructureType::SUBMIT_INFO,
@@ -199,7 +356,7 @@ impl VulkanApp {
             p_wait_semaphores: wait_semaphores.as_ptr(),
             p_wait_dst_stage_mask: wait_stages.as_ptr(),
             command_buffer_count: 1,
-            p_command_buffers: &self.command_buffers[image_index as usize],
+            p_command_buffers: &command_buffer,
             signal_semaphore_count: signal_semaphores.len() as u32,
             p_signal_semaphores: signal_semaphores.as_ptr(),
         }];
@@ -207,17 +364,17 @@ impl VulkanApp {
         unsafe {
             self.device
                 .reset_fences(&wait_fences)
-                .expect("Failed to reset fence!");
+                .vk_context("vkResetFences", "Failed to reset fence!")?;
             
             self.device
                 .queue_submit(
                     self.graphics_queue, 
                     &submit_infos, 
-                    self.in_flight_fences[self.current_frame])
-                .expect("Failed to execute queue submit");
+                    *self.in_flight_fences[self.current_frame])
+                .vk_context("vkQueueSubmit", "Failed to execute queue submit")?;
         }
 
-        let swapchains = [self.swapchain];
+        let swapchains = [*self.swapchain];
 
         let present_info = vk::PresentInfoKHR {
             s_type: vk::StructureType::PRESENT_INFO_KHR,
@@ -233,13 +390,39 @@ impl VulkanApp {
         unsafe {
             self.swapchain_loader
                 .queue_present(self.present_queue, &present_info)
-                .expect("Failed to execute queue present")
-        };
+                .vk_context("vkQueuePresentKHR", "Failed to execute queue present")?;
+        }
+
+        self.current_frame = (self.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
 
-        self.current_frame = (self.current_frame + 1) & MAX_FRAMES_IN_FLIGHT;
+        Ok(())
+    }
+
+    /// Records the command buffer of `frame_index`, drawing into the
+    /// framebuffer of `image_index`.
+    fn record_frame(
+        &self,
+        frame_index: usize,
+        image_index: usize,
+    ) -> Result<vk::CommandBuffer, VkPlaygroundError> {
+        self.frame_commands.record(frame_index, |command_buffer, _| {
+            share::v1::record_command_buffer(
+                &self.device,
+                command_buffer,
+                *self.graphics_pipeline,
+                *self.swapchain_framebuffers[image_index],
+                *self.render_pass,
+                self.swapchain_extent,
+            );
+            Ok(())
+        })
     }
 
-    fn create_render_pass(device: &ash::Device, surface_format: vk::Format) -> vk::RenderPass {
+    fn create_render_pass(
+        device: &ash::Device,
+        surface_format: vk::Format,
+        final_layout: vk::ImageLayout,
+    ) -> Result<vk::RenderPass, VkPlaygroundError> {
         let color_attachment = vk::AttachmentDescription {
             format: surface_format,
             flags: vk::AttachmentDescriptionFlags::empty(),
@@ -249,7 +432,7 @@ impl VulkanApp {
             stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
             stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
             initial_layout: vk::ImageLayout::UNDEFINED,
-            final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+            final_layout,
         };
 
         let color_attachment_ref = vk::AttachmentReference {
@@ -297,11 +480,11 @@ impl VulkanApp {
         unsafe {
             device
                 .create_render_pass(&renderpass_create_info, None)
-                .expect("Failed to create render pass!")
+                .vk_context("vkCreateRenderPass", "Failed to create render pass!")
         }
     }
 
-    fn create_sync_objects(device: &ash::Device) -> SyncObjects {
+    fn create_sync_objects(device: &ash::Device) -> Result<SyncObjects, VkPlaygroundError> {
         let mut sync_objects = SyncObjects {
             image_available_semaphores: vec![],
             render_finished_semaphores: vec![],
@@ -324,13 +507,13 @@ impl VulkanApp {
             unsafe {
                 let image_available_semaphore = device
                     .create_semaphore(&semaphore_create_info, None)
-                    .expect("Failed to create Semaphore Object!");
+                    .vk_context("vkCreateSemaphore", "Failed to create Semaphore Object!")?;
                 let render_finished_semaphore = device
                     .create_semaphore(&semaphore_create_info, None)
-                    .expect("Failed to create Semaphore Object!");
+                    .vk_context("vkCreateSemaphore", "Failed to create Semaphore Object!")?;
                 let inflight_fence = device
                     .create_fence(&fence_create_info, None)
-                    .expect("Failed to create Fence Object!");
+                    .vk_context("vkCreateFence", "Failed to create Fence Object!")?;
 
                 sync_objects
                     .image_available_semaphores
@@ -341,49 +524,10 @@ impl VulkanApp {
                 sync_objects.inflight_fences.push(inflight_fence);
             }
         }
-        sync_objects
+        Ok(sync_objects)
     }
 }
 
-impl Drop for VulkanApp {
-    fn drop(&mut self) {
-        unsafe {
-            for i in 0..MAX_FRAMES_IN_FLIGHT {
-                self.device
-                    .destroy_semaphore(self.image_available_semaphores[i], None);
-                self.device
-                    .destroy_semaphore(self.render_finished_sempahores[i], None);
-                self.device.destroy_fence(self.in_flight_fences[i], None);
-            }
-
-            self.device.destroy_command_pool(self.command_pool, None);
-
-            for &framebuffer in self.swapchain_framebuffers.iter() {
-                self.device.destroy_framebuffer(framebuffer, None);
-            }
-
-            self.device.destroy_pipeline(self.graphics_pipeline, None);
-            self.device
-                .destroy_pipeline_layout(self.pipeline_layout, None);
-            self.device.destroy_render_pass(self.render_pass, None);
-
-            for &imageview in self.swapchain_imageviews.iter() {
-                self.device.destroy_image_view(imageview, None);
-            }
-
-            self.swapchain_loader
-                .destroy_swapchain(self.swapchain, None);
-            self.device.destroy_device(None);
-            self.surface_loader.destroy_surface(self.surface, None);
-
-            if VALIDATION.is_enable {
-                self.debug_utils_loader
-                    .destroy_debug_utils_messenger(self.debug_messenger, None);
-            }
-            self.instance.destroy_instance(None);
-        }
-    }
-}
 impl VulkanApp {
 
     pub fn main_loop(mut self, event_loop: EventLoop<()>) {
@@ -420,16 +564,20 @@ impl VulkanApp {
                     }
                 },
                 | Event::MainEventsCleared => {
-                    self.window.request_redraw();
+                    if let Some(window) = &self.window {
+                        window.request_redraw();
+                    }
                 },
                 | Event::RedrawRequested(_window_id) => {
-                    self.draw_frame();
+                    if let Err(err) = self.draw_frame() {
+                        eprintln!("Failed to draw a frame: {}", err);
+                        *control_flow = ControlFlow::Exit
+                    }
                 },
                 | Event::LoopDestroyed => {
-                    unsafe {
-                        self.device.device_wait_idle()
-                            .expect("Failed to wait device idle!")
-                    };
+                    if let Err(err) = HeadlessApp::wait_device_idle(&self) {
+                        eprintln!("{}", err);
+                    }
                 },
                 _ => (),
             }
@@ -439,10 +587,62 @@ impl VulkanApp {
 
 }
 
+impl HeadlessApp for VulkanApp {
+    // the triangle doesn't animate, so the delta time is ignored
+    // there is a single offscreen image, so only one frame is ever in flight
+    fn draw_frame(&mut self, _delta_time: f32) -> Result<(), VkPlaygroundError> {
+        headless::begin_frame(&self.device, *self.in_flight_fences[0])?;
+        let command_buffer = self.record_frame(0, 0)?;
+        headless::submit_frame(
+            &self.device,
+            self.graphics_queue,
+            command_buffer,
+            *self.in_flight_fences[0],
+        )
+    }
+
+    fn read_frame(&self) -> Result<Vec<u8>, VkPlaygroundError> {
+        headless::read_offscreen_target(
+            &self.device,
+            *self.command_pool,
+            self.graphics_queue,
+            self.offscreen_target
+                .as_ref()
+                .expect("read_frame is only available when headless"),
+        )
+    }
+
+    fn frame_extent(&self) -> vk::Extent2D {
+        self.swapchain_extent
+    }
+
+    fn wait_device_idle(&self) -> Result<(), VkPlaygroundError> {
+        unsafe {
+            self.device
+                .device_wait_idle()
+                .vk_context("vkDeviceWaitIdle", "Failed to wait device idle!")
+        }
+    }
+}
+
 fn main() {
+    args::check_args(&[]).unwrap_or_else(|err| error::exit_with(err));
+    let gpu_options = GpuOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if gpu_options.is_list_requested {
+        gpu::list_gpus(&gpu_options).unwrap_or_else(|err| error::exit_with(err));
+        return;
+    }
+
+    let headless_options = HeadlessOptions::from_args().unwrap_or_else(|err| error::exit_with(err));
+    if let Some(options) = headless_options {
+        let mut vulkan_app = VulkanApp::new_headless().unwrap_or_else(|err| error::exit_with(err));
+        headless::run_headless(&mut vulkan_app, &options)
+            .unwrap_or_else(|err| error::exit_with(err));
+        return;
+    }
 
     let event_loop = EventLoop::new();    
-    let vulkan_app= VulkanApp::new(&event_loop);
+    let vulkan_app= VulkanApp::new(&event_loop).unwrap_or_else(|err| error::exit_with(err));
 
     vulkan_app.main_loop(event_loop);
 }
@@ -0,0 +1,550 @@
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::handles;
+
+// Sub-allocating buffers and images out of a few large vk::DeviceMemory
+// blocks instead of one allocation per resource, since drivers only promise
+// maxMemoryAllocationCount (often 4096) allocations in total.
+//
+// Each memory type gets its own list of blocks. A block keeps its used ranges
+// sorted by offset and new allocations go into the first gap that fits. Host
+// visible blocks stay mapped for their whole life, so allocations from them
+// can be written through `Allocation::mapped_ptr` without map/unmap calls.
+//
+// An `Allocation` returns its range to the block when it's dropped, and the
+// blocks themselves are freed once the `Allocator` and every allocation made
+// from it are gone. That has to happen before the device is destroyed.
+
+const DEFAULT_BLOCK_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+// don't let a single block take more than this fraction of a small heap
+const MAX_HEAP_FRACTION: vk::DeviceSize = 8;
+
+/// Whether a resource is laid out linearly in memory (buffers and linear
+/// images) or not (optimal tiling images). Neighbours of different kinds
+/// must sit on separate `bufferImageGranularity` pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    Linear,
+    Optimal,
+}
+
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+
+    memory_type_index: u32,
+    block_index: usize,
+    // null unless the block is host visible
+    mapped_ptr: *mut u8,
+    // writes and reads go through the mapping without flushes or
+    // invalidates, which is only safe for coherent memory
+    is_host_coherent: bool,
+    state: Arc<Mutex<AllocatorState>>,
+}
+
+// the mapped pointer is only ever used for plain copies
+unsafe impl Send for Allocation {}
+
+impl Allocation {
+    /// Host pointer to the start of the allocation, `None` for memory that
+    /// isn't host visible.
+    pub fn mapped_ptr(&self) -> Option<*mut u8> {
+        if self.mapped_ptr.is_null() {
+            None
+        } else {
+            Some(self.mapped_ptr)
+        }
+    }
+
+    /// Copies `data` to the start of a host visible allocation. The memory
+    /// must also be host coherent, since nothing is flushed.
+    pub fn write<T>(&self, data: &[T]) {
+        self.write_at(0, data);
+    }
+
+    /// Like `write`, starting `offset` bytes into the allocation.
+    pub fn write_at<T>(&self, offset: vk::DeviceSize, data: &[T]) {
+        let byte_count = std::mem::size_of_val(data);
+        assert!(
+            offset + byte_count as vk::DeviceSize <= self.size,
+            "Writing {} bytes at offset {} into a {} byte allocation",
+            byte_count,
+            offset,
+            self.size
+        );
+
+        let data_ptr = self.mapped_ptr().expect("Allocation is not host visible");
+        assert!(self.is_host_coherent, "Writing to memory that isn't host coherent");
+        unsafe {
+            data_ptr
+                .add(offset as usize)
+                .copy_from_nonoverlapping(data.as_ptr() as *const u8, byte_count);
+        }
+    }
+
+    /// Copies the start of a host visible, host coherent allocation into
+    /// `data`.
+    pub fn read(&self, data: &mut [u8]) {
+        assert!(
+            data.len() as vk::DeviceSize <= self.size,
+            "Reading {} bytes from a {} byte allocation",
+            data.len(),
+            self.size
+        );
+
+        let data_ptr = self.mapped_ptr().expect("Allocation is not host visible");
+        assert!(self.is_host_coherent, "Reading from memory that isn't host coherent");
+        unsafe {
+            data_ptr.copy_to_nonoverlapping(data.as_mut_ptr(), data.len());
+        }
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().free(
+            self.memory_type_index,
+            self.block_index,
+            self.offset,
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct UsedRange {
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    kind: ResourceKind,
+}
+
+struct MemoryBlock {
+    memory: vk::DeviceMemory,
+    size: vk::DeviceSize,
+    mapped_ptr: *mut u8,
+    // sorted by offset, never overlapping
+    used_ranges: Vec<UsedRange>,
+}
+
+impl MemoryBlock {
+    // first fit: returns the offset and the index to insert the range at
+    fn find_space(
+        &self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+        kind: ResourceKind,
+        granularity: vk::DeviceSize,
+    ) -> Option<(vk::DeviceSize, usize)> {
+        for insert_index in 0..=self.used_ranges.len() {
+            let previous = insert_index
+                .checked_sub(1)
+                .map(|index| self.used_ranges[index]);
+            let next = self.used_ranges.get(insert_index);
+
+            let gap_start = previous.map_or(0, |range| range.offset + range.size);
+            let gap_end = next.map_or(self.size, |range| range.offset);
+
+            let mut offset = align_up(gap_start, alignment);
+            if let Some(previous) = previous {
+                if previous.kind != kind
+                    && is_on_same_page(previous.offset + previous.size - 1, offset, granularity)
+                {
+                    offset = align_up(offset, granularity);
+                }
+            }
+
+            let end = offset + size;
+            if end > gap_end {
+                continue;
+            }
+            if let Some(next) = next {
+                if next.kind != kind && is_on_same_page(end - 1, next.offset, granularity) {
+                    continue;
+                }
+            }
+
+            return Some((offset, insert_index));
+        }
+
+        None
+    }
+}
+
+struct MemoryTypePool {
+    blocks: Vec<MemoryBlock>,
+}
+
+struct AllocatorState {
+    device: ash::Device,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    buffer_image_granularity: vk::DeviceSize,
+    // indexed by memory type
+    pools: Vec<MemoryTypePool>,
+}
+
+// the mapped pointers in the blocks are only handed out through allocations
+unsafe impl Send for AllocatorState {}
+
+/// Hands out `Allocation`s. Cloning it is cheap, every clone shares the
+/// same blocks.
+#[derive(Clone)]
+pub struct Allocator {
+    state: Arc<Mutex<AllocatorState>>,
+}
+
+impl Allocator {
+    pub fn new(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &ash::Device,
+    ) -> Allocator {
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let device_properties = unsafe { instance.get_physical_device_properties(physical_device) };
+
+        let pools = (0..memory_properties.memory_type_count)
+            .map(|_| MemoryTypePool { blocks: vec![] })
+            .collect();
+
+        let state = AllocatorState {
+            device: device.clone(),
+            memory_properties,
+            buffer_image_granularity: device_properties.limits.buffer_image_granularity.max(1),
+            pools,
+        };
+
+        Allocator {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn memory_properties(&self) -> vk::PhysicalDeviceMemoryProperties {
+        self.state.lock().unwrap().memory_properties
+    }
+
+    /// Allocates and binds memory for `buffer`.
+    pub fn allocate_buffer_memory(
+        &self,
+        device: &ash::Device,
+        buffer: vk::Buffer,
+        required_memory_properties: vk::MemoryPropertyFlags,
+    ) -> Result<Allocation, VkPlaygroundError> {
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let allocation = self.allocate(
+            requirements,
+            required_memory_properties,
+            ResourceKind::Linear,
+        )?;
+
+        unsafe {
+            device
+                .bind_buffer_memory(buffer, allocation.memory, allocation.offset)
+                .vk_context("vkBindBufferMemory", "Failed to bind Buffer")?;
+        }
+
+        Ok(allocation)
+    }
+
+    /// Allocates and binds memory for `image`, `tiling` must be the tiling
+    /// the image was created with.
+    pub fn allocate_image_memory(
+        &self,
+        device: &ash::Device,
+        image: vk::Image,
+        tiling: vk::ImageTiling,
+        required_memory_properties: vk::MemoryPropertyFlags,
+    ) -> Result<Allocation, VkPlaygroundError> {
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let kind = if tiling == vk::ImageTiling::LINEAR {
+            ResourceKind::Linear
+        } else {
+            ResourceKind::Optimal
+        };
+        let allocation = self.allocate(requirements, required_memory_properties, kind)?;
+
+        unsafe {
+            device
+                .bind_image_memory(image, allocation.memory, allocation.offset)
+                .vk_context("vkBindImageMemory", "Failed to bind image memory!")?;
+        }
+
+        Ok(allocation)
+    }
+
+    pub fn allocate(
+        &self,
+        requirements: vk::MemoryRequirements,
+        required_memory_properties: vk::MemoryPropertyFlags,
+        kind: ResourceKind,
+    ) -> Result<Allocation, VkPlaygroundError> {
+        let mut state = self.state.lock().unwrap();
+        let (memory_type_index, block_index, offset) =
+            state.allocate(requirements, required_memory_properties, kind)?;
+
+        let is_host_coherent = state.memory_properties.memory_types[memory_type_index as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_COHERENT);
+        let block = &state.pools[memory_type_index as usize].blocks[block_index];
+        let mapped_ptr = if block.mapped_ptr.is_null() {
+            ptr::null_mut()
+        } else {
+            unsafe { block.mapped_ptr.add(offset as usize) }
+        };
+
+        Ok(Allocation {
+            memory: block.memory,
+            offset,
+            size: requirements.size,
+            memory_type_index,
+            block_index,
+            mapped_ptr,
+            is_host_coherent,
+            state: self.state.clone(),
+        })
+    }
+}
+
+impl AllocatorState {
+    // returns the memory type, block and offset of the new range
+    fn allocate(
+        &mut self,
+        requirements: vk::MemoryRequirements,
+        required_memory_properties: vk::MemoryPropertyFlags,
+        kind: ResourceKind,
+    ) -> Result<(u32, usize, vk::DeviceSize), VkPlaygroundError> {
+        let memory_type_index = crate::utility::share::find_memory_type(
+            requirements.memory_type_bits,
+            required_memory_properties,
+            &self.memory_properties,
+        )?;
+        let granularity = self.buffer_image_granularity;
+        let alignment = requirements.alignment.max(1);
+
+        let pool = &self.pools[memory_type_index as usize];
+        let found = pool.blocks.iter().enumerate().find_map(|(block_index, block)| {
+            block
+                .find_space(requirements.size, alignment, kind, granularity)
+                .map(|(offset, insert_index)| (block_index, offset, insert_index))
+        });
+
+        let (block_index, offset, insert_index) = match found {
+            Some(found) => found,
+            None => {
+                let block_size = self
+                    .preferred_block_size(memory_type_index)
+                    .max(requirements.size);
+                let block = allocate_block(
+                    &self.device,
+                    memory_type_index,
+                    block_size,
+                    &self.memory_properties,
+                )?;
+
+                let pool = &mut self.pools[memory_type_index as usize];
+                pool.blocks.push(block);
+                (pool.blocks.len() - 1, 0, 0)
+            }
+        };
+
+        let block = &mut self.pools[memory_type_index as usize].blocks[block_index];
+        block.used_ranges.insert(
+            insert_index,
+            UsedRange {
+                offset,
+                size: requirements.size,
+                kind,
+            },
+        );
+
+        Ok((memory_type_index, block_index, offset))
+    }
+
+    // Empty blocks are kept around for later allocations, they're only
+    // released when the whole allocator goes away.
+    fn free(&mut self, memory_type_index: u32, block_index: usize, offset: vk::DeviceSize) {
+        let block = &mut self.pools[memory_type_index as usize].blocks[block_index];
+        let range_index = block
+            .used_ranges
+            .iter()
+            .position(|range| range.offset == offset)
+            .expect("Freeing an allocation that isn't live");
+
+        block.used_ranges.remove(range_index);
+    }
+
+    fn preferred_block_size(&self, memory_type_index: u32) -> vk::DeviceSize {
+        let heap_index = self.memory_properties.memory_types[memory_type_index as usize].heap_index;
+        let heap_size = self.memory_properties.memory_heaps[heap_index as usize].size;
+
+        DEFAULT_BLOCK_SIZE.min(heap_size / MAX_HEAP_FRACTION)
+    }
+}
+
+impl Drop for AllocatorState {
+    fn drop(&mut self) {
+        for pool in self.pools.iter_mut() {
+            for block in pool.blocks.drain(..) {
+                handles::untrack_object(&self.device, block.memory);
+                unsafe {
+                    if !block.mapped_ptr.is_null() {
+                        self.device.unmap_memory(block.memory);
+                    }
+                    self.device.free_memory(block.memory, None);
+                }
+            }
+        }
+    }
+}
+
+fn allocate_block(
+    device: &ash::Device,
+    memory_type_index: u32,
+    size: vk::DeviceSize,
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+) -> Result<MemoryBlock, VkPlaygroundError> {
+    let allocate_info = vk::MemoryAllocateInfo {
+        s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
+        p_next: ptr::null(),
+        allocation_size: size,
+        memory_type_index,
+    };
+
+    let memory = unsafe {
+        device
+            .allocate_memory(&allocate_info, None)
+            .vk_context("vkAllocateMemory", "Failed to allocate memory block!")?
+    };
+    handles::track_object(device, memory);
+
+    let is_host_visible = memory_properties.memory_types[memory_type_index as usize]
+        .property_flags
+        .contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+    let mapped_ptr = if is_host_visible {
+        let mapped = unsafe {
+            device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+        };
+        match mapped {
+            Ok(mapped_ptr) => mapped_ptr as *mut u8,
+            Err(result) => {
+                handles::untrack_object(device, memory);
+                unsafe {
+                    device.free_memory(memory, None);
+                }
+                return Err(result).vk_context("vkMapMemory", "Failed to Map Memory");
+            }
+        }
+    } else {
+        ptr::null_mut()
+    };
+
+    Ok(MemoryBlock {
+        memory,
+        size,
+        mapped_ptr,
+        used_ranges: vec![],
+    })
+}
+
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    value.div_ceil(alignment) * alignment
+}
+
+// whether two byte offsets fall on the same bufferImageGranularity page
+fn is_on_same_page(
+    offset_a: vk::DeviceSize,
+    offset_b: vk::DeviceSize,
+    granularity: vk::DeviceSize,
+) -> bool {
+    offset_a / granularity == offset_b / granularity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRANULARITY: vk::DeviceSize = 1024;
+
+    fn block(
+        size: vk::DeviceSize,
+        used_ranges: &[(vk::DeviceSize, vk::DeviceSize, ResourceKind)],
+    ) -> MemoryBlock {
+        MemoryBlock {
+            memory: vk::DeviceMemory::null(),
+            size,
+            mapped_ptr: ptr::null_mut(),
+            used_ranges: used_ranges
+                .iter()
+                .map(|&(offset, size, kind)| UsedRange { offset, size, kind })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn align_up_rounds_to_the_next_multiple() {
+        assert_eq!(align_up(0, 256), 0);
+        assert_eq!(align_up(1, 256), 256);
+        assert_eq!(align_up(256, 256), 256);
+        assert_eq!(align_up(257, 4), 260);
+        assert_eq!(align_up(7, 1), 7);
+    }
+
+    #[test]
+    fn empty_block_starts_at_zero() {
+        let block = block(4096, &[]);
+
+        let found = block.find_space(100, 256, ResourceKind::Linear, GRANULARITY);
+
+        assert_eq!(found, Some((0, 0)));
+    }
+
+    #[test]
+    fn offsets_are_aligned() {
+        let block = block(4096, &[(0, 100, ResourceKind::Linear)]);
+
+        let found = block.find_space(100, 256, ResourceKind::Linear, GRANULARITY);
+
+        assert_eq!(found, Some((256, 1)));
+    }
+
+    #[test]
+    fn first_gap_that_fits_is_used() {
+        let block = block(
+            4096,
+            &[(0, 100, ResourceKind::Linear), (300, 100, ResourceKind::Linear)],
+        );
+
+        let small = block.find_space(150, 1, ResourceKind::Linear, GRANULARITY);
+        let large = block.find_space(250, 1, ResourceKind::Linear, GRANULARITY);
+        let too_large = block.find_space(4000, 1, ResourceKind::Linear, GRANULARITY);
+
+        assert_eq!(small, Some((100, 1)));
+        assert_eq!(large, Some((400, 2)));
+        assert_eq!(too_large, None);
+    }
+
+    #[test]
+    fn different_kinds_after_each_other_skip_to_the_next_page() {
+        let block = block(4096, &[(0, 100, ResourceKind::Linear)]);
+
+        let same_kind = block.find_space(100, 1, ResourceKind::Linear, GRANULARITY);
+        let other_kind = block.find_space(100, 1, ResourceKind::Optimal, GRANULARITY);
+
+        assert_eq!(same_kind, Some((100, 1)));
+        assert_eq!(other_kind, Some((GRANULARITY, 1)));
+    }
+
+    #[test]
+    fn gap_sharing_a_page_with_another_kind_is_skipped() {
+        let block = block(4096, &[(512, 88, ResourceKind::Optimal)]);
+
+        let found = block.find_space(100, 1, ResourceKind::Linear, GRANULARITY);
+
+        // both the gap before the image and the space right after it share its page
+        assert_eq!(found, Some((GRANULARITY, 1)));
+    }
+}
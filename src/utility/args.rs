@@ -0,0 +1,103 @@
+use std::str::FromStr;
+
+use crate::utility::error::VkPlaygroundError;
+use crate::utility::gpu::GpuOptions;
+use crate::utility::headless::HeadlessOptions;
+use crate::utility::recording::RecordingOptions;
+
+// Command line helpers shared by the option parsers. Each parser, such as
+// GpuOptions or HeadlessOptions, reads the whole command line and skips what
+// isn't its own, so a mistyped flag would go unnoticed by all of them.
+// `check_args` catches those, given the flags of the program's own options on
+// top of the ones every demo takes, e.g.
+//
+//     args::check_args(&[HotReloadOptions::FLAGS]).unwrap_or_else(|err| error::exit_with(err));
+
+/// Fails with `Usage` when the program was passed a flag that neither its
+/// own options nor the shared ones know.
+pub fn check_args(program_flags: &[&[&str]]) -> Result<(), VkPlaygroundError> {
+    check_flags(std::env::args().skip(1), program_flags)
+}
+
+/// The value after a flag, failing with `usage` when it's missing or
+/// doesn't parse.
+pub fn parse_value<T: FromStr>(value: Option<String>, usage: &str) -> Result<T, VkPlaygroundError> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| VkPlaygroundError::Usage {
+            context: usage.to_owned(),
+        })
+}
+
+// anything starting with "--" is a flag, values never do
+fn check_flags<I: Iterator<Item = String>>(
+    args: I,
+    program_flags: &[&[&str]],
+) -> Result<(), VkPlaygroundError> {
+    let shared_flags = [GpuOptions::FLAGS, HeadlessOptions::FLAGS, RecordingOptions::FLAGS];
+
+    for arg in args.filter(|arg| arg.starts_with("--")) {
+        let is_known = shared_flags
+            .iter()
+            .chain(program_flags.iter())
+            .any(|flags| flags.contains(&arg.as_str()));
+        if !is_known {
+            return Err(VkPlaygroundError::Usage {
+                context: format!("Unknown option {}", arg),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn shared_and_program_flags_are_accepted() {
+        let result = check_flags(
+            args(&["--gpu", "1", "--headless", "--delta-time", "-0.5", "--hot-reload"]),
+            &[&["--hot-reload"]],
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn unknown_flag_is_rejected() {
+        let result = check_flags(args(&["--headless", "--frame", "2"]), &[]);
+
+        assert!(matches!(
+            result,
+            Err(VkPlaygroundError::Usage { context }) if context == "Unknown option --frame"
+        ));
+    }
+
+    #[test]
+    fn value_is_parsed() {
+        let value: u32 = parse_value(Some("12".to_owned()), "usage").unwrap();
+
+        assert_eq!(value, 12);
+    }
+
+    #[test]
+    fn missing_or_invalid_value_is_a_usage_error() {
+        let missing: Result<u32, _> = parse_value(None, "--frames expects a frame count");
+        let invalid: Result<u32, _> = parse_value(Some("ten".to_owned()), "usage");
+
+        assert!(matches!(
+            missing,
+            Err(VkPlaygroundError::Usage { context }) if context == "--frames expects a frame count"
+        ));
+        assert!(matches!(invalid, Err(VkPlaygroundError::Usage { .. })));
+    }
+}
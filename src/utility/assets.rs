@@ -0,0 +1,764 @@
+use ash::vk;
+use cgmath::{Matrix4, Vector3};
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::utility::allocator::{Allocation, Allocator};
+use crate::utility::compute::Access;
+use crate::utility::error::VkPlaygroundError;
+use crate::utility::handles::Owned;
+use crate::utility::obj::{self, DrawRange, Material, ObjModel};
+use crate::utility::structures::MeshVertex;
+use crate::utility::upload::{UploadId, UploadQueues, Uploader};
+use crate::utility::vkstuff;
+
+// Textures and models streamed in while the app keeps drawing. Decoding a
+// JPEG or parsing an OBJ takes long enough to drop frames, so it happens on
+// worker threads; the main thread only uploads what they hand back, up to a
+// byte budget per `update`, e.g.
+//
+//     let mut assets = AssetLoader::new(device, allocator, context.upload_queues())?;
+//     let texture = assets.load_texture("resources/textures/container.jpg");
+//     ...
+//     // once per frame
+//     assets.update()?;
+//     let view = assets.texture_view(texture);
+//
+// Until a texture is ready `texture_view` is a 1x1 grey placeholder, and
+// `model_or_proxy` draws a model's bounding box, once it is known, with a
+// unit cube. The textures of a model's materials are requested as soon as
+// the model is decoded, see `model_materials`. An asset only becomes ready
+// once its copies have finished on the GPU, so the frame that first draws it
+// never waits for them. `finish` blocks until everything requested is in,
+// for when a frame has to be complete, as in headless runs.
+
+const MAX_WORKER_COUNT: usize = 4;
+const DEFAULT_UPLOAD_BUDGET: vk::DeviceSize = 16 * 1024 * 1024;
+const PLACEHOLDER_PIXEL: [u8; 4] = [128, 128, 128, 255];
+// the corners of each face of the proxy cube, counter-clockwise seen from
+// outside, where corner i is at +0.5 on x, y and z for bits 0, 1 and 2
+const PROXY_FACES: [[u32; 4]; 6] = [
+    [0, 2, 3, 1],
+    [4, 5, 7, 6],
+    [0, 4, 6, 2],
+    [1, 3, 7, 5],
+    [0, 1, 5, 4],
+    [2, 6, 7, 3],
+];
+
+/// A texture requested with `AssetLoader::load_texture`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureHandle(usize);
+
+/// A model requested with `AssetLoader::load_model`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelHandle(usize);
+
+/// An axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Bounds {
+    pub fn of_vertices(vertices: &[MeshVertex]) -> Bounds {
+        let mut bounds = Bounds {
+            min: [f32::MAX; 3],
+            max: [f32::MIN; 3],
+        };
+        for vertex in vertices.iter() {
+            for axis in 0..3 {
+                bounds.min[axis] = bounds.min[axis].min(vertex.pos[axis]);
+                bounds.max[axis] = bounds.max[axis].max(vertex.pos[axis]);
+            }
+        }
+
+        bounds
+    }
+
+    /// Maps the proxy cube, from -0.5 to 0.5 on each axis, onto the box.
+    pub fn proxy_transform(&self) -> Matrix4<f32> {
+        let center = Vector3::new(
+            (self.min[0] + self.max[0]) * 0.5,
+            (self.min[1] + self.max[1]) * 0.5,
+            (self.min[2] + self.max[2]) * 0.5,
+        );
+
+        Matrix4::from_translation(center)
+            * Matrix4::from_nonuniform_scale(
+                self.max[0] - self.min[0],
+                self.max[1] - self.min[1],
+                self.max[2] - self.min[2],
+            )
+    }
+}
+
+/// How far along the requested assets are, from `AssetLoader::progress`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub requested: usize,
+    pub ready: usize,
+    pub failed: usize,
+}
+
+impl LoadProgress {
+    pub fn is_finished(&self) -> bool {
+        self.ready + self.failed == self.requested
+    }
+
+    /// The share of requested assets that are done, failed ones included.
+    pub fn fraction(&self) -> f32 {
+        if self.requested == 0 {
+            1.0
+        } else {
+            (self.ready + self.failed) as f32 / self.requested as f32
+        }
+    }
+}
+
+/// A material of a model along with its textures, each loading on its own.
+#[derive(Debug, Clone)]
+pub struct ModelMaterial {
+    pub material: Material,
+    pub diffuse_texture: Option<TextureHandle>,
+    pub specular_texture: Option<TextureHandle>,
+    pub normal_texture: Option<TextureHandle>,
+}
+
+/// What to draw for a model, see `AssetLoader::model_or_proxy`.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelDraw {
+    pub vertex_buffer: vk::Buffer,
+    pub index_buffer: vk::Buffer,
+    pub index_count: u32,
+    /// Applied before the model's own transform; the identity for the
+    /// model itself.
+    pub transform: Matrix4<f32>,
+    pub is_proxy: bool,
+}
+
+struct Texture {
+    view: Owned<vk::ImageView>,
+    _image: Owned<vk::Image>,
+    _memory: Allocation,
+}
+
+struct Mesh {
+    vertex_buffer: Owned<vk::Buffer>,
+    _vertex_memory: Allocation,
+    index_buffer: Owned<vk::Buffer>,
+    _index_memory: Allocation,
+    index_count: u32,
+    draw_ranges: Vec<DrawRange>,
+}
+
+enum AssetState<T> {
+    // on a worker thread, or decoded and waiting for the upload budget
+    Loading,
+    Uploading(UploadId, T),
+    Ready(T),
+    Failed(VkPlaygroundError),
+}
+
+// what is known of a model once it's decoded, ahead of its upload
+struct ModelInfo {
+    bounds: Bounds,
+    materials: Vec<ModelMaterial>,
+}
+
+struct Asset<T, I = ()> {
+    path: PathBuf,
+    info: Option<I>,
+    state: AssetState<T>,
+}
+
+impl<T, I> Asset<T, I> {
+    fn new(path: PathBuf) -> Asset<T, I> {
+        Asset {
+            path,
+            info: None,
+            state: AssetState::Loading,
+        }
+    }
+
+    fn ready(&self) -> Option<&T> {
+        match &self.state {
+            AssetState::Ready(resource) => Some(resource),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AssetKey {
+    Texture(usize),
+    Model(usize),
+}
+
+struct Request {
+    key: AssetKey,
+    path: PathBuf,
+}
+
+enum Decoded {
+    Texture {
+        extent: vk::Extent2D,
+        pixels: Vec<u8>,
+    },
+    Model(ObjModel),
+}
+
+impl Decoded {
+    fn size(&self) -> vk::DeviceSize {
+        let size = match self {
+            Decoded::Texture { pixels, .. } => pixels.len(),
+            Decoded::Model(model) => {
+                std::mem::size_of_val(model.vertices.as_slice())
+                    + std::mem::size_of_val(model.indices.as_slice())
+            }
+        };
+
+        size as vk::DeviceSize
+    }
+}
+
+type DecodeResult = (AssetKey, Result<Decoded, VkPlaygroundError>);
+
+pub struct AssetLoader {
+    textures: Vec<Asset<Texture>>,
+    models: Vec<Asset<Mesh, ModelInfo>>,
+    // requests the workers haven't answered yet
+    decoding_count: usize,
+    decoded: VecDeque<(AssetKey, Decoded)>,
+    upload_budget: vk::DeviceSize,
+
+    placeholder: Texture,
+    proxy: Mesh,
+
+    // the workers stop once the request sender is dropped
+    requests: Option<Sender<Request>>,
+    results: Receiver<DecodeResult>,
+    workers: Vec<JoinHandle<()>>,
+
+    uploader: Uploader,
+    allocator: Allocator,
+    device: ash::Device,
+}
+
+impl AssetLoader {
+    /// Starts the decoding threads and uploads the placeholder texture and
+    /// proxy cube, waiting for those.
+    pub fn new(
+        device: &ash::Device,
+        allocator: &Allocator,
+        queues: UploadQueues,
+    ) -> Result<AssetLoader, VkPlaygroundError> {
+        let mut uploader = Uploader::new(device, queues)?;
+        let placeholder = upload_texture(
+            device,
+            &mut uploader,
+            allocator,
+            vk::Extent2D { width: 1, height: 1 },
+            &PLACEHOLDER_PIXEL,
+        )?;
+        let proxy = upload_mesh(device, &mut uploader, allocator, &proxy_cube())?;
+        let upload = uploader.submit()?;
+        uploader.wait(upload)?;
+
+        let (request_sender, request_receiver) = mpsc::channel();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let request_receiver = Arc::new(Mutex::new(request_receiver));
+        let worker_count = thread::available_parallelism()
+            .map_or(1, |count| count.get())
+            .min(MAX_WORKER_COUNT);
+        let workers = (0..worker_count)
+            .map(|_| {
+                let requests = Arc::clone(&request_receiver);
+                let results = result_sender.clone();
+                thread::spawn(move || decode_requests(&requests, &results))
+            })
+            .collect();
+
+        Ok(AssetLoader {
+            textures: vec![],
+            models: vec![],
+            decoding_count: 0,
+            decoded: VecDeque::new(),
+            upload_budget: DEFAULT_UPLOAD_BUDGET,
+
+            placeholder,
+            proxy,
+
+            requests: Some(request_sender),
+            results: result_receiver,
+            workers,
+
+            uploader,
+            allocator: allocator.clone(),
+            device: device.clone(),
+        })
+    }
+
+    /// How many bytes `update` uploads at most, though always at least one
+    /// asset when any are waiting.
+    pub fn set_upload_budget(&mut self, upload_budget: vk::DeviceSize) {
+        self.upload_budget = upload_budget;
+    }
+
+    /// Starts decoding the image at `path` as an sRGB texture.
+    pub fn load_texture(&mut self, path: impl Into<PathBuf>) -> TextureHandle {
+        let path = path.into();
+        self.request(AssetKey::Texture(self.textures.len()), &path);
+        self.textures.push(Asset::new(path));
+
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    /// Starts loading the OBJ model at `path`, and its materials.
+    pub fn load_model(&mut self, path: impl Into<PathBuf>) -> ModelHandle {
+        let path = path.into();
+        self.request(AssetKey::Model(self.models.len()), &path);
+        self.models.push(Asset::new(path));
+
+        ModelHandle(self.models.len() - 1)
+    }
+
+    /// Takes in what the workers have decoded, uploads it within the upload
+    /// budget and makes the uploads that have finished ready. Call it once
+    /// a frame; it never blocks.
+    pub fn update(&mut self) -> Result<(), VkPlaygroundError> {
+        while let Ok((key, decoded)) = self.results.try_recv() {
+            self.receive(key, decoded);
+        }
+        self.upload_decoded(self.upload_budget)?;
+
+        self.make_ready()
+    }
+
+    /// Blocks until every requested asset is ready or has failed.
+    pub fn finish(&mut self) -> Result<(), VkPlaygroundError> {
+        while self.decoding_count > 0 {
+            let (key, decoded) = self.results.recv().map_err(|_| {
+                VkPlaygroundError::unsupported("AssetLoader::finish", "the decoding threads exited")
+            })?;
+            self.receive(key, decoded);
+        }
+        self.upload_decoded(vk::DeviceSize::MAX)?;
+
+        for upload in self.pending_uploads() {
+            self.uploader.wait(upload)?;
+        }
+
+        self.make_ready()
+    }
+
+    pub fn progress(&self) -> LoadProgress {
+        let mut progress = LoadProgress {
+            requested: self.textures.len() + self.models.len(),
+            ..LoadProgress::default()
+        };
+        let states = self
+            .textures
+            .iter()
+            .map(|asset| state_progress(&asset.state))
+            .chain(self.models.iter().map(|asset| state_progress(&asset.state)));
+        for (is_ready, is_failed) in states {
+            progress.ready += is_ready as usize;
+            progress.failed += is_failed as usize;
+        }
+
+        progress
+    }
+
+    /// The path and error of every asset that failed to load.
+    pub fn errors(&self) -> Vec<(&Path, &VkPlaygroundError)> {
+        let texture_errors = self.textures.iter().filter_map(|asset| asset_error(asset));
+        let model_errors = self.models.iter().filter_map(|asset| asset_error(asset));
+
+        texture_errors.chain(model_errors).collect()
+    }
+
+    pub fn is_texture_ready(&self, texture: TextureHandle) -> bool {
+        self.textures[texture.0].ready().is_some()
+    }
+
+    /// The view of `texture`, or of the placeholder until it is ready.
+    pub fn texture_view(&self, texture: TextureHandle) -> vk::ImageView {
+        let texture = self.textures[texture.0].ready().unwrap_or(&self.placeholder);
+
+        *texture.view
+    }
+
+    pub fn is_model_ready(&self, model: ModelHandle) -> bool {
+        self.models[model.0].ready().is_some()
+    }
+
+    /// The buffers of `model` once it is ready, until then the proxy cube
+    /// scaled to its bounds, or to a unit cube before those are known. The
+    /// vertices are `structures::MeshVertex`, indexed with u32.
+    pub fn model_or_proxy(&self, model: ModelHandle) -> ModelDraw {
+        let asset = &self.models[model.0];
+        let (mesh, transform) = match asset.ready() {
+            Some(mesh) => (mesh, Matrix4::from_scale(1.0)),
+            None => (
+                &self.proxy,
+                asset
+                    .info
+                    .as_ref()
+                    .map_or(Matrix4::from_scale(1.0), |info| info.bounds.proxy_transform()),
+            ),
+        };
+
+        ModelDraw {
+            vertex_buffer: *mesh.vertex_buffer,
+            index_buffer: *mesh.index_buffer,
+            index_count: mesh.index_count,
+            transform,
+            is_proxy: asset.ready().is_none(),
+        }
+    }
+
+    /// The index ranges of `model` drawn with each of its materials, or the
+    /// single range of the proxy without a material until it is ready.
+    pub fn model_draw_ranges(&self, model: ModelHandle) -> &[DrawRange] {
+        let mesh = self.models[model.0].ready().unwrap_or(&self.proxy);
+
+        &mesh.draw_ranges
+    }
+
+    /// The materials of `model`, empty until it has been decoded. Their
+    /// textures are `None` when the material has none.
+    pub fn model_materials(&self, model: ModelHandle) -> &[ModelMaterial] {
+        self.models[model.0]
+            .info
+            .as_ref()
+            .map_or(&[], |info| info.materials.as_slice())
+    }
+
+    fn request(&mut self, key: AssetKey, path: &Path) {
+        let request = Request {
+            key,
+            path: path.to_path_buf(),
+        };
+        // the workers only stop when the loader is dropped
+        if let Some(requests) = &self.requests {
+            let _ = requests.send(request);
+        }
+        self.decoding_count += 1;
+    }
+
+    fn receive(&mut self, key: AssetKey, decoded: Result<Decoded, VkPlaygroundError>) {
+        self.decoding_count -= 1;
+
+        match decoded {
+            Ok(decoded) => {
+                if let (AssetKey::Model(i), Decoded::Model(model)) = (key, &decoded) {
+                    let materials = model
+                        .materials
+                        .iter()
+                        .map(|material| self.load_material(material))
+                        .collect();
+                    self.models[i].info = Some(ModelInfo {
+                        bounds: Bounds::of_vertices(&model.vertices),
+                        materials,
+                    });
+                }
+                self.decoded.push_back((key, decoded));
+            }
+            Err(err) => match key {
+                AssetKey::Texture(i) => self.textures[i].state = AssetState::Failed(err),
+                AssetKey::Model(i) => self.models[i].state = AssetState::Failed(err),
+            },
+        }
+    }
+
+    // textures shared between materials, or models, are loaded once
+    fn load_material(&mut self, material: &Material) -> ModelMaterial {
+        let mut load = |path: &Option<PathBuf>| {
+            path.as_ref().map(|path| {
+                match self.textures.iter().position(|texture| texture.path == *path) {
+                    Some(i) => TextureHandle(i),
+                    None => self.load_texture(path.clone()),
+                }
+            })
+        };
+
+        ModelMaterial {
+            material: material.clone(),
+            diffuse_texture: load(&material.diffuse_texture),
+            specular_texture: load(&material.specular_texture),
+            normal_texture: load(&material.normal_texture),
+        }
+    }
+
+    fn upload_decoded(&mut self, upload_budget: vk::DeviceSize) -> Result<(), VkPlaygroundError> {
+        let (device, uploader, allocator) = (&self.device, &mut self.uploader, &self.allocator);
+        let mut uploaded_size = 0;
+        let mut textures = vec![];
+        let mut models = vec![];
+        // what went into the batch so far, kept until it is submitted
+        let mut batched = vec![];
+
+        while uploaded_size < upload_budget {
+            let (key, decoded) = match self.decoded.pop_front() {
+                Some(next) => next,
+                None => break,
+            };
+            uploaded_size += decoded.size();
+
+            let uploaded = match (key, &decoded) {
+                (AssetKey::Texture(i), Decoded::Texture { extent, pixels }) => {
+                    upload_texture(device, uploader, allocator, *extent, pixels)
+                        .map(|texture| textures.push((i, texture)))
+                }
+                (AssetKey::Model(i), Decoded::Model(model)) => {
+                    upload_mesh(device, uploader, allocator, model)
+                        .map(|mesh| models.push((i, mesh)))
+                }
+                _ => unreachable!("workers decode each asset as what was requested"),
+            };
+
+            if let Err(err) = uploaded {
+                // The batch may hold half of this asset's copies, into
+                // resources that are gone, so none of it can be submitted.
+                // The assets before it are uploaded again.
+                uploader.discard();
+                textures.clear();
+                models.clear();
+                for batched in batched.drain(..).rev() {
+                    self.decoded.push_front(batched);
+                }
+                match key {
+                    AssetKey::Texture(i) => self.textures[i].state = AssetState::Failed(err),
+                    AssetKey::Model(i) => self.models[i].state = AssetState::Failed(err),
+                }
+                continue;
+            }
+            batched.push((key, decoded));
+        }
+
+        if !uploader.has_unsubmitted() {
+            return Ok(());
+        }
+        let upload = uploader.submit()?;
+        for (i, texture) in textures {
+            self.textures[i].state = AssetState::Uploading(upload, texture);
+        }
+        for (i, mesh) in models {
+            self.models[i].state = AssetState::Uploading(upload, mesh);
+        }
+
+        Ok(())
+    }
+
+    fn make_ready(&mut self) -> Result<(), VkPlaygroundError> {
+        make_ready(&mut self.uploader, &mut self.textures)?;
+
+        make_ready(&mut self.uploader, &mut self.models)
+    }
+
+    fn pending_uploads(&self) -> Vec<UploadId> {
+        let texture_uploads = self.textures.iter().filter_map(|asset| uploading(&asset.state));
+        let model_uploads = self.models.iter().filter_map(|asset| uploading(&asset.state));
+        let mut uploads: Vec<UploadId> = texture_uploads.chain(model_uploads).collect();
+        uploads.sort();
+        uploads.dedup();
+
+        uploads
+    }
+}
+
+impl Drop for AssetLoader {
+    fn drop(&mut self) {
+        // hanging up ends the workers once they're done with their current
+        // asset
+        self.requests = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+
+        // the textures and meshes are dropped ahead of the uploader, so their
+        // copies have to be finished before then
+        for upload in self.pending_uploads() {
+            let _ = self.uploader.wait(upload);
+        }
+    }
+}
+
+fn decode_requests(requests: &Mutex<Receiver<Request>>, results: &Sender<DecodeResult>) {
+    loop {
+        // the lock is only held while waiting, not while decoding
+        let request = requests.lock().unwrap().recv();
+        let request = match request {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+
+        let decoded = match request.key {
+            AssetKey::Texture(_) => decode_texture(&request.path),
+            AssetKey::Model(_) => decode_model(&request.path),
+        };
+        if results.send((request.key, decoded)).is_err() {
+            return;
+        }
+    }
+}
+
+fn decode_texture(path: &Path) -> Result<Decoded, VkPlaygroundError> {
+    let (width, height, pixels) = vkstuff::vkimage::load_texture_pixels(path)?;
+
+    Ok(Decoded::Texture {
+        extent: vk::Extent2D { width, height },
+        pixels,
+    })
+}
+
+fn decode_model(path: &Path) -> Result<Decoded, VkPlaygroundError> {
+    let model = obj::load_obj(path)?;
+    if model.indices.is_empty() {
+        return Err(VkPlaygroundError::unsupported(
+            "AssetLoader::load_model",
+            format!("{} has no faces", path.display()),
+        ));
+    }
+
+    Ok(Decoded::Model(model))
+}
+
+fn upload_texture(
+    device: &ash::Device,
+    uploader: &mut Uploader,
+    allocator: &Allocator,
+    extent: vk::Extent2D,
+    pixels: &[u8],
+) -> Result<Texture, VkPlaygroundError> {
+    let (image, memory) = uploader.upload_image(
+        allocator,
+        extent,
+        vk::Format::R8G8B8A8_SRGB,
+        pixels,
+        Access::FragmentShaderRead,
+    )?;
+    let image = Owned::new(device, image);
+    let view = vkstuff::vkimage::create_texture_image_view(device, *image, 1)?;
+
+    Ok(Texture {
+        view: Owned::new(device, view),
+        _image: image,
+        _memory: memory,
+    })
+}
+
+fn upload_mesh(
+    device: &ash::Device,
+    uploader: &mut Uploader,
+    allocator: &Allocator,
+    model: &ObjModel,
+) -> Result<Mesh, VkPlaygroundError> {
+    let (vertex_buffer, vertex_memory) = uploader.upload_buffer(
+        allocator,
+        vk::BufferUsageFlags::VERTEX_BUFFER,
+        &model.vertices,
+        Access::VertexInput,
+    )?;
+    let vertex_buffer = Owned::new(device, vertex_buffer);
+    let (index_buffer, index_memory) = uploader.upload_buffer(
+        allocator,
+        vk::BufferUsageFlags::INDEX_BUFFER,
+        &model.indices,
+        Access::IndexInput,
+    )?;
+
+    Ok(Mesh {
+        vertex_buffer,
+        _vertex_memory: vertex_memory,
+        index_buffer: Owned::new(device, index_buffer),
+        _index_memory: index_memory,
+        index_count: model.indices.len() as u32,
+        draw_ranges: model.draw_ranges.clone(),
+    })
+}
+
+// a model of its own, normals pointing away from the center
+fn proxy_cube() -> ObjModel {
+    let vertices = (0..8)
+        .map(|corner| {
+            let coordinate = |bit: u32| if corner & (1 << bit) == 0 { -0.5 } else { 0.5 };
+            let pos = [coordinate(0), coordinate(1), coordinate(2)];
+            let normal_length = 3.0_f32.sqrt() * 0.5;
+            MeshVertex {
+                pos,
+                normal: pos.map(|coordinate| coordinate / normal_length),
+                tex_coord: [0.0, 0.0],
+            }
+        })
+        .collect();
+    let indices: Vec<u32> = PROXY_FACES
+        .iter()
+        .flat_map(|&[a, b, c, d]| vec![a, b, c, c, d, a])
+        .collect();
+    let draw_ranges = vec![DrawRange {
+        first_index: 0,
+        index_count: indices.len() as u32,
+        material: None,
+    }];
+
+    ObjModel {
+        vertices,
+        indices,
+        meshes: vec![],
+        materials: vec![],
+        draw_ranges,
+    }
+}
+
+// moves the assets whose uploads have finished to Ready
+fn make_ready<T, I>(
+    uploader: &mut Uploader,
+    assets: &mut [Asset<T, I>],
+) -> Result<(), VkPlaygroundError> {
+    for asset in assets.iter_mut() {
+        let upload = match uploading(&asset.state) {
+            Some(upload) => upload,
+            None => continue,
+        };
+        if !uploader.is_complete(upload)? {
+            continue;
+        }
+
+        if let AssetState::Uploading(_, resource) =
+            std::mem::replace(&mut asset.state, AssetState::Loading)
+        {
+            asset.state = AssetState::Ready(resource);
+        }
+    }
+
+    Ok(())
+}
+
+fn uploading<T>(state: &AssetState<T>) -> Option<UploadId> {
+    match state {
+        AssetState::Uploading(upload, _) => Some(*upload),
+        _ => None,
+    }
+}
+
+// (is ready, has failed)
+fn state_progress<T>(state: &AssetState<T>) -> (bool, bool) {
+    (
+        matches!(state, AssetState::Ready(_)),
+        matches!(state, AssetState::Failed(_)),
+    )
+}
+
+fn asset_error<T, I>(asset: &Asset<T, I>) -> Option<(&Path, &VkPlaygroundError)> {
+    match &asset.state {
+        AssetState::Failed(err) => Some((&asset.path, err)),
+        _ => None,
+    }
+}
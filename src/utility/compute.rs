@@ -0,0 +1,268 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use std::ffi::CString;
+use std::path::Path;
+use std::ptr;
+
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::handles::Owned;
+use crate::utility::vkstuff;
+
+// Compute pipelines, dispatches, and the barriers between a dispatch and the
+// graphics work around it. A compute shader writing a buffer that is then
+// drawn from has to finish first, and the next frame's dispatch has to wait
+// for the draw to be done reading, e.g.
+//
+//     buffer_barrier(device, cmd, particles, Access::VertexInput, Access::ComputeReadWrite);
+//     dispatch(device, cmd, pipeline, layout, &[set], [group_count(count, 256), 1, 1]);
+//     buffer_barrier(device, cmd, particles, Access::ComputeReadWrite, Access::VertexInput);
+//
+// The graphics queue family always supports compute too, see
+// `vkdevice::find_queue_family`, so dispatches can be recorded into the
+// frame's command buffer ahead of the render pass.
+
+const ENTRY_POINT: &str = "main";
+
+/// How a buffer or image is used on one side of a barrier, also what
+/// `upload::Uploader` makes its uploads ready for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Not used yet. Images start out in the UNDEFINED layout, and their
+    /// contents are thrown away by the transition.
+    Nothing,
+    ComputeRead,
+    ComputeWrite,
+    ComputeReadWrite,
+    /// Read as vertex attributes.
+    VertexInput,
+    /// Read as the indices of an indexed draw.
+    IndexInput,
+    /// Read as the arguments of an indirect draw or dispatch.
+    IndirectCommand,
+    /// Read, or sampled, in a vertex shader.
+    VertexShaderRead,
+    /// Read, or sampled, in a fragment shader.
+    FragmentShaderRead,
+    TransferRead,
+    TransferWrite,
+}
+
+impl Access {
+    pub fn stage(self) -> vk::PipelineStageFlags {
+        match self {
+            Access::Nothing => vk::PipelineStageFlags::TOP_OF_PIPE,
+            Access::ComputeRead | Access::ComputeWrite | Access::ComputeReadWrite => {
+                vk::PipelineStageFlags::COMPUTE_SHADER
+            }
+            Access::VertexInput | Access::IndexInput => vk::PipelineStageFlags::VERTEX_INPUT,
+            Access::IndirectCommand => vk::PipelineStageFlags::DRAW_INDIRECT,
+            Access::VertexShaderRead => vk::PipelineStageFlags::VERTEX_SHADER,
+            Access::FragmentShaderRead => vk::PipelineStageFlags::FRAGMENT_SHADER,
+            Access::TransferRead | Access::TransferWrite => vk::PipelineStageFlags::TRANSFER,
+        }
+    }
+
+    pub fn access_mask(self) -> vk::AccessFlags {
+        match self {
+            Access::Nothing => vk::AccessFlags::empty(),
+            Access::ComputeRead | Access::VertexShaderRead | Access::FragmentShaderRead => {
+                vk::AccessFlags::SHADER_READ
+            }
+            Access::ComputeWrite => vk::AccessFlags::SHADER_WRITE,
+            Access::ComputeReadWrite => {
+                vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE
+            }
+            Access::VertexInput => vk::AccessFlags::VERTEX_ATTRIBUTE_READ,
+            Access::IndexInput => vk::AccessFlags::INDEX_READ,
+            Access::IndirectCommand => vk::AccessFlags::INDIRECT_COMMAND_READ,
+            Access::TransferRead => vk::AccessFlags::TRANSFER_READ,
+            Access::TransferWrite => vk::AccessFlags::TRANSFER_WRITE,
+        }
+    }
+
+    /// The layout an image has to be in for this access. Compute shaders
+    /// only reach images as storage images, which need GENERAL.
+    pub fn image_layout(self) -> vk::ImageLayout {
+        match self {
+            Access::Nothing => vk::ImageLayout::UNDEFINED,
+            Access::VertexShaderRead | Access::FragmentShaderRead => {
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            }
+            Access::TransferRead => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            Access::TransferWrite => vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            _ => vk::ImageLayout::GENERAL,
+        }
+    }
+}
+
+/// Creates a compute pipeline from `shader_path`, compiled SPIR-V or a
+/// `.comp` source, along with its layout. The set layouts and push constant
+/// ranges can come from `reflection` on the same shader.
+pub fn create_compute_pipeline(
+    device: &ash::Device,
+    pipeline_cache: vk::PipelineCache,
+    shader_path: &Path,
+    descriptor_set_layouts: &[vk::DescriptorSetLayout],
+    push_constant_ranges: &[vk::PushConstantRange],
+) -> Result<(vk::Pipeline, vk::PipelineLayout), VkPlaygroundError> {
+    // destroyed on the way out, the pipeline doesn't need it once made
+    let shader_module = Owned::new(
+        device,
+        vkstuff::vkshader::create_shader_module(device, &shader_path.to_string_lossy())?,
+    );
+
+    let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo {
+        s_type: vk::StructureType::PIPELINE_LAYOUT_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::PipelineLayoutCreateFlags::empty(),
+        set_layout_count: descriptor_set_layouts.len() as u32,
+        p_set_layouts: descriptor_set_layouts.as_ptr(),
+        push_constant_range_count: push_constant_ranges.len() as u32,
+        p_push_constant_ranges: push_constant_ranges.as_ptr(),
+    };
+
+    let pipeline_layout = unsafe {
+        device
+            .create_pipeline_layout(&pipeline_layout_create_info, None)
+            .vk_context("vkCreatePipelineLayout", "Failed to create pipeline layout!")?
+    };
+    let pipeline_layout = Owned::new(device, pipeline_layout);
+
+    let main_function_name = CString::new(ENTRY_POINT).unwrap();
+    let compute_pipeline_create_infos = [vk::ComputePipelineCreateInfo {
+        s_type: vk::StructureType::COMPUTE_PIPELINE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::PipelineCreateFlags::empty(),
+        stage: vk::PipelineShaderStageCreateInfo {
+            s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineShaderStageCreateFlags::empty(),
+            module: *shader_module,
+            p_name: main_function_name.as_ptr(),
+            p_specialization_info: ptr::null(),
+            stage: vk::ShaderStageFlags::COMPUTE,
+        },
+        layout: *pipeline_layout,
+        base_pipeline_handle: vk::Pipeline::null(),
+        base_pipeline_index: -1,
+    }];
+
+    let compute_pipelines = unsafe {
+        device
+            .create_compute_pipelines(pipeline_cache, &compute_pipeline_create_infos, None)
+            .map_err(|(_, result)| result)
+            .vk_context("vkCreateComputePipelines", "Failed to create Compute Pipeline!")?
+    };
+
+    Ok((compute_pipelines[0], pipeline_layout.into_raw()))
+}
+
+/// The number of workgroups of `local_size` invocations needed to cover
+/// `item_count` items. The shader has to skip the ones past the end.
+pub fn group_count(item_count: usize, local_size: u32) -> u32 {
+    item_count.div_ceil(local_size as usize) as u32
+}
+
+/// Binds `pipeline` and its `descriptor_sets`, from set 0 on, and
+/// dispatches `group_counts` workgroups. Push constants, if any, are pushed
+/// beforehand with `pipeline::push_constants` and COMPUTE stage flags.
+pub fn dispatch(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    pipeline: vk::Pipeline,
+    pipeline_layout: vk::PipelineLayout,
+    descriptor_sets: &[vk::DescriptorSet],
+    group_counts: [u32; 3],
+) {
+    unsafe {
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline);
+        if !descriptor_sets.is_empty() {
+            device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline_layout,
+                0,
+                descriptor_sets,
+                &[],
+            );
+        }
+        device.cmd_dispatch(command_buffer, group_counts[0], group_counts[1], group_counts[2]);
+    }
+}
+
+/// Makes the `after` use of the whole of `buffer` wait for the `before` use
+/// to finish, with its writes visible. Recorded outside a render pass.
+pub fn buffer_barrier(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    buffer: vk::Buffer,
+    before: Access,
+    after: Access,
+) {
+    let buffer_memory_barriers = [vk::BufferMemoryBarrier {
+        s_type: vk::StructureType::BUFFER_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask: before.access_mask(),
+        dst_access_mask: after.access_mask(),
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        buffer,
+        offset: 0,
+        size: vk::WHOLE_SIZE,
+    }];
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            before.stage(),
+            after.stage(),
+            vk::DependencyFlags::empty(),
+            &[],
+            &buffer_memory_barriers,
+            &[],
+        );
+    }
+}
+
+/// Like `buffer_barrier` for the single mip level and layer of a colour
+/// `image`, which is also moved from the layout of `before` to the one of
+/// `after`.
+pub fn image_barrier(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    before: Access,
+    after: Access,
+) {
+    let image_memory_barriers = [vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask: before.access_mask(),
+        dst_access_mask: after.access_mask(),
+        old_layout: before.image_layout(),
+        new_layout: after.image_layout(),
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+    }];
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            before.stage(),
+            after.stage(),
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &image_memory_barriers,
+        );
+    }
+}
@@ -2,7 +2,7 @@ use crate::utility::debug::ValidationInfo;
 use crate::utility::structures::DeviceExtension;
 use ash::vk_make_version;
 
-use std::os::raw::c_char;
+use std::ffi::CString;
 
 pub const APPLICATION_VERSION: u32 = vk_make_version!(1, 0, 0);
 pub const ENGINE_VERSION: u32 = vk_make_version!(1, 0, 0);
@@ -12,21 +12,22 @@ pub const WINDOW_WIDTH: u32 = 800;
 pub const WINDOW_HEIGHT: u32 = 600;
 pub const VALIDATION: ValidationInfo = ValidationInfo {
     is_enable: true,
-    required_validation_layers: ["VK_LAYER_KHRONOS_validation"],
+    required_validation_layers: &["VK_LAYER_KHRONOS_validation"],
 };
 
 pub const DEVICE_EXTENSIONS: DeviceExtension = DeviceExtension {
-    names: ["VK_KHR_swapchain"],
+    names: &["VK_KHR_swapchain"],
 };
 
 pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
 pub const IS_PAINT_FPS_COUNTER: bool = false;
 
 impl DeviceExtension {
-    pub fn get_extensions_raw_names(&self) -> [*const c_char; 1] {
-        [
-            // currently just enable the Swapchain extension
-            ash::extensions::khr::Swapchain::name().as_ptr(),
-        ]
+    // the CStrings have to outlive any pointers taken from them
+    pub fn get_extensions_raw_names(&self) -> Vec<CString> {
+        self.names
+            .iter()
+            .map(|name| CString::new(*name).unwrap())
+            .collect()
     }
 }
@@ -1,6 +1,8 @@
 use ash::version::EntryV1_0;
 use ash::vk;
 
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+
 use std::ffi::CStr;
 use std::os::raw::c_void;
 use std::ptr;
@@ -37,22 +39,25 @@ unsafe extern "system" fn vulkan_debug_utils_callback(
 
 pub struct ValidationInfo {
     pub is_enable: bool,
-    pub required_validation_layers: [&'static str; 1],
+    pub required_validation_layers: &'static [&'static str],
 }
 
 pub fn check_validation_layer_support (
     entry: &ash::Entry,
     required_validation_layers: &Vec<&str>,
-    ) -> bool  {
+    ) -> Result<bool, VkPlaygroundError> {
     // if validation layer is supported, return true
 
     let layer_properties = entry
     .enumerate_instance_layer_properties()
-    .expect("Failed to enumerate Instance layers properly");
+    .vk_context(
+        "vkEnumerateInstanceLayerProperties",
+        "Failed to enumerate Instance layers properly",
+    )?;
 
     if layer_properties.len() <=0 {
         eprintln!("No available layers!");
-        return false;
+        return Ok(false);
     }
 
     for required_layer_name in required_validation_layers.iter() {
@@ -66,33 +71,36 @@ pub fn check_validation_layer_support (
             }
         }
         if is_layer_found == false {
-            return false;
+            return Ok(false);
         }
     }
 
-    true
+    Ok(true)
 }
 
 pub fn setup_debug_utils(
     is_enable_debug: bool,
     entry: &ash::Entry,
     instance: &ash::Instance,
-) -> (ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT) {
+) -> Result<
+    (ash::extensions::ext::DebugUtils, vk::DebugUtilsMessengerEXT),
+    VkPlaygroundError,
+> {
 
     let debug_utils_loader = ash::extensions::ext::DebugUtils::new(entry, instance);
 
     if is_enable_debug == false {
-        (debug_utils_loader, ash::vk::DebugUtilsMessengerEXT::null())
+        Ok((debug_utils_loader, ash::vk::DebugUtilsMessengerEXT::null()))
     } else {
         let messenger_ci = populate_debug_messenger_create_info();
 
         let utils_messenger = unsafe {
             debug_utils_loader
                 .create_debug_utils_messenger(&messenger_ci, None)
-                .expect("Debug Utils Callback")
+                .vk_context("vkCreateDebugUtilsMessengerEXT", "Debug Utils Callback")?
         };
 
-        (debug_utils_loader, utils_messenger)
+        Ok((debug_utils_loader, utils_messenger))
     }
 }
 
@@ -0,0 +1,191 @@
+use ash::vk;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::utility::shader_compiler::ShaderDiagnostic;
+
+// The error type returned by the helpers in `share`, `vkstuff` and friends.
+// Vulkan failures keep the `vk::Result` and the command that produced it, so
+// a caller can react to something specific (e.g. ERROR_OUT_OF_DATE_KHR) and
+// fall back to something else when a format or feature isn't supported.
+
+#[derive(Debug)]
+pub enum VkPlaygroundError {
+    /// The Vulkan loader library couldn't be found or opened.
+    Loader { source: ash::LoadingError },
+    /// The window couldn't be created.
+    Window { source: winit::error::OsError },
+    /// A Vulkan command returned an error code.
+    Vulkan {
+        operation: &'static str,
+        result: vk::Result,
+        context: String,
+    },
+    /// A command line argument is missing its value or couldn't be parsed.
+    Usage { context: String },
+    /// The device can't do what was asked, such as a missing format feature
+    /// or no GPU that meets the requirements.
+    Unsupported {
+        operation: &'static str,
+        context: String,
+    },
+    /// A file such as a shader couldn't be read, or an image couldn't be
+    /// written.
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A shader module couldn't be understood, or doesn't fit what the
+    /// pipeline feeds it.
+    Shader { context: String },
+    /// A GLSL shader failed to compile.
+    ShaderCompile {
+        path: PathBuf,
+        diagnostics: Vec<ShaderDiagnostic>,
+    },
+    /// A model couldn't be loaded.
+    Model {
+        path: PathBuf,
+        source: tobj::LoadError,
+    },
+    /// A texture couldn't be decoded.
+    Image {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    /// A glTF file is malformed, or uses something the importer doesn't
+    /// support.
+    Gltf { path: PathBuf, context: String },
+}
+
+impl VkPlaygroundError {
+    pub fn unsupported(operation: &'static str, context: impl Into<String>) -> VkPlaygroundError {
+        VkPlaygroundError::Unsupported {
+            operation,
+            context: context.into(),
+        }
+    }
+
+    /// The `vk::Result` behind the error, if it came from a Vulkan command.
+    pub fn vk_result(&self) -> Option<vk::Result> {
+        match self {
+            VkPlaygroundError::Vulkan { result, .. } => Some(*result),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VkPlaygroundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VkPlaygroundError::Loader { source } => {
+                write!(f, "Failed to load the Vulkan library: {}", source)
+            }
+            VkPlaygroundError::Window { source } => {
+                write!(f, "Failed to create window: {}", source)
+            }
+            VkPlaygroundError::Vulkan {
+                operation,
+                result,
+                context,
+            } => write!(f, "{} ({} returned {})", context, operation, result),
+            VkPlaygroundError::Unsupported { operation, context } => {
+                write!(f, "{} (in {})", context, operation)
+            }
+            VkPlaygroundError::Usage { context } => write!(f, "{}", context),
+            VkPlaygroundError::Shader { context } => write!(f, "{}", context),
+            VkPlaygroundError::ShaderCompile { path, diagnostics } => {
+                write!(f, "Failed to compile {}", path.display())?;
+                for diagnostic in diagnostics.iter() {
+                    match diagnostic.line {
+                        Some(line) => {
+                            write!(f, "\n{}:{}: {}", path.display(), line, diagnostic.message)?
+                        }
+                        None => write!(f, "\n{}: {}", path.display(), diagnostic.message)?,
+                    }
+                }
+                Ok(())
+            }
+            VkPlaygroundError::Io { path, source } => {
+                write!(f, "{}: {}", path.display(), source)
+            }
+            VkPlaygroundError::Model { path, source } => {
+                write!(f, "Failed to load model {}: {}", path.display(), source)
+            }
+            VkPlaygroundError::Image { path, source } => {
+                write!(f, "Failed to load image {}: {}", path.display(), source)
+            }
+            VkPlaygroundError::Gltf { path, context } => {
+                write!(f, "Failed to load glTF {}: {}", path.display(), context)
+            }
+        }
+    }
+}
+
+impl Error for VkPlaygroundError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VkPlaygroundError::Loader { source } => Some(source),
+            VkPlaygroundError::Window { source } => Some(source),
+            VkPlaygroundError::Io { source, .. } => Some(source),
+            VkPlaygroundError::Model { source, .. } => Some(source),
+            VkPlaygroundError::Image { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Attaches the failing command and a description to an `ash` result, e.g.
+/// `device.create_fence(..).vk_context("vkCreateFence", "Failed to create Fence Object!")?`
+pub trait VkResultExt<T> {
+    fn vk_context(
+        self,
+        operation: &'static str,
+        context: impl Into<String>,
+    ) -> Result<T, VkPlaygroundError>;
+}
+
+impl<T> VkResultExt<T> for Result<T, vk::Result> {
+    fn vk_context(
+        self,
+        operation: &'static str,
+        context: impl Into<String>,
+    ) -> Result<T, VkPlaygroundError> {
+        self.map_err(|result| VkPlaygroundError::Vulkan {
+            operation,
+            result,
+            context: context.into(),
+        })
+    }
+}
+
+// instance creation can also fail before reaching the driver, when the
+// loader is missing some of the entry points
+impl<T> VkResultExt<T> for Result<T, ash::InstanceError> {
+    fn vk_context(
+        self,
+        operation: &'static str,
+        context: impl Into<String>,
+    ) -> Result<T, VkPlaygroundError> {
+        self.map_err(|err| match err {
+            ash::InstanceError::VkError(result) => VkPlaygroundError::Vulkan {
+                operation,
+                result,
+                context: context.into(),
+            },
+            ash::InstanceError::LoadError(names) => VkPlaygroundError::Unsupported {
+                operation,
+                context: format!("{}, failed to load {}", context.into(), names.join(", ")),
+            },
+        })
+    }
+}
+
+/// For the demo binaries: prints `err` and exits with a failure code.
+pub fn exit_with(err: VkPlaygroundError) -> ! {
+    eprintln!("Error: {}", err);
+    std::process::exit(1)
+}
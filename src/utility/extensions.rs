@@ -0,0 +1,349 @@
+use ash::extensions::ext::DebugUtils;
+use ash::version::{EntryV1_0, InstanceV1_0};
+use ash::vk;
+
+use std::ffi::CString;
+
+use crate::utility::debug::ValidationInfo;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::platforms;
+use crate::utility::structures::DeviceExtension;
+use crate::utility::tools;
+
+// Which instance extensions, layers and device extensions to turn on.
+// Required ones fail instance or device creation when they're missing,
+// optional ones are only enabled when the driver supports them, and
+// `EnabledExtensions` reports what was actually enabled, e.g.
+//
+//     let config = ExtensionConfig::for_window(&VALIDATION, &DEVICE_EXTENSIONS)
+//         .request_device_extension("VK_EXT_descriptor_indexing")
+//         .request_device_extension("VK_EXT_debug_marker");
+
+#[derive(Debug, Clone)]
+struct Requested {
+    name: String,
+    is_required: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionConfig {
+    instance_extensions: Vec<Requested>,
+    layers: Vec<Requested>,
+    device_extensions: Vec<Requested>,
+}
+
+/// The names an `ExtensionConfig` ended up enabling.
+#[derive(Debug, Clone, Default)]
+pub struct EnabledExtensions {
+    pub instance_extensions: Vec<String>,
+    pub layers: Vec<String>,
+    pub device_extensions: Vec<String>,
+}
+
+impl ExtensionConfig {
+    pub fn new() -> ExtensionConfig {
+        ExtensionConfig::default()
+    }
+
+    /// What the demos need to draw to a window: the platform's surface
+    /// extensions and `device_extensions`, plus everything `for_headless`
+    /// asks for.
+    pub fn for_window(
+        validation: &ValidationInfo,
+        device_extensions: &DeviceExtension,
+    ) -> ExtensionConfig {
+        let mut config = ExtensionConfig::new().with_validation(validation, true);
+        for name in platforms::surface_extension_names() {
+            config = config.require_instance_extension(&name.to_string_lossy());
+        }
+        for name in device_extensions.names.iter() {
+            config = config.require_device_extension(name);
+        }
+
+        config
+    }
+
+    /// Headless rendering has nothing to present to, so all it needs are
+    /// the validation layers and debug utils when `validation` is enabled.
+    /// Those are only requested, so headless runs still work on a CI
+    /// machine with a driver such as lavapipe but no layers installed.
+    pub fn for_headless(validation: &ValidationInfo) -> ExtensionConfig {
+        ExtensionConfig::new().with_validation(validation, false)
+    }
+
+    fn with_validation(
+        mut self,
+        validation: &ValidationInfo,
+        is_required: bool,
+    ) -> ExtensionConfig {
+        if validation.is_enable {
+            let debug_utils = DebugUtils::name().to_string_lossy();
+            add_request(&mut self.instance_extensions, &debug_utils, is_required);
+            for layer in validation.required_validation_layers.iter() {
+                add_request(&mut self.layers, layer, is_required);
+            }
+        }
+
+        self
+    }
+
+    pub fn require_instance_extension(mut self, name: &str) -> ExtensionConfig {
+        add_request(&mut self.instance_extensions, name, true);
+        self
+    }
+
+    pub fn request_instance_extension(mut self, name: &str) -> ExtensionConfig {
+        add_request(&mut self.instance_extensions, name, false);
+        self
+    }
+
+    pub fn require_layer(mut self, name: &str) -> ExtensionConfig {
+        add_request(&mut self.layers, name, true);
+        self
+    }
+
+    pub fn request_layer(mut self, name: &str) -> ExtensionConfig {
+        add_request(&mut self.layers, name, false);
+        self
+    }
+
+    pub fn require_device_extension(mut self, name: &str) -> ExtensionConfig {
+        add_request(&mut self.device_extensions, name, true);
+        self
+    }
+
+    pub fn request_device_extension(mut self, name: &str) -> ExtensionConfig {
+        add_request(&mut self.device_extensions, name, false);
+        self
+    }
+
+    /// Picks the instance extensions and layers to enable out of what the
+    /// loader supports. The device extensions are left empty, see
+    /// `select_device`.
+    pub fn select_instance(
+        &self,
+        entry: &ash::Entry,
+    ) -> Result<EnabledExtensions, VkPlaygroundError> {
+        let available_extensions: Vec<String> = entry
+            .enumerate_instance_extension_properties()
+            .vk_context(
+                "vkEnumerateInstanceExtensionProperties",
+                "Failed to enumerate instance extensions",
+            )?
+            .iter()
+            .map(|extension| tools::vk_to_string(&extension.extension_name))
+            .collect();
+        let available_layers: Vec<String> = entry
+            .enumerate_instance_layer_properties()
+            .vk_context(
+                "vkEnumerateInstanceLayerProperties",
+                "Failed to enumerate Instance layers properly",
+            )?
+            .iter()
+            .map(|layer| tools::vk_to_string(&layer.layer_name))
+            .collect();
+
+        Ok(EnabledExtensions {
+            instance_extensions: select(
+                "instance extension",
+                &self.instance_extensions,
+                &available_extensions,
+            )?,
+            layers: select("layer", &self.layers, &available_layers)?,
+            device_extensions: vec![],
+        })
+    }
+
+    /// True when `physical_device` has every required device extension.
+    pub fn is_device_supported(
+        &self,
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+    ) -> Result<bool, VkPlaygroundError> {
+        Ok(self
+            .missing_device_extensions(instance, physical_device)?
+            .is_empty())
+    }
+
+    /// The required device extensions `physical_device` doesn't support.
+    pub fn missing_device_extensions(
+        &self,
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+    ) -> Result<Vec<String>, VkPlaygroundError> {
+        let available_extensions = available_device_extensions(instance, physical_device)?;
+
+        Ok(self
+            .device_extensions
+            .iter()
+            .filter(|request| request.is_required)
+            .filter(|request| !available_extensions.contains(&request.name))
+            .map(|request| request.name.clone())
+            .collect())
+    }
+
+    /// Picks the device extensions to enable on `physical_device` and adds
+    /// them to `enabled`.
+    pub fn select_device(
+        &self,
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        enabled: &mut EnabledExtensions,
+    ) -> Result<(), VkPlaygroundError> {
+        let available_extensions = available_device_extensions(instance, physical_device)?;
+        enabled.device_extensions =
+            select("device extension", &self.device_extensions, &available_extensions)?;
+
+        Ok(())
+    }
+}
+
+impl EnabledExtensions {
+    pub fn has_instance_extension(&self, name: &str) -> bool {
+        self.instance_extensions.iter().any(|enabled| enabled == name)
+    }
+
+    pub fn has_layer(&self, name: &str) -> bool {
+        self.layers.iter().any(|enabled| enabled == name)
+    }
+
+    pub fn has_device_extension(&self, name: &str) -> bool {
+        self.device_extensions.iter().any(|enabled| enabled == name)
+    }
+
+    /// Whether a debug messenger can be set up, see `debug::setup_debug_utils`.
+    pub fn has_debug_utils(&self) -> bool {
+        self.has_instance_extension(&DebugUtils::name().to_string_lossy())
+    }
+}
+
+/// Null terminated copies of `names`. Keep them alive for as long as the
+/// pointers handed to Vulkan are in use.
+pub fn to_raw_names(names: &[String]) -> Vec<CString> {
+    names
+        .iter()
+        .map(|name| CString::new(name.as_str()).unwrap())
+        .collect()
+}
+
+// asking for the same name twice keeps one entry, required if either was
+fn add_request(requests: &mut Vec<Requested>, name: &str, is_required: bool) {
+    match requests.iter_mut().find(|request| request.name == name) {
+        Some(request) => request.is_required |= is_required,
+        None => requests.push(Requested {
+            name: name.to_owned(),
+            is_required,
+        }),
+    }
+}
+
+fn select(
+    kind: &str,
+    requests: &[Requested],
+    available: &[String],
+) -> Result<Vec<String>, VkPlaygroundError> {
+    let mut enabled = vec![];
+    for request in requests.iter() {
+        if available.contains(&request.name) {
+            enabled.push(request.name.clone());
+        } else if request.is_required {
+            return Err(VkPlaygroundError::unsupported(
+                "ExtensionConfig",
+                format!("Required {} {} is not supported", kind, request.name),
+            ));
+        }
+    }
+
+    Ok(enabled)
+}
+
+fn available_device_extensions(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+) -> Result<Vec<String>, VkPlaygroundError> {
+    let available_extensions = unsafe {
+        instance
+            .enumerate_device_extension_properties(physical_device)
+            .vk_context(
+                "vkEnumerateDeviceExtensionProperties",
+                "Failed to get device extension properties",
+            )?
+    };
+
+    Ok(available_extensions
+        .iter()
+        .map(|extension| tools::vk_to_string(&extension.extension_name))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn select_enables_what_is_available() {
+        let config = ExtensionConfig::new()
+            .require_layer("VK_LAYER_a")
+            .request_layer("VK_LAYER_b")
+            .request_layer("VK_LAYER_missing");
+
+        let enabled = select("layer", &config.layers, &names(&["VK_LAYER_b", "VK_LAYER_a"]));
+
+        assert_eq!(enabled.unwrap(), names(&["VK_LAYER_a", "VK_LAYER_b"]));
+    }
+
+    #[test]
+    fn select_fails_on_a_missing_required_name() {
+        let config = ExtensionConfig::new()
+            .request_device_extension("VK_KHR_a")
+            .require_device_extension("VK_KHR_missing");
+
+        let err = select("device extension", &config.device_extensions, &names(&["VK_KHR_a"]))
+            .unwrap_err();
+
+        assert!(err.to_string().contains("VK_KHR_missing"), "{}", err);
+    }
+
+    #[test]
+    fn repeated_requests_are_required_if_either_was() {
+        let config = ExtensionConfig::new()
+            .request_instance_extension("VK_EXT_a")
+            .require_instance_extension("VK_EXT_a");
+
+        assert_eq!(config.instance_extensions.len(), 1);
+        assert!(select("instance extension", &config.instance_extensions, &[]).is_err());
+    }
+
+    #[test]
+    fn validation_is_only_required_with_a_window() {
+        let validation = ValidationInfo {
+            is_enable: true,
+            required_validation_layers: &["VK_LAYER_KHRONOS_validation"],
+        };
+        let device_extensions = DeviceExtension { names: &[] };
+
+        let headless = ExtensionConfig::for_headless(&validation);
+        let window = ExtensionConfig::for_window(&validation, &device_extensions);
+
+        assert!(select("layer", &headless.layers, &[]).unwrap().is_empty());
+        assert!(select("layer", &window.layers, &[]).is_err());
+    }
+
+    #[test]
+    fn headless_without_validation_asks_for_nothing() {
+        let validation = ValidationInfo {
+            is_enable: false,
+            required_validation_layers: &["VK_LAYER_KHRONOS_validation"],
+        };
+
+        let config = ExtensionConfig::for_headless(&validation);
+
+        assert!(config.instance_extensions.is_empty());
+        assert!(config.layers.is_empty());
+        assert!(config.device_extensions.is_empty());
+    }
+}
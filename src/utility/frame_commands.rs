@@ -0,0 +1,387 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use std::mem;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::mpsc;
+use std::thread;
+
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::handles::Owned;
+
+// Command buffers recorded again every frame instead of once at startup, so
+// what gets drawn can change from one frame to the next without rebuilding
+// anything. Each frame in flight has a command pool of its own. Once the
+// frame's fence has signalled, the GPU is done with everything recorded from
+// that pool, so the whole pool is reset in one call rather than freeing or
+// resetting its command buffers one at a time.
+//
+// Scenes with thousands of draws can also have them recorded on worker
+// threads, into secondary command buffers that the frame's primary command
+// buffer executes inside the render pass. A command pool may only be used
+// from one thread at a time, so every worker has a pool per frame in flight
+// as well. Each worker is a thread that lives as long as its pools and is
+// handed a job per frame over a channel, so nothing is spawned per frame.
+// A job borrows the scene, which is sound because `record` doesn't return
+// until every job it sent has reported back.
+
+/// The most recording threads `default_worker_count` picks.
+pub const MAX_WORKER_COUNT: usize = 8;
+
+pub struct FrameCommandPools {
+    // allocated from, and freed along with, the pool of the same index
+    command_buffers: Vec<vk::CommandBuffer>,
+    pools: Vec<Owned<vk::CommandPool>>,
+    device: ash::Device,
+}
+
+impl FrameCommandPools {
+    /// A pool with one primary command buffer for each of `frame_count`
+    /// frames in flight.
+    pub fn new(
+        device: &ash::Device,
+        queue_family_index: u32,
+        frame_count: usize,
+    ) -> Result<FrameCommandPools, VkPlaygroundError> {
+        FrameCommandPools::with_level(
+            device,
+            queue_family_index,
+            frame_count,
+            vk::CommandBufferLevel::PRIMARY,
+        )
+    }
+
+    fn with_level(
+        device: &ash::Device,
+        queue_family_index: u32,
+        frame_count: usize,
+        level: vk::CommandBufferLevel,
+    ) -> Result<FrameCommandPools, VkPlaygroundError> {
+        let mut pools = Vec::with_capacity(frame_count);
+        let mut command_buffers = Vec::with_capacity(frame_count);
+
+        for _ in 0..frame_count {
+            let command_pool_create_info = vk::CommandPoolCreateInfo {
+                s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
+                p_next: ptr::null(),
+                flags: vk::CommandPoolCreateFlags::TRANSIENT,
+                queue_family_index,
+            };
+            let pool = unsafe {
+                device
+                    .create_command_pool(&command_pool_create_info, None)
+                    .vk_context("vkCreateCommandPool", "Failed to create Command Pool!")?
+            };
+            pools.push(Owned::new(device, pool));
+
+            let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
+                s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
+                p_next: ptr::null(),
+                command_buffer_count: 1,
+                command_pool: pool,
+                level,
+            };
+            let allocated = unsafe {
+                device
+                    .allocate_command_buffers(&command_buffer_allocate_info)
+                    .vk_context("vkAllocateCommandBuffers", "Failed to allocate Command Buffers!")?
+            };
+            command_buffers.push(allocated[0]);
+        }
+
+        Ok(FrameCommandPools {
+            command_buffers,
+            pools,
+            device: device.clone(),
+        })
+    }
+
+    /// Resets the pool of `frame_index` and has `record` fill its command
+    /// buffer, which is handed back ready to submit once. Only call this
+    /// after the fence of the frame's previous submit has signalled.
+    pub fn record<F>(
+        &self,
+        frame_index: usize,
+        record: F,
+    ) -> Result<vk::CommandBuffer, VkPlaygroundError>
+    where
+        F: FnOnce(vk::CommandBuffer, usize) -> Result<(), VkPlaygroundError>,
+    {
+        let command_buffer = self.command_buffers[frame_index];
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+            s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+            p_next: ptr::null(),
+            p_inheritance_info: ptr::null(),
+            flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+        };
+
+        unsafe {
+            self.device
+                .reset_command_pool(*self.pools[frame_index], vk::CommandPoolResetFlags::empty())
+                .vk_context("vkResetCommandPool", "Failed to reset Command Pool!")?;
+            self.device
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+                .vk_context(
+                    "vkBeginCommandBuffer",
+                    "Failed to begin recording Command Buffer at beginning!",
+                )?;
+        }
+
+        record(command_buffer, frame_index)?;
+
+        unsafe {
+            self.device
+                .end_command_buffer(command_buffer)
+                .vk_context("vkEndCommandBuffer", "Failed to record Command Buffer at Ending!")?;
+        }
+
+        Ok(command_buffer)
+    }
+}
+
+// a secondary command buffer recorded by a worker, and what it reports back
+type Job<'a> = Box<dyn FnOnce() -> Result<vk::CommandBuffer, VkPlaygroundError> + Send + 'a>;
+type JobResult = (usize, thread::Result<Result<vk::CommandBuffer, VkPlaygroundError>>);
+
+pub struct WorkerCommandPools {
+    // [worker][frame], allocated from, and freed along with, the matching pool
+    command_buffers: Vec<Vec<vk::CommandBuffer>>,
+    pools: Vec<Vec<Owned<vk::CommandPool>>>,
+    device: ash::Device,
+    // one per worker, closed on drop to stop the threads
+    job_senders: Vec<mpsc::Sender<Job<'static>>>,
+    results: mpsc::Receiver<JobResult>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl WorkerCommandPools {
+    /// A pool with one secondary command buffer for each of `frame_count`
+    /// frames in flight, for each of `worker_count` workers, and a thread
+    /// for each worker.
+    pub fn new(
+        device: &ash::Device,
+        queue_family_index: u32,
+        worker_count: usize,
+        frame_count: usize,
+    ) -> Result<WorkerCommandPools, VkPlaygroundError> {
+        let mut pools = Vec::with_capacity(worker_count);
+        let mut command_buffers = Vec::with_capacity(worker_count);
+
+        for _ in 0..worker_count {
+            let worker_pools = FrameCommandPools::with_level(
+                device,
+                queue_family_index,
+                frame_count,
+                vk::CommandBufferLevel::SECONDARY,
+            )?;
+            pools.push(worker_pools.pools);
+            command_buffers.push(worker_pools.command_buffers);
+        }
+
+        let (result_sender, results) = mpsc::channel();
+        let mut job_senders = Vec::with_capacity(worker_count);
+        let mut threads = Vec::with_capacity(worker_count);
+        for worker in 0..worker_count {
+            let (job_sender, jobs) = mpsc::channel::<Job<'static>>();
+            let result_sender = result_sender.clone();
+            let thread = thread::Builder::new()
+                .name(format!("recording worker {}", worker))
+                .spawn(move || {
+                    // a panic is reported like any other result, so `record`
+                    // always hears back about every job
+                    for job in jobs.iter() {
+                        let result = panic::catch_unwind(AssertUnwindSafe(job));
+                        if result_sender.send((worker, result)).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .map_err(|err| {
+                    VkPlaygroundError::unsupported(
+                        "WorkerCommandPools::new",
+                        format!("Failed to start a recording thread: {}", err),
+                    )
+                })?;
+            job_senders.push(job_sender);
+            threads.push(thread);
+        }
+
+        Ok(WorkerCommandPools {
+            command_buffers,
+            pools,
+            device: device.clone(),
+            job_senders,
+            results,
+            threads,
+        })
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Splits `0..item_count` into one contiguous range per worker and has
+    /// `record` fill a secondary command buffer with each range on its own
+    /// thread. The buffers continue subpass 0 of `render_pass` and come
+    /// back in the order of their ranges, ready for `cmd_execute_commands`.
+    /// Only call this after the fence of the frame's previous submit has
+    /// signalled.
+    pub fn record<F>(
+        &self,
+        frame_index: usize,
+        render_pass: vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        item_count: usize,
+        record: F,
+    ) -> Result<Vec<vk::CommandBuffer>, VkPlaygroundError>
+    where
+        F: Fn(vk::CommandBuffer, Range<usize>) -> Result<(), VkPlaygroundError> + Sync,
+    {
+        if item_count == 0 {
+            return Ok(vec![]);
+        }
+
+        let chunk_size = item_count.div_ceil(self.worker_count());
+        let record = &record;
+        let device = &self.device;
+
+        // everything that can panic happens before the first job is sent
+        let jobs: Vec<Job> = (0..item_count)
+            .step_by(chunk_size)
+            .enumerate()
+            .map(|(worker, start)| {
+                let items = start..(start + chunk_size).min(item_count);
+                let pool = *self.pools[worker][frame_index];
+                let command_buffer = self.command_buffers[worker][frame_index];
+                Box::new(move || {
+                    let record_items = |command_buffer| record(command_buffer, items);
+                    record_secondary(
+                        device,
+                        pool,
+                        command_buffer,
+                        render_pass,
+                        framebuffer,
+                        record_items,
+                    )
+                }) as Job
+            })
+            .collect();
+
+        let job_count = jobs.len();
+        let mut sent_count = 0;
+        for (worker, job) in jobs.into_iter().enumerate() {
+            // Safety: the job only borrows what this call does, and it's
+            // finished before the results are collected below
+            let job = unsafe { mem::transmute::<Job, Job<'static>>(job) };
+            if self.job_senders[worker].send(job).is_err() {
+                break;
+            }
+            sent_count += 1;
+        }
+
+        // a job that couldn't be sent stays None
+        let mut command_buffers: Vec<_> = (0..job_count).map(|_| None).collect();
+        let mut panic_payload = None;
+        for _ in 0..sent_count {
+            // an error means every worker has exited, so nothing still runs
+            let (worker, result) = match self.results.recv() {
+                Ok(result) => result,
+                Err(_) => break,
+            };
+            match result {
+                Ok(recorded) => command_buffers[worker] = Some(recorded),
+                Err(payload) => panic_payload = Some(payload),
+            }
+        }
+        if let Some(payload) = panic_payload {
+            panic::resume_unwind(payload);
+        }
+
+        command_buffers
+            .into_iter()
+            .map(|command_buffer| {
+                command_buffer.unwrap_or_else(|| {
+                    Err(VkPlaygroundError::unsupported(
+                        "WorkerCommandPools::record",
+                        "A recording thread has stopped",
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+impl Drop for WorkerCommandPools {
+    fn drop(&mut self) {
+        // closing the channels ends the threads' loops
+        self.job_senders.clear();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn record_secondary<F>(
+    device: &ash::Device,
+    pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    render_pass: vk::RenderPass,
+    framebuffer: vk::Framebuffer,
+    record: F,
+) -> Result<vk::CommandBuffer, VkPlaygroundError>
+where
+    F: FnOnce(vk::CommandBuffer) -> Result<(), VkPlaygroundError>,
+{
+    let inheritance_info = vk::CommandBufferInheritanceInfo {
+        s_type: vk::StructureType::COMMAND_BUFFER_INHERITANCE_INFO,
+        p_next: ptr::null(),
+        render_pass,
+        subpass: 0,
+        framebuffer,
+        occlusion_query_enable: vk::FALSE,
+        query_flags: vk::QueryControlFlags::empty(),
+        pipeline_statistics: vk::QueryPipelineStatisticFlags::empty(),
+    };
+    let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+        s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+        p_next: ptr::null(),
+        p_inheritance_info: &inheritance_info,
+        flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT
+            | vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE,
+    };
+
+    unsafe {
+        device
+            .reset_command_pool(pool, vk::CommandPoolResetFlags::empty())
+            .vk_context("vkResetCommandPool", "Failed to reset Command Pool!")?;
+        device
+            .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+            .vk_context(
+                "vkBeginCommandBuffer",
+                "Failed to begin recording Command Buffer at beginning!",
+            )?;
+    }
+
+    record(command_buffer)?;
+
+    unsafe {
+        device
+            .end_command_buffer(command_buffer)
+            .vk_context("vkEndCommandBuffer", "Failed to record Command Buffer at Ending!")?;
+    }
+
+    Ok(command_buffer)
+}
+
+/// One recording thread per core, up to `MAX_WORKER_COUNT`, or none on a
+/// single core machine where they'd only add overhead.
+pub fn default_worker_count() -> usize {
+    let core_count = thread::available_parallelism().map_or(1, |count| count.get());
+    if core_count > 1 {
+        core_count.min(MAX_WORKER_COUNT)
+    } else {
+        0
+    }
+}
@@ -0,0 +1,416 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+// Just enough JSON for glTF: the whole document is parsed into a tree of
+// `Json` values up front, and looked into with `get` and the `as_` helpers,
+// which return `None` for anything missing or of another type, e.g.
+//
+//     let json = Json::parse(&text)?;
+//     let node_count = json.get("nodes").map_or(0, |nodes| nodes.items().len());
+//
+// Objects keep their members in order, and the last of repeated keys wins.
+
+// far deeper than any glTF document, but keeps a hostile one from
+// overflowing the stack
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses `text` as a single JSON value, with nothing but whitespace
+    /// after it. The error says what was wrong and where.
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            offset: 0,
+        };
+        let value = parser.parse_value(0)?;
+        parser.skip_whitespace();
+        match parser.chars.peek() {
+            None => Ok(value),
+            Some(_) => Err(parser.error("trailing characters")),
+        }
+    }
+
+    /// The member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .rev()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// The elements of an array, nothing for anything else.
+    pub fn items(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        self.as_f64().map(|number| number as f32)
+    }
+
+    /// A number that is a whole, non-negative index or count.
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|number| *number >= 0.0 && number.fract() == 0.0)
+            .map(|number| number as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// An array of exactly N numbers, such as a vector or matrix.
+    pub fn as_f32_array<const N: usize>(&self) -> Option<[f32; N]> {
+        let items = self.items();
+        if items.len() != N {
+            return None;
+        }
+
+        let mut array = [0.0; N];
+        for (value, item) in array.iter_mut().zip(items.iter()) {
+            *value = item.as_f32()?;
+        }
+
+        Some(array)
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    // in characters, for errors
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn parse_value(&mut self, depth: usize) -> Result<Json, String> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{') => self.parse_object(depth),
+            Some('[') => self.parse_array(depth),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('t') => self.parse_literal("true", Json::Bool(true)),
+            Some('f') => self.parse_literal("false", Json::Bool(false)),
+            Some('n') => self.parse_literal("null", Json::Null),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut members = vec![];
+
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.parse_value(depth + 1)?;
+            members.push((key, value));
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = vec![];
+
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.parse_value(depth + 1)?);
+
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(items)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => value.push(self.parse_escape()?),
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(self.error("control character in string"));
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, String> {
+        let escaped = match self.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let unit = self.parse_hex4()?;
+                // characters outside the BMP come as a surrogate pair
+                let code_point = if (0xD800..0xDC00).contains(&unit) {
+                    self.expect('\\')?;
+                    self.expect('u')?;
+                    let low = self.parse_hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    unit
+                };
+                return char::from_u32(code_point).ok_or_else(|| self.error("unpaired surrogate"));
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+
+        Ok(escaped)
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid \\u escape"))?;
+            value = value * 16 + digit;
+        }
+
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+                text.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+
+        // Rust is more lenient than JSON about leading zeros and dots
+        let digits = text.strip_prefix('-').unwrap_or(&text);
+        let starts_with_digit = |text: &str| text.starts_with(|c: char| c.is_ascii_digit());
+        let has_leading_zero = digits.starts_with('0') && starts_with_digit(&digits[1..]);
+        let has_bare_dot = digits
+            .split('.')
+            .skip(1)
+            .any(|fraction| !starts_with_digit(fraction));
+        let is_valid = starts_with_digit(digits) && !has_leading_zero && !has_bare_dot;
+        match text.parse() {
+            Ok(number) if is_valid => Ok(Json::Number(number)),
+            _ => Err(self.error("invalid number")),
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        for expected in literal.chars() {
+            if self.next() != Some(expected) {
+                return Err(self.error("invalid literal"));
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected '{}'", expected))),
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.offset += 1;
+        self.chars.next()
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at character {}", message, self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_values_are_parsed() {
+        let text = r#" {"a": [1, -2.5e1, true, false, null], "b": {"c": "d"}} "#;
+
+        let json = Json::parse(text).unwrap();
+
+        let items = json.get("a").unwrap().items();
+        assert_eq!(items[0].as_usize(), Some(1));
+        assert_eq!(items[1].as_f64(), Some(-25.0));
+        assert_eq!(items[2].as_bool(), Some(true));
+        assert_eq!(items[3].as_bool(), Some(false));
+        assert_eq!(items[4], Json::Null);
+        assert_eq!(
+            json.get("b").and_then(|b| b.get("c")).and_then(Json::as_str),
+            Some("d")
+        );
+    }
+
+    #[test]
+    fn empty_object_and_array_are_parsed() {
+        let json = Json::parse(r#"{"a": {}, "b": []}"#).unwrap();
+
+        assert_eq!(json.get("a"), Some(&Json::Object(vec![])));
+        assert_eq!(json.get("b"), Some(&Json::Array(vec![])));
+    }
+
+    #[test]
+    fn last_of_repeated_keys_wins() {
+        let json = Json::parse(r#"{"a": 1, "a": 2}"#).unwrap();
+
+        assert_eq!(json.get("a").and_then(Json::as_usize), Some(2));
+    }
+
+    #[test]
+    fn escapes_are_decoded() {
+        let json = Json::parse(r#""\"\\\/\b\f\n\r\t é 😀""#).unwrap();
+
+        assert_eq!(json.as_str(), Some("\"\\/\u{8}\u{c}\n\r\t é 😀"));
+    }
+
+    #[test]
+    fn unpaired_surrogates_are_rejected() {
+        assert!(Json::parse(r#""\ud83d""#).is_err());
+        assert!(Json::parse(r#""\ud83dA""#).is_err());
+        assert!(Json::parse(r#""\ude00""#).is_err());
+    }
+
+    #[test]
+    fn invalid_numbers_are_rejected() {
+        for text in ["01", "-", "1.", ".5", "1.e5", "+1", "--1", "1e"].iter() {
+            assert!(Json::parse(text).is_err(), "{} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        let texts = [
+            "",
+            "{",
+            r#"{"a" 1}"#,
+            r#"{"a": 1,}"#,
+            "[1 2]",
+            "[1,]",
+            "tru",
+            r#""unterminated"#,
+            "\"\u{1}\"",
+            r#""\x""#,
+            "1 2",
+        ];
+
+        for text in texts.iter() {
+            assert!(Json::parse(text).is_err(), "{:?} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn errors_say_where() {
+        let error = Json::parse("[1, x]").unwrap_err();
+
+        assert_eq!(error, "unexpected character at character 4");
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let text = "[".repeat(MAX_DEPTH + 2) + &"]".repeat(MAX_DEPTH + 2);
+
+        assert!(Json::parse(&text).is_err());
+    }
+
+    #[test]
+    fn as_usize_takes_only_whole_non_negative_numbers() {
+        assert_eq!(Json::Number(3.0).as_usize(), Some(3));
+        assert_eq!(Json::Number(3.5).as_usize(), None);
+        assert_eq!(Json::Number(-1.0).as_usize(), None);
+        assert_eq!(Json::String("3".to_owned()).as_usize(), None);
+    }
+
+    #[test]
+    fn as_f32_array_needs_exactly_n_numbers() {
+        let json = Json::parse("[1, 2, 3]").unwrap();
+
+        assert_eq!(json.as_f32_array::<3>(), Some([1.0, 2.0, 3.0]));
+        assert_eq!(json.as_f32_array::<4>(), None);
+        assert_eq!(Json::parse(r#"[1, "2"]"#).unwrap().as_f32_array::<2>(), None);
+    }
+}
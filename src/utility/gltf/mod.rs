@@ -0,0 +1,1622 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+use cgmath::{Matrix4, Quaternion, SquareMatrix, Vector3};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::structures::PbrVertex;
+
+mod json;
+
+use self::json::Json;
+
+// glTF 2.0 scenes, from a .gltf file with its buffers and images next to it
+// or embedded as data URIs, or from a single binary .glb. Everything is read
+// and decoded up front into plain data that the usual helpers take, e.g.
+//
+//     let scene = gltf::load_gltf(Path::new("resources/models/helmet.glb"))?;
+//     let primitive = &scene.meshes[0].primitives[0];
+//     let (vertex_buffer, _) =
+//         uploader.upload_buffer(allocator, usage, &primitive.vertices, Access::VertexInput)?;
+//     let image = &scene.images[0];
+//     let (texture, _) = uploader.upload_image(allocator, image.extent(), image.format(),
+//         &image.pixels, Access::FragmentShaderRead)?;
+//     let sampler = gltf::create_sampler(device, &scene.textures[0].sampler)?;
+//     let world_transforms = scene.world_transforms();
+//
+// Primitives come out as indexed triangle lists. Missing normals are made
+// flat, as the spec asks, and missing tangents are generated from the
+// texture coordinates. Only the first set of texture coordinates and vertex
+// colours is read; skins, morph targets, animations and sparse accessors
+// aren't supported.
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_HEADER_SIZE: usize = 12;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+// accessor component types
+const COMPONENT_BYTE: usize = 5120;
+const COMPONENT_UNSIGNED_BYTE: usize = 5121;
+const COMPONENT_SHORT: usize = 5122;
+const COMPONENT_UNSIGNED_SHORT: usize = 5123;
+const COMPONENT_UNSIGNED_INT: usize = 5125;
+const COMPONENT_FLOAT: usize = 5126;
+
+// An accessor without a buffer view has no data to bound its count, so it's
+// capped instead. Far more vertices than a primitive has in practice.
+const MAX_ZERO_ACCESSOR_COUNT: usize = 1 << 24;
+
+// primitive modes
+const MODE_TRIANGLES: usize = 4;
+const MODE_TRIANGLE_STRIP: usize = 5;
+const MODE_TRIANGLE_FAN: usize = 6;
+
+// sampler filters and wrap modes, the GL enums
+const FILTER_NEAREST: usize = 9728;
+const FILTER_NEAREST_MIPMAP_NEAREST: usize = 9984;
+const FILTER_LINEAR_MIPMAP_NEAREST: usize = 9985;
+const FILTER_NEAREST_MIPMAP_LINEAR: usize = 9986;
+const WRAP_CLAMP_TO_EDGE: usize = 33071;
+const WRAP_MIRRORED_REPEAT: usize = 33648;
+
+#[derive(Debug, Clone)]
+pub struct GltfScene {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    pub textures: Vec<Texture>,
+    pub images: Vec<Image>,
+    pub cameras: Vec<Camera>,
+    pub nodes: Vec<Node>,
+    /// The top nodes of the file's default scene, or of every node tree
+    /// when it names none.
+    pub root_nodes: Vec<usize>,
+}
+
+impl GltfScene {
+    /// The transform from each node's space into the scene's, by node
+    /// index. Nodes outside the scene keep their local transform.
+    pub fn world_transforms(&self) -> Vec<Matrix4<f32>> {
+        let mut world_transforms: Vec<Matrix4<f32>> =
+            self.nodes.iter().map(|node| node.transform).collect();
+
+        // the loader made sure every node has one parent at most, the roots
+        // none, and that there are no cycles
+        let mut pending: Vec<(usize, Matrix4<f32>)> = self
+            .root_nodes
+            .iter()
+            .map(|&root| (root, Matrix4::identity()))
+            .collect();
+        while let Some((node, parent_transform)) = pending.pop() {
+            let world_transform = parent_transform * self.nodes[node].transform;
+            world_transforms[node] = world_transform;
+            pending.extend(
+                self.nodes[node]
+                    .children
+                    .iter()
+                    .map(|&child| (child, world_transform)),
+            );
+        }
+
+        world_transforms
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub name: String,
+    pub primitives: Vec<Primitive>,
+}
+
+/// Part of a mesh drawn with one material, as an indexed triangle list.
+#[derive(Debug, Clone)]
+pub struct Primitive {
+    pub vertices: Vec<PbrVertex>,
+    pub indices: Vec<u32>,
+    /// `None` for the default material, see `Material::default`.
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Fully transparent below the cutoff, fully opaque otherwise.
+    Mask(f32),
+    Blend,
+}
+
+/// A texture used by a material, sampled with the vertices' texture
+/// coordinates when `tex_coord` is 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureRef {
+    pub texture: usize,
+    pub tex_coord: u32,
+}
+
+/// A PBR metallic-roughness material. The factors multiply what the
+/// textures hold, where they have one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// Roughness in the green channel, metalness in the blue.
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive_texture: Option<TextureRef>,
+    pub emissive_factor: [f32; 3],
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+impl Default for Material {
+    /// The material of primitives without one, per the spec.
+    fn default() -> Material {
+        Material {
+            name: String::new(),
+            base_color_factor: [1.0; 4],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_texture: None,
+            emissive_factor: [0.0; 3],
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Texture {
+    pub image: usize,
+    pub sampler: Sampler,
+}
+
+/// How a texture is sampled, for `create_sampler`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampler {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    pub address_mode_u: vk::SamplerAddressMode,
+    pub address_mode_v: vk::SamplerAddressMode,
+}
+
+impl Default for Sampler {
+    fn default() -> Sampler {
+        Sampler {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_mode_u: vk::SamplerAddressMode::REPEAT,
+            address_mode_v: vk::SamplerAddressMode::REPEAT,
+        }
+    }
+}
+
+/// A decoded image, as tightly packed 8 bit RGBA with a single mip level.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    /// Whether it holds colours, as a base colour or emissive texture of
+    /// any material, rather than data such as normals.
+    pub is_srgb: bool,
+}
+
+impl Image {
+    pub fn extent(&self) -> vk::Extent2D {
+        vk::Extent2D {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub fn format(&self) -> vk::Format {
+        if self.is_srgb {
+            vk::Format::R8G8B8A8_SRGB
+        } else {
+            vk::Format::R8G8B8A8_UNORM
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// An infinite projection when there is no `zfar`.
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+/// A camera looking down -z of the nodes that use it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    pub name: String,
+    pub projection: Projection,
+}
+
+impl Camera {
+    /// The projection matrix with y flipped for Vulkan, like the demos'.
+    /// `aspect_ratio` is the viewport's, used when the camera has none.
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        // a column at a time, the spec's matrices transposed
+        let columns = match self.projection {
+            Projection::Perspective {
+                yfov,
+                aspect_ratio: camera_aspect_ratio,
+                znear,
+                zfar,
+            } => {
+                let aspect_ratio = camera_aspect_ratio.unwrap_or(aspect_ratio);
+                let focal_length = 1.0 / (0.5 * yfov).tan();
+                let (depth_scale, depth_offset) = match zfar {
+                    Some(zfar) => (
+                        (zfar + znear) / (znear - zfar),
+                        2.0 * zfar * znear / (znear - zfar),
+                    ),
+                    None => (-1.0, -2.0 * znear),
+                };
+                [
+                    [focal_length / aspect_ratio, 0.0, 0.0, 0.0],
+                    [0.0, focal_length, 0.0, 0.0],
+                    [0.0, 0.0, depth_scale, -1.0],
+                    [0.0, 0.0, depth_offset, 0.0],
+                ]
+            }
+            Projection::Orthographic {
+                xmag,
+                ymag,
+                znear,
+                zfar,
+            } => [
+                [1.0 / xmag, 0.0, 0.0, 0.0],
+                [0.0, 1.0 / ymag, 0.0, 0.0],
+                [0.0, 0.0, 2.0 / (znear - zfar), 0.0],
+                [0.0, 0.0, (zfar + znear) / (znear - zfar), 1.0],
+            ],
+        };
+
+        let mut projection = Matrix4::from(columns);
+        projection[1][1] *= -1.0;
+
+        projection
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    /// Relative to the parent node, see `GltfScene::world_transforms`.
+    pub transform: Matrix4<f32>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+}
+
+/// Loads a .gltf or .glb file, telling them apart by their contents.
+pub fn load_gltf(path: &Path) -> Result<GltfScene, VkPlaygroundError> {
+    let bytes = fs::read(path).map_err(|source| VkPlaygroundError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let (json_bytes, binary_chunk) = if bytes.starts_with(GLB_MAGIC) {
+        split_glb(&bytes).map_err(|context| gltf_error(path, context))?
+    } else {
+        (bytes.as_slice(), None)
+    };
+    let json_text =
+        std::str::from_utf8(json_bytes).map_err(|_| gltf_error(path, "the JSON isn't UTF-8"))?;
+    let document = Json::parse(json_text).map_err(|context| gltf_error(path, context))?;
+
+    let version = document.get("asset").and_then(|asset| asset.get("version"));
+    if !version
+        .and_then(Json::as_str)
+        .is_some_and(|version| version.starts_with("2."))
+    {
+        return Err(gltf_error(path, "only glTF 2.0 is supported"));
+    }
+    if let Some(required) = document
+        .get("extensionsRequired")
+        .and_then(|list| list.items().first())
+    {
+        return Err(gltf_error(
+            path,
+            format!(
+                "the extension {} is required",
+                required.as_str().unwrap_or("?")
+            ),
+        ));
+    }
+
+    let mut loader = Loader {
+        path,
+        directory: path.parent().unwrap_or_else(|| Path::new("")),
+        document: &document,
+        buffers: vec![],
+    };
+    loader.buffers = loader.load_buffers(binary_chunk)?;
+
+    let materials = loader.load_materials()?;
+    let textures = loader.load_textures()?;
+    let images = loader.load_images(&materials, &textures)?;
+    let meshes = loader.load_meshes(materials.len())?;
+    let cameras = loader.load_cameras()?;
+    let nodes = loader.load_nodes(meshes.len(), cameras.len())?;
+    let root_nodes = loader.load_root_nodes(&nodes)?;
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        textures,
+        images,
+        cameras,
+        nodes,
+        root_nodes,
+    })
+}
+
+/// A sampler set up the way `sampler` asks, with anisotropy like
+/// `vkimage::create_texture_sampler`.
+pub fn create_sampler(
+    device: &ash::Device,
+    sampler: &Sampler,
+) -> Result<vk::Sampler, VkPlaygroundError> {
+    let sampler_create_info = vk::SamplerCreateInfo {
+        s_type: vk::StructureType::SAMPLER_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::SamplerCreateFlags::empty(),
+        mag_filter: sampler.mag_filter,
+        min_filter: sampler.min_filter,
+        address_mode_u: sampler.address_mode_u,
+        address_mode_v: sampler.address_mode_v,
+        address_mode_w: vk::SamplerAddressMode::REPEAT,
+        max_anisotropy: 16.0,
+        compare_enable: vk::FALSE,
+        compare_op: vk::CompareOp::ALWAYS,
+        mipmap_mode: sampler.mipmap_mode,
+        min_lod: 0.0,
+        max_lod: vk::LOD_CLAMP_NONE,
+        mip_lod_bias: 0.0,
+        border_color: vk::BorderColor::INT_OPAQUE_BLACK,
+        anisotropy_enable: vk::TRUE,
+        unnormalized_coordinates: vk::FALSE,
+    };
+
+    unsafe {
+        device
+            .create_sampler(&sampler_create_info, None)
+            .vk_context("vkCreateSampler", "Failed to create Sampler!")
+    }
+}
+
+fn gltf_error(path: &Path, context: impl Into<String>) -> VkPlaygroundError {
+    VkPlaygroundError::Gltf {
+        path: path.to_path_buf(),
+        context: context.into(),
+    }
+}
+
+// The JSON chunk and the binary one, if any, of a .glb file.
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let read_u32 = |offset: usize| {
+        bytes
+            .get(offset..offset + 4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+    };
+
+    if read_u32(4) != Some(2) {
+        return Err("only version 2 .glb files are supported".to_owned());
+    }
+    let length = read_u32(8)
+        .map_or(0, |length| length as usize)
+        .min(bytes.len());
+
+    let mut chunks = vec![];
+    let mut offset = GLB_HEADER_SIZE;
+    while offset + 8 <= length {
+        let (chunk_length, chunk_type) = (read_u32(offset).unwrap() as usize, read_u32(offset + 4));
+        let chunk = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| "a chunk runs past the end of the file".to_owned())?;
+        chunks.push((chunk_type.unwrap(), chunk));
+        // chunks are padded to 4 bytes
+        offset += 8 + chunk_length.div_ceil(4) * 4;
+    }
+
+    match chunks.as_slice() {
+        [(GLB_CHUNK_JSON, json), rest @ ..] => {
+            let binary = rest
+                .first()
+                .filter(|(ty, _)| *ty == GLB_CHUNK_BIN)
+                .map(|(_, bin)| *bin);
+            Ok((json, binary))
+        }
+        _ => Err("the first chunk isn't JSON".to_owned()),
+    }
+}
+
+// An accessor's layout, checked against its buffer view.
+struct AccessorLayout<'a> {
+    // none for an accessor without a buffer view, which is all zeros
+    data: Option<&'a [u8]>,
+    count: usize,
+    components: usize,
+    component_type: usize,
+    normalized: bool,
+    // between elements, in bytes
+    stride: usize,
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    directory: &'a Path,
+    document: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Loader<'_> {
+    fn error(&self, context: impl Into<String>) -> VkPlaygroundError {
+        gltf_error(self.path, context)
+    }
+
+    // the elements of a top level array such as "meshes"
+    fn array(&self, name: &str) -> &[Json] {
+        self.document.get(name).map_or(&[], Json::items)
+    }
+
+    // an optional index into the top level array `into`, checked
+    fn index(
+        &self,
+        value: Option<&Json>,
+        into: &str,
+        count: usize,
+    ) -> Result<Option<usize>, VkPlaygroundError> {
+        match value {
+            None => Ok(None),
+            Some(value) => match value.as_usize() {
+                Some(index) if index < count => Ok(Some(index)),
+                _ => Err(self.error(format!("an index into {} is out of range", into))),
+            },
+        }
+    }
+
+    fn load_buffers(&self, binary_chunk: Option<&[u8]>) -> Result<Vec<Vec<u8>>, VkPlaygroundError> {
+        let mut buffers = vec![];
+        for (i, buffer) in self.array("buffers").iter().enumerate() {
+            let data = match buffer.get("uri").and_then(Json::as_str) {
+                Some(uri) => self.read_uri(uri)?,
+                // only the first buffer of a .glb may leave out its URI
+                None if i == 0 => binary_chunk
+                    .ok_or_else(|| self.error("buffer 0 has no URI and there's no binary chunk"))?
+                    .to_vec(),
+                None => return Err(self.error(format!("buffer {} has no URI", i))),
+            };
+
+            let byte_length = buffer
+                .get("byteLength")
+                .and_then(Json::as_usize)
+                .unwrap_or(0);
+            if data.len() < byte_length {
+                return Err(self.error(format!("buffer {} is shorter than its byteLength", i)));
+            }
+            buffers.push(data);
+        }
+
+        Ok(buffers)
+    }
+
+    // the contents of a data URI, or of a file relative to the glTF
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, VkPlaygroundError> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (media_type, encoded) = data
+                .split_once(',')
+                .ok_or_else(|| self.error("a data URI has no ','"))?;
+            if !media_type.ends_with(";base64") {
+                return Err(self.error("only base64 data URIs are supported"));
+            }
+            return decode_base64(encoded)
+                .ok_or_else(|| self.error("a data URI isn't valid base64"));
+        }
+
+        let path: PathBuf = self.directory.join(decode_percent_escapes(uri));
+        fs::read(&path).map_err(|source| VkPlaygroundError::Io { path, source })
+    }
+
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), VkPlaygroundError> {
+        let view = self
+            .array("bufferViews")
+            .get(index)
+            .ok_or_else(|| self.error("a buffer view index is out of range"))?;
+        let buffer = self.index(view.get("buffer"), "buffers", self.buffers.len())?;
+        let buffer = buffer.ok_or_else(|| self.error("a buffer view has no buffer"))?;
+        let offset = view.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let length = view.get("byteLength").and_then(Json::as_usize).unwrap_or(0);
+
+        let data = offset
+            .checked_add(length)
+            .and_then(|end| self.buffers[buffer].get(offset..end))
+            .ok_or_else(|| self.error(format!("buffer view {} runs past its buffer", index)))?;
+
+        Ok((data, view.get("byteStride").and_then(Json::as_usize)))
+    }
+
+    fn accessor_layout(&self, index: usize) -> Result<AccessorLayout<'_>, VkPlaygroundError> {
+        let accessor = self
+            .array("accessors")
+            .get(index)
+            .ok_or_else(|| self.error("an accessor index is out of range"))?;
+        if accessor.get("sparse").is_some() {
+            return Err(self.error("sparse accessors aren't supported"));
+        }
+
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            _ => return Err(self.error(format!("accessor {} isn't a scalar or vector", index))),
+        };
+        let component_type = accessor
+            .get("componentType")
+            .and_then(Json::as_usize)
+            .unwrap_or(0);
+        let component_size = match component_type {
+            COMPONENT_BYTE | COMPONENT_UNSIGNED_BYTE => 1,
+            COMPONENT_SHORT | COMPONENT_UNSIGNED_SHORT => 2,
+            COMPONENT_UNSIGNED_INT | COMPONENT_FLOAT => 4,
+            _ => {
+                return Err(self.error(format!("accessor {} has an unknown component type", index)))
+            }
+        };
+        let count = accessor.get("count").and_then(Json::as_usize).unwrap_or(0);
+        let element_size = components * component_size;
+
+        let (data, stride) = match accessor.get("bufferView").and_then(Json::as_usize) {
+            Some(view) => {
+                let (view_data, stride) = self.buffer_view(view)?;
+                let offset = accessor
+                    .get("byteOffset")
+                    .and_then(Json::as_usize)
+                    .unwrap_or(0);
+                let data = view_data.get(offset..).ok_or_else(|| {
+                    self.error(format!("accessor {} starts past its buffer view", index))
+                })?;
+                (Some(data), stride.unwrap_or(element_size))
+            }
+            // all zeros, per the spec
+            None => (None, element_size),
+        };
+        // from the start of the first element to the end of the last
+        let extent = match count.checked_sub(1) {
+            Some(last) => last
+                .checked_mul(stride)
+                .and_then(|start| start.checked_add(element_size)),
+            None => Some(0),
+        };
+        if data.is_some_and(|data| extent.is_none_or(|extent| extent > data.len())) {
+            return Err(self.error(format!("accessor {} runs past its buffer view", index)));
+        }
+        if data.is_none() && count > MAX_ZERO_ACCESSOR_COUNT {
+            return Err(self.error(format!("accessor {} has too many elements", index)));
+        }
+
+        Ok(AccessorLayout {
+            data,
+            count,
+            components,
+            component_type,
+            normalized: accessor
+                .get("normalized")
+                .and_then(Json::as_bool)
+                .unwrap_or(false),
+            stride,
+        })
+    }
+
+    // every element of a float, or normalized integer, accessor, which must
+    // have one of `component_counts` components
+    fn read_floats(
+        &self,
+        index: usize,
+        component_counts: &[usize],
+    ) -> Result<(usize, Vec<f32>), VkPlaygroundError> {
+        let layout = self.accessor_layout(index)?;
+        if !component_counts.contains(&layout.components) {
+            return Err(self.error(format!("accessor {} has the wrong type", index)));
+        }
+        let value_count = layout
+            .count
+            .checked_mul(layout.components)
+            .ok_or_else(|| self.error(format!("accessor {} has too many elements", index)))?;
+        let data = match layout.data {
+            Some(data) => data,
+            None => return Ok((layout.components, vec![0.0; value_count])),
+        };
+
+        let mut values = Vec::with_capacity(value_count);
+        for i in 0..layout.count {
+            let element = &data[i * layout.stride..];
+            for component in 0..layout.components {
+                let value = read_component(element, component, layout.component_type);
+                values.push(normalize(value, layout.component_type, layout.normalized));
+            }
+        }
+
+        Ok((layout.components, values))
+    }
+
+    fn read_indices(&self, index: usize) -> Result<Vec<u32>, VkPlaygroundError> {
+        let layout = self.accessor_layout(index)?;
+        let is_integer = matches!(
+            layout.component_type,
+            COMPONENT_UNSIGNED_BYTE | COMPONENT_UNSIGNED_SHORT | COMPONENT_UNSIGNED_INT
+        );
+        if layout.components != 1 || !is_integer {
+            return Err(self.error(format!("accessor {} can't hold indices", index)));
+        }
+        let data = match layout.data {
+            Some(data) => data,
+            None => return Ok(vec![0; layout.count]),
+        };
+
+        Ok((0..layout.count)
+            .map(|i| read_component(&data[i * layout.stride..], 0, layout.component_type) as u32)
+            .collect())
+    }
+
+    fn load_materials(&self) -> Result<Vec<Material>, VkPlaygroundError> {
+        let texture_count = self.array("textures").len();
+        let mut materials = vec![];
+
+        for material in self.array("materials").iter() {
+            let pbr = material.get("pbrMetallicRoughness");
+            let pbr_value = |key: &str| pbr.and_then(|pbr| pbr.get(key));
+            let texture_ref =
+                |info: Option<&Json>| -> Result<Option<TextureRef>, VkPlaygroundError> {
+                    let texture = self.index(
+                        info.and_then(|info| info.get("index")),
+                        "textures",
+                        texture_count,
+                    )?;
+                    Ok(texture.map(|texture| TextureRef {
+                        texture,
+                        tex_coord: info
+                            .and_then(|info| info.get("texCoord"))
+                            .and_then(Json::as_usize)
+                            .unwrap_or(0) as u32,
+                    }))
+                };
+            let default = Material::default();
+            let alpha_cutoff = material
+                .get("alphaCutoff")
+                .and_then(Json::as_f32)
+                .unwrap_or(0.5);
+
+            materials.push(Material {
+                name: material
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .unwrap_or("")
+                    .to_owned(),
+                base_color_factor: pbr_value("baseColorFactor")
+                    .and_then(Json::as_f32_array)
+                    .unwrap_or(default.base_color_factor),
+                base_color_texture: texture_ref(pbr_value("baseColorTexture"))?,
+                metallic_factor: pbr_value("metallicFactor")
+                    .and_then(Json::as_f32)
+                    .unwrap_or(default.metallic_factor),
+                roughness_factor: pbr_value("roughnessFactor")
+                    .and_then(Json::as_f32)
+                    .unwrap_or(default.roughness_factor),
+                metallic_roughness_texture: texture_ref(pbr_value("metallicRoughnessTexture"))?,
+                normal_texture: texture_ref(material.get("normalTexture"))?,
+                normal_scale: material
+                    .get("normalTexture")
+                    .and_then(|info| info.get("scale"))
+                    .and_then(Json::as_f32)
+                    .unwrap_or(default.normal_scale),
+                occlusion_texture: texture_ref(material.get("occlusionTexture"))?,
+                occlusion_strength: material
+                    .get("occlusionTexture")
+                    .and_then(|info| info.get("strength"))
+                    .and_then(Json::as_f32)
+                    .unwrap_or(default.occlusion_strength),
+                emissive_texture: texture_ref(material.get("emissiveTexture"))?,
+                emissive_factor: material
+                    .get("emissiveFactor")
+                    .and_then(Json::as_f32_array)
+                    .unwrap_or(default.emissive_factor),
+                alpha_mode: match material.get("alphaMode").and_then(Json::as_str) {
+                    Some("MASK") => AlphaMode::Mask(alpha_cutoff),
+                    Some("BLEND") => AlphaMode::Blend,
+                    _ => AlphaMode::Opaque,
+                },
+                double_sided: material
+                    .get("doubleSided")
+                    .and_then(Json::as_bool)
+                    .unwrap_or(false),
+            });
+        }
+
+        Ok(materials)
+    }
+
+    fn load_textures(&self) -> Result<Vec<Texture>, VkPlaygroundError> {
+        let image_count = self.array("images").len();
+        let samplers = self.array("samplers");
+        let mut textures = vec![];
+
+        for texture in self.array("textures").iter() {
+            let image = self
+                .index(texture.get("source"), "images", image_count)?
+                .ok_or_else(|| {
+                    self.error("a texture has no image, or only one from an extension")
+                })?;
+            let sampler = match self.index(texture.get("sampler"), "samplers", samplers.len())? {
+                Some(sampler) => convert_sampler(&samplers[sampler]),
+                None => Sampler::default(),
+            };
+
+            textures.push(Texture { image, sampler });
+        }
+
+        Ok(textures)
+    }
+
+    fn load_images(
+        &self,
+        materials: &[Material],
+        textures: &[Texture],
+    ) -> Result<Vec<Image>, VkPlaygroundError> {
+        // colour textures are stored in sRGB, everything else is linear
+        let colour_images: Vec<usize> = materials
+            .iter()
+            .flat_map(|material| [material.base_color_texture, material.emissive_texture])
+            .flatten()
+            .map(|texture_ref| textures[texture_ref.texture].image)
+            .collect();
+
+        let mut images = vec![];
+        for (i, image) in self.array("images").iter().enumerate() {
+            let (bytes, image_path) = match (
+                image.get("uri").and_then(Json::as_str),
+                image.get("bufferView"),
+            ) {
+                (Some(uri), _) => {
+                    let image_path = if uri.starts_with("data:") {
+                        self.path.to_path_buf()
+                    } else {
+                        self.directory.join(decode_percent_escapes(uri))
+                    };
+                    (self.read_uri(uri)?, image_path)
+                }
+                (None, Some(view)) => {
+                    let view = view
+                        .as_usize()
+                        .ok_or_else(|| self.error("an image's buffer view isn't an index"))?;
+                    (self.buffer_view(view)?.0.to_vec(), self.path.to_path_buf())
+                }
+                (None, None) => return Err(self.error(format!("image {} has no data", i))),
+            };
+
+            let decoded = image::load_from_memory(&bytes)
+                .map_err(|source| VkPlaygroundError::Image {
+                    path: image_path,
+                    source,
+                })?
+                .to_rgba();
+            images.push(Image {
+                name: image
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .unwrap_or("")
+                    .to_owned(),
+                width: decoded.width(),
+                height: decoded.height(),
+                pixels: decoded.into_raw(),
+                is_srgb: colour_images.contains(&i),
+            });
+        }
+
+        Ok(images)
+    }
+
+    fn load_meshes(&self, material_count: usize) -> Result<Vec<Mesh>, VkPlaygroundError> {
+        let mut meshes = vec![];
+        for mesh in self.array("meshes").iter() {
+            let mut primitives = vec![];
+            for primitive in mesh.get("primitives").map_or(&[][..], Json::items) {
+                primitives.push(self.load_primitive(primitive, material_count)?);
+            }
+
+            meshes.push(Mesh {
+                name: mesh
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .unwrap_or("")
+                    .to_owned(),
+                primitives,
+            });
+        }
+
+        Ok(meshes)
+    }
+
+    fn load_primitive(
+        &self,
+        primitive: &Json,
+        material_count: usize,
+    ) -> Result<Primitive, VkPlaygroundError> {
+        let attributes = primitive.get("attributes");
+        let attribute = |name: &str| {
+            attributes
+                .and_then(|attributes| attributes.get(name))
+                .and_then(Json::as_usize)
+        };
+
+        let position_accessor =
+            attribute("POSITION").ok_or_else(|| self.error("a primitive has no positions"))?;
+        let (_, positions) = self.read_floats(position_accessor, &[3])?;
+        let vertex_count = positions.len() / 3;
+        let mut vertices: Vec<PbrVertex> = positions
+            .chunks_exact(3)
+            .map(|pos| PbrVertex {
+                pos: [pos[0], pos[1], pos[2]],
+                normal: [0.0; 3],
+                tangent: [0.0; 4],
+                tex_coord: [0.0; 2],
+                color: [1.0; 4],
+            })
+            .collect();
+
+        // each of the optional attributes is copied over when there is one
+        let mut read_attribute = |name: &str,
+                                  component_counts: &[usize],
+                                  write: &mut dyn FnMut(&mut PbrVertex, &[f32])|
+         -> Result<bool, VkPlaygroundError> {
+            let accessor = match attribute(name) {
+                Some(accessor) => accessor,
+                None => return Ok(false),
+            };
+            let (components, values) = self.read_floats(accessor, component_counts)?;
+            if vertex_count.checked_mul(components) != Some(values.len()) {
+                return Err(self.error(format!("{} has a different count than POSITION", name)));
+            }
+            for (vertex, value) in vertices.iter_mut().zip(values.chunks_exact(components)) {
+                write(vertex, value);
+            }
+
+            Ok(true)
+        };
+        let has_normals = read_attribute("NORMAL", &[3], &mut |vertex, normal| {
+            vertex.normal.copy_from_slice(normal)
+        })?;
+        let has_tangents = read_attribute("TANGENT", &[4], &mut |vertex, tangent| {
+            vertex.tangent.copy_from_slice(tangent)
+        })?;
+        let has_tex_coords = read_attribute("TEXCOORD_0", &[2], &mut |vertex, tex_coord| {
+            vertex.tex_coord.copy_from_slice(tex_coord)
+        })?;
+        read_attribute("COLOR_0", &[3, 4], &mut |vertex, color| {
+            vertex.color[..color.len()].copy_from_slice(color)
+        })?;
+
+        let mut indices = match primitive.get("indices").and_then(Json::as_usize) {
+            Some(accessor) => self.read_indices(accessor)?,
+            None => (0..vertex_count as u32).collect(),
+        };
+        if indices.iter().any(|&index| index as usize >= vertex_count) {
+            return Err(self.error("a primitive's indices run past its vertices"));
+        }
+        indices = match primitive
+            .get("mode")
+            .and_then(Json::as_usize)
+            .unwrap_or(MODE_TRIANGLES)
+        {
+            MODE_TRIANGLES => indices,
+            MODE_TRIANGLE_STRIP => triangle_strip_to_list(&indices),
+            MODE_TRIANGLE_FAN => triangle_fan_to_list(&indices),
+            _ => return Err(self.error("only triangle primitives are supported")),
+        };
+        indices.truncate(indices.len() / 3 * 3);
+
+        if !has_normals {
+            // flat normals need each triangle to have vertices of its own
+            vertices = indices
+                .iter()
+                .map(|&index| vertices[index as usize])
+                .collect();
+            indices = (0..vertices.len() as u32).collect();
+            generate_flat_normals(&mut vertices);
+        }
+        if !has_tangents {
+            generate_tangents(&mut vertices, &indices, has_tex_coords);
+        }
+
+        Ok(Primitive {
+            vertices,
+            indices,
+            material: self.index(primitive.get("material"), "materials", material_count)?,
+        })
+    }
+
+    fn load_cameras(&self) -> Result<Vec<Camera>, VkPlaygroundError> {
+        let mut cameras = vec![];
+        for camera in self.array("cameras").iter() {
+            let number = |projection: &str, key: &str| {
+                camera
+                    .get(projection)
+                    .and_then(|values| values.get(key))
+                    .and_then(Json::as_f32)
+            };
+            let projection = match camera.get("type").and_then(Json::as_str) {
+                Some("perspective") => Projection::Perspective {
+                    yfov: number("perspective", "yfov")
+                        .ok_or_else(|| self.error("a camera has no yfov"))?,
+                    aspect_ratio: number("perspective", "aspectRatio"),
+                    znear: number("perspective", "znear")
+                        .ok_or_else(|| self.error("a camera has no znear"))?,
+                    zfar: number("perspective", "zfar"),
+                },
+                Some("orthographic") => {
+                    let required = |key: &str| {
+                        number("orthographic", key)
+                            .ok_or_else(|| self.error(format!("a camera has no {}", key)))
+                    };
+                    Projection::Orthographic {
+                        xmag: required("xmag")?,
+                        ymag: required("ymag")?,
+                        znear: required("znear")?,
+                        zfar: required("zfar")?,
+                    }
+                }
+                _ => return Err(self.error("a camera has an unknown type")),
+            };
+
+            cameras.push(Camera {
+                name: camera
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .unwrap_or("")
+                    .to_owned(),
+                projection,
+            });
+        }
+
+        Ok(cameras)
+    }
+
+    fn load_nodes(
+        &self,
+        mesh_count: usize,
+        camera_count: usize,
+    ) -> Result<Vec<Node>, VkPlaygroundError> {
+        let node_count = self.array("nodes").len();
+        let mut has_parent = vec![false; node_count];
+        let mut nodes = vec![];
+
+        for node in self.array("nodes").iter() {
+            let mut children = vec![];
+            for child in node.get("children").map_or(&[][..], Json::items) {
+                let child = self.index(Some(child), "nodes", node_count)?.unwrap();
+                if has_parent[child] {
+                    return Err(self.error(format!("node {} has more than one parent", child)));
+                }
+                has_parent[child] = true;
+                children.push(child);
+            }
+
+            nodes.push(Node {
+                name: node
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .unwrap_or("")
+                    .to_owned(),
+                transform: self.node_transform(node)?,
+                children,
+                mesh: self.index(node.get("mesh"), "meshes", mesh_count)?,
+                camera: self.index(node.get("camera"), "cameras", camera_count)?,
+            });
+        }
+
+        // with one parent at most each, any node that can't be reached from
+        // one without a parent is in a cycle
+        let mut is_reachable = vec![false; node_count];
+        let mut pending: Vec<usize> = (0..node_count).filter(|&node| !has_parent[node]).collect();
+        while let Some(node) = pending.pop() {
+            is_reachable[node] = true;
+            pending.extend(nodes[node].children.iter());
+        }
+        if let Some(node) = is_reachable.iter().position(|is_reachable| !is_reachable) {
+            return Err(self.error(format!("node {} is its own ancestor", node)));
+        }
+
+        Ok(nodes)
+    }
+
+    // either a whole matrix, or translation, rotation and scale
+    fn node_transform(&self, node: &Json) -> Result<Matrix4<f32>, VkPlaygroundError> {
+        if let Some(matrix) = node.get("matrix") {
+            let columns: [f32; 16] = matrix
+                .as_f32_array()
+                .ok_or_else(|| self.error("a node's matrix doesn't have 16 numbers"))?;
+            // column-major, like cgmath's
+            let column = |i: usize| {
+                [
+                    columns[i * 4],
+                    columns[i * 4 + 1],
+                    columns[i * 4 + 2],
+                    columns[i * 4 + 3],
+                ]
+            };
+            return Ok(Matrix4::from([column(0), column(1), column(2), column(3)]));
+        }
+
+        let [tx, ty, tz] = node
+            .get("translation")
+            .and_then(Json::as_f32_array)
+            .unwrap_or([0.0; 3]);
+        let [x, y, z, w] = node
+            .get("rotation")
+            .and_then(Json::as_f32_array)
+            .unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        let [sx, sy, sz] = node
+            .get("scale")
+            .and_then(Json::as_f32_array)
+            .unwrap_or([1.0; 3]);
+
+        Ok(Matrix4::from_translation(Vector3::new(tx, ty, tz))
+            * Matrix4::from(Quaternion::new(w, x, y, z))
+            * Matrix4::from_nonuniform_scale(sx, sy, sz))
+    }
+
+    fn load_root_nodes(&self, nodes: &[Node]) -> Result<Vec<usize>, VkPlaygroundError> {
+        let is_child = |node: usize| nodes.iter().any(|parent| parent.children.contains(&node));
+        let scenes = self.array("scenes");
+        let scene = match self.index(self.document.get("scene"), "scenes", scenes.len())? {
+            Some(scene) => Some(scene),
+            None if !scenes.is_empty() => Some(0),
+            None => None,
+        };
+
+        let root_nodes = match scene {
+            Some(scene) => {
+                let mut root_nodes = vec![];
+                for node in scenes[scene].get("nodes").map_or(&[][..], Json::items) {
+                    let node = self.index(Some(node), "nodes", nodes.len())?.unwrap();
+                    if is_child(node) {
+                        return Err(
+                            self.error(format!("scene root {} is another node's child", node))
+                        );
+                    }
+                    root_nodes.push(node);
+                }
+                root_nodes
+            }
+            None => (0..nodes.len()).filter(|&node| !is_child(node)).collect(),
+        };
+
+        Ok(root_nodes)
+    }
+}
+
+fn convert_sampler(sampler: &Json) -> Sampler {
+    let filter = |key: &str| sampler.get(key).and_then(Json::as_usize);
+    let wrap = |key: &str| match sampler.get(key).and_then(Json::as_usize) {
+        Some(WRAP_CLAMP_TO_EDGE) => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+        Some(WRAP_MIRRORED_REPEAT) => vk::SamplerAddressMode::MIRRORED_REPEAT,
+        _ => vk::SamplerAddressMode::REPEAT,
+    };
+    let (min_filter, mipmap_mode) = match filter("minFilter") {
+        Some(FILTER_NEAREST) | Some(FILTER_NEAREST_MIPMAP_NEAREST) => {
+            (vk::Filter::NEAREST, vk::SamplerMipmapMode::NEAREST)
+        }
+        Some(FILTER_LINEAR_MIPMAP_NEAREST) => (vk::Filter::LINEAR, vk::SamplerMipmapMode::NEAREST),
+        Some(FILTER_NEAREST_MIPMAP_LINEAR) => (vk::Filter::NEAREST, vk::SamplerMipmapMode::LINEAR),
+        _ => (vk::Filter::LINEAR, vk::SamplerMipmapMode::LINEAR),
+    };
+
+    Sampler {
+        mag_filter: match filter("magFilter") {
+            Some(FILTER_NEAREST) => vk::Filter::NEAREST,
+            _ => vk::Filter::LINEAR,
+        },
+        min_filter,
+        mipmap_mode,
+        address_mode_u: wrap("wrapS"),
+        address_mode_v: wrap("wrapT"),
+    }
+}
+
+// the little-endian component `component` of the element at the start of
+// `element`, as read, without normalizing
+fn read_component(element: &[u8], component: usize, component_type: usize) -> f64 {
+    match component_type {
+        COMPONENT_BYTE => element[component] as i8 as f64,
+        COMPONENT_UNSIGNED_BYTE => element[component] as f64,
+        COMPONENT_SHORT | COMPONENT_UNSIGNED_SHORT => {
+            let bytes = [element[component * 2], element[component * 2 + 1]];
+            if component_type == COMPONENT_SHORT {
+                i16::from_le_bytes(bytes) as f64
+            } else {
+                u16::from_le_bytes(bytes) as f64
+            }
+        }
+        _ => {
+            let offset = component * 4;
+            let bytes = [
+                element[offset],
+                element[offset + 1],
+                element[offset + 2],
+                element[offset + 3],
+            ];
+            if component_type == COMPONENT_FLOAT {
+                f32::from_le_bytes(bytes) as f64
+            } else {
+                u32::from_le_bytes(bytes) as f64
+            }
+        }
+    }
+}
+
+// normalized integers map onto 0 to 1, or -1 to 1 when signed
+fn normalize(value: f64, component_type: usize, normalized: bool) -> f32 {
+    if !normalized {
+        return value as f32;
+    }
+
+    let normalized_value = match component_type {
+        COMPONENT_BYTE => (value / 127.0).max(-1.0),
+        COMPONENT_UNSIGNED_BYTE => value / 255.0,
+        COMPONENT_SHORT => (value / 32767.0).max(-1.0),
+        COMPONENT_UNSIGNED_SHORT => value / 65535.0,
+        _ => value,
+    };
+
+    normalized_value as f32
+}
+
+fn triangle_strip_to_list(strip: &[u32]) -> Vec<u32> {
+    // every other triangle is flipped back to the strip's winding
+    (2..strip.len())
+        .flat_map(|i| {
+            if i % 2 == 0 {
+                [strip[i - 2], strip[i - 1], strip[i]]
+            } else {
+                [strip[i - 1], strip[i - 2], strip[i]]
+            }
+        })
+        .collect()
+}
+
+fn triangle_fan_to_list(fan: &[u32]) -> Vec<u32> {
+    (2..fan.len())
+        .flat_map(|i| [fan[0], fan[i - 1], fan[i]])
+        .collect()
+}
+
+fn subtract(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// `fallback` for vectors too short to have a direction
+fn normalized(v: [f32; 3], fallback: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    if length > f32::EPSILON {
+        [v[0] / length, v[1] / length, v[2] / length]
+    } else {
+        fallback
+    }
+}
+
+// every three vertices are a triangle of their own
+fn generate_flat_normals(vertices: &mut [PbrVertex]) {
+    for triangle in vertices.chunks_exact_mut(3) {
+        let edge_1 = subtract(triangle[1].pos, triangle[0].pos);
+        let edge_2 = subtract(triangle[2].pos, triangle[0].pos);
+        let normal = normalized(cross(edge_1, edge_2), [0.0, 0.0, 1.0]);
+        for vertex in triangle.iter_mut() {
+            vertex.normal = normal;
+        }
+    }
+}
+
+// Tangents along which u grows, summed over the triangles around each
+// vertex and made perpendicular to its normal. Without texture coordinates
+// any perpendicular direction does.
+fn generate_tangents(vertices: &mut [PbrVertex], indices: &[u32], has_tex_coords: bool) {
+    let mut tangents = vec![[0.0_f32; 3]; vertices.len()];
+    let mut bitangents = vec![[0.0_f32; 3]; vertices.len()];
+
+    if has_tex_coords {
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| vertices[triangle[corner] as usize]);
+            let edge_1 = subtract(b.pos, a.pos);
+            let edge_2 = subtract(c.pos, a.pos);
+            let (du_1, dv_1) = (
+                b.tex_coord[0] - a.tex_coord[0],
+                b.tex_coord[1] - a.tex_coord[1],
+            );
+            let (du_2, dv_2) = (
+                c.tex_coord[0] - a.tex_coord[0],
+                c.tex_coord[1] - a.tex_coord[1],
+            );
+            let determinant = du_1 * dv_2 - du_2 * dv_1;
+            if determinant.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            let r = 1.0 / determinant;
+            let tangent = [0, 1, 2].map(|axis| (edge_1[axis] * dv_2 - edge_2[axis] * dv_1) * r);
+            let bitangent = [0, 1, 2].map(|axis| (edge_2[axis] * du_1 - edge_1[axis] * du_2) * r);
+            for &index in triangle.iter() {
+                for axis in 0..3 {
+                    tangents[index as usize][axis] += tangent[axis];
+                    bitangents[index as usize][axis] += bitangent[axis];
+                }
+            }
+        }
+    }
+
+    for (i, vertex) in vertices.iter_mut().enumerate() {
+        let normal = vertex.normal;
+        // any axis not along the normal, for when there's no better one
+        let axis = if normal[0].abs() < 0.9 {
+            [1.0, 0.0, 0.0]
+        } else {
+            [0.0, 1.0, 0.0]
+        };
+        let fallback = normalized(cross(axis, normal), [1.0, 0.0, 0.0]);
+
+        // Gram-Schmidt against the normal
+        let along_normal = dot(tangents[i], normal);
+        let tangent = normalized(
+            [0, 1, 2].map(|axis| tangents[i][axis] - normal[axis] * along_normal),
+            fallback,
+        );
+        let handedness = if dot(cross(normal, tangent), bitangents[i]) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        vertex.tangent = [tangent[0], tangent[1], tangent[2], handedness];
+    }
+}
+
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    let (mut bits, mut bit_count) = (0_u32, 0);
+    for &c in encoded.iter() {
+        // only the bits not yet pushed are kept
+        bits = ((bits << 6) | sextet(c)? as u32) & 0xFFFF;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            decoded.push((bits >> bit_count) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+// URIs in glTF are percent-encoded, "my%20model.bin"
+fn decode_percent_escapes(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources/models")
+            .join(name)
+    }
+
+    // runs `check` on a loader over `document` and one buffer of `buffer`
+    fn with_loader<T>(document: &str, buffer: &[u8], check: impl FnOnce(&Loader) -> T) -> T {
+        let document = Json::parse(document).unwrap();
+        let loader = Loader {
+            path: Path::new("test.gltf"),
+            directory: Path::new(""),
+            document: &document,
+            buffers: vec![buffer.to_vec()],
+        };
+
+        check(&loader)
+    }
+
+    fn glb(chunks: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut bytes = GLB_MAGIC.to_vec();
+        bytes.extend_from_slice(&2_u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        for (chunk_type, chunk) in chunks.iter() {
+            bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&chunk_type.to_le_bytes());
+            bytes.extend_from_slice(chunk);
+        }
+        let length = (bytes.len() as u32).to_le_bytes();
+        bytes[8..12].copy_from_slice(&length);
+
+        bytes
+    }
+
+    #[test]
+    fn base64_is_decoded() {
+        assert_eq!(decode_base64(""), Some(vec![]));
+        assert_eq!(decode_base64("Zg=="), Some(b"f".to_vec()));
+        assert_eq!(decode_base64("Zm8="), Some(b"fo".to_vec()));
+        assert_eq!(decode_base64("Zm9v"), Some(b"foo".to_vec()));
+        assert_eq!(decode_base64("Zm9vYmFy"), Some(b"foobar".to_vec()));
+    }
+
+    #[test]
+    fn url_safe_base64_is_decoded() {
+        assert_eq!(decode_base64("-_8"), Some(vec![0xFB, 0xFF]));
+        assert_eq!(decode_base64("+/8"), Some(vec![0xFB, 0xFF]));
+    }
+
+    #[test]
+    fn invalid_base64_is_rejected() {
+        assert_eq!(decode_base64("Zm9v YmFy"), None);
+        assert_eq!(decode_base64("Zm9v!"), None);
+    }
+
+    #[test]
+    fn percent_escapes_are_decoded() {
+        assert_eq!(decode_percent_escapes("my%20model.bin"), "my model.bin");
+        assert_eq!(decode_percent_escapes("caf%C3%A9.bin"), "café.bin");
+        assert_eq!(decode_percent_escapes("plain.bin"), "plain.bin");
+    }
+
+    #[test]
+    fn malformed_percent_escapes_are_kept() {
+        assert_eq!(decode_percent_escapes("100%"), "100%");
+        assert_eq!(decode_percent_escapes("%zz.bin"), "%zz.bin");
+        assert_eq!(decode_percent_escapes("a%2"), "a%2");
+    }
+
+    #[test]
+    fn glb_is_split_into_json_and_binary() {
+        let bytes = glb(&[(GLB_CHUNK_JSON, b"{}  "), (GLB_CHUNK_BIN, &[1, 2, 3, 4])]);
+
+        let (json, binary) = split_glb(&bytes).unwrap();
+
+        assert_eq!(json, b"{}  ");
+        assert_eq!(binary, Some(&[1, 2, 3, 4][..]));
+    }
+
+    #[test]
+    fn glb_without_binary_chunk_is_split() {
+        let bytes = glb(&[(GLB_CHUNK_JSON, b"{}  ")]);
+
+        assert_eq!(split_glb(&bytes), Ok((&b"{}  "[..], None)));
+    }
+
+    #[test]
+    fn malformed_glb_is_rejected() {
+        let mut other_version = glb(&[(GLB_CHUNK_JSON, b"{}  ")]);
+        other_version[4] = 1;
+        let binary_first = glb(&[(GLB_CHUNK_BIN, &[0; 4]), (GLB_CHUNK_JSON, b"{}  ")]);
+        let mut truncated = glb(&[(GLB_CHUNK_JSON, b"{}  ")]);
+        truncated[GLB_HEADER_SIZE] = 8;
+
+        assert!(split_glb(&other_version).is_err());
+        assert!(split_glb(&binary_first).is_err());
+        assert!(split_glb(&truncated).is_err());
+        assert!(split_glb(&GLB_MAGIC[..]).is_err());
+    }
+
+    #[test]
+    fn triangle_strip_keeps_its_winding() {
+        assert_eq!(
+            triangle_strip_to_list(&[0, 1, 2, 3, 4]),
+            vec![0, 1, 2, 2, 1, 3, 2, 3, 4]
+        );
+        assert_eq!(triangle_strip_to_list(&[0, 1]), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn triangle_fan_shares_its_first_vertex() {
+        assert_eq!(triangle_fan_to_list(&[0, 1, 2, 3]), vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(triangle_fan_to_list(&[0]), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn accessor_without_buffer_view_is_zeros() {
+        let document = r#"{"accessors": [{"componentType": 5126, "count": 2, "type": "VEC2"}]}"#;
+
+        let floats = with_loader(document, &[], |loader| loader.read_floats(0, &[2]).unwrap());
+
+        assert_eq!(floats, (2, vec![0.0; 4]));
+    }
+
+    #[test]
+    fn accessor_offset_past_its_buffer_view_is_rejected() {
+        let document = r#"{
+            "bufferViews": [{"buffer": 0, "byteLength": 4}],
+            "accessors": [
+                {"bufferView": 0, "byteOffset": 8, "componentType": 5121, "count": 1,
+                    "type": "SCALAR"}
+            ]
+        }"#;
+
+        let result = with_loader(document, &[0; 4], |loader| loader.read_indices(0));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn accessor_at_the_end_of_its_buffer_view_is_rejected() {
+        let document = r#"{
+            "bufferViews": [{"buffer": 0, "byteLength": 4}],
+            "accessors": [
+                {"bufferView": 0, "byteOffset": 4, "componentType": 5121, "count": 1,
+                    "type": "SCALAR"}
+            ]
+        }"#;
+
+        let result = with_loader(document, &[0; 4], |loader| loader.read_indices(0));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn huge_accessor_count_is_rejected() {
+        let document = r#"{
+            "bufferViews": [{"buffer": 0, "byteLength": 12, "byteStride": 12}],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 1e30, "type": "VEC3"}
+            ]
+        }"#;
+
+        let result = with_loader(document, &[0; 12], |loader| loader.read_floats(0, &[3]));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn huge_accessor_count_without_buffer_view_is_rejected() {
+        let document = r#"{
+            "accessors": [
+                {"componentType": 5126, "count": 1e15, "type": "VEC3"},
+                {"componentType": 5125, "count": 1e15, "type": "SCALAR"}
+            ]
+        }"#;
+
+        let floats = with_loader(document, &[], |loader| loader.read_floats(0, &[3]).err());
+        let indices = with_loader(document, &[], |loader| loader.read_indices(1).err());
+
+        assert!(floats.is_some());
+        assert!(indices.is_some());
+    }
+
+    #[test]
+    fn huge_buffer_view_is_rejected() {
+        let document = r#"{"bufferViews": [{"buffer": 0, "byteOffset": 4, "byteLength": 1e30}]}"#;
+
+        let result = with_loader(document, &[0; 4], |loader| loader.buffer_view(0).err());
+
+        assert!(result.is_some());
+    }
+
+    #[test]
+    fn strided_accessor_is_read() {
+        let mut buffer = vec![];
+        for value in [1_u16, 0xFFFF, 2, 0xFFFF, 3].iter() {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        let document = r#"{
+            "bufferViews": [{"buffer": 0, "byteLength": 10, "byteStride": 4}],
+            "accessors": [{"bufferView": 0, "componentType": 5123, "count": 3, "type": "SCALAR"}]
+        }"#;
+
+        let indices = with_loader(document, &buffer, |loader| loader.read_indices(0).unwrap());
+
+        assert_eq!(indices, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn gltf_fixture_is_loaded() {
+        let scene = load_gltf(&fixture("triangle.gltf")).unwrap();
+
+        let primitive = &scene.meshes[0].primitives[0];
+        let positions: Vec<[f32; 3]> = primitive.vertices.iter().map(|v| v.pos).collect();
+        assert_eq!(positions, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        assert_eq!(primitive.vertices[1].tex_coord, [1.0, 0.0]);
+        assert_eq!(primitive.vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(primitive.vertices[0].tangent, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(primitive.indices, vec![0, 1, 2]);
+        assert_eq!(primitive.material, Some(0));
+        assert_eq!(scene.materials[0].name, "red");
+        assert_eq!(scene.materials[0].base_color_factor, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(scene.materials[0].metallic_factor, 0.5);
+        assert_eq!(scene.root_nodes, vec![0]);
+        assert_eq!(
+            scene.world_transforms()[1],
+            Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0))
+        );
+    }
+
+    #[test]
+    fn glb_fixture_matches_gltf_fixture() {
+        let gltf = load_gltf(&fixture("triangle.gltf")).unwrap();
+
+        let glb = load_gltf(&fixture("triangle.glb")).unwrap();
+
+        assert_eq!(glb.meshes[0].primitives[0].vertices, gltf.meshes[0].primitives[0].vertices);
+        assert_eq!(glb.meshes[0].primitives[0].indices, gltf.meshes[0].primitives[0].indices);
+        assert_eq!(glb.materials[0].base_color_factor, gltf.materials[0].base_color_factor);
+        assert_eq!(glb.world_transforms(), gltf.world_transforms());
+    }
+}
@@ -0,0 +1,118 @@
+use ash::vk;
+
+use std::path::Path;
+
+// Comparing rendered frames against reference ("golden") images, used by
+// tests/golden.rs to catch rendering regressions in the demos. All images
+// are tightly packed RGBA8, the same layout headless::read_offscreen_target
+// hands back.
+
+pub struct ImageComparison {
+    pub mismatched_pixels: usize,
+    pub max_channel_difference: u8,
+    // the reference faded to grey, with mismatched pixels painted red
+    pub diff_pixels: Vec<u8>,
+}
+
+impl ImageComparison {
+    pub fn is_match(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+pub fn load_png(path: &Path) -> image::ImageResult<(vk::Extent2D, Vec<u8>)> {
+    let image = image::open(path)?.to_rgba();
+    let (width, height) = image.dimensions();
+
+    Ok((vk::Extent2D { width, height }, image.into_raw()))
+}
+
+/// Compares two images of the same size. A pixel only counts as mismatched
+/// when one of its channels differs by more than `tolerance`, which leaves
+/// some room for rounding differences between drivers.
+pub fn compare_images(expected: &[u8], actual: &[u8], tolerance: u8) -> ImageComparison {
+    assert_eq!(
+        expected.len(),
+        actual.len(),
+        "Images must be the same size to be compared"
+    );
+
+    let mut mismatched_pixels = 0;
+    let mut max_channel_difference = 0;
+    let mut diff_pixels = Vec::with_capacity(expected.len());
+
+    for (expected_pixel, actual_pixel) in expected.chunks(4).zip(actual.chunks(4)) {
+        let pixel_difference = expected_pixel
+            .iter()
+            .zip(actual_pixel.iter())
+            .map(|(&e, &a)| (i16::from(e) - i16::from(a)).unsigned_abs() as u8)
+            .max()
+            .unwrap_or(0);
+        max_channel_difference = max_channel_difference.max(pixel_difference);
+
+        if pixel_difference > tolerance {
+            mismatched_pixels += 1;
+            diff_pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = (u32::from(expected_pixel[0])
+                + u32::from(expected_pixel[1])
+                + u32::from(expected_pixel[2]))
+                / 3;
+            let faded = (luma / 4) as u8;
+            diff_pixels.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+
+    ImageComparison {
+        mismatched_pixels,
+        max_channel_difference,
+        diff_pixels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_images_match() {
+        let pixels = [10, 20, 30, 255, 200, 100, 0, 255];
+
+        let comparison = compare_images(&pixels, &pixels, 0);
+
+        assert!(comparison.is_match());
+        assert_eq!(comparison.max_channel_difference, 0);
+        // luma 20 and 100, faded to a quarter
+        assert_eq!(comparison.diff_pixels, vec![5, 5, 5, 255, 25, 25, 25, 255]);
+    }
+
+    #[test]
+    fn differences_within_the_tolerance_match() {
+        let expected = [100, 100, 100, 255];
+        let actual = [102, 98, 100, 255];
+
+        let comparison = compare_images(&expected, &actual, 2);
+
+        assert!(comparison.is_match());
+        assert_eq!(comparison.max_channel_difference, 2);
+    }
+
+    #[test]
+    fn pixels_past_the_tolerance_are_painted_red() {
+        let expected = [0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255];
+        let actual = [0, 0, 0, 255, 0, 3, 0, 255, 0, 0, 0, 0];
+
+        let comparison = compare_images(&expected, &actual, 2);
+
+        assert!(!comparison.is_match());
+        assert_eq!(comparison.mismatched_pixels, 2);
+        assert_eq!(comparison.max_channel_difference, 255);
+        assert_eq!(&comparison.diff_pixels[4..], &[255, 0, 0, 255, 255, 0, 0, 255]);
+    }
+
+    #[test]
+    #[should_panic(expected = "same size")]
+    fn images_of_different_sizes_panic() {
+        compare_images(&[0; 8], &[0; 4], 0);
+    }
+}
@@ -0,0 +1,428 @@
+use ash::vk;
+use ash::{vk_version_major, vk_version_minor, vk_version_patch};
+
+use std::fmt;
+
+use crate::utility::constants::{DEVICE_EXTENSIONS, VALIDATION};
+use crate::utility::error::VkPlaygroundError;
+use crate::utility::extensions::ExtensionConfig;
+use crate::utility::handles::OwnedInstance;
+use crate::utility::vkstuff;
+
+// Choosing between several GPUs, e.g. an integrated GPU, a discrete one and
+// lavapipe. Every physical device gets a score from its type, how much
+// device local memory it has and a few features and limits, and the best
+// suitable one wins.
+//
+// `--gpu <selector>` or the VK_PLAYGROUND_GPU environment variable overrides
+// that. The selector is an index into the enumeration order (`--gpu 1`), a
+// hex vendor:device ID pair or just a vendor ID (`--gpu 10de:2204`,
+// `--gpu 0x10de`), or else part of the device name (`--gpu llvmpipe`).
+// `--list-gpus` prints every device with its score, or why it was rejected.
+
+pub const GPU_ENV_VAR: &str = "VK_PLAYGROUND_GPU";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpuSelector {
+    Index(usize),
+    Ids {
+        vendor_id: u32,
+        device_id: Option<u32>,
+    },
+    /// Matched case insensitively against part of the device name.
+    Name(String),
+}
+
+impl GpuSelector {
+    pub fn parse(value: &str) -> GpuSelector {
+        let value = value.trim();
+
+        if let Ok(index) = value.parse() {
+            return GpuSelector::Index(index);
+        }
+        if let Some(selector) = GpuSelector::parse_ids(value) {
+            return selector;
+        }
+
+        GpuSelector::Name(value.to_owned())
+    }
+
+    // "10de:2204", "0x10de:0x2204" or "0x10de"; a bare "10de" is a name
+    fn parse_ids(value: &str) -> Option<GpuSelector> {
+        let parse_hex = |id: &str| {
+            let id = id.trim_start_matches("0x").trim_start_matches("0X");
+            u32::from_str_radix(id, 16).ok()
+        };
+
+        match value.find(':') {
+            Some(split) => Some(GpuSelector::Ids {
+                vendor_id: parse_hex(&value[..split])?,
+                device_id: Some(parse_hex(&value[split + 1..])?),
+            }),
+            None if value.starts_with("0x") || value.starts_with("0X") => {
+                Some(GpuSelector::Ids {
+                    vendor_id: parse_hex(value)?,
+                    device_id: None,
+                })
+            }
+            None => None,
+        }
+    }
+
+    pub fn matches(&self, gpu: &GpuInfo) -> bool {
+        match self {
+            GpuSelector::Index(index) => gpu.index == *index,
+            GpuSelector::Ids {
+                vendor_id,
+                device_id,
+            } => {
+                gpu.vendor_id == *vendor_id
+                    && device_id.is_none_or(|device_id| gpu.device_id == device_id)
+            }
+            GpuSelector::Name(name) => gpu.name.to_lowercase().contains(&name.to_lowercase()),
+        }
+    }
+}
+
+impl fmt::Display for GpuSelector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpuSelector::Index(index) => write!(f, "index {}", index),
+            GpuSelector::Ids {
+                vendor_id,
+                device_id: Some(device_id),
+            } => write!(f, "ID {:04x}:{:04x}", vendor_id, device_id),
+            GpuSelector::Ids {
+                vendor_id,
+                device_id: None,
+            } => write!(f, "vendor {:04x}", vendor_id),
+            GpuSelector::Name(name) => write!(f, "name \"{}\"", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GpuOptions {
+    pub selector: Option<GpuSelector>,
+    pub is_list_requested: bool,
+}
+
+impl GpuOptions {
+    pub const FLAGS: &'static [&'static str] = &["--gpu", "--list-gpus"];
+
+    /// Reads `--gpu` and `--list-gpus`, falling back to VK_PLAYGROUND_GPU
+    /// when `--gpu` isn't given.
+    pub fn from_args() -> Result<GpuOptions, VkPlaygroundError> {
+        GpuOptions::parse(std::env::args().skip(1), std::env::var(GPU_ENV_VAR).ok())
+    }
+
+    pub fn parse<I: Iterator<Item = String>>(
+        mut args: I,
+        env_value: Option<String>,
+    ) -> Result<GpuOptions, VkPlaygroundError> {
+        let mut options = GpuOptions::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--gpu" => {
+                    let value = args.next().ok_or_else(|| VkPlaygroundError::Usage {
+                        context: "--gpu expects an index, vendor:device ID or name".to_owned(),
+                    })?;
+                    options.selector = Some(GpuSelector::parse(&value));
+                }
+                "--list-gpus" => options.is_list_requested = true,
+                _ => {}
+            }
+        }
+
+        if options.selector.is_none() {
+            options.selector = env_value
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| GpuSelector::parse(&value));
+        }
+
+        Ok(options)
+    }
+}
+
+/// A physical device along with what `pick_physical_device` made of it.
+#[derive(Debug, Clone)]
+pub struct GpuInfo {
+    /// Position in `vkEnumeratePhysicalDevices` order.
+    pub index: usize,
+    pub physical_device: vk::PhysicalDevice,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub api_version: u32,
+    pub driver_version: u32,
+    pub device_local_memory: vk::DeviceSize,
+    pub score: u64,
+    /// Why the device can't be used, `None` when it's suitable.
+    pub rejection: Option<String>,
+}
+
+impl GpuInfo {
+    pub fn is_suitable(&self) -> bool {
+        self.rejection.is_none()
+    }
+}
+
+/// Higher is better. The device type dominates, so a discrete GPU always
+/// beats an integrated one, then memory size breaks ties between devices
+/// of the same type, and the features and limits after that.
+pub fn score(
+    properties: &vk::PhysicalDeviceProperties,
+    features: &vk::PhysicalDeviceFeatures,
+    device_local_memory: vk::DeviceSize,
+) -> u64 {
+    let type_score = match properties.device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 100_000,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 50_000,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 20_000,
+        vk::PhysicalDeviceType::CPU => 10_000,
+        _ => 0,
+    };
+    let memory_score = device_local_memory / (16 * 1024 * 1024);
+    let limits_score = u64::from(properties.limits.max_image_dimension2_d / 1024);
+    let feature_score = [
+        features.geometry_shader,
+        features.tessellation_shader,
+        features.multi_draw_indirect,
+        features.fill_mode_non_solid,
+        features.wide_lines,
+    ]
+    .iter()
+    .filter(|&&feature| feature == vk::TRUE)
+    .count() as u64
+        * 10;
+
+    type_score + memory_score + limits_score + feature_score
+}
+
+/// The size of every device local memory heap added together.
+pub fn device_local_memory(
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+) -> vk::DeviceSize {
+    memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize]
+        .iter()
+        .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+        .map(|heap| heap.size)
+        .sum()
+}
+
+/// The GPU a selector asks for, or the suitable one with the best score.
+/// Ties go to whichever was enumerated first.
+pub fn choose<'a>(
+    gpus: &'a [GpuInfo],
+    selector: Option<&GpuSelector>,
+) -> Result<&'a GpuInfo, VkPlaygroundError> {
+    if let Some(selector) = selector {
+        let gpu = gpus.iter().find(|gpu| selector.matches(gpu)).ok_or_else(|| {
+            VkPlaygroundError::unsupported(
+                "pick_physical_device",
+                format!("No GPU matches {}, see --list-gpus", selector),
+            )
+        })?;
+
+        return match &gpu.rejection {
+            None => Ok(gpu),
+            Some(rejection) => Err(VkPlaygroundError::unsupported(
+                "pick_physical_device",
+                format!("{} was selected but can't be used: {}", gpu.name, rejection),
+            )),
+        };
+    }
+
+    let mut best: Option<&GpuInfo> = None;
+    for gpu in gpus.iter().filter(|gpu| gpu.is_suitable()) {
+        if best.is_none_or(|best| gpu.score > best.score) {
+            best = Some(gpu);
+        }
+    }
+
+    best.ok_or_else(|| {
+        VkPlaygroundError::unsupported("pick_physical_device", "Failed to find a suitable GPU")
+    })
+}
+
+pub fn print_gpus(gpus: &[GpuInfo], selector: Option<&GpuSelector>) {
+    let chosen = choose(gpus, selector).ok().map(|gpu| gpu.index);
+
+    for gpu in gpus.iter() {
+        println!(
+            "{} [{}] {} ({:?}, {:04x}:{:04x})",
+            if chosen == Some(gpu.index) { "*" } else { " " },
+            gpu.index,
+            gpu.name,
+            gpu.device_type,
+            gpu.vendor_id,
+            gpu.device_id,
+        );
+        println!(
+            "      API {}.{}.{}, driver {:#x}, {} MiB device local memory",
+            vk_version_major!(gpu.api_version),
+            vk_version_minor!(gpu.api_version),
+            vk_version_patch!(gpu.api_version),
+            gpu.driver_version,
+            gpu.device_local_memory / (1024 * 1024),
+        );
+        match &gpu.rejection {
+            None => println!("      score {}", gpu.score),
+            Some(rejection) => println!("      rejected: {}", rejection),
+        }
+    }
+}
+
+/// `--list-gpus`: prints every physical device against the requirements of
+/// the windowed demos. Present support needs a window, so it isn't checked.
+pub fn list_gpus(options: &GpuOptions) -> Result<(), VkPlaygroundError> {
+    let entry = vkstuff::vkinstance::load_entry()?;
+    // no validation, there's nothing to validate
+    let (instance, _) =
+        vkstuff::vkinstance::create_instance(&entry, "list-gpus", &ExtensionConfig::new())?;
+    let instance = OwnedInstance::new(instance);
+
+    let device_config = ExtensionConfig::for_window(&VALIDATION, &DEVICE_EXTENSIONS);
+    let gpus = vkstuff::vkdevice::describe_physical_devices(&instance, None, &device_config)?;
+    if gpus.is_empty() {
+        println!("No Vulkan devices found");
+    }
+    print_gpus(&gpus, options.selector.as_ref());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gpu(index: usize, name: &str, vendor_id: u32, device_id: u32, score: u64) -> GpuInfo {
+        GpuInfo {
+            index,
+            physical_device: vk::PhysicalDevice::null(),
+            name: name.to_owned(),
+            device_type: vk::PhysicalDeviceType::DISCRETE_GPU,
+            vendor_id,
+            device_id,
+            api_version: 0,
+            driver_version: 0,
+            device_local_memory: 0,
+            score,
+            rejection: None,
+        }
+    }
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn selector_parses_an_index() {
+        assert_eq!(GpuSelector::parse(" 1 "), GpuSelector::Index(1));
+    }
+
+    #[test]
+    fn selector_parses_vendor_and_device_ids() {
+        let expected = GpuSelector::Ids {
+            vendor_id: 0x10de,
+            device_id: Some(0x2204),
+        };
+
+        assert_eq!(GpuSelector::parse("10de:2204"), expected);
+        assert_eq!(GpuSelector::parse("0x10de:0X2204"), expected);
+    }
+
+    #[test]
+    fn selector_parses_a_prefixed_vendor_id() {
+        let expected = GpuSelector::Ids {
+            vendor_id: 0x10de,
+            device_id: None,
+        };
+
+        assert_eq!(GpuSelector::parse("0x10de"), expected);
+    }
+
+    #[test]
+    fn anything_else_is_a_name() {
+        assert_eq!(GpuSelector::parse("10de"), GpuSelector::Name("10de".to_owned()));
+        assert_eq!(GpuSelector::parse("ab:cd:ef"), GpuSelector::Name("ab:cd:ef".to_owned()));
+        assert_eq!(GpuSelector::parse("llvmpipe"), GpuSelector::Name("llvmpipe".to_owned()));
+    }
+
+    #[test]
+    fn gpu_flag_wins_over_the_environment() {
+        let options = GpuOptions::parse(args(&["--gpu", "1", "--list-gpus"]), Some("0".into()));
+
+        let options = options.unwrap();
+        assert_eq!(options.selector, Some(GpuSelector::Index(1)));
+        assert!(options.is_list_requested);
+    }
+
+    #[test]
+    fn blank_environment_value_is_ignored() {
+        let options = GpuOptions::parse(args(&[]), Some("  ".into())).unwrap();
+
+        assert_eq!(options.selector, None);
+    }
+
+    #[test]
+    fn gpu_flag_without_a_value_is_a_usage_error() {
+        let result = GpuOptions::parse(args(&["--gpu"]), None);
+
+        match result {
+            Err(VkPlaygroundError::Usage { .. }) => {}
+            other => panic!("expected a usage error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn best_suitable_score_is_chosen() {
+        let mut gpus = vec![
+            gpu(0, "Integrated", 0x8086, 0x1, 50),
+            gpu(1, "Discrete", 0x10de, 0x2, 100),
+            gpu(2, "Also discrete", 0x10de, 0x3, 100),
+            gpu(3, "Rejected", 0x1002, 0x4, 1000),
+        ];
+        gpus[3].rejection = Some("no graphics queue".to_owned());
+
+        let chosen = choose(&gpus, None).unwrap();
+
+        // the tie goes to the one enumerated first
+        assert_eq!(chosen.index, 1);
+    }
+
+    #[test]
+    fn selector_picks_a_lower_scoring_gpu() {
+        let gpus = vec![
+            gpu(0, "llvmpipe (LLVM 15)", 0x10005, 0x0, 10),
+            gpu(1, "NVIDIA GeForce", 0x10de, 0x2204, 100),
+        ];
+
+        let by_name = choose(&gpus, Some(&GpuSelector::Name("LLVMPIPE".to_owned())));
+        let by_ids = choose(
+            &gpus,
+            Some(&GpuSelector::Ids {
+                vendor_id: 0x10005,
+                device_id: None,
+            }),
+        );
+
+        assert_eq!(by_name.unwrap().index, 0);
+        assert_eq!(by_ids.unwrap().index, 0);
+    }
+
+    #[test]
+    fn selecting_a_rejected_or_missing_gpu_fails() {
+        let mut gpus = vec![gpu(0, "Rejected", 0x10de, 0x1, 100)];
+        gpus[0].rejection = Some("no swapchain".to_owned());
+
+        assert!(choose(&gpus, Some(&GpuSelector::Index(0))).is_err());
+        assert!(choose(&gpus, Some(&GpuSelector::Index(1))).is_err());
+        assert!(choose(&gpus, None).is_err());
+    }
+}
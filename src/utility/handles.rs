@@ -0,0 +1,390 @@
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+use ash::vk::Handle;
+
+use std::ops::Deref;
+
+// Owned wrappers for Vulkan handles. Each one destroys its handle when it is
+// dropped, so a struct holding them tears itself down in field declaration
+// order: declare everything created from the device before the device, and
+// the device before the instance.
+//
+// Null handles are never destroyed, which covers the surface and swapchain
+// stand-ins used when rendering headless.
+//
+// Debug builds keep a list of every live device object. When an
+// `OwnedDevice` is dropped, whatever is still on the list for that device
+// is printed as a leak report. Those objects are never destroyed: once
+// the device is gone it's too late to, so the report is the only sign of
+// them.
+
+/// A handle that is created from and destroyed against an `ash::Device`.
+pub trait DeviceObject: Handle + Copy {
+    /// # Safety
+    /// The handle must not be in use by the device any more.
+    unsafe fn destroy(self, device: &ash::Device);
+}
+
+macro_rules! impl_device_object {
+    ($($handle:ty => $destroy:ident,)*) => {
+        $(
+            impl DeviceObject for $handle {
+                unsafe fn destroy(self, device: &ash::Device) {
+                    device.$destroy(self, None);
+                }
+            }
+        )*
+    };
+}
+
+impl_device_object! {
+    vk::Buffer => destroy_buffer,
+    vk::Image => destroy_image,
+    vk::ImageView => destroy_image_view,
+    vk::Sampler => destroy_sampler,
+    vk::ShaderModule => destroy_shader_module,
+    vk::Pipeline => destroy_pipeline,
+    vk::PipelineLayout => destroy_pipeline_layout,
+    vk::PipelineCache => destroy_pipeline_cache,
+    vk::RenderPass => destroy_render_pass,
+    vk::Framebuffer => destroy_framebuffer,
+    vk::DescriptorSetLayout => destroy_descriptor_set_layout,
+    vk::DescriptorPool => destroy_descriptor_pool,
+    vk::CommandPool => destroy_command_pool,
+    vk::Semaphore => destroy_semaphore,
+    vk::Fence => destroy_fence,
+    vk::QueryPool => destroy_query_pool,
+}
+
+/// Owns a device object and destroys it on drop. Derefs to the raw handle,
+/// so `*owned` can be passed anywhere the handle is expected.
+pub struct Owned<T: DeviceObject> {
+    device: ash::Device,
+    handle: T,
+}
+
+impl<T: DeviceObject> Owned<T> {
+    pub fn new(device: &ash::Device, handle: T) -> Owned<T> {
+        track_object(device, handle);
+
+        Owned {
+            device: device.clone(),
+            handle,
+        }
+    }
+
+    /// An empty slot, e.g. for resources that are only created later.
+    pub fn null(device: &ash::Device) -> Owned<T> {
+        Owned {
+            device: device.clone(),
+            handle: T::from_raw(0),
+        }
+    }
+
+    pub fn handle(&self) -> T {
+        self.handle
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.handle.as_raw() == 0
+    }
+
+    /// Destroys the handle now and leaves the wrapper null, for resources
+    /// that get recreated in place such as everything tied to the swapchain.
+    pub fn reset(&mut self) {
+        if self.is_null() {
+            return;
+        }
+
+        untrack_object(&self.device, self.handle);
+        unsafe {
+            self.handle.destroy(&self.device);
+        }
+        self.handle = T::from_raw(0);
+    }
+
+    /// Hands the handle back without destroying it, e.g. once whatever could
+    /// fail after creating it has succeeded.
+    pub fn into_raw(mut self) -> T {
+        let handle = self.handle;
+        if !self.is_null() {
+            untrack_object(&self.device, handle);
+        }
+        self.handle = T::from_raw(0);
+
+        handle
+    }
+}
+
+impl<T: DeviceObject> Deref for Owned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.handle
+    }
+}
+
+impl<T: DeviceObject> Drop for Owned<T> {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+/// Wraps every handle in `handles`, e.g. the image views or framebuffers
+/// the helpers in `share` hand back.
+pub fn own_all<T: DeviceObject>(device: &ash::Device, handles: Vec<T>) -> Vec<Owned<T>> {
+    handles
+        .into_iter()
+        .map(|handle| Owned::new(device, handle))
+        .collect()
+}
+
+/// The raw handles of `owned`, for helpers that take a slice of handles.
+pub fn raw_handles<T: DeviceObject>(owned: &[Owned<T>]) -> Vec<T> {
+    owned.iter().map(|owned| owned.handle()).collect()
+}
+
+/// Owns the logical device. Dropping it reports any device objects that are
+/// still alive (in debug builds) and then destroys the device.
+pub struct OwnedDevice {
+    device: ash::Device,
+}
+
+impl OwnedDevice {
+    pub fn new(device: ash::Device) -> OwnedDevice {
+        OwnedDevice { device }
+    }
+}
+
+impl Deref for OwnedDevice {
+    type Target = ash::Device;
+
+    fn deref(&self) -> &ash::Device {
+        &self.device
+    }
+}
+
+impl Drop for OwnedDevice {
+    fn drop(&mut self) {
+        report_leaks(&self.device);
+
+        unsafe {
+            self.device.destroy_device(None);
+        }
+    }
+}
+
+pub struct OwnedInstance {
+    instance: ash::Instance,
+}
+
+impl OwnedInstance {
+    pub fn new(instance: ash::Instance) -> OwnedInstance {
+        OwnedInstance { instance }
+    }
+}
+
+impl Deref for OwnedInstance {
+    type Target = ash::Instance;
+
+    fn deref(&self) -> &ash::Instance {
+        &self.instance
+    }
+}
+
+impl Drop for OwnedInstance {
+    fn drop(&mut self) {
+        unsafe {
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+pub struct OwnedSurface {
+    loader: ash::extensions::khr::Surface,
+    handle: vk::SurfaceKHR,
+}
+
+impl OwnedSurface {
+    pub fn new(loader: &ash::extensions::khr::Surface, handle: vk::SurfaceKHR) -> OwnedSurface {
+        OwnedSurface {
+            loader: loader.clone(),
+            handle,
+        }
+    }
+}
+
+impl Deref for OwnedSurface {
+    type Target = vk::SurfaceKHR;
+
+    fn deref(&self) -> &vk::SurfaceKHR {
+        &self.handle
+    }
+}
+
+impl Drop for OwnedSurface {
+    fn drop(&mut self) {
+        if self.handle != vk::SurfaceKHR::null() {
+            unsafe {
+                self.loader.destroy_surface(self.handle, None);
+            }
+        }
+    }
+}
+
+pub struct OwnedDebugMessenger {
+    loader: ash::extensions::ext::DebugUtils,
+    handle: vk::DebugUtilsMessengerEXT,
+}
+
+impl OwnedDebugMessenger {
+    pub fn new(
+        loader: &ash::extensions::ext::DebugUtils,
+        handle: vk::DebugUtilsMessengerEXT,
+    ) -> OwnedDebugMessenger {
+        OwnedDebugMessenger {
+            loader: loader.clone(),
+            handle,
+        }
+    }
+}
+
+impl Drop for OwnedDebugMessenger {
+    fn drop(&mut self) {
+        if self.handle != vk::DebugUtilsMessengerEXT::null() {
+            unsafe {
+                self.loader
+                    .destroy_debug_utils_messenger(self.handle, None);
+            }
+        }
+    }
+}
+
+/// Owns a swapchain. Like `Owned`, it can be reset and recreated in place.
+pub struct OwnedSwapchain {
+    device: ash::Device,
+    loader: ash::extensions::khr::Swapchain,
+    handle: vk::SwapchainKHR,
+}
+
+impl OwnedSwapchain {
+    pub fn new(
+        device: &ash::Device,
+        loader: &ash::extensions::khr::Swapchain,
+        handle: vk::SwapchainKHR,
+    ) -> OwnedSwapchain {
+        track_object(device, handle);
+
+        OwnedSwapchain {
+            device: device.clone(),
+            loader: loader.clone(),
+            handle,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        if self.handle == vk::SwapchainKHR::null() {
+            return;
+        }
+
+        untrack_object(&self.device, self.handle);
+        unsafe {
+            self.loader.destroy_swapchain(self.handle, None);
+        }
+        self.handle = vk::SwapchainKHR::null();
+    }
+}
+
+impl Deref for OwnedSwapchain {
+    type Target = vk::SwapchainKHR;
+
+    fn deref(&self) -> &vk::SwapchainKHR {
+        &self.handle
+    }
+}
+
+impl Drop for OwnedSwapchain {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+#[cfg(debug_assertions)]
+mod leak_tracking {
+    use ash::vk;
+
+    use std::sync::Mutex;
+
+    struct LiveObject {
+        device: vk::Device,
+        object_type: vk::ObjectType,
+        handle: u64,
+    }
+
+    static LIVE_OBJECTS: Mutex<Vec<LiveObject>> = Mutex::new(Vec::new());
+
+    pub fn track(device: vk::Device, object_type: vk::ObjectType, handle: u64) {
+        if handle == 0 {
+            return;
+        }
+
+        LIVE_OBJECTS.lock().unwrap().push(LiveObject {
+            device,
+            object_type,
+            handle,
+        });
+    }
+
+    pub fn untrack(device: vk::Device, object_type: vk::ObjectType, handle: u64) {
+        let mut live_objects = LIVE_OBJECTS.lock().unwrap();
+        if let Some(index) = live_objects.iter().position(|object| {
+            object.device == device && object.object_type == object_type && object.handle == handle
+        }) {
+            live_objects.swap_remove(index);
+        }
+    }
+
+    pub fn report(device: vk::Device) {
+        let mut live_objects = LIVE_OBJECTS.lock().unwrap();
+        let (leaked, others): (Vec<_>, Vec<_>) = live_objects
+            .drain(..)
+            .partition(|object| object.device == device);
+        *live_objects = others;
+
+        if leaked.is_empty() {
+            return;
+        }
+
+        eprintln!(
+            "Leak report: {} Vulkan objects were never destroyed before their device",
+            leaked.len()
+        );
+        for object in leaked.iter() {
+            eprintln!("    {:?} {:#x}", object.object_type, object.handle);
+        }
+    }
+}
+
+/// Adds a device object to the debug-build leak report until it's untracked.
+/// The wrappers here do this themselves, it's for code that manages raw
+/// handles with a lifetime of their own, such as the allocator's blocks.
+pub fn track_object<T: Handle>(device: &ash::Device, handle: T) {
+    #[cfg(debug_assertions)]
+    leak_tracking::track(device.handle(), T::TYPE, handle.as_raw());
+    #[cfg(not(debug_assertions))]
+    let _ = (device, handle);
+}
+
+pub fn untrack_object<T: Handle>(device: &ash::Device, handle: T) {
+    #[cfg(debug_assertions)]
+    leak_tracking::untrack(device.handle(), T::TYPE, handle.as_raw());
+    #[cfg(not(debug_assertions))]
+    let _ = (device, handle);
+}
+
+fn report_leaks(device: &ash::Device) {
+    #[cfg(debug_assertions)]
+    leak_tracking::report(device.handle());
+    #[cfg(not(debug_assertions))]
+    let _ = device;
+}
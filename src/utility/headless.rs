@@ -0,0 +1,442 @@
+use ash::version::DeviceV1_0;
+use ash::version::EntryV1_0;
+use ash::version::InstanceV1_0;
+use ash::vk;
+
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use crate::utility::allocator::{Allocation, Allocator};
+use crate::utility::args;
+use crate::utility::constants::API_VERSION;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::handles::Owned;
+use crate::utility::share;
+use crate::utility::structures::{SurfaceStuff, SwapChainStuff};
+
+// Rendering without a window: instead of a surface and a swapchain the
+// frame goes into an offscreen image that can be copied back to the host.
+// Handy for running the demos on a software driver (e.g. lavapipe) in CI.
+
+pub const OFFSCREEN_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+// the render pass leaves the offscreen image ready to be copied out
+pub const OFFSCREEN_FINAL_LAYOUT: vk::ImageLayout = vk::ImageLayout::TRANSFER_SRC_OPTIMAL;
+
+const DEFAULT_FRAME_COUNT: u32 = 1;
+const DEFAULT_DELTA_TIME: f32 = 1.0 / 60.0;
+const DEFAULT_OUTPUT_PATH: &str = "headless.png";
+
+/// Command line options for a headless run, e.g.
+/// `cargo run --bin cube -- --headless --frames 10 --delta-time 0.016 --output cube.png`
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub frame_count: u32,
+    pub delta_time: f32,
+    pub output_path: PathBuf,
+}
+
+impl HeadlessOptions {
+    pub const FLAGS: &'static [&'static str] =
+        &["--headless", "--frames", "--delta-time", "--output"];
+
+    /// Returns `None` unless `--headless` was passed to the program.
+    pub fn from_args() -> Result<Option<HeadlessOptions>, VkPlaygroundError> {
+        HeadlessOptions::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<I: Iterator<Item = String>>(
+        mut args: I,
+    ) -> Result<Option<HeadlessOptions>, VkPlaygroundError> {
+        let mut is_headless = false;
+        let mut options = HeadlessOptions {
+            frame_count: DEFAULT_FRAME_COUNT,
+            delta_time: DEFAULT_DELTA_TIME,
+            output_path: PathBuf::from(DEFAULT_OUTPUT_PATH),
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => is_headless = true,
+                "--frames" => {
+                    options.frame_count =
+                        args::parse_value(args.next(), "--frames expects a frame count")?;
+                }
+                "--delta-time" => {
+                    options.delta_time =
+                        args::parse_value(args.next(), "--delta-time expects a time in seconds")?;
+                }
+                "--output" => {
+                    options.output_path =
+                        args::parse_value(args.next(), "--output expects a file path")?;
+                }
+                _ => {}
+            }
+        }
+
+        if is_headless {
+            Ok(Some(options))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Implemented by demos that can render into an `OffscreenTarget`.
+pub trait HeadlessApp {
+    fn draw_frame(&mut self, delta_time: f32) -> Result<(), VkPlaygroundError>;
+    fn read_frame(&self) -> Result<Vec<u8>, VkPlaygroundError>;
+    fn frame_extent(&self) -> vk::Extent2D;
+    fn wait_device_idle(&self) -> Result<(), VkPlaygroundError>;
+}
+
+/// Draws the requested number of frames with a fixed delta time and
+/// writes the last one out as a PNG.
+pub fn run_headless<A: HeadlessApp>(
+    app: &mut A,
+    options: &HeadlessOptions,
+) -> Result<(), VkPlaygroundError> {
+    for _ in 0..options.frame_count {
+        app.draw_frame(options.delta_time)?;
+    }
+    app.wait_device_idle()?;
+
+    let extent = app.frame_extent();
+    let pixels = app.read_frame()?;
+    save_png(&options.output_path, extent, &pixels)?;
+
+    println!(
+        "Wrote frame {} to {}",
+        options.frame_count,
+        options.output_path.display()
+    );
+
+    Ok(())
+}
+
+/// True when a Vulkan loader is installed and reports at least one physical
+/// device, so tests can be skipped on machines without a driver.
+pub fn is_vulkan_available() -> bool {
+    let entry = match ash::Entry::new() {
+        Ok(entry) => entry,
+        Err(_) => return false,
+    };
+
+    let app_info = vk::ApplicationInfo {
+        s_type: vk::StructureType::APPLICATION_INFO,
+        p_next: ptr::null(),
+        p_application_name: ptr::null(),
+        application_version: 0,
+        p_engine_name: ptr::null(),
+        engine_version: 0,
+        api_version: API_VERSION,
+    };
+    let create_info = vk::InstanceCreateInfo {
+        s_type: vk::StructureType::INSTANCE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::InstanceCreateFlags::empty(),
+        p_application_info: &app_info,
+        enabled_layer_count: 0,
+        pp_enabled_layer_names: ptr::null(),
+        enabled_extension_count: 0,
+        pp_enabled_extension_names: ptr::null(),
+    };
+
+    let instance = match unsafe { entry.create_instance(&create_info, None) } {
+        Ok(instance) => instance,
+        Err(_) => return false,
+    };
+    let has_physical_device = unsafe { instance.enumerate_physical_devices() }
+        .map(|physical_devices| !physical_devices.is_empty())
+        .unwrap_or(false);
+    unsafe { instance.destroy_instance(None) };
+
+    has_physical_device
+}
+
+// everything is destroyed when the target is dropped, which has to happen
+// before the image view made from it is gone, like swapchain image views
+pub struct OffscreenTarget {
+    pub image: Owned<vk::Image>,
+    pub image_memory: Allocation,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+
+    // host visible copy of the image, filled by read_offscreen_target
+    pub readback_buffer: Owned<vk::Buffer>,
+    pub readback_memory: Allocation,
+}
+
+impl OffscreenTarget {
+    // mirrors SwapChainStuff::swapchain_images so image views and
+    // framebuffers can be created the same way for both paths
+    pub fn images(&self) -> Vec<vk::Image> {
+        vec![*self.image]
+    }
+
+    pub fn readback_size(&self) -> vk::DeviceSize {
+        (self.extent.width * self.extent.height * 4) as vk::DeviceSize
+    }
+}
+
+pub fn create_offscreen_target(
+    device: &ash::Device,
+    extent: vk::Extent2D,
+    allocator: &Allocator,
+) -> Result<OffscreenTarget, VkPlaygroundError> {
+    let (image, image_memory) = share::v1::create_image(
+        device,
+        extent.width,
+        extent.height,
+        1,
+        vk::SampleCountFlags::TYPE_1,
+        OFFSCREEN_FORMAT,
+        vk::ImageTiling::OPTIMAL,
+        offscreen_usage(),
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        allocator,
+    )?;
+
+    let readback_size = (extent.width * extent.height * 4) as vk::DeviceSize;
+    let (readback_buffer, readback_memory) = share::create_buffer(
+        device,
+        readback_size,
+        vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        allocator,
+    )?;
+
+    Ok(OffscreenTarget {
+        image: Owned::new(device, image),
+        image_memory,
+        format: OFFSCREEN_FORMAT,
+        extent,
+        readback_buffer: Owned::new(device, readback_buffer),
+        readback_memory,
+    })
+}
+
+fn offscreen_usage() -> vk::ImageUsageFlags {
+    vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC
+}
+
+// Stand-in for share::create_surface. The loader is only there so the
+// demo structs keep the same shape, it must never be called.
+pub fn create_null_surface_stuff(
+    entry: &ash::Entry,
+    instance: &ash::Instance,
+    screen_width: u32,
+    screen_height: u32,
+) -> SurfaceStuff {
+    SurfaceStuff {
+        surface_loader: ash::extensions::khr::Surface::new(entry, instance),
+        surface: vk::SurfaceKHR::null(),
+        screen_width,
+        screen_height,
+    }
+}
+
+// Stand-in for share::create_swapchain, the offscreen image takes the
+// place of the swapchain images. Again, the loader must never be called.
+pub fn create_offscreen_swapchain_stuff(
+    instance: &ash::Instance,
+    device: &ash::Device,
+    target: &OffscreenTarget,
+) -> SwapChainStuff {
+    SwapChainStuff {
+        swapchain_loader: ash::extensions::khr::Swapchain::new(instance, device),
+        swapchain: vk::SwapchainKHR::null(),
+        swapchain_images: target.images(),
+        swapchain_format: target.format,
+        swapchain_extent: target.extent,
+        swapchain_usage: offscreen_usage(),
+    }
+}
+
+/// Copies the last rendered frame back to the host as tightly packed RGBA8.
+/// The image must already be in `OFFSCREEN_FINAL_LAYOUT`.
+pub fn read_offscreen_target(
+    device: &ash::Device,
+    command_pool: vk::CommandPool,
+    submit_queue: vk::Queue,
+    target: &OffscreenTarget,
+) -> Result<Vec<u8>, VkPlaygroundError> {
+    let command_buffer = share::begin_single_time_command(device, command_pool)?;
+
+    // make the colour attachment writes visible to the transfer
+    let image_barriers = [vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+        old_layout: OFFSCREEN_FINAL_LAYOUT,
+        new_layout: OFFSCREEN_FINAL_LAYOUT,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image: *target.image,
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+    }];
+
+    let copy_regions = [vk::BufferImageCopy {
+        buffer_offset: 0,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+        image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        image_extent: vk::Extent3D {
+            width: target.extent.width,
+            height: target.extent.height,
+            depth: 1,
+        },
+    }];
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &image_barriers,
+        );
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            *target.image,
+            OFFSCREEN_FINAL_LAYOUT,
+            *target.readback_buffer,
+            &copy_regions,
+        );
+    }
+
+    share::end_single_time_command(device, command_pool, submit_queue, command_buffer)?;
+
+    let mut pixels = vec![0_u8; target.readback_size() as usize];
+    target.readback_memory.read(&mut pixels);
+
+    Ok(pixels)
+}
+
+/// Waits for the previous submission that used `in_flight_fence` and resets
+/// it, so per-frame resources such as uniform buffers can be updated.
+pub fn begin_frame(
+    device: &ash::Device,
+    in_flight_fence: vk::Fence,
+) -> Result<(), VkPlaygroundError> {
+    let wait_fences = [in_flight_fence];
+
+    unsafe {
+        device
+            .wait_for_fences(&wait_fences, true, u64::MAX)
+            .vk_context("vkWaitForFences", "Failed to wait for Fence!")?;
+        device
+            .reset_fences(&wait_fences)
+            .vk_context("vkResetFences", "Failed to reset Fence!")?;
+    }
+
+    Ok(())
+}
+
+/// Submits a recorded command buffer with no semaphores, since there is
+/// no swapchain image to wait for or present. Call `begin_frame` first.
+pub fn submit_frame(
+    device: &ash::Device,
+    submit_queue: vk::Queue,
+    command_buffer: vk::CommandBuffer,
+    in_flight_fence: vk::Fence,
+) -> Result<(), VkPlaygroundError> {
+    let command_buffers = [command_buffer];
+
+    let submit_infos = [vk::SubmitInfo {
+        s_type: vk::StructureType::SUBMIT_INFO,
+        p_next: ptr::null(),
+        wait_semaphore_count: 0,
+        p_wait_semaphores: ptr::null(),
+        p_wait_dst_stage_mask: ptr::null(),
+        command_buffer_count: command_buffers.len() as u32,
+        p_command_buffers: command_buffers.as_ptr(),
+        signal_semaphore_count: 0,
+        p_signal_semaphores: ptr::null(),
+    }];
+
+    unsafe {
+        device
+            .queue_submit(submit_queue, &submit_infos, in_flight_fence)
+            .vk_context("vkQueueSubmit", "Failed to execute queue submit.")
+    }
+}
+
+pub fn save_png(
+    path: &Path,
+    extent: vk::Extent2D,
+    pixels: &[u8],
+) -> Result<(), VkPlaygroundError> {
+    image::save_buffer(
+        path,
+        pixels,
+        extent.width,
+        extent.height,
+        image::ColorType::RGBA(8),
+    )
+    .map_err(|source| VkPlaygroundError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn options_are_none_without_the_headless_flag() {
+        let options = HeadlessOptions::parse(args(&["--frames", "3"])).unwrap();
+
+        assert!(options.is_none());
+    }
+
+    #[test]
+    fn headless_options_are_parsed() {
+        let options = HeadlessOptions::parse(args(&[
+            "--headless",
+            "--frames",
+            "3",
+            "--delta-time",
+            "0.5",
+            "--output",
+            "out.png",
+        ]));
+
+        let options = options.unwrap().unwrap();
+        assert_eq!(options.frame_count, 3);
+        assert_eq!(options.delta_time, 0.5);
+        assert_eq!(options.output_path, PathBuf::from("out.png"));
+    }
+
+    #[test]
+    fn missing_or_invalid_values_are_usage_errors() {
+        for bad_args in [&["--headless", "--frames"][..], &["--headless", "--delta-time", "x"]] {
+            match HeadlessOptions::parse(args(bad_args)) {
+                Err(VkPlaygroundError::Usage { .. }) => {}
+                other => panic!("expected a usage error for {:?}, got {:?}", bad_args, other),
+            }
+        }
+    }
+}
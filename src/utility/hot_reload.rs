@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::utility::shader_compiler;
+
+// Reloading shaders while a demo is running, e.g.
+// `cargo run --bin cube -- --hot-reload`, then edit shaders/cube/cube.frag.
+// The watched files are checked for a new modification time between frames,
+// which needs no extra thread or platform file notification API and is
+// cheap enough for the handful of shaders a demo uses. Reading the time by
+// path also copes with editors that save by renaming a new file over the
+// old one, which a watch on the old file would miss.
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone)]
+pub struct HotReloadOptions {
+    /// How often the watched files are checked.
+    pub poll_interval: Duration,
+}
+
+impl HotReloadOptions {
+    pub const FLAGS: &'static [&'static str] = &["--hot-reload"];
+
+    /// Returns `None` unless `--hot-reload` was passed to the program.
+    pub fn from_args() -> Option<HotReloadOptions> {
+        HotReloadOptions::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Option<HotReloadOptions> {
+        if args.any(|arg| arg == "--hot-reload") {
+            Some(HotReloadOptions {
+                poll_interval: DEFAULT_POLL_INTERVAL,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+struct WatchedFile {
+    path: PathBuf,
+    // None while the file can't be read, e.g. mid-save in some editors
+    modified: Option<SystemTime>,
+}
+
+/// Watches shader sources for changes.
+pub struct ShaderWatcher {
+    files: Vec<WatchedFile>,
+    poll_interval: Duration,
+    last_poll: Instant,
+    // changed files that weren't reloaded yet, see `keep_pending`
+    pending: Vec<PathBuf>,
+}
+
+impl ShaderWatcher {
+    pub fn new(paths: Vec<PathBuf>, options: &HotReloadOptions) -> ShaderWatcher {
+        let files = paths
+            .into_iter()
+            .map(|path| WatchedFile {
+                modified: modified_time(&path),
+                path,
+            })
+            .collect();
+
+        ShaderWatcher {
+            files,
+            poll_interval: options.poll_interval,
+            last_poll: Instant::now(),
+            pending: vec![],
+        }
+    }
+
+    /// Every file under `directory` that `load_spirv` would compile, i.e.
+    /// the `.vert`, `.frag` and `.comp` sources.
+    pub fn watch_directory(
+        directory: &Path,
+        options: &HotReloadOptions,
+    ) -> std::io::Result<ShaderWatcher> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if shader_compiler::ShaderStage::from_path(&path).is_some() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        Ok(ShaderWatcher::new(paths, options))
+    }
+
+    /// The watched files modified since the last call, checked at most once
+    /// per poll interval, along with any handed back by `keep_pending`.
+    /// Empty until something is modified.
+    pub fn changed_files(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.poll_interval {
+            return vec![];
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = vec![];
+        for file in self.files.iter_mut() {
+            let modified = modified_time(&file.path);
+            if modified.is_some() && modified != file.modified {
+                changed.push(file.path.clone());
+            }
+            file.modified = modified;
+        }
+        if changed.is_empty() {
+            return changed;
+        }
+
+        for path in self.pending.drain(..) {
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+        changed.sort();
+
+        changed
+    }
+
+    /// Hands back changed files that couldn't be reloaded, e.g. because
+    /// another one didn't compile. They're returned again along with the
+    /// next modified file, so fixing the broken shader reloads them all.
+    pub fn keep_pending(&mut self, paths: Vec<PathBuf>) {
+        self.pending = paths;
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+
+    fn touch(path: &Path, seconds: u64) {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 + seconds);
+        File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn pending_files_come_back_with_the_next_change() {
+        let directory = std::env::temp_dir().join(format!("hot-reload-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let vert = directory.join("a.vert");
+        let frag = directory.join("b.frag");
+        fs::write(&vert, "").unwrap();
+        fs::write(&frag, "").unwrap();
+        touch(&vert, 0);
+        touch(&frag, 0);
+        let options = HotReloadOptions {
+            poll_interval: Duration::from_secs(0),
+        };
+        let mut watcher = ShaderWatcher::watch_directory(&directory, &options).unwrap();
+
+        let unchanged = watcher.changed_files();
+        touch(&vert, 1);
+        touch(&frag, 1);
+        let both = watcher.changed_files();
+        // say b.frag didn't compile, so neither was reloaded
+        watcher.keep_pending(both.clone());
+        let before_the_fix = watcher.changed_files();
+        touch(&frag, 2);
+        let after_the_fix = watcher.changed_files();
+        let after_the_reload = watcher.changed_files();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(unchanged.is_empty());
+        assert_eq!(both, vec![vert.clone(), frag.clone()]);
+        assert!(before_the_fix.is_empty());
+        assert_eq!(after_the_fix, vec![vert, frag]);
+        assert!(after_the_reload.is_empty());
+    }
+}
@@ -1,9 +1,32 @@
 
+pub mod allocator;
+pub mod args;
+pub mod assets;
+pub mod compute;
 pub mod constants;
 pub mod fps_limiter;
+pub mod frame_commands;
+pub mod golden;
+pub mod gltf;
+pub mod gpu;
+pub mod handles;
+pub mod headless;
+pub mod hot_reload;
 pub mod window;
+pub mod obj;
+pub mod pipeline;
+pub mod pipeline_cache;
 pub mod platforms;
+pub mod recording;
+pub mod reflection;
+pub mod renderer;
+pub mod screenshot;
+pub mod shader_compiler;
+pub mod uniform_ring;
+pub mod upload;
 pub mod debug;
+pub mod error;
+pub mod extensions;
 pub mod tools;
 pub mod structures;
 pub mod share;
@@ -0,0 +1,416 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::utility::error::VkPlaygroundError;
+use crate::utility::structures::MeshVertex;
+
+// Wavefront OBJ models with their MTL materials. Every object and group in
+// the file is kept as a sub-mesh, but they all share one vertex and one index
+// buffer, with the indices of each sub-mesh offset past the vertices of the
+// ones before it. The sub-meshes are ordered by material, so each material
+// is drawn with a single indexed draw, e.g.
+//
+//     let model = obj::load_obj(Path::new("resources/models/chalet.obj"))?;
+//     for range in model.draw_ranges.iter() {
+//         // bind the textures of model.materials[range.material]
+//         device.cmd_draw_indexed(cmd, range.index_count, 1, range.first_index, 0, 0);
+//     }
+//
+// Faces without texture coordinates get (0, 0), and sub-meshes without
+// normals get smooth ones generated from their faces.
+
+// when a vertex only touches degenerate faces
+const FALLBACK_NORMAL: [f32; 3] = [0.0, 0.0, 1.0];
+// MTL statements naming a normal map; tobj reads map_Ns, the specular
+// exponent map, as the normal texture, so its own field is left alone
+const NORMAL_MAP_KEYWORDS: [&str; 4] = ["map_Bump", "map_bump", "bump", "norm"];
+
+/// A material from the model's MTL files. Texture paths are resolved
+/// against the directory of the OBJ file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    pub diffuse_texture: Option<PathBuf>,
+    pub specular_texture: Option<PathBuf>,
+    pub normal_texture: Option<PathBuf>,
+}
+
+/// An object or group of the OBJ file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubMesh {
+    pub name: String,
+    pub first_index: u32,
+    pub index_count: u32,
+    pub material: Option<usize>,
+}
+
+/// The indices drawn with one material, `None` for faces without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawRange {
+    pub first_index: u32,
+    pub index_count: u32,
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjModel {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+    pub meshes: Vec<SubMesh>,
+    pub materials: Vec<Material>,
+    /// One per material in use, covering the sub-meshes with it.
+    pub draw_ranges: Vec<DrawRange>,
+}
+
+impl ObjModel {
+    /// Every texture the materials refer to, each once.
+    pub fn texture_paths(&self) -> Vec<&Path> {
+        let mut paths: Vec<&Path> = vec![];
+        for material in self.materials.iter() {
+            let textures = [
+                &material.diffuse_texture,
+                &material.specular_texture,
+                &material.normal_texture,
+            ];
+            for path in textures.iter().filter_map(|texture| texture.as_deref()) {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        paths
+    }
+}
+
+pub fn load_obj(model_path: &Path) -> Result<ObjModel, VkPlaygroundError> {
+    let file = File::open(model_path).map_err(|source| VkPlaygroundError::Io {
+        path: model_path.to_path_buf(),
+        source,
+    })?;
+    let directory = model_path.parent().unwrap_or_else(|| Path::new(""));
+
+    // so a broken MTL file is reported rather than the OBJ naming it
+    let failed_material_path = RefCell::new(None);
+    let (models, tobj_materials) = tobj::load_obj_buf(&mut BufReader::new(file), |mtl_path| {
+        let mtl_path = directory.join(mtl_path);
+        tobj::load_mtl(&mtl_path).inspect_err(|_| {
+            *failed_material_path.borrow_mut() = Some(mtl_path.clone());
+        })
+    })
+    .map_err(|source| VkPlaygroundError::Model {
+        path: failed_material_path.into_inner().unwrap_or_else(|| model_path.to_path_buf()),
+        source,
+    })?;
+
+    let materials: Vec<Material> = tobj_materials
+        .iter()
+        .map(|material| convert_material(material, directory))
+        .collect();
+
+    // keep the file's order within each material, faces without one last
+    let mut order: Vec<usize> = (0..models.len()).collect();
+    order.sort_by_key(|&i| models[i].mesh.material_id.unwrap_or(usize::MAX));
+
+    let mut model = ObjModel {
+        vertices: vec![],
+        indices: vec![],
+        meshes: vec![],
+        materials,
+        draw_ranges: vec![],
+    };
+    for i in order {
+        let tobj::Model { mesh, name } = &models[i];
+        let material = mesh.material_id.filter(|&id| id < model.materials.len());
+        append_mesh(&mut model, mesh, name, material);
+    }
+
+    Ok(model)
+}
+
+fn append_mesh(model: &mut ObjModel, mesh: &tobj::Mesh, name: &str, material: Option<usize>) {
+    let first_vertex = model.vertices.len();
+    let first_index = model.indices.len() as u32;
+    let vertex_count = mesh.positions.len() / 3;
+
+    for i in 0..vertex_count {
+        let normal = if mesh.normals.len() >= vertex_count * 3 {
+            [mesh.normals[i * 3], mesh.normals[i * 3 + 1], mesh.normals[i * 3 + 2]]
+        } else {
+            [0.0; 3]
+        };
+        let tex_coord = if mesh.texcoords.len() >= vertex_count * 2 {
+            [mesh.texcoords[i * 2], mesh.texcoords[i * 2 + 1]]
+        } else {
+            [0.0; 2]
+        };
+
+        model.vertices.push(MeshVertex {
+            pos: [mesh.positions[i * 3], mesh.positions[i * 3 + 1], mesh.positions[i * 3 + 2]],
+            normal,
+            tex_coord,
+        });
+    }
+    if mesh.normals.len() < vertex_count * 3 {
+        generate_normals(&mut model.vertices[first_vertex..], &mesh.indices);
+    }
+
+    model
+        .indices
+        .extend(mesh.indices.iter().map(|&index| index + first_vertex as u32));
+    let index_count = mesh.indices.len() as u32;
+    model.meshes.push(SubMesh {
+        name: name.to_owned(),
+        first_index,
+        index_count,
+        material,
+    });
+
+    // the meshes come sorted by material, so only the last range can grow
+    match model.draw_ranges.last_mut() {
+        Some(range) if range.material == material => range.index_count += index_count,
+        _ => model.draw_ranges.push(DrawRange {
+            first_index,
+            index_count,
+            material,
+        }),
+    }
+}
+
+// Sums the normals of the faces around each vertex, weighted by their area
+// since the cross product is twice that.
+fn generate_normals(vertices: &mut [MeshVertex], indices: &[u32]) {
+    for vertex in vertices.iter_mut() {
+        vertex.normal = [0.0; 3];
+    }
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|corner| vertices[triangle[corner] as usize].pos);
+        let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let face_normal = [
+            ab[1] * ac[2] - ab[2] * ac[1],
+            ab[2] * ac[0] - ab[0] * ac[2],
+            ab[0] * ac[1] - ab[1] * ac[0],
+        ];
+
+        for &index in triangle.iter() {
+            let normal = &mut vertices[index as usize].normal;
+            for axis in 0..3 {
+                normal[axis] += face_normal[axis];
+            }
+        }
+    }
+
+    for vertex in vertices.iter_mut() {
+        let [x, y, z] = vertex.normal;
+        let length = (x * x + y * y + z * z).sqrt();
+        vertex.normal = if length > f32::EPSILON {
+            [x / length, y / length, z / length]
+        } else {
+            FALLBACK_NORMAL
+        };
+    }
+}
+
+fn convert_material(material: &tobj::Material, directory: &Path) -> Material {
+    let normal_texture = NORMAL_MAP_KEYWORDS
+        .iter()
+        .find_map(|keyword| material.unknown_param.get(*keyword))
+        .and_then(|statement| texture_path(statement, directory));
+
+    Material {
+        name: material.name.clone(),
+        diffuse: material.diffuse,
+        specular: material.specular,
+        shininess: material.shininess,
+        diffuse_texture: texture_path(&material.diffuse_texture, directory),
+        specular_texture: texture_path(&material.specular_texture, directory),
+        normal_texture,
+    }
+}
+
+// The file name is the last word of a texture statement, after options such
+// as `-bm 0.5` or `-s 1 1 1`.
+fn texture_path(statement: &str, directory: &Path) -> Option<PathBuf> {
+    statement
+        .split_whitespace()
+        .last()
+        .map(|file_name| directory.join(file_name.replace('\\', "/")))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_directory() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/models")
+    }
+
+    fn load_fixture() -> ObjModel {
+        load_obj(&fixture_directory().join("submeshes.obj")).unwrap()
+    }
+
+    fn vertex(x: f32, y: f32, z: f32) -> MeshVertex {
+        MeshVertex {
+            pos: [x, y, z],
+            normal: [0.0; 3],
+            tex_coord: [0.0; 2],
+        }
+    }
+
+    #[test]
+    fn sub_meshes_are_ordered_by_material() {
+        let model = load_fixture();
+
+        let meshes: Vec<(&str, u32, u32, Option<usize>)> = model
+            .meshes
+            .iter()
+            .map(|mesh| (mesh.name.as_str(), mesh.first_index, mesh.index_count, mesh.material))
+            .collect();
+        assert_eq!(
+            meshes,
+            vec![
+                ("first", 0, 3, Some(0)),
+                ("third", 3, 3, Some(0)),
+                ("second", 6, 6, Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn sub_mesh_indices_are_offset_past_earlier_vertices() {
+        let model = load_fixture();
+
+        assert_eq!(model.vertices.len(), 10);
+        assert_eq!(model.indices, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 6, 8, 9]);
+        let first_corners: Vec<[f32; 3]> = model
+            .meshes
+            .iter()
+            .map(|mesh| model.vertices[model.indices[mesh.first_index as usize] as usize].pos)
+            .collect();
+        assert_eq!(
+            first_corners,
+            vec![[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 0.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn draw_ranges_merge_sub_meshes_with_the_same_material() {
+        let model = load_fixture();
+
+        assert_eq!(
+            model.draw_ranges,
+            vec![
+                DrawRange {
+                    first_index: 0,
+                    index_count: 6,
+                    material: Some(0),
+                },
+                DrawRange {
+                    first_index: 6,
+                    index_count: 6,
+                    material: Some(1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn normals_are_read_or_generated() {
+        let model = load_fixture();
+
+        for vertex in model.vertices.iter() {
+            assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+        }
+        assert_eq!(model.vertices[1].tex_coord, [1.0, 0.0]);
+        assert_eq!(model.vertices[7].tex_coord, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn generated_normals_are_smoothed_across_faces() {
+        // two faces folded along the y axis, one facing +z and one +x
+        let mut vertices = vec![
+            vertex(0.0, 0.0, 0.0),
+            vertex(0.0, 1.0, 0.0),
+            vertex(-1.0, 0.0, 0.0),
+            vertex(0.0, 0.0, -1.0),
+        ];
+
+        generate_normals(&mut vertices, &[0, 1, 2, 0, 3, 1]);
+
+        let diagonal = 0.5_f32.sqrt();
+        assert_eq!(vertices[2].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(vertices[3].normal, [1.0, 0.0, 0.0]);
+        for axis in 0..3 {
+            let expected = [diagonal, 0.0, diagonal][axis];
+            assert!((vertices[0].normal[axis] - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn degenerate_faces_get_the_fallback_normal() {
+        let mut vertices = vec![
+            vertex(0.0, 0.0, 0.0),
+            vertex(1.0, 0.0, 0.0),
+            vertex(2.0, 0.0, 0.0),
+        ];
+
+        generate_normals(&mut vertices, &[0, 1, 2]);
+
+        assert_eq!(vertices[0].normal, FALLBACK_NORMAL);
+    }
+
+    #[test]
+    fn materials_are_read_from_the_mtl_file() {
+        let model = load_fixture();
+        let directory = fixture_directory();
+
+        let red = &model.materials[0];
+        assert_eq!(red.name, "red");
+        assert_eq!(red.diffuse, [1.0, 0.0, 0.0]);
+        assert_eq!(red.diffuse_texture, Some(directory.join("textures/red.png")));
+        assert_eq!(red.normal_texture, Some(directory.join("red_normal.png")));
+        let blue = &model.materials[1];
+        assert_eq!(blue.shininess, 32.0);
+        assert_eq!(blue.specular_texture, Some(directory.join("blue_specular.png")));
+        assert_eq!(blue.normal_texture, None);
+    }
+
+    #[test]
+    fn texture_path_skips_options_and_fixes_backslashes() {
+        let directory = Path::new("models");
+
+        assert_eq!(
+            texture_path("-bm 0.5 -s 1 1 1 textures\\brick.png", directory),
+            Some(PathBuf::from("models/textures/brick.png"))
+        );
+        assert_eq!(
+            texture_path("brick.png", directory),
+            Some(PathBuf::from("models/brick.png"))
+        );
+        assert_eq!(texture_path("", directory), None);
+    }
+
+    #[test]
+    fn texture_paths_are_listed_once() {
+        let mut model = load_fixture();
+        model.materials.push(model.materials[0].clone());
+
+        let directory = fixture_directory();
+        assert_eq!(
+            model.texture_paths(),
+            vec![
+                directory.join("textures/red.png"),
+                directory.join("red_normal.png"),
+                directory.join("blue_specular.png"),
+            ]
+        );
+    }
+}
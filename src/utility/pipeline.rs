@@ -0,0 +1,970 @@
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::handles::Owned;
+use crate::utility::vkstuff;
+
+// Graphics pipelines described with chained setters instead of a page of
+// `vk::Pipeline*StateCreateInfo` structs, e.g.
+//
+//     let (pipeline, layout) = PipelineBuilder::new()
+//         .vertex_shader("shaders/cube/vert.spv")
+//         .fragment_shader("shaders/cube/frag.spv")
+//         .vertex_input(&VertexV3::get_binding_description(), &attributes)
+//         .dynamic_viewport()
+//         .depth_test(vk::CompareOp::LESS_OR_EQUAL, true)
+//         .descriptor_set_layouts(&[ubo_layout])
+//         .build(instance, physical_device, device, render_pass)?;
+//
+// Anything left unset gets the usual defaults: a triangle list, filled
+// polygons with back faces culled, no depth or stencil test and a single
+// opaque colour attachment. `build` checks the combination against the
+// features `vkdevice::create_logical_device` enabled and the device limits
+// first, so a missing feature is an error rather than a validation message.
+
+const ENTRY_POINT: &str = "main";
+
+#[derive(Debug, Clone)]
+struct ShaderStage {
+    stage: vk::ShaderStageFlags,
+    path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DepthBias {
+    constant_factor: f32,
+    clamp: f32,
+    slope_factor: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct PipelineBuilder {
+    shader_stages: Vec<ShaderStage>,
+
+    vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+    vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    topology: vk::PrimitiveTopology,
+    is_primitive_restart_enabled: bool,
+    patch_control_points: u32,
+
+    // None when the viewport and scissor are dynamic
+    extent: Option<vk::Extent2D>,
+
+    cull_mode: vk::CullModeFlags,
+    front_face: vk::FrontFace,
+    polygon_mode: vk::PolygonMode,
+    line_width: f32,
+    depth_bias: Option<DepthBias>,
+    is_depth_clamp_enabled: bool,
+
+    depth_compare_op: Option<vk::CompareOp>,
+    is_depth_write_enabled: bool,
+    stencil_ops: Option<(vk::StencilOpState, vk::StencilOpState)>,
+
+    color_attachments: Vec<vk::PipelineColorBlendAttachmentState>,
+    logic_op: Option<vk::LogicOp>,
+    blend_constants: [f32; 4],
+
+    dynamic_states: Vec<vk::DynamicState>,
+
+    descriptor_set_layouts: Vec<vk::DescriptorSetLayout>,
+    push_constant_ranges: Vec<vk::PushConstantRange>,
+
+    pipeline_cache: vk::PipelineCache,
+}
+
+impl Default for PipelineBuilder {
+    fn default() -> PipelineBuilder {
+        PipelineBuilder {
+            shader_stages: vec![],
+
+            vertex_bindings: vec![],
+            vertex_attributes: vec![],
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            is_primitive_restart_enabled: false,
+            patch_control_points: 3,
+
+            extent: None,
+
+            cull_mode: vk::CullModeFlags::BACK,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            polygon_mode: vk::PolygonMode::FILL,
+            line_width: 1.0,
+            depth_bias: None,
+            is_depth_clamp_enabled: false,
+
+            depth_compare_op: None,
+            is_depth_write_enabled: false,
+            stencil_ops: None,
+
+            color_attachments: vec![],
+            logic_op: None,
+            blend_constants: [0.0, 0.0, 0.0, 0.0],
+
+            dynamic_states: vec![],
+
+            descriptor_set_layouts: vec![],
+            push_constant_ranges: vec![],
+
+            pipeline_cache: vk::PipelineCache::null(),
+        }
+    }
+}
+
+impl PipelineBuilder {
+    pub fn new() -> PipelineBuilder {
+        PipelineBuilder::default()
+    }
+
+    /// Adds a shader stage, either SPIR-V or a GLSL source, see
+    /// `shader_compiler::load_spirv`. Its entry point is `main`.
+    pub fn shader<P: AsRef<Path>>(
+        mut self,
+        stage: vk::ShaderStageFlags,
+        path: P,
+    ) -> PipelineBuilder {
+        self.shader_stages.push(ShaderStage {
+            stage,
+            path: path.as_ref().to_owned(),
+        });
+        self
+    }
+
+    pub fn vertex_shader<P: AsRef<Path>>(self, path: P) -> PipelineBuilder {
+        self.shader(vk::ShaderStageFlags::VERTEX, path)
+    }
+
+    pub fn fragment_shader<P: AsRef<Path>>(self, path: P) -> PipelineBuilder {
+        self.shader(vk::ShaderStageFlags::FRAGMENT, path)
+    }
+
+    /// The vertex buffer layout. Leave it out for shaders that make up
+    /// their own vertices, like the triangle demo's.
+    pub fn vertex_input(
+        mut self,
+        bindings: &[vk::VertexInputBindingDescription],
+        attributes: &[vk::VertexInputAttributeDescription],
+    ) -> PipelineBuilder {
+        self.vertex_bindings = bindings.to_vec();
+        self.vertex_attributes = attributes.to_vec();
+        self
+    }
+
+    pub fn topology(mut self, topology: vk::PrimitiveTopology) -> PipelineBuilder {
+        self.topology = topology;
+        self
+    }
+
+    /// Only allowed for strip and fan topologies.
+    pub fn primitive_restart(mut self, is_enabled: bool) -> PipelineBuilder {
+        self.is_primitive_restart_enabled = is_enabled;
+        self
+    }
+
+    /// How many vertices make up a patch with PATCH_LIST, which takes a
+    /// tessellation control and evaluation shader. Defaults to 3.
+    pub fn patch_control_points(mut self, count: u32) -> PipelineBuilder {
+        self.patch_control_points = count;
+        self
+    }
+
+    /// A viewport and scissor covering all of `extent`, baked into the
+    /// pipeline. Not needed when both are dynamic states.
+    pub fn viewport(mut self, extent: vk::Extent2D) -> PipelineBuilder {
+        self.extent = Some(extent);
+        self
+    }
+
+    /// Leaves the viewport and scissor to `set_viewport` while recording,
+    /// so the pipeline doesn't have to be rebuilt when the window resizes.
+    pub fn dynamic_viewport(self) -> PipelineBuilder {
+        self.dynamic_state(vk::DynamicState::VIEWPORT)
+            .dynamic_state(vk::DynamicState::SCISSOR)
+    }
+
+    pub fn cull_mode(mut self, cull_mode: vk::CullModeFlags) -> PipelineBuilder {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn front_face(mut self, front_face: vk::FrontFace) -> PipelineBuilder {
+        self.front_face = front_face;
+        self
+    }
+
+    /// Anything but `FILL` needs the `fill_mode_non_solid` feature.
+    pub fn polygon_mode(mut self, polygon_mode: vk::PolygonMode) -> PipelineBuilder {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    /// Widths other than 1.0 need the `wide_lines` feature.
+    pub fn line_width(mut self, line_width: f32) -> PipelineBuilder {
+        self.line_width = line_width;
+        self
+    }
+
+    /// A non-zero `clamp` needs the `depth_bias_clamp` feature.
+    pub fn depth_bias(
+        mut self,
+        constant_factor: f32,
+        clamp: f32,
+        slope_factor: f32,
+    ) -> PipelineBuilder {
+        self.depth_bias = Some(DepthBias {
+            constant_factor,
+            clamp,
+            slope_factor,
+        });
+        self
+    }
+
+    /// Needs the `depth_clamp` feature.
+    pub fn depth_clamp(mut self, is_enabled: bool) -> PipelineBuilder {
+        self.is_depth_clamp_enabled = is_enabled;
+        self
+    }
+
+    /// Turns the depth test on. The render pass needs a depth attachment.
+    pub fn depth_test(
+        mut self,
+        compare_op: vk::CompareOp,
+        is_write_enabled: bool,
+    ) -> PipelineBuilder {
+        self.depth_compare_op = Some(compare_op);
+        self.is_depth_write_enabled = is_write_enabled;
+        self
+    }
+
+    pub fn stencil_test(
+        mut self,
+        front: vk::StencilOpState,
+        back: vk::StencilOpState,
+    ) -> PipelineBuilder {
+        self.stencil_ops = Some((front, back));
+        self
+    }
+
+    /// Adds the blending for the next colour attachment of the subpass, see
+    /// `opaque`, `alpha_blend` and `additive_blend`. Attachments that differ
+    /// need the `independent_blend` feature. With none added the pipeline
+    /// gets a single `opaque` one.
+    pub fn color_attachment(
+        mut self,
+        blend_state: vk::PipelineColorBlendAttachmentState,
+    ) -> PipelineBuilder {
+        self.color_attachments.push(blend_state);
+        self
+    }
+
+    /// Needs the `logic_op` feature, and replaces blending on every
+    /// attachment.
+    pub fn logic_op(mut self, logic_op: vk::LogicOp) -> PipelineBuilder {
+        self.logic_op = Some(logic_op);
+        self
+    }
+
+    pub fn blend_constants(mut self, blend_constants: [f32; 4]) -> PipelineBuilder {
+        self.blend_constants = blend_constants;
+        self
+    }
+
+    /// State set while recording with `vkCmdSet*` instead of baked in.
+    pub fn dynamic_state(mut self, dynamic_state: vk::DynamicState) -> PipelineBuilder {
+        if !self.dynamic_states.contains(&dynamic_state) {
+            self.dynamic_states.push(dynamic_state);
+        }
+        self
+    }
+
+    pub fn descriptor_set_layouts(
+        mut self,
+        layouts: &[vk::DescriptorSetLayout],
+    ) -> PipelineBuilder {
+        self.descriptor_set_layouts = layouts.to_vec();
+        self
+    }
+
+    pub fn push_constant_range(
+        mut self,
+        stage_flags: vk::ShaderStageFlags,
+        offset: u32,
+        size: u32,
+    ) -> PipelineBuilder {
+        self.push_constant_ranges.push(vk::PushConstantRange {
+            stage_flags,
+            offset,
+            size,
+        });
+        self
+    }
+
+    /// A push constant range at offset 0 the size of `T`, to be filled in
+    /// with `push_constants` while recording.
+    pub fn push_constants<T>(self, stage_flags: vk::ShaderStageFlags) -> PipelineBuilder {
+        self.push_constant_range(stage_flags, 0, std::mem::size_of::<T>() as u32)
+    }
+
+    /// Where the driver looks up and stores the compiled pipeline, see
+    /// `utility::pipeline_cache`.
+    pub fn pipeline_cache(mut self, pipeline_cache: vk::PipelineCache) -> PipelineBuilder {
+        self.pipeline_cache = pipeline_cache;
+        self
+    }
+
+    /// Every problem with the pipeline as described, given the enabled
+    /// `features` and the device `limits`. Empty when it can be built.
+    pub fn problems(
+        &self,
+        features: &vk::PhysicalDeviceFeatures,
+        limits: &vk::PhysicalDeviceLimits,
+    ) -> Vec<String> {
+        let mut problems = vec![];
+        let mut require = |feature: vk::Bool32, name: &str, reason: &str| {
+            if feature != vk::TRUE {
+                problems.push(format!("{} needs the {} feature", reason, name));
+            }
+        };
+
+        let stages = self
+            .shader_stages
+            .iter()
+            .fold(vk::ShaderStageFlags::empty(), |stages, shader| stages | shader.stage);
+        if stages.contains(vk::ShaderStageFlags::GEOMETRY) {
+            require(features.geometry_shader, "geometry_shader", "A geometry shader");
+        }
+        let tessellation_stages = vk::ShaderStageFlags::TESSELLATION_CONTROL
+            | vk::ShaderStageFlags::TESSELLATION_EVALUATION;
+        if stages.intersects(tessellation_stages) {
+            require(features.tessellation_shader, "tessellation_shader", "Tessellation");
+        }
+        if self.polygon_mode != vk::PolygonMode::FILL {
+            require(
+                features.fill_mode_non_solid,
+                "fill_mode_non_solid",
+                "A polygon mode other than FILL",
+            );
+        }
+        if self.line_width != 1.0 {
+            require(features.wide_lines, "wide_lines", "A line width other than 1.0");
+        }
+        if self.is_depth_clamp_enabled {
+            require(features.depth_clamp, "depth_clamp", "Depth clamping");
+        }
+        if self.depth_bias.is_some_and(|depth_bias| depth_bias.clamp != 0.0) {
+            require(features.depth_bias_clamp, "depth_bias_clamp", "A depth bias clamp");
+        }
+        if self.logic_op.is_some() {
+            require(features.logic_op, "logic_op", "A logic op");
+        }
+        let attachments = self.color_attachments();
+        if attachments.windows(2).any(|pair| !same_blend(&pair[0], &pair[1])) {
+            require(
+                features.independent_blend,
+                "independent_blend",
+                "Different blending per attachment",
+            );
+        }
+        if attachments.iter().any(uses_dual_source_blending) {
+            require(features.dual_src_blend, "dual_src_blend", "Dual source blending");
+        }
+
+        if !stages.contains(vk::ShaderStageFlags::VERTEX) {
+            problems.push("A graphics pipeline needs a vertex shader".to_owned());
+        }
+        if stages.intersects(tessellation_stages)
+            != (self.topology == vk::PrimitiveTopology::PATCH_LIST)
+        {
+            problems.push("Tessellation shaders go with, and only with, PATCH_LIST".to_owned());
+        }
+        if stages.intersects(tessellation_stages) && !stages.contains(tessellation_stages) {
+            problems.push(
+                "Tessellation needs both a control and an evaluation shader".to_owned(),
+            );
+        }
+        let max_patch_size = limits.max_tessellation_patch_size;
+        if self.topology == vk::PrimitiveTopology::PATCH_LIST
+            && (self.patch_control_points == 0 || self.patch_control_points > max_patch_size)
+        {
+            problems.push(format!(
+                "{} patch control points is outside the supported 1..={}",
+                self.patch_control_points, max_patch_size
+            ));
+        }
+        if self.is_primitive_restart_enabled && is_list_topology(self.topology) {
+            problems.push(format!("Primitive restart can't be used with {:?}", self.topology));
+        }
+        let [min_line_width, max_line_width] = limits.line_width_range;
+        if self.line_width < min_line_width || self.line_width > max_line_width {
+            problems.push(format!(
+                "Line width {} is outside the supported {}..={}",
+                self.line_width, min_line_width, max_line_width
+            ));
+        }
+        let is_viewport_dynamic = self.dynamic_states.contains(&vk::DynamicState::VIEWPORT)
+            && self.dynamic_states.contains(&vk::DynamicState::SCISSOR);
+        if self.extent.is_none() && !is_viewport_dynamic {
+            problems.push("Either a viewport or dynamic viewport and scissor is needed".to_owned());
+        }
+        for range in self.push_constant_ranges.iter() {
+            if range.size == 0
+                || !range.offset.is_multiple_of(4)
+                || !range.size.is_multiple_of(4)
+                || range.offset + range.size > limits.max_push_constants_size
+            {
+                problems.push(format!(
+                    "Push constant range {}..{} must be a non-empty multiple of 4 bytes within \
+                     the {} byte limit",
+                    range.offset,
+                    range.offset + range.size,
+                    limits.max_push_constants_size
+                ));
+            }
+        }
+
+        problems
+    }
+
+    /// Checks the pipeline against the device, see `problems`, then creates
+    /// it along with its layout for subpass 0 of `render_pass`.
+    pub fn build(
+        &self,
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &ash::Device,
+        render_pass: vk::RenderPass,
+    ) -> Result<(vk::Pipeline, vk::PipelineLayout), VkPlaygroundError> {
+        let features = vkstuff::vkdevice::device_features(instance, physical_device);
+        let limits = unsafe { instance.get_physical_device_properties(physical_device).limits };
+        let problems = self.problems(&features, &limits);
+        if !problems.is_empty() {
+            return Err(VkPlaygroundError::unsupported(
+                "PipelineBuilder",
+                problems.join("; "),
+            ));
+        }
+
+        // destroyed on the way out, the pipeline doesn't need them once made
+        let mut shader_modules = vec![];
+        for shader in self.shader_stages.iter() {
+            let shader_module =
+                vkstuff::vkshader::create_shader_module(device, &shader.path.to_string_lossy())?;
+            shader_modules.push(Owned::new(device, shader_module));
+        }
+
+        let main_function_name = CString::new(ENTRY_POINT).unwrap();
+        let shader_stages: Vec<vk::PipelineShaderStageCreateInfo> = self
+            .shader_stages
+            .iter()
+            .zip(shader_modules.iter())
+            .map(|(shader, module)| vk::PipelineShaderStageCreateInfo {
+                s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
+                p_next: ptr::null(),
+                flags: vk::PipelineShaderStageCreateFlags::empty(),
+                module: **module,
+                p_name: main_function_name.as_ptr(),
+                p_specialization_info: ptr::null(),
+                stage: shader.stage,
+            })
+            .collect();
+
+        let vertex_input_state_create_info = vk::PipelineVertexInputStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_VERTEX_INPUT_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineVertexInputStateCreateFlags::empty(),
+            vertex_attribute_description_count: self.vertex_attributes.len() as u32,
+            p_vertex_attribute_descriptions: self.vertex_attributes.as_ptr(),
+            vertex_binding_description_count: self.vertex_bindings.len() as u32,
+            p_vertex_binding_descriptions: self.vertex_bindings.as_ptr(),
+        };
+        let vertex_input_assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO,
+            flags: vk::PipelineInputAssemblyStateCreateFlags::empty(),
+            p_next: ptr::null(),
+            primitive_restart_enable: vk_bool(self.is_primitive_restart_enabled),
+            topology: self.topology,
+        };
+
+        let tessellation_state_create_info = vk::PipelineTessellationStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_TESSELLATION_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineTessellationStateCreateFlags::empty(),
+            patch_control_points: self.patch_control_points,
+        };
+
+        // the counts still matter when the viewport and scissor are dynamic
+        let extent = self.extent.unwrap_or_default();
+        let viewports = [vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: extent.width as f32,
+            height: extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }];
+        let scissors = [vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent,
+        }];
+        let viewport_state_create_info = vk::PipelineViewportStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_VIEWPORT_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineViewportStateCreateFlags::empty(),
+            scissor_count: scissors.len() as u32,
+            p_scissors: scissors.as_ptr(),
+            viewport_count: viewports.len() as u32,
+            p_viewports: viewports.as_ptr(),
+        };
+
+        let depth_bias = self.depth_bias.unwrap_or(DepthBias {
+            constant_factor: 0.0,
+            clamp: 0.0,
+            slope_factor: 0.0,
+        });
+        let rasterization_state_create_info = vk::PipelineRasterizationStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineRasterizationStateCreateFlags::empty(),
+            depth_clamp_enable: vk_bool(self.is_depth_clamp_enabled),
+            cull_mode: self.cull_mode,
+            front_face: self.front_face,
+            line_width: self.line_width,
+            polygon_mode: self.polygon_mode,
+            rasterizer_discard_enable: vk::FALSE,
+            depth_bias_clamp: depth_bias.clamp,
+            depth_bias_constant_factor: depth_bias.constant_factor,
+            depth_bias_enable: vk_bool(self.depth_bias.is_some()),
+            depth_bias_slope_factor: depth_bias.slope_factor,
+        };
+        let multisample_state_create_info = vk::PipelineMultisampleStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
+            flags: vk::PipelineMultisampleStateCreateFlags::empty(),
+            p_next: ptr::null(),
+            rasterization_samples: vk::SampleCountFlags::TYPE_1,
+            sample_shading_enable: vk::FALSE,
+            min_sample_shading: 0.0,
+            p_sample_mask: ptr::null(),
+            alpha_to_one_enable: vk::FALSE,
+            alpha_to_coverage_enable: vk::FALSE,
+        };
+
+        let stencil_state = vk::StencilOpState {
+            fail_op: vk::StencilOp::KEEP,
+            pass_op: vk::StencilOp::KEEP,
+            depth_fail_op: vk::StencilOp::KEEP,
+            compare_op: vk::CompareOp::ALWAYS,
+            compare_mask: 0,
+            write_mask: 0,
+            reference: 0,
+        };
+        let (front, back) = self.stencil_ops.unwrap_or((stencil_state, stencil_state));
+        let depth_state_create_info = vk::PipelineDepthStencilStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineDepthStencilStateCreateFlags::empty(),
+            depth_test_enable: vk_bool(self.depth_compare_op.is_some()),
+            depth_write_enable: vk_bool(self.is_depth_write_enabled),
+            depth_compare_op: self.depth_compare_op.unwrap_or(vk::CompareOp::LESS_OR_EQUAL),
+            depth_bounds_test_enable: vk::FALSE,
+            stencil_test_enable: vk_bool(self.stencil_ops.is_some()),
+            front,
+            back,
+            max_depth_bounds: 1.0,
+            min_depth_bounds: 0.0,
+        };
+
+        let color_blend_attachment_states = self.color_attachments();
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineColorBlendStateCreateFlags::empty(),
+            logic_op_enable: vk_bool(self.logic_op.is_some()),
+            logic_op: self.logic_op.unwrap_or(vk::LogicOp::COPY),
+            attachment_count: color_blend_attachment_states.len() as u32,
+            p_attachments: color_blend_attachment_states.as_ptr(),
+            blend_constants: self.blend_constants,
+        };
+
+        let dynamic_state_create_info = vk::PipelineDynamicStateCreateInfo {
+            s_type: vk::StructureType::PIPELINE_DYNAMIC_STATE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineDynamicStateCreateFlags::empty(),
+            dynamic_state_count: self.dynamic_states.len() as u32,
+            p_dynamic_states: self.dynamic_states.as_ptr(),
+        };
+
+        let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo {
+            s_type: vk::StructureType::PIPELINE_LAYOUT_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineLayoutCreateFlags::empty(),
+            set_layout_count: self.descriptor_set_layouts.len() as u32,
+            p_set_layouts: self.descriptor_set_layouts.as_ptr(),
+            push_constant_range_count: self.push_constant_ranges.len() as u32,
+            p_push_constant_ranges: self.push_constant_ranges.as_ptr(),
+        };
+
+        let pipeline_layout = unsafe {
+            device
+                .create_pipeline_layout(&pipeline_layout_create_info, None)
+                .vk_context("vkCreatePipelineLayout", "Failed to create pipeline layout!")?
+        };
+        let pipeline_layout = Owned::new(device, pipeline_layout);
+
+        let graphic_pipeline_create_infos = [vk::GraphicsPipelineCreateInfo {
+            s_type: vk::StructureType::GRAPHICS_PIPELINE_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineCreateFlags::empty(),
+            stage_count: shader_stages.len() as u32,
+            p_stages: shader_stages.as_ptr(),
+            p_vertex_input_state: &vertex_input_state_create_info,
+            p_input_assembly_state: &vertex_input_assembly_state_info,
+            p_tessellation_state: if self.topology == vk::PrimitiveTopology::PATCH_LIST {
+                &tessellation_state_create_info
+            } else {
+                ptr::null()
+            },
+            p_viewport_state: &viewport_state_create_info,
+            p_rasterization_state: &rasterization_state_create_info,
+            p_multisample_state: &multisample_state_create_info,
+            p_depth_stencil_state: &depth_state_create_info,
+            p_color_blend_state: &color_blend_state,
+            p_dynamic_state: if self.dynamic_states.is_empty() {
+                ptr::null()
+            } else {
+                &dynamic_state_create_info
+            },
+            layout: *pipeline_layout,
+            render_pass,
+            subpass: 0,
+            base_pipeline_handle: vk::Pipeline::null(),
+            base_pipeline_index: -1,
+        }];
+
+        let graphics_pipelines = unsafe {
+            device
+                .create_graphics_pipelines(
+                    self.pipeline_cache,
+                    &graphic_pipeline_create_infos,
+                    None,
+                )
+                .map_err(|(_, result)| result)
+                .vk_context("vkCreateGraphicsPipelines", "Failed to create Graphics Pipeline!.")?
+        };
+
+        Ok((graphics_pipelines[0], pipeline_layout.into_raw()))
+    }
+
+    fn color_attachments(&self) -> Vec<vk::PipelineColorBlendAttachmentState> {
+        if self.color_attachments.is_empty() {
+            vec![opaque()]
+        } else {
+            self.color_attachments.clone()
+        }
+    }
+}
+
+/// Sets a viewport and scissor covering all of `extent`, for pipelines made
+/// with `dynamic_viewport`.
+pub fn set_viewport(device: &ash::Device, command_buffer: vk::CommandBuffer, extent: vk::Extent2D) {
+    let viewports = [vk::Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: extent.width as f32,
+        height: extent.height as f32,
+        min_depth: 0.0,
+        max_depth: 1.0,
+    }];
+    let scissors = [vk::Rect2D {
+        offset: vk::Offset2D { x: 0, y: 0 },
+        extent,
+    }];
+
+    unsafe {
+        device.cmd_set_viewport(command_buffer, 0, &viewports);
+        device.cmd_set_scissor(command_buffer, 0, &scissors);
+    }
+}
+
+/// Writes `constants` to the start of the push constant range declared with
+/// `PipelineBuilder::push_constants::<T>`. `T` should be `#[repr(C)]` and
+/// laid out like the shader's push constant block.
+pub fn push_constants<T: Copy>(
+    device: &ash::Device,
+    command_buffer: vk::CommandBuffer,
+    pipeline_layout: vk::PipelineLayout,
+    stage_flags: vk::ShaderStageFlags,
+    constants: &T,
+) {
+    let bytes = unsafe {
+        std::slice::from_raw_parts(constants as *const T as *const u8, std::mem::size_of::<T>())
+    };
+
+    unsafe {
+        device.cmd_push_constants(command_buffer, pipeline_layout, stage_flags, 0, bytes);
+    }
+}
+
+/// Writes the colour as is.
+pub fn opaque() -> vk::PipelineColorBlendAttachmentState {
+    vk::PipelineColorBlendAttachmentState {
+        blend_enable: vk::FALSE,
+        color_write_mask: vk::ColorComponentFlags::all(),
+        src_color_blend_factor: vk::BlendFactor::ONE,
+        dst_color_blend_factor: vk::BlendFactor::ZERO,
+        color_blend_op: vk::BlendOp::ADD,
+        src_alpha_blend_factor: vk::BlendFactor::ONE,
+        dst_alpha_blend_factor: vk::BlendFactor::ZERO,
+        alpha_blend_op: vk::BlendOp::ADD,
+    }
+}
+
+/// Blends over what's there by the source alpha.
+pub fn alpha_blend() -> vk::PipelineColorBlendAttachmentState {
+    vk::PipelineColorBlendAttachmentState {
+        blend_enable: vk::TRUE,
+        src_color_blend_factor: vk::BlendFactor::SRC_ALPHA,
+        dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        dst_alpha_blend_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        ..opaque()
+    }
+}
+
+/// Adds to what's there, e.g. for particles and glows.
+pub fn additive_blend() -> vk::PipelineColorBlendAttachmentState {
+    vk::PipelineColorBlendAttachmentState {
+        blend_enable: vk::TRUE,
+        src_color_blend_factor: vk::BlendFactor::SRC_ALPHA,
+        dst_color_blend_factor: vk::BlendFactor::ONE,
+        dst_alpha_blend_factor: vk::BlendFactor::ONE,
+        ..opaque()
+    }
+}
+
+fn vk_bool(value: bool) -> vk::Bool32 {
+    if value {
+        vk::TRUE
+    } else {
+        vk::FALSE
+    }
+}
+
+fn is_list_topology(topology: vk::PrimitiveTopology) -> bool {
+    matches!(
+        topology,
+        vk::PrimitiveTopology::POINT_LIST
+            | vk::PrimitiveTopology::LINE_LIST
+            | vk::PrimitiveTopology::TRIANGLE_LIST
+            | vk::PrimitiveTopology::LINE_LIST_WITH_ADJACENCY
+            | vk::PrimitiveTopology::TRIANGLE_LIST_WITH_ADJACENCY
+            | vk::PrimitiveTopology::PATCH_LIST
+    )
+}
+
+// vk::PipelineColorBlendAttachmentState has no PartialEq
+fn same_blend(
+    a: &vk::PipelineColorBlendAttachmentState,
+    b: &vk::PipelineColorBlendAttachmentState,
+) -> bool {
+    a.blend_enable == b.blend_enable
+        && a.color_write_mask == b.color_write_mask
+        && a.src_color_blend_factor == b.src_color_blend_factor
+        && a.dst_color_blend_factor == b.dst_color_blend_factor
+        && a.color_blend_op == b.color_blend_op
+        && a.src_alpha_blend_factor == b.src_alpha_blend_factor
+        && a.dst_alpha_blend_factor == b.dst_alpha_blend_factor
+        && a.alpha_blend_op == b.alpha_blend_op
+}
+
+fn uses_dual_source_blending(attachment: &vk::PipelineColorBlendAttachmentState) -> bool {
+    let is_dual_source = |factor: vk::BlendFactor| {
+        matches!(
+            factor,
+            vk::BlendFactor::SRC1_COLOR
+                | vk::BlendFactor::ONE_MINUS_SRC1_COLOR
+                | vk::BlendFactor::SRC1_ALPHA
+                | vk::BlendFactor::ONE_MINUS_SRC1_ALPHA
+        )
+    };
+
+    attachment.blend_enable == vk::TRUE
+        && [
+            attachment.src_color_blend_factor,
+            attachment.dst_color_blend_factor,
+            attachment.src_alpha_blend_factor,
+            attachment.dst_alpha_blend_factor,
+        ]
+        .iter()
+        .any(|&factor| is_dual_source(factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> vk::PhysicalDeviceLimits {
+        vk::PhysicalDeviceLimits {
+            line_width_range: [1.0, 8.0],
+            max_push_constants_size: 128,
+            max_tessellation_patch_size: 32,
+            ..Default::default()
+        }
+    }
+
+    fn minimal() -> PipelineBuilder {
+        PipelineBuilder::new()
+            .vertex_shader("shader.vert")
+            .fragment_shader("shader.frag")
+            .dynamic_viewport()
+    }
+
+    fn tessellated() -> PipelineBuilder {
+        minimal()
+            .shader(vk::ShaderStageFlags::TESSELLATION_CONTROL, "shader.tesc")
+            .shader(vk::ShaderStageFlags::TESSELLATION_EVALUATION, "shader.tese")
+            .topology(vk::PrimitiveTopology::PATCH_LIST)
+    }
+
+    fn tessellation_features() -> vk::PhysicalDeviceFeatures {
+        vk::PhysicalDeviceFeatures {
+            tessellation_shader: vk::TRUE,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn minimal_pipeline_has_no_problems() {
+        let problems = minimal().problems(&Default::default(), &limits());
+
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn missing_vertex_shader_and_viewport_are_problems() {
+        let problems = PipelineBuilder::new()
+            .fragment_shader("shader.frag")
+            .problems(&Default::default(), &limits());
+
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+    }
+
+    #[test]
+    fn features_are_required_only_when_used() {
+        let builder = minimal()
+            .polygon_mode(vk::PolygonMode::LINE)
+            .line_width(2.0)
+            .depth_clamp(true);
+        let features = vk::PhysicalDeviceFeatures {
+            fill_mode_non_solid: vk::TRUE,
+            wide_lines: vk::TRUE,
+            depth_clamp: vk::TRUE,
+            ..Default::default()
+        };
+
+        let without_features = builder.problems(&Default::default(), &limits());
+        let with_features = builder.problems(&features, &limits());
+
+        assert_eq!(without_features.len(), 3, "{:?}", without_features);
+        assert!(without_features[0].contains("fill_mode_non_solid"));
+        assert!(with_features.is_empty(), "{:?}", with_features);
+    }
+
+    #[test]
+    fn different_blending_per_attachment_needs_independent_blend() {
+        let problems = minimal()
+            .color_attachment(opaque())
+            .color_attachment(alpha_blend())
+            .problems(&Default::default(), &limits());
+
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].contains("independent_blend"));
+    }
+
+    #[test]
+    fn tessellation_with_patch_list_has_no_problems() {
+        let problems = tessellated()
+            .patch_control_points(4)
+            .problems(&tessellation_features(), &limits());
+
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn tessellation_and_patch_list_need_each_other() {
+        let features = tessellation_features();
+
+        let without_shaders = minimal()
+            .topology(vk::PrimitiveTopology::PATCH_LIST)
+            .problems(&features, &limits());
+        let without_patches = tessellated()
+            .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
+            .problems(&features, &limits());
+
+        assert_eq!(without_shaders.len(), 1, "{:?}", without_shaders);
+        assert_eq!(without_patches.len(), 1, "{:?}", without_patches);
+    }
+
+    #[test]
+    fn tessellation_needs_both_stages() {
+        let problems = minimal()
+            .shader(vk::ShaderStageFlags::TESSELLATION_EVALUATION, "shader.tese")
+            .topology(vk::PrimitiveTopology::PATCH_LIST)
+            .problems(&tessellation_features(), &limits());
+
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].contains("control and an evaluation"));
+    }
+
+    #[test]
+    fn patch_control_points_must_be_within_the_limit() {
+        let features = tessellation_features();
+
+        let none = tessellated().patch_control_points(0).problems(&features, &limits());
+        let too_many = tessellated().patch_control_points(33).problems(&features, &limits());
+
+        assert_eq!(none.len(), 1, "{:?}", none);
+        assert_eq!(too_many.len(), 1, "{:?}", too_many);
+    }
+
+    #[test]
+    fn primitive_restart_needs_a_strip_or_fan() {
+        let list = minimal().primitive_restart(true).problems(&Default::default(), &limits());
+        let strip = minimal()
+            .topology(vk::PrimitiveTopology::TRIANGLE_STRIP)
+            .primitive_restart(true)
+            .problems(&Default::default(), &limits());
+
+        assert_eq!(list.len(), 1, "{:?}", list);
+        assert!(strip.is_empty(), "{:?}", strip);
+    }
+
+    #[test]
+    fn line_width_must_be_within_the_limits() {
+        let features = vk::PhysicalDeviceFeatures {
+            wide_lines: vk::TRUE,
+            ..Default::default()
+        };
+
+        let problems = minimal().line_width(9.0).problems(&features, &limits());
+
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+    }
+
+    #[test]
+    fn push_constant_ranges_must_be_aligned_and_within_the_limit() {
+        let stage = vk::ShaderStageFlags::VERTEX;
+
+        let problems = minimal()
+            .push_constant_range(stage, 0, 64)
+            .push_constant_range(stage, 2, 4)
+            .push_constant_range(stage, 64, 0)
+            .push_constant_range(stage, 64, 68)
+            .problems(&Default::default(), &limits());
+
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+    }
+}
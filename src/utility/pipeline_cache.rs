@@ -0,0 +1,262 @@
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::handles::Owned;
+
+// A pipeline cache kept on disk between runs, so drivers that compile
+// pipelines slowly (lavapipe especially) only do it once. The blob lives in
+// `.pipeline-cache/<vendor>-<device>.bin`, or in the directory
+// VK_PLAYGROUND_PIPELINE_CACHE points at, and is written back when the cache
+// is dropped.
+//
+// A blob is only handed to the driver when its header matches the device's
+// vendor ID, device ID and pipeline cache UUID. The UUID changes with the
+// driver version, so after an update the old blob is dropped and the cache
+// starts out empty, as it does for a missing or truncated file.
+
+pub const PIPELINE_CACHE_ENV_VAR: &str = "VK_PLAYGROUND_PIPELINE_CACHE";
+const DEFAULT_CACHE_DIR: &str = ".pipeline-cache";
+
+// header length, header version, vendor ID, device ID and the UUID
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+pub struct PipelineCache {
+    cache: Owned<vk::PipelineCache>,
+    device: ash::Device,
+    path: PathBuf,
+}
+
+impl PipelineCache {
+    /// Creates the cache from the blob saved for this device by a previous
+    /// run, if there is a usable one.
+    pub fn load(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &ash::Device,
+    ) -> Result<PipelineCache, VkPlaygroundError> {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let path = cache_dir().join(format!(
+            "{:04x}-{:04x}.bin",
+            properties.vendor_id, properties.device_id
+        ));
+
+        let initial_data = match fs::read(&path) {
+            Ok(data) => match check_header(&data, &properties) {
+                Ok(()) => data,
+                Err(reason) => {
+                    eprintln!("Discarding pipeline cache {}: {}", path.display(), reason);
+                    vec![]
+                }
+            },
+            Err(_) => vec![],
+        };
+
+        // a blob can pass the header check and still be corrupt, the driver
+        // gets the final say and an empty cache is the fallback
+        let cache = match create_pipeline_cache(device, &initial_data) {
+            Ok(cache) => cache,
+            Err(_) if !initial_data.is_empty() => {
+                eprintln!(
+                    "Discarding pipeline cache {}: rejected by the driver",
+                    path.display()
+                );
+                create_pipeline_cache(device, &[])?
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok(PipelineCache {
+            cache: Owned::new(device, cache),
+            device: device.clone(),
+            path,
+        })
+    }
+
+    /// The cache to pass to pipeline creation, see
+    /// `PipelineBuilder::pipeline_cache`.
+    pub fn handle(&self) -> vk::PipelineCache {
+        *self.cache
+    }
+
+    /// Writes the cache's current contents to disk. Also done on drop.
+    pub fn save(&self) -> Result<(), VkPlaygroundError> {
+        let data = unsafe {
+            self.device
+                .get_pipeline_cache_data(*self.cache)
+                .vk_context("vkGetPipelineCacheData", "Failed to read the pipeline cache")?
+        };
+        write_atomically(&self.path, &data).map_err(|source| VkPlaygroundError::Io {
+            path: self.path.clone(),
+            source,
+        })
+    }
+}
+
+impl Drop for PipelineCache {
+    fn drop(&mut self) {
+        if let Err(err) = self.save() {
+            eprintln!("Failed to save the pipeline cache: {}", err);
+        }
+    }
+}
+
+pub fn cache_dir() -> PathBuf {
+    std::env::var_os(PIPELINE_CACHE_ENV_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_DIR))
+}
+
+/// Why `data` can't be used as the initial data of a pipeline cache on the
+/// device with `properties`, if it can't.
+pub fn check_header(
+    data: &[u8],
+    properties: &vk::PhysicalDeviceProperties,
+) -> Result<(), String> {
+    if data.len() < HEADER_SIZE {
+        return Err(format!("{} bytes is too short for a header", data.len()));
+    }
+
+    // the header is little endian whatever the host is
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+    };
+    let header_size = read_u32(0);
+    let header_version = read_u32(4);
+    let vendor_id = read_u32(8);
+    let device_id = read_u32(12);
+    let uuid = &data[16..HEADER_SIZE];
+
+    if (header_size as usize) < HEADER_SIZE || header_size as usize > data.len() {
+        return Err(format!("bad header size {}", header_size));
+    }
+    if header_version != vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32 {
+        return Err(format!("unknown header version {}", header_version));
+    }
+    if vendor_id != properties.vendor_id || device_id != properties.device_id {
+        return Err(format!(
+            "made on {:04x}:{:04x}, not {:04x}:{:04x}",
+            vendor_id, device_id, properties.vendor_id, properties.device_id
+        ));
+    }
+    if uuid != &properties.pipeline_cache_uuid[..] {
+        return Err("made by a different driver version".to_owned());
+    }
+
+    Ok(())
+}
+
+fn create_pipeline_cache(
+    device: &ash::Device,
+    initial_data: &[u8],
+) -> Result<vk::PipelineCache, VkPlaygroundError> {
+    let pipeline_cache_create_info = vk::PipelineCacheCreateInfo {
+        s_type: vk::StructureType::PIPELINE_CACHE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::PipelineCacheCreateFlags::empty(),
+        initial_data_size: initial_data.len(),
+        p_initial_data: initial_data.as_ptr() as *const std::ffi::c_void,
+    };
+
+    unsafe {
+        device
+            .create_pipeline_cache(&pipeline_cache_create_info, None)
+            .vk_context("vkCreatePipelineCache", "Failed to create the pipeline cache")
+    }
+}
+
+// written next to the destination and renamed into place, so a crash while
+// saving can't leave half a blob behind
+fn write_atomically(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    let result = fs::write(&temp_path, data).and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: [u8; vk::UUID_SIZE] = [7; vk::UUID_SIZE];
+
+    fn properties() -> vk::PhysicalDeviceProperties {
+        vk::PhysicalDeviceProperties {
+            vendor_id: 0x10de,
+            device_id: 0x2204,
+            pipeline_cache_uuid: UUID,
+            ..Default::default()
+        }
+    }
+
+    fn header(vendor_id: u32, device_id: u32, uuid: &[u8]) -> Vec<u8> {
+        let version = vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32;
+        let mut data = vec![];
+        data.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        data.extend_from_slice(&version.to_le_bytes());
+        data.extend_from_slice(&vendor_id.to_le_bytes());
+        data.extend_from_slice(&device_id.to_le_bytes());
+        data.extend_from_slice(uuid);
+        data
+    }
+
+    #[test]
+    fn matching_header_is_accepted() {
+        let mut data = header(0x10de, 0x2204, &UUID);
+        data.extend_from_slice(&[1, 2, 3]);
+
+        assert_eq!(check_header(&data, &properties()), Ok(()));
+    }
+
+    #[test]
+    fn short_header_is_rejected() {
+        let data = header(0x10de, 0x2204, &UUID);
+
+        assert!(check_header(&data[..HEADER_SIZE - 1], &properties()).is_err());
+        assert!(check_header(&[], &properties()).is_err());
+    }
+
+    #[test]
+    fn other_device_is_rejected() {
+        let other_vendor = header(0x1002, 0x2204, &UUID);
+        let other_device = header(0x10de, 0x2206, &UUID);
+
+        assert!(check_header(&other_vendor, &properties()).is_err());
+        assert!(check_header(&other_device, &properties()).is_err());
+    }
+
+    #[test]
+    fn other_driver_version_is_rejected() {
+        let data = header(0x10de, 0x2204, &[8; vk::UUID_SIZE]);
+
+        assert!(check_header(&data, &properties()).is_err());
+    }
+
+    #[test]
+    fn big_endian_header_is_rejected() {
+        let mut data = header(0x10de, 0x2204, &UUID);
+        for field in data[..16].chunks_mut(4) {
+            field.reverse();
+        }
+
+        assert!(check_header(&data, &properties()).is_err());
+    }
+
+    #[test]
+    fn header_size_past_the_end_is_rejected() {
+        let mut data = header(0x10de, 0x2204, &UUID);
+        data[..4].copy_from_slice(&(HEADER_SIZE as u32 + 1).to_le_bytes());
+
+        assert!(check_header(&data, &properties()).is_err());
+    }
+}
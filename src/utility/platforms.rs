@@ -9,6 +9,8 @@ use ash::extensions::khr::Win32Surface;
 use ash::extensions::ext::DebugUtils;
 use ash::extensions::khr::Surface;
 
+use std::ffi::CStr;
+
 #[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
 pub fn required_extension_names() -> Vec<*const i8> {
     vec![
@@ -18,6 +20,17 @@ pub fn required_extension_names() -> Vec<*const i8> {
     ]
 }
 
+// the instance extensions create_surface needs, for ExtensionConfig
+#[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
+pub fn surface_extension_names() -> Vec<&'static CStr> {
+    vec![Surface::name(), XlibSurface::name()]
+}
+
+#[cfg(target_os = "windows")]
+pub fn surface_extension_names() -> Vec<&'static CStr> {
+    vec![Surface::name(), Win32Surface::name()]
+}
+
 #[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
 pub unsafe fn create_surface<E: EntryV1_0, I: InstanceV1_0>(
     entry: &E,
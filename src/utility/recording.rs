@@ -0,0 +1,301 @@
+use ash::vk;
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::utility::args;
+use crate::utility::error::VkPlaygroundError;
+use crate::utility::screenshot::CapturedFrame;
+
+// Recording a fixed number of frames while driving the app with a fixed
+// delta time instead of the wall clock, so animations come out the same on
+// every run. Frames go either to numbered PNGs in a directory or to a single
+// raw Y4M stream, which ffmpeg and most players understand, e.g.
+// `cargo run --bin spinny_cube -- --record 120 --record-output spin.y4m`
+
+const DEFAULT_DELTA_TIME: f32 = 1.0 / 60.0;
+const DEFAULT_OUTPUT_PATH: &str = "recording";
+
+#[derive(Debug, Clone)]
+pub struct RecordingOptions {
+    pub frame_count: u32,
+    pub delta_time: f32,
+    // a directory for PNG frames, or a file ending in .y4m
+    pub output_path: PathBuf,
+}
+
+impl RecordingOptions {
+    pub const FLAGS: &'static [&'static str] = &["--record", "--delta-time", "--record-output"];
+
+    /// Returns `None` unless `--record <frames>` was passed to the program.
+    pub fn from_args() -> Result<Option<RecordingOptions>, VkPlaygroundError> {
+        RecordingOptions::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<I: Iterator<Item = String>>(
+        mut args: I,
+    ) -> Result<Option<RecordingOptions>, VkPlaygroundError> {
+        let mut frame_count = None;
+        let mut delta_time = DEFAULT_DELTA_TIME;
+        let mut output_path = PathBuf::from(DEFAULT_OUTPUT_PATH);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => {
+                    frame_count =
+                        Some(args::parse_value(args.next(), "--record expects a frame count")?);
+                }
+                "--delta-time" => {
+                    delta_time =
+                        args::parse_value(args.next(), "--delta-time expects a time in seconds")?;
+                }
+                "--record-output" => {
+                    output_path =
+                        args::parse_value(args.next(), "--record-output expects a path")?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(frame_count.map(|frame_count| RecordingOptions {
+            frame_count,
+            delta_time,
+            output_path,
+        }))
+    }
+}
+
+enum RecordingSink {
+    PngSequence(PathBuf),
+    // the writer is opened on the first frame, the header needs its size
+    Y4m(PathBuf, Option<BufWriter<File>>),
+}
+
+pub struct FrameRecorder {
+    options: RecordingOptions,
+    sink: RecordingSink,
+    frames_written: u32,
+    frame_extent: Option<vk::Extent2D>,
+}
+
+impl FrameRecorder {
+    pub fn new(options: RecordingOptions) -> Result<FrameRecorder, VkPlaygroundError> {
+        let is_y4m = options
+            .output_path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("y4m"));
+
+        let sink = if is_y4m {
+            RecordingSink::Y4m(options.output_path.clone(), None)
+        } else {
+            fs::create_dir_all(&options.output_path)
+                .map_err(|source| io_error(&options.output_path, source))?;
+            RecordingSink::PngSequence(options.output_path.clone())
+        };
+
+        Ok(FrameRecorder {
+            options,
+            sink,
+            frames_written: 0,
+            frame_extent: None,
+        })
+    }
+
+    pub fn delta_time(&self) -> f32 {
+        self.options.delta_time
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames_written >= self.options.frame_count
+    }
+
+    pub fn write_frame(&mut self, frame: &CapturedFrame) -> Result<(), VkPlaygroundError> {
+        // a Y4M stream has one size for every frame, keep PNGs the same
+        let frame_extent = *self.frame_extent.get_or_insert(frame.extent);
+        if frame_extent.width != frame.extent.width || frame_extent.height != frame.extent.height
+        {
+            return Err(VkPlaygroundError::unsupported(
+                "FrameRecorder::write_frame",
+                "The window was resized while recording",
+            ));
+        }
+
+        match &mut self.sink {
+            RecordingSink::PngSequence(directory) => {
+                let path = directory.join(format!("frame_{:05}.png", self.frames_written));
+                image::save_buffer(
+                    &path,
+                    &frame.pixels,
+                    frame.extent.width,
+                    frame.extent.height,
+                    image::ColorType::RGBA(8),
+                )
+                .map_err(|source| io_error(&path, source))?;
+            }
+            RecordingSink::Y4m(path, writer) => {
+                let writer = match writer {
+                    Some(writer) => writer,
+                    None => writer.insert(create_y4m_writer(path, frame, self.options.delta_time)?),
+                };
+                write_y4m_frame(writer, frame).map_err(|source| io_error(path, source))?;
+            }
+        }
+
+        self.frames_written += 1;
+
+        if self.is_finished() {
+            if let RecordingSink::Y4m(path, Some(writer)) = &mut self.sink {
+                writer.flush().map_err(|source| io_error(path, source))?;
+            }
+            println!(
+                "Recorded {} frames to {}",
+                self.frames_written,
+                self.options.output_path.display()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn io_error(path: &Path, source: io::Error) -> VkPlaygroundError {
+    VkPlaygroundError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn create_y4m_writer(
+    path: &Path,
+    first_frame: &CapturedFrame,
+    delta_time: f32,
+) -> Result<BufWriter<File>, VkPlaygroundError> {
+    let file = File::create(path).map_err(|source| io_error(path, source))?;
+    let mut writer = BufWriter::new(file);
+    write_y4m_header(&mut writer, first_frame.extent, delta_time)
+        .map_err(|source| io_error(path, source))?;
+
+    Ok(writer)
+}
+
+fn write_y4m_header<W: Write>(
+    writer: &mut W,
+    extent: vk::Extent2D,
+    delta_time: f32,
+) -> io::Result<()> {
+    // frame rate as a fraction, to a thousandth of a frame per second
+    let frame_rate_millis = (1000.0 / delta_time).round() as u32;
+    writeln!(
+        writer,
+        "YUV4MPEG2 W{} H{} F{}:1000 Ip A1:1 C444",
+        extent.width, extent.height, frame_rate_millis
+    )
+}
+
+// full resolution 4:4:4 planes, converted with the BT.601 studio swing
+// coefficients players assume when the header doesn't say otherwise
+fn write_y4m_frame<W: Write>(writer: &mut W, frame: &CapturedFrame) -> io::Result<()> {
+    let pixel_count = (frame.extent.width * frame.extent.height) as usize;
+    let mut planes = vec![0_u8; pixel_count * 3];
+    let (y_plane, chroma_planes) = planes.split_at_mut(pixel_count);
+    let (u_plane, v_plane) = chroma_planes.split_at_mut(pixel_count);
+
+    for (i, pixel) in frame.pixels.chunks(4).enumerate() {
+        let (r, g, b) = (
+            f32::from(pixel[0]),
+            f32::from(pixel[1]),
+            f32::from(pixel[2]),
+        );
+
+        y_plane[i] = (16.0 + (65.738 * r + 129.057 * g + 25.064 * b) / 256.0).round() as u8;
+        u_plane[i] = (128.0 + (-37.945 * r - 74.494 * g + 112.439 * b) / 256.0).round() as u8;
+        v_plane[i] = (128.0 + (112.439 * r - 94.154 * g - 18.285 * b) / 256.0).round() as u8;
+    }
+
+    writer.write_all(b"FRAME\n")?;
+    writer.write_all(&planes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn options_are_none_without_the_record_flag() {
+        let options = RecordingOptions::parse(args(&["--delta-time", "0.5"])).unwrap();
+
+        assert!(options.is_none());
+    }
+
+    #[test]
+    fn recording_options_are_parsed() {
+        let options = RecordingOptions::parse(args(&[
+            "--record",
+            "120",
+            "--delta-time",
+            "0.5",
+            "--record-output",
+            "spin.y4m",
+        ]))
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(options.frame_count, 120);
+        assert_eq!(options.delta_time, 0.5);
+        assert_eq!(options.output_path, PathBuf::from("spin.y4m"));
+    }
+
+    #[test]
+    fn missing_or_invalid_values_are_usage_errors() {
+        for bad_args in [&["--record"][..], &["--record", "10", "--delta-time", "x"]] {
+            match RecordingOptions::parse(args(bad_args)) {
+                Err(VkPlaygroundError::Usage { .. }) => {}
+                other => panic!("expected a usage error for {:?}, got {:?}", bad_args, other),
+            }
+        }
+    }
+
+    #[test]
+    fn header_has_the_size_and_frame_rate() {
+        let mut output = vec![];
+        let extent = vk::Extent2D {
+            width: 640,
+            height: 480,
+        };
+
+        write_y4m_header(&mut output, extent, 1.0 / 60.0).unwrap();
+
+        assert_eq!(output, b"YUV4MPEG2 W640 H480 F60000:1000 Ip A1:1 C444\n");
+    }
+
+    #[test]
+    fn frame_is_converted_to_bt601_planes() {
+        // white, black, red, green and blue, with alpha ignored
+        let frame = CapturedFrame {
+            extent: vk::Extent2D {
+                width: 5,
+                height: 1,
+            },
+            pixels: vec![
+                255, 255, 255, 255, 0, 0, 0, 0, 255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255,
+            ],
+        };
+        let mut output = vec![];
+
+        write_y4m_frame(&mut output, &frame).unwrap();
+
+        let mut expected = b"FRAME\n".to_vec();
+        expected.extend_from_slice(&[235, 16, 81, 145, 41]);
+        expected.extend_from_slice(&[128, 128, 90, 54, 240]);
+        expected.extend_from_slice(&[128, 128, 240, 34, 110]);
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,1029 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use std::collections::HashMap;
+use std::ptr;
+
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+
+// Reads what a SPIR-V module expects from the pipeline, so descriptor set
+// layouts and push constant ranges don't have to be kept in sync with the
+// shaders by hand, and a Rust vertex type can be checked against the
+// vertex shader's inputs, e.g.
+//
+//     let vert = ShaderReflection::from_spirv(&tools::read_shader_code(vert_path)?)?;
+//     let frag = ShaderReflection::from_spirv(&tools::read_shader_code(frag_path)?)?;
+//     vert.check_vertex_input(&VertexV3::get_attribute_descriptions())?;
+//     let set_layouts = reflection::create_descriptor_set_layouts(device, &[vert, frag])?;
+//
+// Only the parts of SPIR-V that GLSL shaders use for their interface are
+// understood, anything else in the module is skipped over.
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+// opcodes
+const OP_NAME: u32 = 5;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+// decorations
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// storage classes
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+// image dimensions
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub descriptor_count: u32,
+    pub stage_flags: vk::ShaderStageFlags,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexInput {
+    pub location: u32,
+    pub format: vk::Format,
+    pub name: String,
+}
+
+/// The interface of one shader stage.
+#[derive(Debug, Clone)]
+pub struct ShaderReflection {
+    pub stage: vk::ShaderStageFlags,
+    pub entry_point: String,
+    pub descriptor_bindings: Vec<DescriptorBinding>,
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+    /// Sorted by location. Only filled in for vertex shaders.
+    pub vertex_inputs: Vec<VertexInput>,
+}
+
+#[derive(Debug, Clone)]
+enum SpirvType {
+    Bool,
+    Int { width: u32, is_signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray,
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+}
+
+#[derive(Debug, Default)]
+struct Decorations {
+    set: Option<u32>,
+    binding: Option<u32>,
+    location: Option<u32>,
+    array_stride: Option<u32>,
+    is_built_in: bool,
+    is_buffer_block: bool,
+}
+
+#[derive(Debug, Default)]
+struct MemberDecorations {
+    offset: Option<u32>,
+    matrix_stride: Option<u32>,
+}
+
+struct Variable {
+    id: u32,
+    type_id: u32,
+    storage_class: u32,
+}
+
+#[derive(Default)]
+struct Module {
+    execution_model: Option<u32>,
+    entry_point: String,
+    names: HashMap<u32, String>,
+    types: HashMap<u32, SpirvType>,
+    constants: HashMap<u32, u32>,
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), MemberDecorations>,
+    variables: Vec<Variable>,
+}
+
+impl ShaderReflection {
+    /// Reflects a SPIR-V module, such as the bytes from
+    /// `tools::read_shader_code`. The first entry point decides the stage.
+    pub fn from_spirv(code: &[u8]) -> Result<ShaderReflection, VkPlaygroundError> {
+        let words = spirv_words(code)?;
+        let module = Module::parse(&words)?;
+        let stage = module.stage()?;
+
+        let mut reflection = ShaderReflection {
+            stage,
+            entry_point: module.entry_point.clone(),
+            descriptor_bindings: vec![],
+            push_constant_ranges: vec![],
+            vertex_inputs: vec![],
+        };
+
+        for variable in module.variables.iter() {
+            let pointee = match module.types.get(&variable.type_id) {
+                Some(SpirvType::Pointer { pointee }) => *pointee,
+                _ => return Err(shader_error("a variable isn't a pointer")),
+            };
+
+            match variable.storage_class {
+                STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {
+                    reflection.descriptor_bindings.push(module.descriptor_binding(
+                        variable,
+                        pointee,
+                        stage,
+                    )?);
+                }
+                STORAGE_PUSH_CONSTANT => {
+                    let (offset, size) = module.block_range(pointee)?;
+                    reflection.push_constant_ranges.push(vk::PushConstantRange {
+                        stage_flags: stage,
+                        offset,
+                        size,
+                    });
+                }
+                STORAGE_INPUT if stage == vk::ShaderStageFlags::VERTEX => {
+                    let decorations = module.decorations.get(&variable.id);
+                    // built-ins such as gl_VertexIndex aren't vertex attributes
+                    let location = match decorations {
+                        Some(decorations) if !decorations.is_built_in => decorations.location,
+                        _ => None,
+                    };
+                    if let Some(location) = location {
+                        reflection.vertex_inputs.push(VertexInput {
+                            location,
+                            format: module.vertex_format(pointee)?,
+                            name: module.name(variable.id),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        reflection
+            .descriptor_bindings
+            .sort_by_key(|binding| (binding.set, binding.binding));
+        reflection.vertex_inputs.sort_by_key(|input| input.location);
+
+        Ok(reflection)
+    }
+
+    /// Makes every UNIFORM_BUFFER binding a UNIFORM_BUFFER_DYNAMIC one. The
+    /// shader reads both the same way, so the SPIR-V can't tell them apart;
+    /// it's up to how the buffer is bound, e.g. from a `UniformRing`.
+    pub fn with_dynamic_uniform_buffers(mut self) -> ShaderReflection {
+        for binding in self.descriptor_bindings.iter_mut() {
+            if binding.descriptor_type == vk::DescriptorType::UNIFORM_BUFFER {
+                binding.descriptor_type = vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC;
+            }
+        }
+        self
+    }
+
+    /// Compares a vertex type's attributes with this shader's inputs. An
+    /// attribute may have more components than the shader reads, but not
+    /// fewer, and must be the same kind of number.
+    pub fn vertex_input_mismatches(
+        &self,
+        attributes: &[vk::VertexInputAttributeDescription],
+    ) -> Vec<String> {
+        let mut mismatches = vec![];
+
+        for input in self.vertex_inputs.iter() {
+            let attribute = attributes
+                .iter()
+                .find(|attribute| attribute.location == input.location);
+            let attribute = match attribute {
+                Some(attribute) => attribute,
+                None => {
+                    mismatches.push(format!(
+                        "location {} ({}) isn't provided by the vertex type",
+                        input.location, input.name
+                    ));
+                    continue;
+                }
+            };
+
+            let is_compatible = match (format_class(input.format), format_class(attribute.format))
+            {
+                (Some((shader_kind, shader_count)), Some((vertex_kind, vertex_count))) => {
+                    shader_kind == vertex_kind && vertex_count >= shader_count
+                }
+                _ => input.format == attribute.format,
+            };
+            if !is_compatible {
+                mismatches.push(format!(
+                    "location {} ({}) is {:?} in the shader but {:?} in the vertex type",
+                    input.location, input.name, input.format, attribute.format
+                ));
+            }
+        }
+
+        mismatches
+    }
+
+    pub fn check_vertex_input(
+        &self,
+        attributes: &[vk::VertexInputAttributeDescription],
+    ) -> Result<(), VkPlaygroundError> {
+        let mismatches = self.vertex_input_mismatches(attributes);
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(shader_error(format!(
+                "the vertex type doesn't match the shader: {}",
+                mismatches.join(", ")
+            )))
+        }
+    }
+
+    /// A vertex layout for the shader's inputs packed one after the other in
+    /// location order, for vertex types laid out that way.
+    pub fn packed_vertex_input(
+        &self,
+        binding: u32,
+    ) -> (vk::VertexInputBindingDescription, Vec<vk::VertexInputAttributeDescription>) {
+        let mut offset = 0;
+        let mut attributes = vec![];
+        for input in self.vertex_inputs.iter() {
+            attributes.push(vk::VertexInputAttributeDescription {
+                binding,
+                location: input.location,
+                format: input.format,
+                offset,
+            });
+            offset += format_size(input.format);
+        }
+
+        let binding_description = vk::VertexInputBindingDescription {
+            binding,
+            stride: offset,
+            input_rate: vk::VertexInputRate::VERTEX,
+        };
+
+        (binding_description, attributes)
+    }
+}
+
+/// The bindings of every stage, merged by set and binding number. The same
+/// binding used by several stages gets all of their stage flags.
+pub fn merge_descriptor_bindings(
+    reflections: &[ShaderReflection],
+) -> Result<Vec<DescriptorBinding>, VkPlaygroundError> {
+    let mut merged: Vec<DescriptorBinding> = vec![];
+
+    for binding in reflections
+        .iter()
+        .flat_map(|reflection| reflection.descriptor_bindings.iter())
+    {
+        let existing = merged
+            .iter_mut()
+            .find(|existing| existing.set == binding.set && existing.binding == binding.binding);
+        match existing {
+            Some(existing) => {
+                if existing.descriptor_type != binding.descriptor_type
+                    || existing.descriptor_count != binding.descriptor_count
+                {
+                    return Err(shader_error(format!(
+                        "set {} binding {} is {:?} in one stage but {:?} in another",
+                        binding.set,
+                        binding.binding,
+                        existing.descriptor_type,
+                        binding.descriptor_type
+                    )));
+                }
+                existing.stage_flags |= binding.stage_flags;
+            }
+            None => merged.push(binding.clone()),
+        }
+    }
+    merged.sort_by_key(|binding| (binding.set, binding.binding));
+
+    Ok(merged)
+}
+
+/// The push constant ranges of every stage. Stages sharing a range get one
+/// range with all of their stage flags.
+pub fn merge_push_constant_ranges(reflections: &[ShaderReflection]) -> Vec<vk::PushConstantRange> {
+    let mut merged: Vec<vk::PushConstantRange> = vec![];
+
+    for range in reflections
+        .iter()
+        .flat_map(|reflection| reflection.push_constant_ranges.iter())
+    {
+        let existing = merged
+            .iter_mut()
+            .find(|existing| existing.offset == range.offset && existing.size == range.size);
+        match existing {
+            Some(existing) => existing.stage_flags |= range.stage_flags,
+            None => merged.push(*range),
+        }
+    }
+
+    merged
+}
+
+/// One descriptor set layout per set number used by the shaders, indexed
+/// by set. Unused set numbers in between get an empty layout.
+pub fn create_descriptor_set_layouts(
+    device: &ash::Device,
+    reflections: &[ShaderReflection],
+) -> Result<Vec<vk::DescriptorSetLayout>, VkPlaygroundError> {
+    let bindings = merge_descriptor_bindings(reflections)?;
+    let set_count = bindings.iter().map(|binding| binding.set + 1).max().unwrap_or(0);
+
+    let mut set_layouts = vec![];
+    for set in 0..set_count {
+        let layout_bindings: Vec<vk::DescriptorSetLayoutBinding> = bindings
+            .iter()
+            .filter(|binding| binding.set == set)
+            .map(|binding| vk::DescriptorSetLayoutBinding {
+                binding: binding.binding,
+                descriptor_type: binding.descriptor_type,
+                descriptor_count: binding.descriptor_count,
+                stage_flags: binding.stage_flags,
+                p_immutable_samplers: ptr::null(),
+            })
+            .collect();
+
+        let layout_create_info = vk::DescriptorSetLayoutCreateInfo {
+            s_type: vk::StructureType::DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::DescriptorSetLayoutCreateFlags::empty(),
+            binding_count: layout_bindings.len() as u32,
+            p_bindings: layout_bindings.as_ptr(),
+        };
+
+        let set_layout = unsafe {
+            device
+                .create_descriptor_set_layout(&layout_create_info, None)
+                .vk_context(
+                    "vkCreateDescriptorSetLayout",
+                    "Failed to create descriptor set layout",
+                )
+        };
+        match set_layout {
+            Ok(set_layout) => set_layouts.push(set_layout),
+            Err(err) => {
+                for &set_layout in set_layouts.iter() {
+                    unsafe { device.destroy_descriptor_set_layout(set_layout, None) };
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(set_layouts)
+}
+
+fn shader_error(context: impl Into<String>) -> VkPlaygroundError {
+    VkPlaygroundError::Shader {
+        context: format!("SPIR-V reflection: {}", context.into()),
+    }
+}
+
+fn spirv_words(code: &[u8]) -> Result<Vec<u32>, VkPlaygroundError> {
+    if !code.len().is_multiple_of(4) || code.len() < 20 {
+        return Err(shader_error("the code isn't a whole number of words"));
+    }
+
+    let mut words: Vec<u32> = code
+        .chunks(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+    if words[0] == SPIRV_MAGIC.swap_bytes() {
+        for word in words.iter_mut() {
+            *word = word.swap_bytes();
+        }
+    } else if words[0] != SPIRV_MAGIC {
+        return Err(shader_error("missing the SPIR-V magic number"));
+    }
+
+    Ok(words)
+}
+
+// literal strings are nul terminated and padded out to a whole word
+fn parse_string(words: &[u32]) -> Result<String, VkPlaygroundError> {
+    let mut bytes = vec![];
+    for word in words.iter() {
+        for &byte in word.to_le_bytes().iter() {
+            if byte == 0 {
+                return Ok(String::from_utf8_lossy(&bytes).into_owned());
+            }
+            bytes.push(byte);
+        }
+    }
+
+    Err(shader_error("a string literal runs past the end of its instruction"))
+}
+
+impl Module {
+    fn parse(words: &[u32]) -> Result<Module, VkPlaygroundError> {
+        let mut module = Module::default();
+
+        // the header is magic, version, generator, id bound and schema
+        let mut position = 5;
+        while position < words.len() {
+            let word_count = (words[position] >> 16) as usize;
+            let opcode = words[position] & 0xffff;
+            if word_count == 0 || position + word_count > words.len() {
+                return Err(shader_error("an instruction runs past the end of the module"));
+            }
+            let operands = &words[position + 1..position + word_count];
+            position += word_count;
+
+            module.parse_instruction(opcode, operands)?;
+        }
+
+        Ok(module)
+    }
+
+    fn parse_instruction(
+        &mut self,
+        opcode: u32,
+        operands: &[u32],
+    ) -> Result<(), VkPlaygroundError> {
+        let missing_operands = || shader_error(format!("opcode {} is missing operands", opcode));
+        let operand = |index: usize| operands.get(index).copied().ok_or_else(missing_operands);
+        let operands_from = |index: usize| operands.get(index..).ok_or_else(missing_operands);
+
+        match opcode {
+            OP_NAME => {
+                let name = parse_string(operands_from(1)?)?;
+                self.names.insert(operand(0)?, name);
+            }
+            OP_ENTRY_POINT if self.execution_model.is_none() => {
+                self.execution_model = Some(operand(0)?);
+                self.entry_point = parse_string(operands_from(2)?)?;
+            }
+            OP_TYPE_BOOL => {
+                self.types.insert(operand(0)?, SpirvType::Bool);
+            }
+            OP_TYPE_INT => {
+                self.types.insert(
+                    operand(0)?,
+                    SpirvType::Int {
+                        width: operand(1)?,
+                        is_signed: operand(2)? == 1,
+                    },
+                );
+            }
+            OP_TYPE_FLOAT => {
+                self.types
+                    .insert(operand(0)?, SpirvType::Float { width: operand(1)? });
+            }
+            OP_TYPE_VECTOR => {
+                self.types.insert(
+                    operand(0)?,
+                    SpirvType::Vector {
+                        component: operand(1)?,
+                        count: operand(2)?,
+                    },
+                );
+            }
+            OP_TYPE_MATRIX => {
+                self.types.insert(
+                    operand(0)?,
+                    SpirvType::Matrix {
+                        column: operand(1)?,
+                        count: operand(2)?,
+                    },
+                );
+            }
+            OP_TYPE_IMAGE => {
+                self.types.insert(
+                    operand(0)?,
+                    SpirvType::Image {
+                        dim: operand(2)?,
+                        sampled: operand(6)?,
+                    },
+                );
+            }
+            OP_TYPE_SAMPLER => {
+                self.types.insert(operand(0)?, SpirvType::Sampler);
+            }
+            OP_TYPE_SAMPLED_IMAGE => {
+                self.types.insert(operand(0)?, SpirvType::SampledImage);
+            }
+            OP_TYPE_ARRAY => {
+                // the length is the id of a constant, which comes first
+                let length = *self.constants.get(&operand(2)?).ok_or_else(|| {
+                    shader_error("array lengths from specialization constants aren't supported")
+                })?;
+                self.types.insert(
+                    operand(0)?,
+                    SpirvType::Array {
+                        element: operand(1)?,
+                        length,
+                    },
+                );
+            }
+            OP_TYPE_RUNTIME_ARRAY => {
+                self.types.insert(operand(0)?, SpirvType::RuntimeArray);
+            }
+            OP_TYPE_STRUCT => {
+                self.types.insert(
+                    operand(0)?,
+                    SpirvType::Struct {
+                        members: operands_from(1)?.to_vec(),
+                    },
+                );
+            }
+            OP_TYPE_POINTER => {
+                self.types
+                    .insert(operand(0)?, SpirvType::Pointer { pointee: operand(2)? });
+            }
+            OP_CONSTANT => {
+                // only the low word matters, it's used for array lengths
+                self.constants.insert(operand(1)?, operand(2)?);
+            }
+            OP_VARIABLE => {
+                self.variables.push(Variable {
+                    type_id: operand(0)?,
+                    id: operand(1)?,
+                    storage_class: operand(2)?,
+                });
+            }
+            OP_DECORATE => {
+                let decorations = self.decorations.entry(operand(0)?).or_default();
+                match operand(1)? {
+                    DECORATION_BUFFER_BLOCK => decorations.is_buffer_block = true,
+                    DECORATION_BUILT_IN => decorations.is_built_in = true,
+                    DECORATION_ARRAY_STRIDE => decorations.array_stride = Some(operand(2)?),
+                    DECORATION_LOCATION => decorations.location = Some(operand(2)?),
+                    DECORATION_BINDING => decorations.binding = Some(operand(2)?),
+                    DECORATION_DESCRIPTOR_SET => decorations.set = Some(operand(2)?),
+                    _ => {}
+                }
+            }
+            OP_MEMBER_DECORATE => {
+                let decorations = self
+                    .member_decorations
+                    .entry((operand(0)?, operand(1)?))
+                    .or_default();
+                match operand(2)? {
+                    DECORATION_OFFSET => decorations.offset = Some(operand(3)?),
+                    DECORATION_MATRIX_STRIDE => decorations.matrix_stride = Some(operand(3)?),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn stage(&self) -> Result<vk::ShaderStageFlags, VkPlaygroundError> {
+        match self.execution_model {
+            Some(0) => Ok(vk::ShaderStageFlags::VERTEX),
+            Some(1) => Ok(vk::ShaderStageFlags::TESSELLATION_CONTROL),
+            Some(2) => Ok(vk::ShaderStageFlags::TESSELLATION_EVALUATION),
+            Some(3) => Ok(vk::ShaderStageFlags::GEOMETRY),
+            Some(4) => Ok(vk::ShaderStageFlags::FRAGMENT),
+            Some(5) => Ok(vk::ShaderStageFlags::COMPUTE),
+            Some(model) => Err(shader_error(format!("unknown execution model {}", model))),
+            None => Err(shader_error("the module has no entry point")),
+        }
+    }
+
+    fn name(&self, id: u32) -> String {
+        self.names.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn get_type(&self, id: u32) -> Result<&SpirvType, VkPlaygroundError> {
+        self.types
+            .get(&id)
+            .ok_or_else(|| shader_error(format!("type %{} is never declared", id)))
+    }
+
+    fn descriptor_binding(
+        &self,
+        variable: &Variable,
+        pointee: u32,
+        stage: vk::ShaderStageFlags,
+    ) -> Result<DescriptorBinding, VkPlaygroundError> {
+        let decorations = self.decorations.get(&variable.id);
+        let binding = decorations
+            .and_then(|decorations| decorations.binding)
+            .ok_or_else(|| {
+                shader_error(format!("{} has no binding number", self.name(variable.id)))
+            })?;
+        let set = decorations
+            .and_then(|decorations| decorations.set)
+            .unwrap_or(0);
+
+        // arrays of descriptors take one slot per element
+        let (type_id, descriptor_count) = match self.get_type(pointee)? {
+            SpirvType::Array { element, length } => (*element, *length),
+            SpirvType::RuntimeArray => {
+                return Err(shader_error(format!(
+                    "{} is a runtime sized descriptor array, which needs descriptor indexing",
+                    self.name(variable.id)
+                )))
+            }
+            _ => (pointee, 1),
+        };
+
+        let descriptor_type = match (variable.storage_class, self.get_type(type_id)?) {
+            (STORAGE_STORAGE_BUFFER, _) => vk::DescriptorType::STORAGE_BUFFER,
+            (STORAGE_UNIFORM, _) => {
+                let is_buffer_block = self
+                    .decorations
+                    .get(&type_id)
+                    .is_some_and(|decorations| decorations.is_buffer_block);
+                if is_buffer_block {
+                    vk::DescriptorType::STORAGE_BUFFER
+                } else {
+                    vk::DescriptorType::UNIFORM_BUFFER
+                }
+            }
+            (_, SpirvType::SampledImage) => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            (_, SpirvType::Sampler) => vk::DescriptorType::SAMPLER,
+            (_, SpirvType::Image { dim, sampled }) => match (*dim, *sampled) {
+                (DIM_SUBPASS_DATA, _) => vk::DescriptorType::INPUT_ATTACHMENT,
+                (DIM_BUFFER, 2) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+                (DIM_BUFFER, _) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+                (_, 2) => vk::DescriptorType::STORAGE_IMAGE,
+                _ => vk::DescriptorType::SAMPLED_IMAGE,
+            },
+            _ => {
+                return Err(shader_error(format!(
+                    "can't tell what kind of descriptor {} is",
+                    self.name(variable.id)
+                )))
+            }
+        };
+
+        // a uniform block's variable is often unnamed, fall back to the block
+        let mut name = self.name(variable.id);
+        if name.is_empty() {
+            name = self.name(type_id);
+        }
+
+        Ok(DescriptorBinding {
+            set,
+            binding,
+            descriptor_type,
+            descriptor_count,
+            stage_flags: stage,
+            name,
+        })
+    }
+
+    // the offset and size of the bytes a push constant block uses
+    fn block_range(&self, struct_id: u32) -> Result<(u32, u32), VkPlaygroundError> {
+        let member_count = match self.get_type(struct_id)? {
+            SpirvType::Struct { members } => members.len() as u32,
+            _ => return Err(shader_error("a push constant block isn't a struct")),
+        };
+
+        let offset = (0..member_count)
+            .filter_map(|member| {
+                self.member_decorations
+                    .get(&(struct_id, member))
+                    .and_then(|decorations| decorations.offset)
+            })
+            .min()
+            .unwrap_or(0);
+        let end = self.type_size(struct_id)?;
+
+        Ok((offset, end - offset))
+    }
+
+    fn type_size(&self, id: u32) -> Result<u32, VkPlaygroundError> {
+        match self.get_type(id)? {
+            SpirvType::Bool => Ok(4),
+            SpirvType::Int { width, .. } | SpirvType::Float { width } => Ok(width / 8),
+            SpirvType::Vector { component, count } => Ok(self.type_size(*component)? * count),
+            SpirvType::Matrix { column, count } => Ok(self.type_size(*column)? * count),
+            SpirvType::Array { element, length } => {
+                let stride = self
+                    .decorations
+                    .get(&id)
+                    .and_then(|decorations| decorations.array_stride);
+                match stride {
+                    Some(stride) => Ok(stride * length),
+                    None => Ok(self.type_size(*element)? * length),
+                }
+            }
+            SpirvType::Struct { members } => {
+                let mut end = 0;
+                for (index, &member) in members.iter().enumerate() {
+                    let decorations = self.member_decorations.get(&(id, index as u32));
+                    let offset = decorations
+                        .and_then(|decorations| decorations.offset)
+                        .unwrap_or(end);
+                    let matrix_stride =
+                        decorations.and_then(|decorations| decorations.matrix_stride);
+                    let size = match (self.get_type(member)?, matrix_stride) {
+                        (SpirvType::Matrix { count, .. }, Some(stride)) => count * stride,
+                        _ => self.type_size(member)?,
+                    };
+                    end = end.max(offset + size);
+                }
+                Ok(end)
+            }
+            _ => Err(shader_error(format!("type %{} has no size in a block", id))),
+        }
+    }
+
+    fn vertex_format(&self, type_id: u32) -> Result<vk::Format, VkPlaygroundError> {
+        let (component, count) = match self.get_type(type_id)? {
+            SpirvType::Vector { component, count } => (*component, *count),
+            _ => (type_id, 1),
+        };
+
+        let formats = match self.get_type(component)? {
+            SpirvType::Float { width: 32 } => [
+                vk::Format::R32_SFLOAT,
+                vk::Format::R32G32_SFLOAT,
+                vk::Format::R32G32B32_SFLOAT,
+                vk::Format::R32G32B32A32_SFLOAT,
+            ],
+            SpirvType::Float { width: 64 } => [
+                vk::Format::R64_SFLOAT,
+                vk::Format::R64G64_SFLOAT,
+                vk::Format::R64G64B64_SFLOAT,
+                vk::Format::R64G64B64A64_SFLOAT,
+            ],
+            SpirvType::Int {
+                width: 32,
+                is_signed: true,
+            } => [
+                vk::Format::R32_SINT,
+                vk::Format::R32G32_SINT,
+                vk::Format::R32G32B32_SINT,
+                vk::Format::R32G32B32A32_SINT,
+            ],
+            SpirvType::Int {
+                width: 32,
+                is_signed: false,
+            } => [
+                vk::Format::R32_UINT,
+                vk::Format::R32G32_UINT,
+                vk::Format::R32G32B32_UINT,
+                vk::Format::R32G32B32A32_UINT,
+            ],
+            _ => return Err(shader_error("unsupported vertex input type")),
+        };
+
+        (count as usize)
+            .checked_sub(1)
+            .and_then(|index| formats.get(index))
+            .copied()
+            .ok_or_else(|| shader_error("vertex inputs have one to four components"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberKind {
+    // floats, and normalized integers which the shader also reads as floats
+    Float,
+    SignedInt,
+    UnsignedInt,
+}
+
+fn format_class(format: vk::Format) -> Option<(NumberKind, u32)> {
+    use vk::Format as F;
+
+    let class = match format {
+        F::R32_SFLOAT | F::R64_SFLOAT | F::R16_SFLOAT | F::R8_UNORM | F::R8_SNORM
+        | F::R16_UNORM | F::R16_SNORM => (NumberKind::Float, 1),
+        F::R32G32_SFLOAT | F::R64G64_SFLOAT | F::R16G16_SFLOAT | F::R8G8_UNORM
+        | F::R8G8_SNORM | F::R16G16_UNORM | F::R16G16_SNORM => (NumberKind::Float, 2),
+        F::R32G32B32_SFLOAT | F::R64G64B64_SFLOAT | F::R16G16B16_SFLOAT
+        | F::R8G8B8_UNORM | F::R8G8B8_SNORM => (NumberKind::Float, 3),
+        F::R32G32B32A32_SFLOAT | F::R64G64B64A64_SFLOAT | F::R16G16B16A16_SFLOAT
+        | F::R8G8B8A8_UNORM | F::R8G8B8A8_SNORM | F::R16G16B16A16_UNORM
+        | F::R16G16B16A16_SNORM | F::B8G8R8A8_UNORM => (NumberKind::Float, 4),
+        F::R32_SINT | F::R16_SINT | F::R8_SINT => (NumberKind::SignedInt, 1),
+        F::R32G32_SINT | F::R16G16_SINT | F::R8G8_SINT => (NumberKind::SignedInt, 2),
+        F::R32G32B32_SINT | F::R16G16B16_SINT | F::R8G8B8_SINT => (NumberKind::SignedInt, 3),
+        F::R32G32B32A32_SINT | F::R16G16B16A16_SINT | F::R8G8B8A8_SINT => {
+            (NumberKind::SignedInt, 4)
+        }
+        F::R32_UINT | F::R16_UINT | F::R8_UINT => (NumberKind::UnsignedInt, 1),
+        F::R32G32_UINT | F::R16G16_UINT | F::R8G8_UINT => (NumberKind::UnsignedInt, 2),
+        F::R32G32B32_UINT | F::R16G16B16_UINT | F::R8G8B8_UINT => (NumberKind::UnsignedInt, 3),
+        F::R32G32B32A32_UINT | F::R16G16B16A16_UINT | F::R8G8B8A8_UINT => {
+            (NumberKind::UnsignedInt, 4)
+        }
+        _ => return None,
+    };
+
+    Some(class)
+}
+
+// only covers the formats vertex_format hands out
+fn format_size(format: vk::Format) -> u32 {
+    let component_size = match format {
+        vk::Format::R64_SFLOAT
+        | vk::Format::R64G64_SFLOAT
+        | vk::Format::R64G64B64_SFLOAT
+        | vk::Format::R64G64B64A64_SFLOAT => 8,
+        _ => 4,
+    };
+
+    format_class(format).map_or(0, |(_, count)| count * component_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    fn read_spirv(path: &str) -> Vec<u8> {
+        std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join(path)).unwrap()
+    }
+
+    // a module header followed by the instructions, each given as its opcode
+    // and operands
+    fn module(instructions: &[&[u32]]) -> Vec<u8> {
+        let mut words = vec![SPIRV_MAGIC, 0x0001_0000, 0, 100, 0];
+        for instruction in instructions.iter() {
+            words.push(((instruction.len() as u32) << 16) | instruction[0]);
+            words.extend_from_slice(&instruction[1..]);
+        }
+
+        words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect()
+    }
+
+    fn string(value: &[u8; 4]) -> [u32; 2] {
+        [u32::from_le_bytes(*value), 0]
+    }
+
+    #[test]
+    fn cube_vertex_shader_is_reflected() {
+        let code = read_spirv("shaders/cube/vert.spv");
+
+        let reflection = ShaderReflection::from_spirv(&code).unwrap();
+
+        assert_eq!(reflection.stage, vk::ShaderStageFlags::VERTEX);
+        assert_eq!(reflection.entry_point, "main");
+        assert_eq!(reflection.descriptor_bindings.len(), 1);
+        let ubo = &reflection.descriptor_bindings[0];
+        assert_eq!((ubo.set, ubo.binding), (0, 0));
+        assert_eq!(ubo.descriptor_type, vk::DescriptorType::UNIFORM_BUFFER);
+        assert_eq!(ubo.descriptor_count, 1);
+        assert_eq!(reflection.push_constant_ranges.len(), 1);
+        assert_eq!(reflection.push_constant_ranges[0].offset, 0);
+        assert_eq!(reflection.push_constant_ranges[0].size, 64);
+        let inputs: Vec<_> = reflection
+            .vertex_inputs
+            .iter()
+            .map(|input| (input.location, input.format, input.name.as_str()))
+            .collect();
+        assert_eq!(
+            inputs,
+            vec![
+                (0, vk::Format::R32G32B32_SFLOAT, "inPosition"),
+                (1, vk::Format::R32G32B32_SFLOAT, "inColor"),
+                (2, vk::Format::R32G32_SFLOAT, "inTexCoord"),
+            ]
+        );
+    }
+
+    #[test]
+    fn uniform_buffers_can_be_made_dynamic() {
+        let code = read_spirv("shaders/cube/vert.spv");
+
+        let reflection = ShaderReflection::from_spirv(&code).unwrap();
+        let reflection = reflection.with_dynamic_uniform_buffers();
+
+        assert_eq!(
+            reflection.descriptor_bindings[0].descriptor_type,
+            vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+        );
+    }
+
+    #[test]
+    fn cube_fragment_shader_is_reflected() {
+        let code = read_spirv("shaders/cube/frag.spv");
+
+        let reflection = ShaderReflection::from_spirv(&code).unwrap();
+
+        assert_eq!(reflection.stage, vk::ShaderStageFlags::FRAGMENT);
+        assert_eq!(
+            reflection.descriptor_bindings,
+            vec![DescriptorBinding {
+                set: 0,
+                binding: 1,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::FRAGMENT,
+                name: "texSampler".to_owned(),
+            }]
+        );
+        assert!(reflection.push_constant_ranges.is_empty());
+        assert!(reflection.vertex_inputs.is_empty());
+    }
+
+    #[test]
+    fn particles_shaders_are_reflected() {
+        let comp = read_spirv("shaders/particles/comp.spv");
+        let vert = read_spirv("shaders/particles/vert.spv");
+
+        let comp = ShaderReflection::from_spirv(&comp).unwrap();
+        let vert = ShaderReflection::from_spirv(&vert).unwrap();
+
+        assert_eq!(comp.stage, vk::ShaderStageFlags::COMPUTE);
+        assert_eq!(comp.descriptor_bindings.len(), 1);
+        assert_eq!(
+            comp.descriptor_bindings[0].descriptor_type,
+            vk::DescriptorType::STORAGE_BUFFER
+        );
+        assert_eq!(comp.push_constant_ranges[0].size, 8);
+        let formats: Vec<_> = vert.vertex_inputs.iter().map(|input| input.format).collect();
+        assert_eq!(
+            formats,
+            vec![vk::Format::R32G32_SFLOAT, vk::Format::R32G32B32A32_SFLOAT]
+        );
+        assert_eq!(vert.push_constant_ranges[0].size, 8);
+    }
+
+    #[test]
+    fn module_cut_off_mid_instruction_is_an_error() {
+        let code = read_spirv("shaders/cube/vert.spv");
+
+        // the header, then the first word of OpCapability
+        assert!(ShaderReflection::from_spirv(&code[..24]).is_err());
+        assert!(ShaderReflection::from_spirv(&code[..code.len() - 1]).is_err());
+        assert!(ShaderReflection::from_spirv(&code[..16]).is_err());
+    }
+
+    #[test]
+    fn truncated_name_is_an_error() {
+        let main = string(b"main");
+        let entry_point = [OP_ENTRY_POINT, 0, 1, main[0], main[1]];
+
+        let without_string = module(&[&entry_point, &[OP_NAME, 1]]);
+        let without_target = module(&[&entry_point, &[OP_NAME]]);
+        let without_nul = module(&[&entry_point, &[OP_NAME, 1, main[0]]]);
+
+        assert!(ShaderReflection::from_spirv(&without_string).is_err());
+        assert!(ShaderReflection::from_spirv(&without_target).is_err());
+        assert!(ShaderReflection::from_spirv(&without_nul).is_err());
+    }
+
+    #[test]
+    fn truncated_entry_point_is_an_error() {
+        let without_name = module(&[&[OP_ENTRY_POINT, 0, 1]]);
+        let without_id = module(&[&[OP_ENTRY_POINT, 0]]);
+
+        assert!(ShaderReflection::from_spirv(&without_name).is_err());
+        assert!(ShaderReflection::from_spirv(&without_id).is_err());
+    }
+
+    #[test]
+    fn vertex_input_without_components_is_an_error() {
+        let main = string(b"main");
+        let code = module(&[
+            &[OP_ENTRY_POINT, 0, 1, main[0], main[1]],
+            &[OP_DECORATE, 5, DECORATION_LOCATION, 0],
+            &[OP_TYPE_FLOAT, 2, 32],
+            &[OP_TYPE_VECTOR, 3, 2, 0],
+            &[OP_TYPE_POINTER, 4, STORAGE_INPUT, 3],
+            &[OP_VARIABLE, 4, 5, STORAGE_INPUT],
+        ]);
+
+        assert!(ShaderReflection::from_spirv(&code).is_err());
+    }
+}
@@ -0,0 +1,1184 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use std::cell::OnceCell;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::ptr;
+
+use crate::utility::allocator::{Allocation, Allocator};
+use crate::utility::constants::*;
+use crate::utility::debug::setup_debug_utils;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::extensions::{EnabledExtensions, ExtensionConfig};
+use crate::utility::frame_commands::{self, FrameCommandPools, WorkerCommandPools};
+use crate::utility::gpu::GpuOptions;
+use crate::utility::handles::{
+    self, Owned, OwnedDebugMessenger, OwnedDevice, OwnedInstance, OwnedSurface, OwnedSwapchain,
+};
+use crate::utility::headless::{self, HeadlessApp, OffscreenTarget};
+use crate::utility::pipeline;
+use crate::utility::pipeline_cache::PipelineCache;
+use crate::utility::hot_reload::ShaderWatcher;
+use crate::utility::screenshot::{self, CapturedFrame};
+use crate::utility::shader_compiler;
+use crate::utility::structures::{QueueFamilyIndices, SurfaceStuff};
+use crate::utility::upload::UploadQueues;
+use crate::utility::vkstuff;
+use crate::utility::window::{self, VulkanApp};
+
+// The part of the demos that doesn't change from one to the next: instance,
+// device, swapchain (or offscreen target when headless), a depth buffer, a
+// render pass that clears both, and a command pool and sync objects for each
+// frame in flight. The command buffer is recorded again every frame, so a
+// scene can draw something different each time without rebuilding anything,
+// and can dispatch compute work ahead of the render pass.
+// Scenes with a lot of draws can hand them out as `ParallelDraws` to be
+// recorded on worker threads instead.
+//
+// A demo implements `RenderScene` for its pipelines and geometry and hands
+// it to a `Renderer`, which runs the frame loop for both `ProgramProc` and
+// `headless::run_headless`.
+
+/// What a demo supplies on top of the `RenderContext`.
+pub trait RenderScene {
+    /// Creates the graphics pipelines, with `PipelineBuilder::dynamic_viewport`
+    /// since the viewport and scissor are set before `record_commands`.
+    /// Called again only if the render pass has to be replaced, which
+    /// happens when a recreated swapchain comes back in a different format.
+    fn create_pipelines(&mut self, context: &RenderContext) -> Result<(), VkPlaygroundError>;
+
+    /// Records the draw commands for `frame`, every frame, after `update`.
+    /// The render pass has already been begun on `command_buffer`, with the
+    /// viewport and scissor set, and is ended afterwards. Not called for
+    /// scenes that return `Some` from `parallel_draws`.
+    fn record_commands(
+        &self,
+        _context: &RenderContext,
+        _command_buffer: vk::CommandBuffer,
+        _frame: Frame,
+    ) {
+    }
+
+    /// Records compute work for `frame` ahead of the render pass, every
+    /// frame after `update`, e.g. with `compute::dispatch`. The scene puts
+    /// in the barriers its draws need against it, see `compute::Access`.
+    fn record_compute(
+        &self,
+        _context: &RenderContext,
+        _command_buffer: vk::CommandBuffer,
+        _frame: Frame,
+    ) {
+    }
+
+    /// Draws to record on the renderer's worker threads instead of through
+    /// `record_commands`, see `Renderer::set_recording_threads`.
+    fn parallel_draws(&self) -> Option<&dyn ParallelDraws> {
+        None
+    }
+
+    /// Called before the commands for `frame` are recorded, e.g. to update
+    /// the uniform buffer of `frame.image_index`. An error ends the app.
+    fn update(&mut self, _frame: Frame, _delta_time: f32) -> Result<(), VkPlaygroundError> {
+        Ok(())
+    }
+
+    /// Rebuilds the pipelines made from any of the `changed` shader sources
+    /// and returns whether there were any. On an error the old pipelines
+    /// have to be left in place. Scenes without hot reload ignore it.
+    fn reload_shaders(
+        &mut self,
+        _context: &RenderContext,
+        _changed: &[PathBuf],
+    ) -> Result<bool, VkPlaygroundError> {
+        Ok(false)
+    }
+}
+
+/// Draw commands that can be split between threads. Usually a part of the
+/// scene holding just what recording needs, so that it can be `Sync`.
+pub trait ParallelDraws: Sync {
+    fn draw_count(&self) -> usize;
+
+    /// Records `draws`, a part of `0..draw_count()`, into a secondary
+    /// command buffer that continues the render pass with the viewport and
+    /// scissor already set. Nothing else is inherited from other threads, so
+    /// bind the pipeline, buffers and descriptor sets here as well.
+    fn record_draws(
+        &self,
+        device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        frame: Frame,
+        draws: Range<usize>,
+    );
+}
+
+/// The frame a `RenderScene` is recording commands for.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    /// Which of the MAX_FRAMES_IN_FLIGHT frames this is. Its previous submit
+    /// has finished, so anything kept per frame in flight is free to reuse.
+    pub index: usize,
+    /// The swapchain image drawn to, which picks per-image resources such as
+    /// uniform buffers made for each image.
+    pub image_index: usize,
+}
+
+pub struct RenderContext {
+    // Fields are dropped in declaration order and the owned handles destroy
+    // themselves, so everything made from the device is declared before the
+    // device, and the device before the instance.
+    image_available_semaphores: Vec<Owned<vk::Semaphore>>,
+    render_finished_semaphores: Vec<Owned<vk::Semaphore>>,
+    in_flight_fences: Vec<Owned<vk::Fence>>,
+    current_frame: usize,
+    frame_commands: FrameCommandPools,
+    // 0 when everything is recorded on the main thread
+    worker_count: usize,
+    // made the first time a scene has parallel draws, see `worker_commands`
+    worker_commands: OnceCell<WorkerCommandPools>,
+
+    // for one-off commands such as uploads and captures
+    command_pool: Owned<vk::CommandPool>,
+    pipeline_cache: PipelineCache,
+
+    swapchain_framebuffers: Vec<Owned<vk::Framebuffer>>,
+    render_pass: Owned<vk::RenderPass>,
+
+    depth_image_view: Owned<vk::ImageView>,
+    depth_image: Owned<vk::Image>,
+    _depth_image_memory: Allocation,
+
+    swapchain_imageviews: Vec<Owned<vk::ImageView>>,
+    swapchain_loader: ash::extensions::khr::Swapchain,
+    swapchain: OwnedSwapchain,
+    swapchain_images: Vec<vk::Image>,
+    swapchain_format: vk::Format,
+    swapchain_extent: vk::Extent2D,
+    swapchain_usage: vk::ImageUsageFlags,
+    offscreen_target: Option<OffscreenTarget>,
+
+    allocator: Allocator,
+    queue_family: QueueFamilyIndices,
+    graphics_queue: vk::Queue,
+    present_queue: vk::Queue,
+    compute_queue: vk::Queue,
+    transfer_queue: vk::Queue,
+    device: OwnedDevice,
+    physical_device: vk::PhysicalDevice,
+
+    surface_loader: ash::extensions::khr::Surface,
+    surface: OwnedSurface,
+    // None when rendering headless, outlives the surface made from it
+    window: Option<winit::window::Window>,
+    _debug_messenger: OwnedDebugMessenger,
+    instance: OwnedInstance,
+    _entry: ash::Entry,
+    enabled_extensions: EnabledExtensions,
+
+    window_width: u32,
+    window_height: u32,
+    is_framebuffer_resized: bool,
+    is_capture_requested: bool,
+    captured_frame: Option<CapturedFrame>,
+}
+
+impl RenderContext {
+    /// The GPU is picked by `vkdevice::pick_physical_device`, honouring
+    /// `--gpu` and VK_PLAYGROUND_GPU.
+    pub fn new(
+        event_loop: &winit::event_loop::EventLoop<()>,
+        title: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderContext, VkPlaygroundError> {
+        let config = ExtensionConfig::for_window(&VALIDATION, &DEVICE_EXTENSIONS);
+
+        RenderContext::with_config(event_loop, title, width, height, &config)
+    }
+
+    pub fn new_headless(
+        title: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<RenderContext, VkPlaygroundError> {
+        let config = ExtensionConfig::for_headless(&VALIDATION);
+
+        RenderContext::headless_with_config(title, width, height, &config)
+    }
+
+    /// Like `new`, with the extensions and layers to enable coming from
+    /// `config`, e.g. `ExtensionConfig::for_window` plus a few optional
+    /// device extensions. See `enabled_extensions` for what was enabled.
+    pub fn with_config(
+        event_loop: &winit::event_loop::EventLoop<()>,
+        title: &str,
+        width: u32,
+        height: u32,
+        config: &ExtensionConfig,
+    ) -> Result<RenderContext, VkPlaygroundError> {
+        let window = window::init_window(event_loop, title, width, height)?;
+
+        RenderContext::init(Some(window), title, width, height, config)
+    }
+
+    pub fn headless_with_config(
+        title: &str,
+        width: u32,
+        height: u32,
+        config: &ExtensionConfig,
+    ) -> Result<RenderContext, VkPlaygroundError> {
+        RenderContext::init(None, title, width, height, config)
+    }
+
+    fn init(
+        window: Option<winit::window::Window>,
+        title: &str,
+        width: u32,
+        height: u32,
+        config: &ExtensionConfig,
+    ) -> Result<RenderContext, VkPlaygroundError> {
+        let entry = vkstuff::vkinstance::load_entry()?;
+        let (instance, mut enabled_extensions) =
+            vkstuff::vkinstance::create_instance(&entry, title, config)?;
+        let instance = OwnedInstance::new(instance);
+        let surface_stuff = match &window {
+            Some(window) => {
+                vkstuff::vksurface::create_surface(&entry, &instance, window, width, height)?
+            }
+            None => headless::create_null_surface_stuff(&entry, &instance, width, height),
+        };
+        // only a real surface needs present support from the device
+        let present_surface = window.as_ref().map(|_| &surface_stuff);
+        let (debug_utils_loader, debug_messenger) =
+            setup_debug_utils(enabled_extensions.has_debug_utils(), &entry, &instance)?;
+        let gpu_options = GpuOptions::from_args()?;
+        let physical_device = vkstuff::vkdevice::pick_physical_device(
+            &instance,
+            present_surface,
+            config,
+            gpu_options.selector.as_ref(),
+        )?;
+        config.select_device(&instance, physical_device, &mut enabled_extensions)?;
+        let (device, queue_family) = vkstuff::vkdevice::create_logical_device(
+            &instance,
+            physical_device,
+            &enabled_extensions,
+            present_surface,
+        )?;
+        let device = OwnedDevice::new(device);
+        let allocator = Allocator::new(&instance, physical_device, &device);
+        let graphics_queue =
+            unsafe { device.get_device_queue(queue_family.graphics_family.unwrap(), 0) };
+        // headless devices have no present queue, nothing is presented anyway
+        let present_queue = match queue_family.present_family {
+            Some(present_family) => unsafe { device.get_device_queue(present_family, 0) },
+            None => graphics_queue,
+        };
+        let compute_queue =
+            unsafe { device.get_device_queue(queue_family.compute_family.unwrap(), 0) };
+        let transfer_queue =
+            unsafe { device.get_device_queue(queue_family.transfer_family.unwrap(), 0) };
+        let (swapchain_stuff, offscreen_target, final_layout) = match &window {
+            Some(window) => {
+                let swapchain_stuff = vkstuff::vkswapchain::create_swapchain(
+                    &instance,
+                    &device,
+                    physical_device,
+                    window,
+                    &surface_stuff,
+                    &queue_family,
+                )?;
+                (swapchain_stuff, None, vk::ImageLayout::PRESENT_SRC_KHR)
+            }
+            None => {
+                let offscreen_target = headless::create_offscreen_target(
+                    &device,
+                    vk::Extent2D { width, height },
+                    &allocator,
+                )?;
+                let swapchain_stuff = headless::create_offscreen_swapchain_stuff(
+                    &instance,
+                    &device,
+                    &offscreen_target,
+                );
+                (swapchain_stuff, Some(offscreen_target), headless::OFFSCREEN_FINAL_LAYOUT)
+            }
+        };
+        let swapchain_imageviews = vkstuff::vkimage::create_image_views(
+            &device,
+            swapchain_stuff.swapchain_format,
+            &swapchain_stuff.swapchain_images,
+        )?;
+        let render_pass = RenderContext::create_render_pass(
+            &instance,
+            &device,
+            physical_device,
+            swapchain_stuff.swapchain_format,
+            final_layout,
+        )?;
+        let command_pool = vkstuff::vkcommand::create_command_pool(&device, &queue_family)?;
+        let frame_commands = FrameCommandPools::new(
+            &device,
+            queue_family.graphics_family.unwrap(),
+            MAX_FRAMES_IN_FLIGHT,
+        )?;
+        let pipeline_cache = PipelineCache::load(&instance, physical_device, &device)?;
+        let (depth_image, depth_image_view, depth_image_memory) =
+            RenderContext::create_depth_resources(
+                &instance,
+                &device,
+                physical_device,
+                swapchain_stuff.swapchain_extent,
+                &allocator,
+            )?;
+        let swapchain_framebuffers = RenderContext::create_framebuffers(
+            &device,
+            render_pass,
+            &swapchain_imageviews,
+            depth_image_view,
+            swapchain_stuff.swapchain_extent,
+        )?;
+        let sync_objects =
+            vkstuff::vksemaphore::create_sync_objects(&device, MAX_FRAMES_IN_FLIGHT)?;
+
+        Ok(RenderContext {
+            image_available_semaphores: handles::own_all(
+                &device,
+                sync_objects.image_available_semaphores,
+            ),
+            render_finished_semaphores: handles::own_all(
+                &device,
+                sync_objects.render_finished_semaphores,
+            ),
+            in_flight_fences: handles::own_all(&device, sync_objects.inflight_fences),
+            current_frame: 0,
+            frame_commands,
+            worker_count: frame_commands::default_worker_count(),
+            worker_commands: OnceCell::new(),
+
+            command_pool: Owned::new(&device, command_pool),
+            pipeline_cache,
+
+            swapchain_framebuffers: handles::own_all(&device, swapchain_framebuffers),
+            render_pass: Owned::new(&device, render_pass),
+
+            depth_image_view: Owned::new(&device, depth_image_view),
+            depth_image: Owned::new(&device, depth_image),
+            _depth_image_memory: depth_image_memory,
+
+            swapchain_imageviews: handles::own_all(&device, swapchain_imageviews),
+            swapchain: OwnedSwapchain::new(
+                &device,
+                &swapchain_stuff.swapchain_loader,
+                swapchain_stuff.swapchain,
+            ),
+            swapchain_loader: swapchain_stuff.swapchain_loader,
+            swapchain_images: swapchain_stuff.swapchain_images,
+            swapchain_format: swapchain_stuff.swapchain_format,
+            swapchain_extent: swapchain_stuff.swapchain_extent,
+            swapchain_usage: swapchain_stuff.swapchain_usage,
+            offscreen_target,
+
+            allocator,
+            queue_family,
+            graphics_queue,
+            present_queue,
+            compute_queue,
+            transfer_queue,
+            device,
+            physical_device,
+
+            surface: OwnedSurface::new(&surface_stuff.surface_loader, surface_stuff.surface),
+            surface_loader: surface_stuff.surface_loader,
+            window,
+            _debug_messenger: OwnedDebugMessenger::new(&debug_utils_loader, debug_messenger),
+            instance,
+            _entry: entry,
+            enabled_extensions,
+
+            window_width: width,
+            window_height: height,
+            is_framebuffer_resized: false,
+            is_capture_requested: false,
+            captured_frame: None,
+        })
+    }
+
+    pub fn instance(&self) -> &ash::Instance {
+        &self.instance
+    }
+
+    pub fn physical_device(&self) -> vk::PhysicalDevice {
+        self.physical_device
+    }
+
+    pub fn device(&self) -> &ash::Device {
+        &self.device
+    }
+
+    pub fn allocator(&self) -> &Allocator {
+        &self.allocator
+    }
+
+    pub fn graphics_queue(&self) -> vk::Queue {
+        self.graphics_queue
+    }
+
+    /// A queue of a compute-only family if the device has one, for work that
+    /// runs alongside the frame. Otherwise the graphics queue. Dispatches
+    /// recorded into the frame itself, see `RenderScene::record_compute`,
+    /// don't need it since the graphics family always supports compute.
+    pub fn compute_queue(&self) -> vk::Queue {
+        self.compute_queue
+    }
+
+    /// The family of `compute_queue`, for its command pools and for queue
+    /// family ownership transfers.
+    pub fn compute_queue_family(&self) -> u32 {
+        self.queue_family.compute_family.unwrap()
+    }
+
+    /// The queues an `upload::Uploader` copies on and hands its results over
+    /// to: a transfer-only queue if the device has one, and the graphics
+    /// queue.
+    pub fn upload_queues(&self) -> UploadQueues {
+        UploadQueues {
+            transfer_family: self.queue_family.transfer_family.unwrap(),
+            transfer_queue: self.transfer_queue,
+            graphics_family: self.queue_family.graphics_family.unwrap(),
+            graphics_queue: self.graphics_queue,
+        }
+    }
+
+    pub fn command_pool(&self) -> vk::CommandPool {
+        *self.command_pool
+    }
+
+    /// Kept on disk between runs, pass it to `PipelineBuilder::pipeline_cache`.
+    pub fn pipeline_cache(&self) -> vk::PipelineCache {
+        self.pipeline_cache.handle()
+    }
+
+    pub fn render_pass(&self) -> vk::RenderPass {
+        *self.render_pass
+    }
+
+    pub fn swapchain_extent(&self) -> vk::Extent2D {
+        self.swapchain_extent
+    }
+
+    /// The number of swapchain images, i.e. how many copies of per-image
+    /// resources such as uniform buffers a scene needs.
+    pub fn swapchain_image_count(&self) -> usize {
+        self.swapchain_images.len()
+    }
+
+    /// The instance extensions, layers and device extensions that were
+    /// enabled, e.g. to check whether an optional extension made it.
+    pub fn enabled_extensions(&self) -> &EnabledExtensions {
+        &self.enabled_extensions
+    }
+
+    fn create_depth_resources(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        swapchain_extent: vk::Extent2D,
+        allocator: &Allocator,
+    ) -> Result<(vk::Image, vk::ImageView, Allocation), VkPlaygroundError> {
+        let depth_format = vkstuff::vkformat::find_depth_format(instance, physical_device)?;
+        let (depth_image, depth_image_memory) = vkstuff::vkimage::create_image(
+            device,
+            swapchain_extent.width,
+            swapchain_extent.height,
+            1,
+            vk::SampleCountFlags::TYPE_1,
+            depth_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            allocator,
+        )?;
+
+        let depth_image_view = vkstuff::vkimage::create_image_view(
+            device,
+            depth_image,
+            depth_format,
+            vk::ImageAspectFlags::DEPTH,
+            1,
+        )?;
+
+        Ok((depth_image, depth_image_view, depth_image_memory))
+    }
+
+    fn create_render_pass(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        surface_format: vk::Format,
+        final_layout: vk::ImageLayout,
+    ) -> Result<vk::RenderPass, VkPlaygroundError> {
+        let color_attachment = vk::AttachmentDescription {
+            flags: vk::AttachmentDescriptionFlags::empty(),
+            format: surface_format,
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::STORE,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout,
+        };
+
+        let depth_attachment = vk::AttachmentDescription {
+            flags: vk::AttachmentDescriptionFlags::empty(),
+            format: vkstuff::vkformat::find_depth_format(instance, physical_device)?,
+            samples: vk::SampleCountFlags::TYPE_1,
+            load_op: vk::AttachmentLoadOp::CLEAR,
+            store_op: vk::AttachmentStoreOp::DONT_CARE,
+            stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+            stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+
+        let color_attachment_ref = vk::AttachmentReference {
+            attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+        };
+
+        let depth_attachment_ref = vk::AttachmentReference {
+            attachment: 1,
+            layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        };
+
+        let subpasses = [vk::SubpassDescription {
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment_ref,
+            p_depth_stencil_attachment: &depth_attachment_ref,
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            input_attachment_count: 0,
+            p_input_attachments: ptr::null(),
+            p_resolve_attachments: ptr::null(),
+            preserve_attachment_count: 0,
+            p_preserve_attachments: ptr::null(),
+        }];
+
+        let render_pass_attachments = [color_attachment, depth_attachment];
+
+        let subpass_dependencies = [vk::SubpassDependency {
+            src_subpass: vk::SUBPASS_EXTERNAL,
+            dst_subpass: 0,
+            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: vk::AccessFlags::empty(),
+            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ
+                | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dependency_flags: vk::DependencyFlags::empty(),
+        }];
+
+        let renderpass_create_info = vk::RenderPassCreateInfo {
+            s_type: vk::StructureType::RENDER_PASS_CREATE_INFO,
+            flags: vk::RenderPassCreateFlags::empty(),
+            p_next: ptr::null(),
+            attachment_count: render_pass_attachments.len() as u32,
+            p_attachments: render_pass_attachments.as_ptr(),
+            subpass_count: subpasses.len() as u32,
+            p_subpasses: subpasses.as_ptr(),
+            dependency_count: subpass_dependencies.len() as u32,
+            p_dependencies: subpass_dependencies.as_ptr(),
+        };
+
+        unsafe {
+            device
+                .create_render_pass(&renderpass_create_info, None)
+                .vk_context("vkCreateRenderPass", "Failed to create render pass!")
+        }
+    }
+
+    fn create_framebuffers(
+        device: &ash::Device,
+        render_pass: vk::RenderPass,
+        image_views: &[vk::ImageView],
+        depth_image_view: vk::ImageView,
+        swapchain_extent: vk::Extent2D,
+    ) -> Result<Vec<vk::Framebuffer>, VkPlaygroundError> {
+        let mut framebuffers = vec![];
+
+        for &image_view in image_views.iter() {
+            let attachments = [image_view, depth_image_view];
+
+            let framebuffer_create_info = vk::FramebufferCreateInfo {
+                s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
+                p_next: ptr::null(),
+                flags: vk::FramebufferCreateFlags::empty(),
+                render_pass,
+                attachment_count: attachments.len() as u32,
+                p_attachments: attachments.as_ptr(),
+                width: swapchain_extent.width,
+                height: swapchain_extent.height,
+                layers: 1,
+            };
+
+            let framebuffer = unsafe {
+                device
+                    .create_framebuffer(&framebuffer_create_info, None)
+                    .vk_context("vkCreateFramebuffer", "Failed to create Framebuffer!")?
+            };
+
+            framebuffers.push(framebuffer);
+        }
+
+        Ok(framebuffers)
+    }
+
+    // Only scenes with parallel draws use the workers, so their pools and
+    // threads aren't made until one asks for them.
+    fn worker_commands(&self) -> Result<Option<&WorkerCommandPools>, VkPlaygroundError> {
+        if self.worker_count == 0 {
+            return Ok(None);
+        }
+        if self.worker_commands.get().is_none() {
+            let worker_commands = WorkerCommandPools::new(
+                &self.device,
+                self.queue_family.graphics_family.unwrap(),
+                self.worker_count,
+                MAX_FRAMES_IN_FLIGHT,
+            )?;
+            let _ = self.worker_commands.set(worker_commands);
+        }
+
+        Ok(self.worker_commands.get())
+    }
+
+    /// Records the commands for `frame`, drawing `scene` into the
+    /// framebuffer of `frame.image_index`.
+    fn record_frame<S: RenderScene>(
+        &self,
+        scene: &S,
+        frame: Frame,
+    ) -> Result<vk::CommandBuffer, VkPlaygroundError> {
+        let framebuffer = *self.swapchain_framebuffers[frame.image_index];
+        let parallel_draws = scene.parallel_draws();
+        let workers = match parallel_draws {
+            Some(_) => self.worker_commands()?,
+            None => None,
+        };
+
+        self.frame_commands.record(frame.index, |command_buffer, _| {
+            let clear_values = [
+                vk::ClearValue {
+                    // clear value for color buffer
+                    color: vk::ClearColorValue {
+                        float32: [0.0, 0.0, 0.0, 1.0],
+                    },
+                },
+                // clear value for depth buffer
+                vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: 1.0,
+                        stencil: 0,
+                    },
+                },
+            ];
+
+            let render_pass_begin_info = vk::RenderPassBeginInfo {
+                s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+                p_next: ptr::null(),
+                render_pass: *self.render_pass,
+                framebuffer,
+                render_area: vk::Rect2D {
+                    offset: vk::Offset2D { x: 0, y: 0 },
+                    extent: self.swapchain_extent,
+                },
+                clear_value_count: clear_values.len() as u32,
+                p_clear_values: clear_values.as_ptr(),
+            };
+            // a subpass is recorded either inline or entirely from
+            // secondary command buffers
+            let contents = if workers.is_some() {
+                vk::SubpassContents::SECONDARY_COMMAND_BUFFERS
+            } else {
+                vk::SubpassContents::INLINE
+            };
+
+            scene.record_compute(self, command_buffer, frame);
+
+            unsafe {
+                self.device
+                    .cmd_begin_render_pass(command_buffer, &render_pass_begin_info, contents);
+            }
+
+            match (parallel_draws, workers) {
+                (Some(draws), Some(workers)) => {
+                    let device: &ash::Device = &self.device;
+                    let extent = self.swapchain_extent;
+                    let secondary_command_buffers = workers.record(
+                        frame.index,
+                        *self.render_pass,
+                        framebuffer,
+                        draws.draw_count(),
+                        |secondary_command_buffer, draw_range| {
+                            pipeline::set_viewport(device, secondary_command_buffer, extent);
+                            draws.record_draws(device, secondary_command_buffer, frame, draw_range);
+                            Ok(())
+                        },
+                    )?;
+                    if !secondary_command_buffers.is_empty() {
+                        unsafe {
+                            self.device
+                                .cmd_execute_commands(command_buffer, &secondary_command_buffers);
+                        }
+                    }
+                }
+                (Some(draws), None) => {
+                    pipeline::set_viewport(&self.device, command_buffer, self.swapchain_extent);
+                    draws.record_draws(&self.device, command_buffer, frame, 0..draws.draw_count());
+                }
+                (None, _) => {
+                    pipeline::set_viewport(&self.device, command_buffer, self.swapchain_extent);
+                    scene.record_commands(self, command_buffer, frame);
+                }
+            }
+
+            unsafe {
+                self.device.cmd_end_render_pass(command_buffer);
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Returns whether the render pass had to be recreated as well, i.e.
+    /// the pipelines made for the old one are no longer compatible.
+    fn recreate_swapchain(&mut self) -> Result<bool, VkPlaygroundError> {
+        let surface_stuff = SurfaceStuff {
+            surface_loader: self.surface_loader.clone(),
+            surface: *self.surface,
+            screen_width: self.window_width,
+            screen_height: self.window_height,
+        };
+
+        unsafe {
+            self.device
+                .device_wait_idle()
+                .vk_context("vkDeviceWaitIdle", "Failed to wait device idle!")?;
+        }
+        self.cleanup_swapchain();
+
+        let swapchain_stuff = vkstuff::vkswapchain::create_swapchain(
+            &self.instance,
+            &self.device,
+            self.physical_device,
+            self.window_ref(),
+            &surface_stuff,
+            &self.queue_family,
+        )?;
+        self.swapchain = OwnedSwapchain::new(
+            &self.device,
+            &swapchain_stuff.swapchain_loader,
+            swapchain_stuff.swapchain,
+        );
+        let is_format_changed = swapchain_stuff.swapchain_format != self.swapchain_format;
+        self.swapchain_loader = swapchain_stuff.swapchain_loader;
+        self.swapchain_images = swapchain_stuff.swapchain_images;
+        self.swapchain_format = swapchain_stuff.swapchain_format;
+        self.swapchain_extent = swapchain_stuff.swapchain_extent;
+        self.swapchain_usage = swapchain_stuff.swapchain_usage;
+
+        let swapchain_imageviews = vkstuff::vkimage::create_image_views(
+            &self.device,
+            self.swapchain_format,
+            &self.swapchain_images,
+        )?;
+        self.swapchain_imageviews = handles::own_all(&self.device, swapchain_imageviews);
+        // a resize keeps the format, and with it the render pass
+        if is_format_changed {
+            let render_pass = RenderContext::create_render_pass(
+                &self.instance,
+                &self.device,
+                self.physical_device,
+                self.swapchain_format,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+            )?;
+            self.render_pass = Owned::new(&self.device, render_pass);
+        }
+
+        let (depth_image, depth_image_view, depth_image_memory) =
+            RenderContext::create_depth_resources(
+                &self.instance,
+                &self.device,
+                self.physical_device,
+                self.swapchain_extent,
+                &self.allocator,
+            )?;
+        self.depth_image = Owned::new(&self.device, depth_image);
+        self.depth_image_view = Owned::new(&self.device, depth_image_view);
+        self._depth_image_memory = depth_image_memory;
+
+        let swapchain_framebuffers = RenderContext::create_framebuffers(
+            &self.device,
+            *self.render_pass,
+            &handles::raw_handles(&self.swapchain_imageviews),
+            *self.depth_image_view,
+            self.swapchain_extent,
+        )?;
+        self.swapchain_framebuffers = handles::own_all(&self.device, swapchain_framebuffers);
+
+        Ok(is_format_changed)
+    }
+
+    fn cleanup_swapchain(&mut self) {
+        self.depth_image_view.reset();
+        self.depth_image.reset();
+
+        self.swapchain_framebuffers.clear();
+        self.swapchain_imageviews.clear();
+        self.swapchain.reset();
+    }
+
+    fn wait_device_idle(&self) -> Result<(), VkPlaygroundError> {
+        unsafe {
+            self.device
+                .device_wait_idle()
+                .vk_context("vkDeviceWaitIdle", "Failed to wait device idle!")
+        }
+    }
+
+    fn window_ref(&self) -> &winit::window::Window {
+        self.window
+            .as_ref()
+            .expect("There is no window when rendering headless")
+    }
+}
+
+/// A `RenderContext` together with the scene drawn into it. Implements both
+/// `VulkanApp` and `HeadlessApp`, so a demo's `main` only has to build one.
+pub struct Renderer<S: RenderScene> {
+    // the scene's resources are made from the context's device, so it has
+    // to be dropped first
+    scene: S,
+    context: RenderContext,
+    shader_watcher: Option<ShaderWatcher>,
+}
+
+impl<S: RenderScene> Renderer<S> {
+    pub fn new<F>(context: RenderContext, create_scene: F) -> Result<Renderer<S>, VkPlaygroundError>
+    where
+        F: FnOnce(&RenderContext) -> Result<S, VkPlaygroundError>,
+    {
+        let mut scene = create_scene(&context)?;
+        scene.create_pipelines(&context)?;
+
+        Ok(Renderer {
+            scene,
+            context,
+            shader_watcher: None,
+        })
+    }
+
+    pub fn scene(&self) -> &S {
+        &self.scene
+    }
+
+    pub fn scene_mut(&mut self) -> &mut S {
+        &mut self.scene
+    }
+
+    pub fn context(&self) -> &RenderContext {
+        &self.context
+    }
+
+    /// Records the commands for `frame` without submitting them, e.g. to time
+    /// the recording. The previous submit for `frame.index` has to have
+    /// finished.
+    pub fn record_frame(&self, frame: Frame) -> Result<vk::CommandBuffer, VkPlaygroundError> {
+        self.context.record_frame(&self.scene, frame)
+    }
+
+    /// Records the scene's `ParallelDraws` on `thread_count` worker threads,
+    /// or on the main thread for 0. Defaults to
+    /// `frame_commands::default_worker_count`.
+    pub fn set_recording_threads(&mut self, thread_count: usize) -> Result<(), VkPlaygroundError> {
+        // the old pools may still be in use by frames in flight
+        unsafe {
+            self.context
+                .device
+                .device_wait_idle()
+                .vk_context("vkDeviceWaitIdle", "Failed to wait device idle!")?;
+        }
+        self.context.worker_commands = OnceCell::new();
+        self.context.worker_count = thread_count;
+
+        Ok(())
+    }
+
+    /// Reloads the scene's shaders whenever `watcher` sees them change, see
+    /// `RenderScene::reload_shaders`.
+    pub fn watch_shaders(&mut self, watcher: ShaderWatcher) {
+        self.shader_watcher = Some(watcher);
+    }
+
+    // A broken shader is only reported, the old pipelines keep drawing
+    // until it's fixed.
+    fn reload_changed_shaders(&mut self) -> Result<(), VkPlaygroundError> {
+        let changed = match &mut self.shader_watcher {
+            Some(watcher) => watcher.changed_files(),
+            None => return Ok(()),
+        };
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        // compiled up front, so a compile error doesn't stall the GPU, and
+        // the pipelines below pick the results up from the cache
+        let errors: Vec<VkPlaygroundError> = changed
+            .iter()
+            .filter_map(|path| shader_compiler::load_spirv(path).err())
+            .collect();
+        if !errors.is_empty() {
+            for err in errors.iter() {
+                eprintln!("{}", err);
+            }
+            if let Some(watcher) = &mut self.shader_watcher {
+                watcher.keep_pending(changed);
+            }
+            return Ok(());
+        }
+
+        // the old pipelines may still be in use by frames in flight
+        unsafe {
+            self.context
+                .device
+                .device_wait_idle()
+                .vk_context("vkDeviceWaitIdle", "Failed to wait device idle!")?;
+        }
+        match self.scene.reload_shaders(&self.context, &changed) {
+            Ok(true) => {
+                for path in changed.iter() {
+                    println!("Reloaded {}", path.display());
+                }
+            }
+            Ok(false) => {}
+            Err(err) => {
+                eprintln!("Failed to reload shaders, keeping the old pipelines: {}", err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: RenderScene> VulkanApp for Renderer<S> {
+    fn draw_frame(&mut self, delta_time: f32) -> Result<(), VkPlaygroundError> {
+        self.reload_changed_shaders()?;
+
+        let context = &mut self.context;
+        let wait_fences = [*context.in_flight_fences[context.current_frame]];
+
+        unsafe {
+            context
+                .device
+                .wait_for_fences(&wait_fences, true, u64::MAX)
+                .vk_context("vkWaitForFences", "Failed to wait for Fence!")?;
+        }
+
+        let (image_index, _is_sub_optimal) = unsafe {
+            let result = context.swapchain_loader.acquire_next_image(
+                *context.swapchain,
+                u64::MAX,
+                *context.image_available_semaphores[context.current_frame],
+                vk::Fence::null(),
+            );
+            match result {
+                Ok(image_index) => image_index,
+                Err(vk_result) => match vk_result {
+                    vk::Result::ERROR_OUT_OF_DATE_KHR => {
+                        return self.recreate_swapchain();
+                    }
+                    _ => {
+                        return Err(vk_result).vk_context(
+                            "vkAcquireNextImageKHR",
+                            "Failed to acquire Swap Chain Image!",
+                        );
+                    }
+                },
+            }
+        };
+
+        let frame = Frame {
+            index: self.context.current_frame,
+            image_index: image_index as usize,
+        };
+        self.scene.update(frame, delta_time)?;
+
+        let context = &mut self.context;
+        let command_buffer = context.record_frame(&self.scene, frame)?;
+        let wait_semaphores = [*context.image_available_semaphores[context.current_frame]];
+        let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let signal_semaphores = [*context.render_finished_semaphores[context.current_frame]];
+
+        let submit_infos = [vk::SubmitInfo {
+            s_type: vk::StructureType::SUBMIT_INFO,
+            p_next: ptr::null(),
+            wait_semaphore_count: wait_semaphores.len() as u32,
+            p_wait_semaphores: wait_semaphores.as_ptr(),
+            p_wait_dst_stage_mask: wait_stages.as_ptr(),
+            command_buffer_count: 1,
+            p_command_buffers: &command_buffer,
+            signal_semaphore_count: signal_semaphores.len() as u32,
+            p_signal_semaphores: signal_semaphores.as_ptr(),
+        }];
+
+        unsafe {
+            context
+                .device
+                .reset_fences(&wait_fences)
+                .vk_context("vkResetFences", "Failed to reset Fence!")?;
+
+            context
+                .device
+                .queue_submit(
+                    context.graphics_queue,
+                    &submit_infos,
+                    *context.in_flight_fences[context.current_frame],
+                )
+                .vk_context("vkQueueSubmit", "Failed to execute queue submit.")?;
+        }
+
+        if context.is_capture_requested {
+            context.is_capture_requested = false;
+            // a failed capture shouldn't take the frame down with it
+            let captured_frame = screenshot::check_capture_supported(context.swapchain_usage)
+                .and_then(|()| {
+                    screenshot::capture_swapchain_image(
+                        &context.device,
+                        *context.command_pool,
+                        context.graphics_queue,
+                        &context.allocator,
+                        context.swapchain_images[image_index as usize],
+                        context.swapchain_format,
+                        context.swapchain_extent,
+                    )
+                });
+            context.captured_frame = match captured_frame {
+                Ok(frame) => Some(frame),
+                Err(err) => {
+                    eprintln!("Failed to capture frame: {}", err);
+                    None
+                }
+            };
+        }
+
+        let swapchains = [*context.swapchain];
+
+        let present_info = vk::PresentInfoKHR {
+            s_type: vk::StructureType::PRESENT_INFO_KHR,
+            p_next: ptr::null(),
+            wait_semaphore_count: 1,
+            p_wait_semaphores: signal_semaphores.as_ptr(),
+            swapchain_count: 1,
+            p_swapchains: swapchains.as_ptr(),
+            p_image_indices: &image_index,
+            p_results: ptr::null_mut(),
+        };
+
+        let result = unsafe {
+            context
+                .swapchain_loader
+                .queue_present(context.present_queue, &present_info)
+        };
+
+        let is_resized = match result {
+            Ok(_) => context.is_framebuffer_resized,
+            Err(vk_result) => match vk_result {
+                vk::Result::ERROR_OUT_OF_DATE_KHR | vk::Result::SUBOPTIMAL_KHR => true,
+                _ => {
+                    return Err(vk_result)
+                        .vk_context("vkQueuePresentKHR", "Failed to execute queue present.");
+                }
+            },
+        };
+        if is_resized {
+            self.context.is_framebuffer_resized = false;
+            self.recreate_swapchain()?;
+        }
+
+        self.context.current_frame = (self.context.current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
+
+        Ok(())
+    }
+
+    fn recreate_swapchain(&mut self) -> Result<(), VkPlaygroundError> {
+        if self.context.recreate_swapchain()? {
+            self.scene.create_pipelines(&self.context)?;
+        }
+
+        Ok(())
+    }
+
+    fn cleanup_swapchain(&mut self) {
+        self.context.cleanup_swapchain();
+    }
+
+    fn wait_device_idle(&self) -> Result<(), VkPlaygroundError> {
+        self.context.wait_device_idle()
+    }
+
+    fn resize_framebuffer(&mut self) {
+        self.context.is_framebuffer_resized = true;
+    }
+
+    fn window_ref(&self) -> &winit::window::Window {
+        self.context.window_ref()
+    }
+
+    fn request_capture(&mut self) {
+        self.context.is_capture_requested = true;
+    }
+
+    fn take_captured_frame(&mut self) -> Option<CapturedFrame> {
+        self.context.captured_frame.take()
+    }
+}
+
+impl<S: RenderScene> HeadlessApp for Renderer<S> {
+    // there is a single offscreen image, so only one frame is ever in flight
+    fn draw_frame(&mut self, delta_time: f32) -> Result<(), VkPlaygroundError> {
+        headless::begin_frame(&self.context.device, *self.context.in_flight_fences[0])?;
+        let frame = Frame {
+            index: 0,
+            image_index: 0,
+        };
+        self.scene.update(frame, delta_time)?;
+        let command_buffer = self.context.record_frame(&self.scene, frame)?;
+        headless::submit_frame(
+            &self.context.device,
+            self.context.graphics_queue,
+            command_buffer,
+            *self.context.in_flight_fences[0],
+        )
+    }
+
+    fn read_frame(&self) -> Result<Vec<u8>, VkPlaygroundError> {
+        headless::read_offscreen_target(
+            &self.context.device,
+            *self.context.command_pool,
+            self.context.graphics_queue,
+            self.context
+                .offscreen_target
+                .as_ref()
+                .expect("read_frame is only available when headless"),
+        )
+    }
+
+    fn frame_extent(&self) -> vk::Extent2D {
+        self.context.swapchain_extent
+    }
+
+    fn wait_device_idle(&self) -> Result<(), VkPlaygroundError> {
+        self.context.wait_device_idle()
+    }
+}
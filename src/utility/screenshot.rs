@@ -0,0 +1,289 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+use winit::event::VirtualKeyCode;
+
+use std::path::PathBuf;
+use std::ptr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::utility::allocator::Allocator;
+use crate::utility::error::VkPlaygroundError;
+use crate::utility::handles::Owned;
+use crate::utility::share;
+
+// Copying what's on screen back to the host. ProgramProc::main_loop asks the
+// app for a capture when SCREENSHOT_KEY is pressed (or every frame while
+// recording), and the app copies the swapchain image it renders next, after
+// the draw is submitted but before it's presented, which is the last point
+// the image still belongs to us.
+
+pub const SCREENSHOT_KEY: VirtualKeyCode = VirtualKeyCode::F12;
+
+const SWAPCHAIN_LAYOUT: vk::ImageLayout = vk::ImageLayout::PRESENT_SRC_KHR;
+
+// a frame copied back from the swapchain, as tightly packed RGBA8
+pub struct CapturedFrame {
+    pub extent: vk::Extent2D,
+    pub pixels: Vec<u8>,
+}
+
+/// The usage to create swapchain images with. TRANSFER_SRC, which captures
+/// copy from, is only added when the surface supports it, since drivers
+/// don't have to.
+pub fn swapchain_image_usage(capabilities: &vk::SurfaceCapabilitiesKHR) -> vk::ImageUsageFlags {
+    vk::ImageUsageFlags::COLOR_ATTACHMENT
+        | (capabilities.supported_usage_flags & vk::ImageUsageFlags::TRANSFER_SRC)
+}
+
+/// Fails with `Unsupported` unless swapchain images of `usage` can be
+/// captured.
+pub fn check_capture_supported(usage: vk::ImageUsageFlags) -> Result<(), VkPlaygroundError> {
+    if usage.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+        Ok(())
+    } else {
+        Err(VkPlaygroundError::unsupported(
+            "check_capture_supported",
+            "Screenshots aren't supported, the surface can't be copied from",
+        ))
+    }
+}
+
+/// Copies a rendered swapchain image back to the host. The image must be in
+/// `PRESENT_SRC_KHR` layout and is left that way, ready to be presented.
+pub fn capture_swapchain_image(
+    device: &ash::Device,
+    command_pool: vk::CommandPool,
+    submit_queue: vk::Queue,
+    allocator: &Allocator,
+    image: vk::Image,
+    format: vk::Format,
+    extent: vk::Extent2D,
+) -> Result<CapturedFrame, VkPlaygroundError> {
+    let mut pixels = read_swapchain_image(
+        device,
+        command_pool,
+        submit_queue,
+        allocator,
+        image,
+        extent,
+    )?;
+    swizzle_to_rgba(format, &mut pixels)?;
+
+    Ok(CapturedFrame { extent, pixels })
+}
+
+/// Writes a captured frame to a timestamped PNG in the working directory.
+pub fn save_screenshot(frame: &CapturedFrame) -> Result<PathBuf, VkPlaygroundError> {
+    let path = screenshot_path();
+    image::save_buffer(
+        &path,
+        &frame.pixels,
+        frame.extent.width,
+        frame.extent.height,
+        image::ColorType::RGBA(8),
+    )
+    .map_err(|source| VkPlaygroundError::Io {
+        path: path.clone(),
+        source,
+    })?;
+
+    println!("Saved screenshot to {}", path.display());
+
+    Ok(path)
+}
+
+fn read_swapchain_image(
+    device: &ash::Device,
+    command_pool: vk::CommandPool,
+    submit_queue: vk::Queue,
+    allocator: &Allocator,
+    image: vk::Image,
+    extent: vk::Extent2D,
+) -> Result<Vec<u8>, VkPlaygroundError> {
+    let readback_size = (extent.width * extent.height * 4) as vk::DeviceSize;
+    let (readback_buffer, readback_memory) = share::create_buffer(
+        device,
+        readback_size,
+        vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        allocator,
+    )?;
+    let readback_buffer = Owned::new(device, readback_buffer);
+
+    let subresource_range = vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+
+    // wait for the draw that was just submitted, then hand the image back
+    // in the layout the present engine expects
+    let to_transfer_barriers = [vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        dst_access_mask: vk::AccessFlags::TRANSFER_READ,
+        old_layout: SWAPCHAIN_LAYOUT,
+        new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range,
+    }];
+    let to_present_barriers = [vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask: vk::AccessFlags::TRANSFER_READ,
+        dst_access_mask: vk::AccessFlags::empty(),
+        old_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        new_layout: SWAPCHAIN_LAYOUT,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range,
+    }];
+
+    let copy_regions = [vk::BufferImageCopy {
+        buffer_offset: 0,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+        image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        image_extent: vk::Extent3D {
+            width: extent.width,
+            height: extent.height,
+            depth: 1,
+        },
+    }];
+
+    let command_buffer = share::begin_single_time_command(device, command_pool)?;
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &to_transfer_barriers,
+        );
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            *readback_buffer,
+            &copy_regions,
+        );
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &to_present_barriers,
+        );
+    }
+
+    share::end_single_time_command(device, command_pool, submit_queue, command_buffer)?;
+
+    let mut pixels = vec![0_u8; readback_size as usize];
+    readback_memory.read(&mut pixels);
+
+    Ok(pixels)
+}
+
+/// Rearranges swapchain pixels into the RGBA8 order PNGs expect. SRGB and
+/// UNORM formats hold the same bytes as far as the PNG is concerned, the SRGB
+/// ones are already gamma encoded by the hardware when they were written.
+pub fn swizzle_to_rgba(format: vk::Format, pixels: &mut [u8]) -> Result<(), VkPlaygroundError> {
+    match format {
+        vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => {}
+        _ => {
+            return Err(VkPlaygroundError::unsupported(
+                "swizzle_to_rgba",
+                format!("Screenshots of {:?} swapchains aren't supported", format),
+            ))
+        }
+    }
+
+    // the swapchain is composited as opaque, whatever ended up in alpha
+    // would only make the PNG see-through
+    for pixel in pixels.chunks_mut(4) {
+        pixel[3] = 255;
+    }
+
+    Ok(())
+}
+
+fn screenshot_path() -> PathBuf {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is set before 1970!");
+
+    PathBuf::from(format!(
+        "screenshot-{}-{:03}.png",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(supported_usage_flags: vk::ImageUsageFlags) -> vk::SurfaceCapabilitiesKHR {
+        vk::SurfaceCapabilitiesKHR {
+            supported_usage_flags,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn transfer_src_is_used_when_supported() {
+        let supported = vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | vk::ImageUsageFlags::TRANSFER_SRC
+            | vk::ImageUsageFlags::STORAGE;
+
+        let usage = swapchain_image_usage(&capabilities(supported));
+
+        assert_eq!(
+            usage,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC
+        );
+        assert!(check_capture_supported(usage).is_ok());
+    }
+
+    #[test]
+    fn capture_is_unsupported_without_transfer_src() {
+        let usage = swapchain_image_usage(&capabilities(vk::ImageUsageFlags::COLOR_ATTACHMENT));
+
+        assert_eq!(usage, vk::ImageUsageFlags::COLOR_ATTACHMENT);
+        assert!(matches!(
+            check_capture_supported(usage),
+            Err(VkPlaygroundError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn bgra_is_swizzled_to_opaque_rgba() {
+        let mut pixels = [1, 2, 3, 0, 4, 5, 6, 128];
+
+        swizzle_to_rgba(vk::Format::B8G8R8A8_SRGB, &mut pixels).unwrap();
+
+        assert_eq!(pixels, [3, 2, 1, 255, 6, 5, 4, 255]);
+    }
+}
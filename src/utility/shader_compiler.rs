@@ -0,0 +1,361 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utility::error::VkPlaygroundError;
+
+// GLSL sources compiled to SPIR-V at runtime, so a shader can be loaded
+// straight from its `.vert`, `.frag` or `.comp` file instead of a `.spv`
+// that may have gone stale.
+//
+// With the `shaderc` feature the compiler runs in-process. Without it
+// glslangValidator from the Vulkan SDK is run instead, the same tool
+// shaders/*/compileshaders.sh uses. shaderc isn't the default because
+// shaderc-sys builds glslang from source with cmake and a C++ compiler
+// unless it finds the SDK's libshaderc, which would break `cargo build` on
+// a machine that can otherwise run the prebuilt `.spv` files. Either way the result is cached in
+// `.shader-cache` (or wherever VK_PLAYGROUND_SHADER_CACHE points) under a
+// hash of the source, so an unchanged source is only compiled once.
+
+pub const SHADER_CACHE_ENV_VAR: &str = "VK_PLAYGROUND_SHADER_CACHE";
+const DEFAULT_CACHE_DIR: &str = ".shader-cache";
+
+// part of the cache key, so switching compilers doesn't reuse old results
+#[cfg(feature = "shaderc")]
+const COMPILER_ID: &str = "shaderc";
+#[cfg(not(feature = "shaderc"))]
+const COMPILER_ID: &str = "glslangValidator";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    /// The stage of a GLSL source, from its extension. `None` for anything
+    /// else, such as a `.spv` file.
+    pub fn from_path(path: &Path) -> Option<ShaderStage> {
+        match path.extension()?.to_str()? {
+            "vert" => Some(ShaderStage::Vertex),
+            "frag" => Some(ShaderStage::Fragment),
+            "comp" => Some(ShaderStage::Compute),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "vert",
+            ShaderStage::Fragment => "frag",
+            ShaderStage::Compute => "comp",
+        }
+    }
+}
+
+/// One error or warning from the compiler. `line` is `None` when the
+/// compiler didn't point at a line, e.g. for a missing `main`.
+#[derive(Debug, Clone)]
+pub struct ShaderDiagnostic {
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+/// The SPIR-V for `path`. GLSL sources are compiled, or taken from the
+/// cache, and anything else is read as SPIR-V.
+pub fn load_spirv(path: &Path) -> Result<Vec<u8>, VkPlaygroundError> {
+    match ShaderStage::from_path(path) {
+        Some(stage) => compile_glsl_cached(path, stage, &cache_dir()),
+        None => fs::read(path).map_err(|source| VkPlaygroundError::Io {
+            path: path.into(),
+            source,
+        }),
+    }
+}
+
+pub fn cache_dir() -> PathBuf {
+    std::env::var_os(SHADER_CACHE_ENV_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_DIR))
+}
+
+pub fn compile_glsl_cached(
+    path: &Path,
+    stage: ShaderStage,
+    cache_dir: &Path,
+) -> Result<Vec<u8>, VkPlaygroundError> {
+    let source = fs::read_to_string(path).map_err(|source| VkPlaygroundError::Io {
+        path: path.into(),
+        source,
+    })?;
+
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let cache_path = cache_dir.join(format!(
+        "{}-{:016x}.spv",
+        file_name,
+        source_hash(&source, stage)
+    ));
+    if let Ok(spirv) = fs::read(&cache_path) {
+        return Ok(spirv);
+    }
+
+    let spirv = compile_glsl(path, &source, stage)?;
+
+    // a cache that can't be written only costs a compile next time, and
+    // renaming into place keeps a half written file from being read back
+    let temp_path = cache_path.with_extension(format!("{}.tmp", std::process::id()));
+    let is_written =
+        fs::create_dir_all(cache_dir).is_ok() && fs::write(&temp_path, &spirv).is_ok();
+    if !is_written || fs::rename(&temp_path, &cache_path).is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    Ok(spirv)
+}
+
+/// Compiles `source`, read from `path`, without touching the cache.
+#[cfg(feature = "shaderc")]
+pub fn compile_glsl(
+    path: &Path,
+    source: &str,
+    stage: ShaderStage,
+) -> Result<Vec<u8>, VkPlaygroundError> {
+    let mut compiler = shaderc::Compiler::new().ok_or_else(|| {
+        VkPlaygroundError::unsupported("compile_glsl", "Failed to start shaderc")
+    })?;
+    let mut options = shaderc::CompileOptions::new().ok_or_else(|| {
+        VkPlaygroundError::unsupported("compile_glsl", "Failed to create shaderc options")
+    })?;
+    options.set_target_env(shaderc::TargetEnv::Vulkan, shaderc::EnvVersion::Vulkan1_0 as u32);
+
+    let kind = match stage {
+        ShaderStage::Vertex => shaderc::ShaderKind::Vertex,
+        ShaderStage::Fragment => shaderc::ShaderKind::Fragment,
+        ShaderStage::Compute => shaderc::ShaderKind::Compute,
+    };
+
+    // just the file name, a drive letter's colon would confuse parse_diagnostic
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy())
+        .unwrap_or_default();
+    match compiler.compile_into_spirv(source, kind, &file_name, "main", Some(&options)) {
+        Ok(artifact) => Ok(artifact.as_binary_u8().to_vec()),
+        Err(shaderc::Error::CompilationError(_, log)) => Err(compile_error(path, &log)),
+        Err(err) => Err(VkPlaygroundError::ShaderCompile {
+            path: path.into(),
+            diagnostics: vec![ShaderDiagnostic {
+                line: None,
+                message: err.to_string(),
+            }],
+        }),
+    }
+}
+
+/// Compiles `source`, read from `path`, without touching the cache.
+#[cfg(not(feature = "shaderc"))]
+pub fn compile_glsl(
+    path: &Path,
+    source: &str,
+    stage: ShaderStage,
+) -> Result<Vec<u8>, VkPlaygroundError> {
+    use std::io::{self, Write};
+    use std::process::{Command, Stdio};
+
+    // the source goes in on stdin, so what's compiled is what was hashed
+    let output_path = std::env::temp_dir().join(format!(
+        "vk_playground-{}-{:016x}.spv",
+        std::process::id(),
+        source_hash(source, stage)
+    ));
+    let io_error = |source: io::Error| match source.kind() {
+        io::ErrorKind::NotFound => VkPlaygroundError::unsupported(
+            "compile_glsl",
+            "GLSL shaders need glslangValidator on the PATH, or building with --features shaderc",
+        ),
+        _ => VkPlaygroundError::Io {
+            path: path.into(),
+            source,
+        },
+    };
+
+    let mut child = Command::new("glslangValidator")
+        .arg("-V")
+        .arg("--stdin")
+        .arg("-S")
+        .arg(stage.extension())
+        .arg("-o")
+        .arg(&output_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(io_error)?;
+    child
+        .stdin
+        .take()
+        .expect("glslangValidator was started with a piped stdin")
+        .write_all(source.as_bytes())
+        .map_err(io_error)?;
+    let output = child.wait_with_output().map_err(io_error)?;
+
+    if !output.status.success() {
+        let _ = fs::remove_file(&output_path);
+        let log =
+            String::from_utf8_lossy(&output.stdout) + String::from_utf8_lossy(&output.stderr);
+        return Err(compile_error(path, &log));
+    }
+
+    let spirv = fs::read(&output_path).map_err(|source| VkPlaygroundError::Io {
+        path: output_path.clone(),
+        source,
+    });
+    let _ = fs::remove_file(&output_path);
+
+    spirv
+}
+
+fn compile_error(path: &Path, log: &str) -> VkPlaygroundError {
+    let mut diagnostics: Vec<ShaderDiagnostic> = log
+        .lines()
+        .filter(|line| line.to_lowercase().contains("error"))
+        .filter_map(parse_diagnostic)
+        .collect();
+    // nothing recognisable, hand over the whole log rather than nothing
+    if diagnostics.is_empty() {
+        diagnostics.push(ShaderDiagnostic {
+            line: None,
+            message: log.trim().to_owned(),
+        });
+    }
+
+    VkPlaygroundError::ShaderCompile {
+        path: path.into(),
+        diagnostics,
+    }
+}
+
+// glslangValidator: "ERROR: 0:12: 'foo' : undeclared identifier"
+// shaderc:          "shaders/cube/cube.frag:12: error: 'foo' : undeclared identifier"
+fn parse_diagnostic(line: &str) -> Option<ShaderDiagnostic> {
+    let line = line.trim();
+    // summary lines such as "1 compilation errors.  No code generated."
+    if line.contains("compilation errors") || line.ends_with("generated.") {
+        return None;
+    }
+
+    let parts: Vec<&str> = line.splitn(4, ':').collect();
+    let diagnostic = match parts.as_slice() {
+        ["ERROR", _, line_number, message] => ShaderDiagnostic {
+            line: line_number.trim().parse().ok(),
+            message: message.trim().to_owned(),
+        },
+        [_, line_number, kind, message] if line_number.trim().parse::<u32>().is_ok() => {
+            ShaderDiagnostic {
+                line: line_number.trim().parse().ok(),
+                message: format!("{}:{}", kind.trim(), message),
+            }
+        }
+        _ => ShaderDiagnostic {
+            line: None,
+            message: line.to_owned(),
+        },
+    };
+
+    Some(diagnostic)
+}
+
+// 64-bit FNV-1a, which is stable across runs and Rust versions unlike the
+// hasher in std
+fn source_hash(source: &str, stage: ShaderStage) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = FNV_OFFSET_BASIS;
+    let parts = [COMPILER_ID.as_bytes(), stage.extension().as_bytes(), source.as_bytes()];
+    for part in parts.iter() {
+        for &byte in part.iter() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+        // keeps "ab" + "c" from hashing the same as "a" + "bc"
+        hash ^= 0xff;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glslang_validator_errors_are_parsed() {
+        let diagnostic = parse_diagnostic("ERROR: 0:12: 'foo' : undeclared identifier").unwrap();
+
+        assert_eq!(diagnostic.line, Some(12));
+        assert_eq!(diagnostic.message, "'foo' : undeclared identifier");
+    }
+
+    #[test]
+    fn shaderc_errors_are_parsed() {
+        let diagnostic =
+            parse_diagnostic("cube.frag:12: error: 'foo' : undeclared identifier").unwrap();
+
+        assert_eq!(diagnostic.line, Some(12));
+        assert_eq!(diagnostic.message, "error: 'foo' : undeclared identifier");
+    }
+
+    #[test]
+    fn summary_lines_are_skipped() {
+        assert!(parse_diagnostic("1 compilation errors.  No code generated.").is_none());
+        assert!(parse_diagnostic("ERROR: 1 compilation errors.  No code generated.").is_none());
+    }
+
+    #[test]
+    fn unrecognised_lines_are_kept_whole() {
+        let diagnostic = parse_diagnostic("  ERROR: Linking vertex stage: Missing entry point  ")
+            .unwrap();
+
+        assert_eq!(diagnostic.line, None);
+        assert_eq!(diagnostic.message, "ERROR: Linking vertex stage: Missing entry point");
+    }
+
+    #[test]
+    fn log_without_errors_becomes_one_diagnostic() {
+        let error = compile_error(Path::new("cube.vert"), "something went wrong\n");
+
+        match error {
+            VkPlaygroundError::ShaderCompile { diagnostics, .. } => {
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(diagnostics[0].message, "something went wrong");
+            }
+            other => panic!("expected a compile error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn source_hash_is_stable() {
+        let source = "#version 450\nvoid main() {}\n";
+
+        assert_eq!(
+            source_hash(source, ShaderStage::Vertex),
+            source_hash(source, ShaderStage::Vertex)
+        );
+    }
+
+    #[test]
+    fn source_hash_depends_on_the_source_and_stage() {
+        let source = "#version 450\nvoid main() {}\n";
+
+        let hash = source_hash(source, ShaderStage::Vertex);
+
+        assert_ne!(hash, source_hash(source, ShaderStage::Fragment));
+        assert_ne!(hash, source_hash("#version 450\nvoid main() { }\n", ShaderStage::Vertex));
+        assert_ne!(hash, source_hash("", ShaderStage::Vertex));
+    }
+}
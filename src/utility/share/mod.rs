@@ -2,260 +2,95 @@ pub mod v1;
 pub mod v2;
 
 use ash::version::DeviceV1_0;
-use ash::version::EntryV1_0;
 use ash::version::InstanceV1_0;
 use ash::vk;
 
-use std::ffi::CString;
-use std::os::raw::c_char;
-use std::os::raw::c_void;
 use std::path::Path;
 use std::ptr;
 
-use crate::utility::constants::*;
-use crate::utility::debug;
+use crate::utility::allocator::{Allocation, Allocator};
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::shader_compiler;
+use crate::utility::obj;
 use crate::utility::platforms;
+use crate::utility::screenshot;
 use crate::utility::structures::*;
 
 // functions for creating instances and debug buffers
 // and other things. Another prime candidate for refactoring
 
-pub fn create_instance(
-    entry: &ash::Entry,
-    window_title: &str,
-    is_enable_debug: bool,
-    required_validation_layers: &Vec<&str>,
-) -> ash::Instance {
-    if is_enable_debug
-        && debug::check_validation_layer_support(entry, required_validation_layers) == false
-    {
-        panic!("Validation layers requested, but not available!");
-    }
-
-    let app_name = CString::new(window_title).unwrap();
-    let engine_name = CString::new("Vulkan Engine").unwrap();
-    let app_info = vk::ApplicationInfo {
-        p_application_name: app_name.as_ptr(),
-        s_type: vk::StructureType::APPLICATION_INFO,
-        p_next: ptr::null(),
-        application_version: APPLICATION_VERSION,
-        p_engine_name: engine_name.as_ptr(),
-        engine_version: ENGINE_VERSION,
-        api_version: API_VERSION,
-    };
-
-    // this creates info used to debug issues in vk::createInstance
-    let debug_utils_create_info = debug::populate_debug_messenger_create_info();
-
-    // VK_EXT debug report has been requested here
-    let extension_names = platforms::required_extension_names();
-
-    let required_validation_layer_raw_names: Vec<CString> = required_validation_layers
-        .iter()
-        .map(|layer_name| CString::new(*layer_name).unwrap())
-        .collect();
-    let layer_names: Vec<*const i8> = required_validation_layer_raw_names
-        .iter()
-        .map(|layer_name| layer_name.as_ptr())
-        .collect(); 
-
-    // fill out instance info
-    let create_info = vk::InstanceCreateInfo {
-        s_type: vk::StructureType::INSTANCE_CREATE_INFO,
-        p_next: if VALIDATION.is_enable {
-            &debug_utils_create_info as *const vk::DebugUtilsMessengerCreateInfoEXT
-                    as *const c_void
-        } else {
-            ptr::null()
-        },
-        flags: vk::InstanceCreateFlags::empty(),
-        p_application_info: &app_info,
-        pp_enabled_layer_names: if is_enable_debug {
-            layer_names.as_ptr()
-        } else {
-            ptr::null()
-        },
-        enabled_layer_count: if is_enable_debug {
-            layer_names.len()
-        } else {
-            0
-        } as u32,
-        pp_enabled_extension_names: extension_names.as_ptr(),
-        enabled_extension_count: extension_names.len() as u32,
-    };
-
-    let instance: ash::Instance = unsafe {
-        entry
-            .create_instance(&create_info, None)
-            .expect("Failed to create instance!")
-    };
-
-    instance
-}
-
 pub fn create_surface(
     entry: &ash::Entry,
     instance: &ash::Instance,
     window: &winit::window::Window,
     screen_width: u32,
     screen_height: u32,
-)   -> SurfaceStuff {
+)   -> Result<SurfaceStuff, VkPlaygroundError> {
 
     let surface = unsafe {
-        platforms::create_surface(entry, instance, window).expect("Failed to create surface!")
+        platforms::create_surface(entry, instance, window)
+            .vk_context("vkCreateSurfaceKHR", "Failed to create surface!")?
     };
 
     let surface_loader = ash::extensions::khr::Surface::new(entry, instance);
 
-    SurfaceStuff {
+    Ok(SurfaceStuff {
         surface_loader,
         surface,
         screen_width,
         screen_height,
-    }
+    })
 }
 
 
-pub fn pick_physical_device(
-    instance: &ash::Instance,
-    surface_stuff: &SurfaceStuff,
-    required_device_extensions: &DeviceExtension
-) -> vk::PhysicalDevice {
-    let physical_devices = unsafe {
-        instance
-            .enumerate_physical_devices()
-            .expect("Failed to enumerate Physical Devices!")
-    };
-
-    let result = physical_devices.iter().find(|physical_device| {
-        let is_suitable = is_physical_device_suitable(
-            instance,
-            **physical_device,
-            surface_stuff,
-            required_device_extensions,
-        );
-
-        is_suitable
-    });
-
-    match result {
-        Some(p_physical_device) => *p_physical_device,
-        None => panic!("Failed to find a suitable GPU"),
-    }
-}
-
+// With no surface (headless rendering) only a graphics queue is needed, and
+// the device extensions are skipped since they only cover presentation.
 pub fn is_physical_device_suitable(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-    surface_stuff: &SurfaceStuff,
+    surface_stuff: Option<&SurfaceStuff>,
     required_device_extensions: &DeviceExtension,
-) -> bool {
+) -> Result<bool, VkPlaygroundError> {
 
     let device_features = unsafe {instance.get_physical_device_features(physical_device)};
 
     let indices = find_queue_family(instance, physical_device, surface_stuff);
 
-    let is_queue_family_supported = indices.is_complete();
-
-    let is_device_extension_supported = 
-        check_device_extension_support(instance, physical_device, required_device_extensions);
-
-    let is_swapchain_supported = if is_device_extension_supported {
-        let swapchain_support = query_swapchain_support(physical_device, surface_stuff);
-        !swapchain_support.formats.is_empty() && !swapchain_support.present_modes.is_empty()
-    } else {
-        false
-    };
+    let (is_queue_family_supported, is_device_extension_supported, is_swapchain_supported) =
+        match surface_stuff {
+            Some(surface_stuff) => {
+                let is_device_extension_supported = check_device_extension_support(
+                    instance,
+                    physical_device,
+                    required_device_extensions,
+                )?;
+
+                let is_swapchain_supported = if is_device_extension_supported {
+                    let swapchain_support =
+                        query_swapchain_support(physical_device, surface_stuff)?;
+                    !swapchain_support.formats.is_empty()
+                        && !swapchain_support.present_modes.is_empty()
+                } else {
+                    false
+                };
+
+                (indices.is_complete(), is_device_extension_supported, is_swapchain_supported)
+            }
+            None => (indices.is_headless_complete(), true, true),
+        };
 
     let is_support_sampler_anisotropy = device_features.sampler_anisotropy == 1;
 
-    return is_queue_family_supported
+    Ok(is_queue_family_supported
         && is_device_extension_supported
         && is_swapchain_supported
-        && is_support_sampler_anisotropy;
-    
+        && is_support_sampler_anisotropy)
 }
 
-pub fn create_logical_device(
-    instance: &ash::Instance,
-    physical_device: vk::PhysicalDevice,
-    validation: &super::debug::ValidationInfo,
-    device_extensions: &DeviceExtension,
-    surface_stuff: &SurfaceStuff,
-) -> (ash::Device, QueueFamilyIndices) {
-    let indices = find_queue_family(instance, physical_device, surface_stuff);
-
-    use std::collections::HashSet;
-    let mut unique_queue_families = HashSet::new();
-    unique_queue_families.insert(indices.graphics_family.unwrap());
-    unique_queue_families.insert(indices.present_family.unwrap());
-
-    let queue_priorities = [1.0_f32];
-    let mut queue_create_infos = vec![];
-    for &queue_family in unique_queue_families.iter() {
-        let queue_create_info = vk::DeviceQueueCreateInfo {
-            s_type: vk::StructureType::DEVICE_QUEUE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::DeviceQueueCreateFlags::empty(),
-            queue_family_index: queue_family,
-            p_queue_priorities: queue_priorities.as_ptr(),
-            queue_count: queue_priorities.len() as u32,
-        };
-        queue_create_infos.push(queue_create_info);
-    }
-
-    let physical_device_features = vk::PhysicalDeviceFeatures {
-        sampler_anisotropy: vk::TRUE,
-        ..Default::default()
-    };
-
-    let required_validation_layer_raw_names: Vec<CString> = validation
-        .required_validation_layers
-        .iter()
-        .map(|layer_name| CString::new(*layer_name).unwrap())
-        .collect();
-    let enable_layer_names: Vec<*const c_char> = required_validation_layer_raw_names
-        .iter()
-        .map(|layer_name| layer_name.as_ptr())
-        .collect();
-    
-    let enable_extension_names = device_extensions.get_extensions_raw_names();
-
-    let device_create_info = vk::DeviceCreateInfo {
-        s_type: vk::StructureType::DEVICE_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: vk::DeviceCreateFlags::empty(),
-        queue_create_info_count: queue_create_infos.len() as u32,
-        p_queue_create_infos: queue_create_infos.as_ptr(),
-        enabled_layer_count: if validation.is_enable {
-            enable_layer_names.len()
-        } else {
-            0
-        } as u32,
-        pp_enabled_layer_names: if validation.is_enable {
-            enable_layer_names.as_ptr()
-        } else {
-            ptr::null()
-        },
-        enabled_extension_count: enable_extension_names.len() as u32,
-        pp_enabled_extension_names: enable_extension_names.as_ptr(),
-        p_enabled_features: &physical_device_features,
-    };
-
-    let device: ash::Device = unsafe {
-        instance
-            .create_device(physical_device, &device_create_info, None)
-            .expect("Failed to create logical device!")
-    };
-
-    (device, indices)
-}
-
-
 pub fn find_queue_family(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-    surface_stuff: &SurfaceStuff
+    surface_stuff: Option<&SurfaceStuff>
 ) -> QueueFamilyIndices {
     let queue_families = 
         unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
@@ -264,33 +99,67 @@ pub fn find_queue_family(
 
     let mut index = 0;
     for queue_family in queue_families.iter() {
+        // every device with graphics has a family that also does compute,
+        // which lets compute dispatches go in the frame's command buffer
         if queue_family.queue_count > 0
-            && queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+            && queue_family
+                .queue_flags
+                .contains(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
             {
                 queue_family_indices.graphics_family = Some(index);
             }
         
-        let is_present_support = unsafe {
-            surface_stuff
-                .surface_loader
-                .get_physical_device_surface_support(
-                    physical_device,
-                    index as u32,
-                    surface_stuff.surface,
-                )
-        };
+        if let Some(surface_stuff) = surface_stuff {
+            let is_present_support = unsafe {
+                surface_stuff
+                    .surface_loader
+                    .get_physical_device_surface_support(
+                        physical_device,
+                        index as u32,
+                        surface_stuff.surface,
+                    )
+            };
 
-        if queue_family.queue_count > 0 && is_present_support {
-            queue_family_indices.present_family = Some(index);
-        }
+            if queue_family.queue_count > 0 && is_present_support {
+                queue_family_indices.present_family = Some(index);
+            }
 
-        if queue_family_indices.is_complete() {
+            if queue_family_indices.is_complete() {
+                break;
+            }
+        } else if queue_family_indices.is_headless_complete() {
             break;
         }
 
         index += 1;
     }
 
+    // a compute-only family can run alongside graphics, otherwise compute
+    // shares the graphics family
+    queue_family_indices.compute_family = queue_families
+        .iter()
+        .position(|queue_family| {
+            queue_family.queue_count > 0
+                && queue_family.queue_flags.contains(vk::QueueFlags::COMPUTE)
+                && !queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+        })
+        .map(|index| index as u32)
+        .or(queue_family_indices.graphics_family);
+
+    // a transfer-only family is usually a DMA engine that copies alongside
+    // rendering, otherwise uploads go through the graphics family
+    queue_family_indices.transfer_family = queue_families
+        .iter()
+        .position(|queue_family| {
+            queue_family.queue_count > 0
+                && queue_family.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                && !queue_family
+                    .queue_flags
+                    .intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+        })
+        .map(|index| index as u32)
+        .or(queue_family_indices.graphics_family);
+
     queue_family_indices
 }
 
@@ -298,11 +167,14 @@ pub fn check_device_extension_support(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
     device_extensions: &DeviceExtension,
-) -> bool {
+) -> Result<bool, VkPlaygroundError> {
     let available_extensions = unsafe {
         instance
             .enumerate_device_extension_properties(physical_device)
-            .expect("Failed to get device extension properties")
+            .vk_context(
+                "vkEnumerateDeviceExtensionProperties",
+                "Failed to get device extension properties",
+            )?
     };
 
     let mut available_extension_names = vec![];
@@ -323,33 +195,42 @@ pub fn check_device_extension_support(
         required_extensions.remove(extension_name);
     }
 
-    return required_extensions.is_empty();
+    Ok(required_extensions.is_empty())
 }
 
 pub fn query_swapchain_support(
     physical_device: vk::PhysicalDevice,
     surface_stuff: &SurfaceStuff
-) -> SwapChainSupportDetail {
+) -> Result<SwapChainSupportDetail, VkPlaygroundError> {
 
     unsafe {
         let capabilities = surface_stuff
             .surface_loader
             .get_physical_device_surface_capabilities(physical_device, surface_stuff.surface)
-            .expect("Failed to query for surface capabilities");
+            .vk_context(
+                "vkGetPhysicalDeviceSurfaceCapabilitiesKHR",
+                "Failed to query for surface capabilities",
+            )?;
         let formats = surface_stuff
             .surface_loader
             .get_physical_device_surface_formats(physical_device, surface_stuff.surface)
-            .expect("Failed to queryt for surface formats");
+            .vk_context(
+                "vkGetPhysicalDeviceSurfaceFormatsKHR",
+                "Failed to queryt for surface formats",
+            )?;
         let present_modes = surface_stuff
             .surface_loader
             .get_physical_device_surface_present_modes(physical_device, surface_stuff.surface)
-            .expect("Failed to query for surface present mode");
+            .vk_context(
+                "vkGetPhysicalDeviceSurfacePresentModesKHR",
+                "Failed to query for surface present mode",
+            )?;
 
-        SwapChainSupportDetail {
+        Ok(SwapChainSupportDetail {
             capabilities,
             formats,
             present_modes,
-        }
+        })
     }
 }
 
@@ -360,9 +241,9 @@ pub fn create_swapchain(
     window: &winit::window::Window,
     surface_stuff: &SurfaceStuff,
     queue_family: &QueueFamilyIndices,
-) -> SwapChainStuff {
+) -> Result<SwapChainStuff, VkPlaygroundError> {
 
-    let swapchain_support = query_swapchain_support(physical_device, surface_stuff);
+    let swapchain_support = query_swapchain_support(physical_device, surface_stuff)?;
 
     let surface_format = choose_swapchain_format(&swapchain_support.formats);
     let present_mode = choose_swapchain_present_mode(&swapchain_support.present_modes);
@@ -389,6 +270,7 @@ pub fn create_swapchain(
             (vk::SharingMode::EXCLUSIVE, 0, vec![])
         };
     
+    let image_usage = screenshot::swapchain_image_usage(&swapchain_support.capabilities);
     let swapchain_create_info = vk::SwapchainCreateInfoKHR {
         s_type: vk::StructureType::SWAPCHAIN_CREATE_INFO_KHR,
         p_next: ptr::null(),
@@ -398,7 +280,7 @@ pub fn create_swapchain(
         image_color_space: surface_format.color_space,
         image_format: surface_format.format,
         image_extent: extent,
-        image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        image_usage,
         image_sharing_mode,
         p_queue_family_indices: queue_family_indices.as_ptr(),
         queue_family_index_count,
@@ -414,22 +296,23 @@ pub fn create_swapchain(
     let swapchain = unsafe {
         swapchain_loader
             .create_swapchain(&swapchain_create_info, None)
-            .expect("Failed to create swapchain")
+            .vk_context("vkCreateSwapchainKHR", "Failed to create swapchain")?
     };
 
     let swapchain_images = unsafe {
         swapchain_loader
             .get_swapchain_images(swapchain)
-            .expect("Failed to get swapchain images")
+            .vk_context("vkGetSwapchainImagesKHR", "Failed to get swapchain images")?
     };
 
-    SwapChainStuff {
+    Ok(SwapChainStuff {
         swapchain_loader,
         swapchain,
         swapchain_format: surface_format.format,
         swapchain_extent: extent,
+        swapchain_usage: image_usage,
         swapchain_images
-    }
+    })
 }
 
 pub fn choose_swapchain_format(
@@ -493,10 +376,9 @@ pub fn choose_swapchain_extent(
 
 pub fn create_shader_module(
     device: &ash::Device,
-    spirv_path: &str) -> vk::ShaderModule {
-    // read the spirv file
-    let spirv_u8 = std::fs::read(spirv_path)
-        .unwrap_or_else(|_| panic!("Failed to read spirv file at {}!"));
+    spirv_path: &str) -> Result<vk::ShaderModule, VkPlaygroundError> {
+    // read the spirv file, or compile it first if it's GLSL
+    let spirv_u8 = shader_compiler::load_spirv(Path::new(spirv_path))?;
     
     // create shader module
     let spirv_u32 = {
@@ -517,10 +399,10 @@ pub fn create_shader_module(
     let vk_shader_module = unsafe {
         device
             .create_shader_module(&create_info, None)
-            .expect("Failed to create shader module!")
+            .vk_context("vkCreateShaderModule", "Failed to create shader module!")?
     };
 
-    vk_shader_module
+    Ok(vk_shader_module)
 }
 
 pub fn create_buffer(
@@ -528,8 +410,8 @@ pub fn create_buffer(
     size: vk::DeviceSize,
     usage: vk::BufferUsageFlags,
     required_memory_properties: vk::MemoryPropertyFlags,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
-) -> (vk::Buffer, vk::DeviceMemory) {
+    allocator: &Allocator,
+) -> Result<(vk::Buffer, Allocation), VkPlaygroundError> {
 
 
     let buffer_create_info = vk::BufferCreateInfo {
@@ -546,36 +428,13 @@ pub fn create_buffer(
     let buffer = unsafe {
         device
             .create_buffer(&buffer_create_info, None)
-            .expect("Failed to create Vertex Buffer")
-    };
-
-    let mem_requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
-    let memory_type = find_memory_type(
-        mem_requirements.memory_type_bits,
-        required_memory_properties,
-        device_memory_properties,
-    );
-
-    let allocate_info = vk::MemoryAllocateInfo {
-        s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
-        p_next: ptr::null(),
-        allocation_size: mem_requirements.size,
-        memory_type_index: memory_type,
-    };
-
-    let buffer_memory = unsafe {
-        device
-            .allocate_memory(&allocate_info, None)
-            .expect("Failed to allocate vertex buffer memory!")
+            .vk_context("vkCreateBuffer", "Failed to create Vertex Buffer")?
     };
 
-    unsafe {
-        device
-            .bind_buffer_memory(buffer, buffer_memory, 0)
-            .expect("Failed to bind Buffer");
-    }
+    let buffer_memory =
+        allocator.allocate_buffer_memory(device, buffer, required_memory_properties)?;
 
-    (buffer, buffer_memory)
+    Ok((buffer, buffer_memory))
 }
 
 pub fn copy_buffer(
@@ -585,8 +444,8 @@ pub fn copy_buffer(
     src_buffer: vk::Buffer,
     dst_buffer: vk::Buffer,
     size: vk::DeviceSize,
-) {
-    let command_buffer = begin_single_time_command(device, command_pool);
+) -> Result<(), VkPlaygroundError> {
+    let command_buffer = begin_single_time_command(device, command_pool)?;
 
     let copy_regions = [vk::BufferCopy {
         src_offset: 0,
@@ -598,13 +457,13 @@ pub fn copy_buffer(
         device.cmd_copy_buffer(command_buffer, src_buffer, dst_buffer, &copy_regions);
     }
 
-    end_single_time_command(device, command_pool, submit_queue, command_buffer);
-
+    end_single_time_command(device, command_pool, submit_queue, command_buffer)
 }
+
 pub fn begin_single_time_command(
     device: &ash::Device,
     command_pool: vk::CommandPool,
-) -> vk::CommandBuffer {
+) -> Result<vk::CommandBuffer, VkPlaygroundError> {
     let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
         s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
         p_next: ptr::null(),
@@ -616,7 +475,7 @@ pub fn begin_single_time_command(
     let command_buffer = unsafe {
         device
             .allocate_command_buffers(&command_buffer_allocate_info)
-            .expect("Failed to allocate Command Buffers!")
+            .vk_context("vkAllocateCommandBuffers", "Failed to allocate Command Buffers!")?
     }[0];
 
     let command_buffer_begin_info = vk::CommandBufferBeginInfo {
@@ -629,10 +488,13 @@ pub fn begin_single_time_command(
     unsafe {
         device
             .begin_command_buffer(command_buffer, &command_buffer_begin_info)
-            .expect("Failed to begin recording Command Buffer at beginning!");
+            .vk_context(
+                "vkBeginCommandBuffer",
+                "Failed to begin recording Command Buffer at beginning!",
+            )?;
     }
 
-    command_buffer
+    Ok(command_buffer)
 }
 
 pub fn end_single_time_command(
@@ -640,11 +502,11 @@ pub fn end_single_time_command(
     command_pool: vk::CommandPool,
     submit_queue: vk::Queue,
     command_buffer: vk::CommandBuffer,
-) {
+) -> Result<(), VkPlaygroundError> {
     unsafe {
         device
             .end_command_buffer(command_buffer)
-            .expect("Failed to record Command Buffer at Ending!");
+            .vk_context("vkEndCommandBuffer", "Failed to record Command Buffer at Ending!")?;
     }
 
     let buffers_to_submit = [command_buffer];
@@ -664,29 +526,32 @@ pub fn end_single_time_command(
     unsafe {
         device
             .queue_submit(submit_queue, &sumbit_infos, vk::Fence::null())
-            .expect("Failed to Queue Submit!");
+            .vk_context("vkQueueSubmit", "Failed to Queue Submit!")?;
         device
             .queue_wait_idle(submit_queue)
-            .expect("Failed to wait Queue idle!");
+            .vk_context("vkQueueWaitIdle", "Failed to wait Queue idle!")?;
         device.free_command_buffers(command_pool, &buffers_to_submit);
     }
 
+    Ok(())
 }
 
 pub fn find_memory_type(
     type_filter: u32,
     required_properties: vk::MemoryPropertyFlags,
     mem_properties: &vk::PhysicalDeviceMemoryProperties
-) -> u32 {
+) -> Result<u32, VkPlaygroundError> {
     for (i, memory_type) in mem_properties.memory_types.iter().enumerate() {
         if (type_filter & (1 << i)) > 0 && memory_type.property_flags.contains(required_properties)
         {
-            return i as u32;
+            return Ok(i as u32);
         }
     }
 
-    panic!("Failed to find suitable memory type!")
-
+    Err(VkPlaygroundError::unsupported(
+        "find_memory_type",
+        format!("Failed to find suitable memory type with {:?}!", required_properties),
+    ))
 }
 
 pub fn has_stencil_component(format: vk::Format) -> bool {
@@ -701,8 +566,8 @@ pub fn copy_buffer_to_image(
     image: vk::Image,
     width: u32,
     height: u32,
-) {
-    let command_buffer = begin_single_time_command(device, command_pool);
+) -> Result<(), VkPlaygroundError> {
+    let command_buffer = begin_single_time_command(device, command_pool)?;
 
     let buffer_image_regions = [vk::BufferImageCopy {
         image_subresource: vk::ImageSubresourceLayers {
@@ -732,13 +597,13 @@ pub fn copy_buffer_to_image(
         );
     }
 
-    end_single_time_command(device, command_pool, submit_queue, command_buffer);
+    end_single_time_command(device, command_pool, submit_queue, command_buffer)
 }
 
 pub fn find_depth_format(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-) -> vk::Format {
+) -> Result<vk::Format, VkPlaygroundError> {
     find_supported_format(
         instance,
         physical_device,
@@ -759,65 +624,52 @@ pub fn find_supported_format(
     candidate_formats: &[vk::Format],
     tiling: vk::ImageTiling,
     features: vk::FormatFeatureFlags,
-) -> vk::Format {
+) -> Result<vk::Format, VkPlaygroundError> {
     for &format in candidate_formats.iter() {
         let format_properties =
             unsafe { instance.get_physical_device_format_properties(physical_device, format) };
         if tiling == vk::ImageTiling::LINEAR
             && format_properties.linear_tiling_features.contains(features)
         {
-            return format.clone();
+            return Ok(format.clone());
         } else if tiling == vk::ImageTiling::OPTIMAL
             && format_properties.optimal_tiling_features.contains(features)
         {
-            return format.clone();
+            return Ok(format.clone());
         }
     }
 
-    panic!("Failed to find supported format!")
-
+    Err(VkPlaygroundError::unsupported(
+        "find_supported_format",
+        format!(
+            "None of {:?} support {:?} with {:?} tiling",
+            candidate_formats, features, tiling
+        ),
+    ))
 }
 
-pub fn load_model(model_path: &Path) -> (Vec<VertexV3>, Vec<u32>) {
-    let model_obj = tobj::load_obj(model_path).expect("Failed to load model object!");
-
-    let mut vertices = vec![];
-    let mut indices = vec![];
-
-    let (models, _) = model_obj;
-    for m in models.iter() {
-        let mesh = &m.mesh;
-
-        if mesh.texcoords.len() == 0 {
-            panic!("Missing texture coordinate for the model.")
-        }
-
-        let total_vertices_count = mesh.positions.len() / 3;
-        for i in 0..total_vertices_count {
-            let vertex = VertexV3 {
-                pos: [
-                    mesh.positions[i * 3],
-                    mesh.positions[i * 3 + 1],
-                    mesh.positions[i * 3 + 2],
-                    1.0,
-                ],
-                color: [1.0, 1.0, 1.0, 1.0],
-                tex_coord: [mesh.texcoords[i * 2], mesh.texcoords[i * 2 + 1]],
-            };
-            vertices.push(vertex);
-        }
-
-        indices = mesh.indices.clone();
-    }
+/// The vertices and indices of every sub-mesh of an OBJ model, see
+/// `obj::load_obj` for its materials and normals too.
+pub fn load_model(model_path: &Path) -> Result<(Vec<VertexV3>, Vec<u32>), VkPlaygroundError> {
+    let model = obj::load_obj(model_path)?;
+    let vertices = model
+        .vertices
+        .iter()
+        .map(|vertex| VertexV3 {
+            pos: [vertex.pos[0], vertex.pos[1], vertex.pos[2], 1.0],
+            color: [1.0, 1.0, 1.0, 1.0],
+            tex_coord: vertex.tex_coord,
+        })
+        .collect();
 
-    (vertices, indices)
+    Ok((vertices, model.indices))
 }
 
 pub fn check_mipmap_support(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
     image_format: vk::Format,
-) {
+) -> Result<(), VkPlaygroundError> {
     let format_properties =
         unsafe { instance.get_physical_device_format_properties(physical_device, image_format) };
 
@@ -826,7 +678,11 @@ pub fn check_mipmap_support(
         .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR);
 
     if is_sample_image_filter_linear_support == false {
-        panic!("Texture Image format does not support linear blitting!")
+        return Err(VkPlaygroundError::unsupported(
+            "check_mipmap_support",
+            format!("{:?} does not support linear blitting!", image_format),
+        ));
     }
 
+    Ok(())
 }
\ No newline at end of file
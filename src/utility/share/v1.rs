@@ -3,16 +3,30 @@ use image;
 use image::GenericImageView;
 
 use std::cmp::max;
-use std::ffi::CString;
 use std::path::Path;
 use std::ptr;
 
 use super::*;
 
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::pipeline::{self, PipelineBuilder};
+
 // creating render passes, descriptor pools, command buffers
 // and so on. A prime candidate for refactoring in future.
 
-pub fn create_render_pass(device: &ash::Device, surface_format: vk::Format) -> vk::RenderPass {
+pub fn create_render_pass(
+    device: &ash::Device,
+    surface_format: vk::Format,
+) -> Result<vk::RenderPass, VkPlaygroundError> {
+    create_render_pass_with_final_layout(device, surface_format, vk::ImageLayout::PRESENT_SRC_KHR)
+}
+
+// same as create_render_pass, for colour attachments that aren't presented
+pub fn create_render_pass_with_final_layout(
+    device: &ash::Device,
+    surface_format: vk::Format,
+    final_layout: vk::ImageLayout,
+) -> Result<vk::RenderPass, VkPlaygroundError> {
     let color_attachment = vk::AttachmentDescription {
         format: surface_format,
         flags: vk::AttachmentDescriptionFlags::empty(),
@@ -22,7 +36,7 @@ pub fn create_render_pass(device: &ash::Device, surface_format: vk::Format) -> v
         stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
         stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
         initial_layout: vk::ImageLayout::UNDEFINED,
-        final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+        final_layout,
     };
 
     let color_attachment_ref = vk::AttachmentReference {
@@ -70,220 +84,28 @@ pub fn create_render_pass(device: &ash::Device, surface_format: vk::Format) -> v
     unsafe {
         device
             .create_render_pass(&renderpass_create_info, None)
-            .expect("Failed to create render pass!")
+            .vk_context("vkCreateRenderPass", "Failed to create render pass!")
     }
 }
 
+// no vertex buffers or descriptors, for shaders that make up their own
+// vertices like the triangle demo's
 pub fn create_graphics_pipeline(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
     device: &ash::Device,
     render_pass: vk::RenderPass,
-    swapchain_extent: vk::Extent2D,
+    pipeline_cache: vk::PipelineCache,
     vert_path: &str,
     frag_path: &str,
-) -> (vk::Pipeline, vk::PipelineLayout) {
-    let vert_shader_module = create_shader_module(
-        device,
-        vert_path,
-    );
-    let frag_shader_module = create_shader_module(
-        device,
-        frag_path,
-    );
-
-    let main_function_name = CString::new("main").unwrap(); // the beginning function name in shader code.
-
-    let shader_stages = [
-        vk::PipelineShaderStageCreateInfo {
-            // Vertex Shader
-            s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineShaderStageCreateFlags::empty(),
-            module: vert_shader_module,
-            p_name: main_function_name.as_ptr(),
-            p_specialization_info: ptr::null(),
-            stage: vk::ShaderStageFlags::VERTEX,
-        },
-        vk::PipelineShaderStageCreateInfo {
-            // Fragment Shader
-            s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::PipelineShaderStageCreateFlags::empty(),
-            module: frag_shader_module,
-            p_name: main_function_name.as_ptr(),
-            p_specialization_info: ptr::null(),
-            stage: vk::ShaderStageFlags::FRAGMENT,
-        },
-    ];
-
-    let vertex_input_state_create_info = vk::PipelineVertexInputStateCreateInfo {
-        s_type: vk::StructureType::PIPELINE_VERTEX_INPUT_STATE_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: vk::PipelineVertexInputStateCreateFlags::empty(),
-        vertex_attribute_description_count: 0,
-        p_vertex_attribute_descriptions: ptr::null(),
-        vertex_binding_description_count: 0,
-        p_vertex_binding_descriptions: ptr::null(),
-    };
-    let vertex_input_assembly_state_info = vk::PipelineInputAssemblyStateCreateInfo {
-        s_type: vk::StructureType::PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO,
-        flags: vk::PipelineInputAssemblyStateCreateFlags::empty(),
-        p_next: ptr::null(),
-        primitive_restart_enable: vk::FALSE,
-        topology: vk::PrimitiveTopology::TRIANGLE_LIST,
-    };
-
-    let viewports = [vk::Viewport {
-        x: 0.0,
-        y: 0.0,
-        width: swapchain_extent.width as f32,
-        height: swapchain_extent.height as f32,
-        min_depth: 0.0,
-        max_depth: 1.0,
-    }];
-
-    let scissors = [vk::Rect2D {
-        offset: vk::Offset2D { x: 0, y: 0 },
-        extent: swapchain_extent,
-    }];
-
-    let viewport_state_create_info = vk::PipelineViewportStateCreateInfo {
-        s_type: vk::StructureType::PIPELINE_VIEWPORT_STATE_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: vk::PipelineViewportStateCreateFlags::empty(),
-        scissor_count: scissors.len() as u32,
-        p_scissors: scissors.as_ptr(),
-        viewport_count: viewports.len() as u32,
-        p_viewports: viewports.as_ptr(),
-    };
-
-    let rasterization_statue_create_info = vk::PipelineRasterizationStateCreateInfo {
-        s_type: vk::StructureType::PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: vk::PipelineRasterizationStateCreateFlags::empty(),
-        depth_clamp_enable: vk::FALSE,
-        cull_mode: vk::CullModeFlags::BACK,
-        front_face: vk::FrontFace::CLOCKWISE,
-        line_width: 1.0,
-        polygon_mode: vk::PolygonMode::FILL,
-        rasterizer_discard_enable: vk::FALSE,
-        depth_bias_clamp: 0.0,
-        depth_bias_constant_factor: 0.0,
-        depth_bias_enable: vk::FALSE,
-        depth_bias_slope_factor: 0.0,
-    };
-    let multisample_state_create_info = vk::PipelineMultisampleStateCreateInfo {
-        s_type: vk::StructureType::PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
-        flags: vk::PipelineMultisampleStateCreateFlags::empty(),
-        p_next: ptr::null(),
-        rasterization_samples: vk::SampleCountFlags::TYPE_1,
-        sample_shading_enable: vk::FALSE,
-        min_sample_shading: 0.0,
-        p_sample_mask: ptr::null(),
-        alpha_to_one_enable: vk::FALSE,
-        alpha_to_coverage_enable: vk::FALSE,
-    };
-
-    let stencil_state = vk::StencilOpState {
-        fail_op: vk::StencilOp::KEEP,
-        pass_op: vk::StencilOp::KEEP,
-        depth_fail_op: vk::StencilOp::KEEP,
-        compare_op: vk::CompareOp::ALWAYS,
-        compare_mask: 0,
-        write_mask: 0,
-        reference: 0,
-    };
-
-    let depth_state_create_info = vk::PipelineDepthStencilStateCreateInfo {
-        s_type: vk::StructureType::PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: vk::PipelineDepthStencilStateCreateFlags::empty(),
-        depth_test_enable: vk::FALSE,
-        depth_write_enable: vk::FALSE,
-        depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
-        depth_bounds_test_enable: vk::FALSE,
-        stencil_test_enable: vk::FALSE,
-        front: stencil_state,
-        back: stencil_state,
-        max_depth_bounds: 1.0,
-        min_depth_bounds: 0.0,
-    };
-
-    let color_blend_attachment_states = [vk::PipelineColorBlendAttachmentState {
-        blend_enable: vk::FALSE,
-        color_write_mask: vk::ColorComponentFlags::all(),
-        src_color_blend_factor: vk::BlendFactor::ONE,
-        dst_color_blend_factor: vk::BlendFactor::ZERO,
-        color_blend_op: vk::BlendOp::ADD,
-        src_alpha_blend_factor: vk::BlendFactor::ONE,
-        dst_alpha_blend_factor: vk::BlendFactor::ZERO,
-        alpha_blend_op: vk::BlendOp::ADD,
-    }];
-
-    let color_blend_state = vk::PipelineColorBlendStateCreateInfo {
-        s_type: vk::StructureType::PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: vk::PipelineColorBlendStateCreateFlags::empty(),
-        logic_op_enable: vk::FALSE,
-        logic_op: vk::LogicOp::COPY,
-        attachment_count: color_blend_attachment_states.len() as u32,
-        p_attachments: color_blend_attachment_states.as_ptr(),
-        blend_constants: [0.0, 0.0, 0.0, 0.0],
-    };
-
-    let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo {
-        s_type: vk::StructureType::PIPELINE_LAYOUT_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: vk::PipelineLayoutCreateFlags::empty(),
-        set_layout_count: 0,
-        p_set_layouts: ptr::null(),
-        push_constant_range_count: 0,
-        p_push_constant_ranges: ptr::null(),
-    };
-
-    let pipeline_layout = unsafe {
-        device
-            .create_pipeline_layout(&pipeline_layout_create_info, None)
-            .expect("Failed to create pipeline layout!")
-    };
-
-    let graphic_pipeline_create_infos = [vk::GraphicsPipelineCreateInfo {
-        s_type: vk::StructureType::GRAPHICS_PIPELINE_CREATE_INFO,
-        p_next: ptr::null(),
-        flags: vk::PipelineCreateFlags::empty(),
-        stage_count: shader_stages.len() as u32,
-        p_stages: shader_stages.as_ptr(),
-        p_vertex_input_state: &vertex_input_state_create_info,
-        p_input_assembly_state: &vertex_input_assembly_state_info,
-        p_tessellation_state: ptr::null(),
-        p_viewport_state: &viewport_state_create_info,
-        p_rasterization_state: &rasterization_statue_create_info,
-        p_multisample_state: &multisample_state_create_info,
-        p_depth_stencil_state: &depth_state_create_info,
-        p_color_blend_state: &color_blend_state,
-        p_dynamic_state: ptr::null(),
-        layout: pipeline_layout,
-        render_pass,
-        subpass: 0,
-        base_pipeline_handle: vk::Pipeline::null(),
-        base_pipeline_index: -1,
-    }];
-
-    let graphics_pipelines = unsafe {
-        device
-            .create_graphics_pipelines(
-                vk::PipelineCache::null(),
-                &graphic_pipeline_create_infos,
-                None,
-            )
-            .expect("Failed to create Graphics Pipeline!.")
-    };
-
-    unsafe {
-        device.destroy_shader_module(vert_shader_module, None);
-        device.destroy_shader_module(frag_shader_module, None);
-    }
-
-    (graphics_pipelines[0], pipeline_layout)
+) -> Result<(vk::Pipeline, vk::PipelineLayout), VkPlaygroundError> {
+    PipelineBuilder::new()
+        .vertex_shader(vert_path)
+        .fragment_shader(frag_path)
+        .dynamic_viewport()
+        .front_face(vk::FrontFace::CLOCKWISE)
+        .pipeline_cache(pipeline_cache)
+        .build(instance, physical_device, device, render_pass)
 }
 
 pub fn create_framebuffers(
@@ -291,7 +113,7 @@ pub fn create_framebuffers(
     render_pass: vk::RenderPass,
     image_views: &Vec<vk::ImageView>,
     swapchain_extent: vk::Extent2D,
-) -> Vec<vk::Framebuffer> {
+) -> Result<Vec<vk::Framebuffer>, VkPlaygroundError> {
     let mut framebuffers = vec![];
 
     for &image_view in image_views.iter() {
@@ -312,19 +134,19 @@ pub fn create_framebuffers(
         let framebuffer = unsafe {
             device
                 .create_framebuffer(&framebuffer_create_info, None)
-                .expect("Failed to create Framebuffer!")
+                .vk_context("vkCreateFramebuffer", "Failed to create Framebuffer!")?
         };
 
         framebuffers.push(framebuffer);
     }
 
-    framebuffers
+    Ok(framebuffers)
 }
 
 pub fn create_command_pool(
     device: &ash::Device,
     queue_families: &QueueFamilyIndices,
-) -> vk::CommandPool {
+) -> Result<vk::CommandPool, VkPlaygroundError> {
     let command_pool_create_info = vk::CommandPoolCreateInfo {
         s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
         p_next: ptr::null(),
@@ -335,90 +157,61 @@ pub fn create_command_pool(
     unsafe {
         device
             .create_command_pool(&command_pool_create_info, None)
-            .expect("Failed to create Command Pool!")
+            .vk_context("vkCreateCommandPool", "Failed to create Command Pool!")
     }
 }
 
-pub fn create_command_buffers(
+/// Records drawing the triangle into `framebuffer`, for a command buffer
+/// that's already begun.
+pub fn record_command_buffer(
     device: &ash::Device,
-    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
     graphics_pipeline: vk::Pipeline,
-    framebuffers: &Vec<vk::Framebuffer>,
+    framebuffer: vk::Framebuffer,
     render_pass: vk::RenderPass,
     surface_extent: vk::Extent2D,
-) -> Vec<vk::CommandBuffer> {
-    let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
-        s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
-        p_next: ptr::null(),
-        command_buffer_count: framebuffers.len() as u32,
-        command_pool,
-        level: vk::CommandBufferLevel::PRIMARY,
-    };
+) {
+    let clear_values = [vk::ClearValue {
+        color: vk::ClearColorValue {
+            float32: [0.0, 0.0, 0.0, 1.0],
+        },
+    }];
 
-    let command_buffers = unsafe {
-        device
-            .allocate_command_buffers(&command_buffer_allocate_info)
-            .expect("Failed to allocate Command Buffers!")
+    let render_pass_begin_info = vk::RenderPassBeginInfo {
+        s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+        p_next: ptr::null(),
+        render_pass,
+        framebuffer,
+        render_area: vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: surface_extent,
+        },
+        clear_value_count: clear_values.len() as u32,
+        p_clear_values: clear_values.as_ptr(),
     };
 
-    for (i, &command_buffer) in command_buffers.iter().enumerate() {
-        let command_buffer_begin_info = vk::CommandBufferBeginInfo {
-            s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
-            p_next: ptr::null(),
-            p_inheritance_info: ptr::null(),
-            flags: vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
-        };
-
-        unsafe {
-            device
-                .begin_command_buffer(command_buffer, &command_buffer_begin_info)
-                .expect("Failed to begin recording Command Buffer at beginning!");
-        }
-
-        let clear_values = [vk::ClearValue {
-            color: vk::ClearColorValue {
-                float32: [0.0, 0.0, 0.0, 1.0],
-            },
-        }];
-
-        let render_pass_begin_info = vk::RenderPassBeginInfo {
-            s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
-            p_next: ptr::null(),
-            render_pass,
-            framebuffer: framebuffers[i],
-            render_area: vk::Rect2D {
-                offset: vk::Offset2D { x: 0, y: 0 },
-                extent: surface_extent,
-            },
-            clear_value_count: clear_values.len() as u32,
-            p_clear_values: clear_values.as_ptr(),
-        };
-
-        unsafe {
-            device.cmd_begin_render_pass(
-                command_buffer,
-                &render_pass_begin_info,
-                vk::SubpassContents::INLINE,
-            );
-            device.cmd_bind_pipeline(
-                command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                graphics_pipeline,
-            );
-            device.cmd_draw(command_buffer, 3, 1, 0, 0);
-
-            device.cmd_end_render_pass(command_buffer);
+    unsafe {
+        device.cmd_begin_render_pass(
+            command_buffer,
+            &render_pass_begin_info,
+            vk::SubpassContents::INLINE,
+        );
+        device.cmd_bind_pipeline(
+            command_buffer,
+            vk::PipelineBindPoint::GRAPHICS,
+            graphics_pipeline,
+        );
+        pipeline::set_viewport(device, command_buffer, surface_extent);
+        device.cmd_draw(command_buffer, 3, 1, 0, 0);
 
-            device
-                .end_command_buffer(command_buffer)
-                .expect("Failed to record Command Buffer at Ending!");
-        }
+        device.cmd_end_render_pass(command_buffer);
     }
-
-    command_buffers
 }
 
-pub fn create_sync_objects(device: &ash::Device, max_frame_in_flight: usize) -> SyncObjects {
+pub fn create_sync_objects(
+    device: &ash::Device,
+    max_frame_in_flight: usize,
+) -> Result<SyncObjects, VkPlaygroundError> {
     let mut sync_objects = SyncObjects {
         image_available_semaphores: vec![],
         render_finished_semaphores: vec![],
@@ -441,13 +234,13 @@ pub fn create_sync_objects(device: &ash::Device, max_frame_in_flight: usize) ->
         unsafe {
             let image_available_semaphore = device
                 .create_semaphore(&semaphore_create_info, None)
-                .expect("Failed to create Semaphore Object!");
+                .vk_context("vkCreateSemaphore", "Failed to create Semaphore Object!")?;
             let render_finished_semaphore = device
                 .create_semaphore(&semaphore_create_info, None)
-                .expect("Failed to create Semaphore Object!");
+                .vk_context("vkCreateSemaphore", "Failed to create Semaphore Object!")?;
             let inflight_fence = device
                 .create_fence(&fence_create_info, None)
-                .expect("Failed to create Fence Object!");
+                .vk_context("vkCreateFence", "Failed to create Fence Object!")?;
 
             sync_objects
                 .image_available_semaphores
@@ -459,16 +252,16 @@ pub fn create_sync_objects(device: &ash::Device, max_frame_in_flight: usize) ->
         }
     }
 
-    sync_objects
+    Ok(sync_objects)
 }
 
 pub fn create_vertex_buffer<T>(
     device: &ash::Device,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allocator: &Allocator,
     command_pool: vk::CommandPool,
     submit_queue: vk::Queue,
     data: &[T],
-) -> (vk::Buffer, vk::DeviceMemory) {
+) -> Result<(vk::Buffer, Allocation), VkPlaygroundError> {
     let buffer_size = ::std::mem::size_of_val(data) as vk::DeviceSize;
 
     let (staging_buffer, staging_buffer_memory) = create_buffer(
@@ -476,31 +269,18 @@ pub fn create_vertex_buffer<T>(
         buffer_size,
         vk::BufferUsageFlags::TRANSFER_SRC,
         vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        &device_memory_properties,
-    );
+        allocator,
+    )?;
 
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                staging_buffer_memory,
-                0,
-                buffer_size,
-                vk::MemoryMapFlags::empty(),
-            )
-            .expect("Failed to Map Memory") as *mut T;
-
-        data_ptr.copy_from_nonoverlapping(data.as_ptr(), data.len());
-
-        device.unmap_memory(staging_buffer_memory);
-    }
+    staging_buffer_memory.write(data);
 
     let (vertex_buffer, vertex_buffer_memory) = create_buffer(
         device,
         buffer_size,
         vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::VERTEX_BUFFER,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        &device_memory_properties,
-    );
+        allocator,
+    )?;
 
     copy_buffer(
         device,
@@ -509,23 +289,22 @@ pub fn create_vertex_buffer<T>(
         staging_buffer,
         vertex_buffer,
         buffer_size,
-    );
+    )?;
 
     unsafe {
         device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
     }
 
-    (vertex_buffer, vertex_buffer_memory)
+    Ok((vertex_buffer, vertex_buffer_memory))
 }
 
 pub fn create_index_buffer(
     device: &ash::Device,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allocator: &Allocator,
     command_pool: vk::CommandPool,
     submit_queue: vk::Queue,
     data: &[u32],
-) -> (vk::Buffer, vk::DeviceMemory) {
+) -> Result<(vk::Buffer, Allocation), VkPlaygroundError> {
     let buffer_size = ::std::mem::size_of_val(data) as vk::DeviceSize;
 
     let (staging_buffer, staging_buffer_memory) = create_buffer(
@@ -533,31 +312,18 @@ pub fn create_index_buffer(
         buffer_size,
         vk::BufferUsageFlags::TRANSFER_SRC,
         vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        &device_memory_properties,
-    );
+        allocator,
+    )?;
 
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                staging_buffer_memory,
-                0,
-                buffer_size,
-                vk::MemoryMapFlags::empty(),
-            )
-            .expect("Failed to Map Memory") as *mut u32;
-
-        data_ptr.copy_from_nonoverlapping(data.as_ptr(), data.len());
-
-        device.unmap_memory(staging_buffer_memory);
-    }
+    staging_buffer_memory.write(data);
 
     let (index_buffer, index_buffer_memory) = create_buffer(
         device,
         buffer_size,
         vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::INDEX_BUFFER,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        &device_memory_properties,
-    );
+        allocator,
+    )?;
 
     copy_buffer(
         device,
@@ -566,20 +332,19 @@ pub fn create_index_buffer(
         staging_buffer,
         index_buffer,
         buffer_size,
-    );
+    )?;
 
     unsafe {
         device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
     }
 
-    (index_buffer, index_buffer_memory)
+    Ok((index_buffer, index_buffer_memory))
 }
 
 pub fn create_descriptor_pool(
     device: &ash::Device,
     swapchain_images_size: usize,
-) -> vk::DescriptorPool {
+) -> Result<vk::DescriptorPool, VkPlaygroundError> {
     let pool_sizes = [vk::DescriptorPoolSize {
         ty: vk::DescriptorType::UNIFORM_BUFFER,
         descriptor_count: swapchain_images_size as u32,
@@ -597,7 +362,7 @@ pub fn create_descriptor_pool(
     unsafe {
         device
             .create_descriptor_pool(&descriptor_pool_create_info, None)
-            .expect("Failed to create Descriptor Pool!")
+            .vk_context("vkCreateDescriptorPool", "Failed to create Descriptor Pool!")
     }
 }
 
@@ -607,7 +372,7 @@ pub fn create_descriptor_sets(
     descriptor_set_layout: vk::DescriptorSetLayout,
     uniforms_buffers: &Vec<vk::Buffer>,
     swapchain_images_size: usize,
-) -> Vec<vk::DescriptorSet> {
+) -> Result<Vec<vk::DescriptorSet>, VkPlaygroundError> {
     let mut layouts: Vec<vk::DescriptorSetLayout> = vec![];
     for _ in 0..swapchain_images_size {
         layouts.push(descriptor_set_layout);
@@ -624,7 +389,7 @@ pub fn create_descriptor_sets(
     let descriptor_sets = unsafe {
         device
             .allocate_descriptor_sets(&descriptor_set_allocate_info)
-            .expect("Failed to allocate descriptor sets!")
+            .vk_context("vkAllocateDescriptorSets", "Failed to allocate descriptor sets!")?
     };
 
     for (i, &descritptor_set) in descriptor_sets.iter().enumerate() {
@@ -652,10 +417,12 @@ pub fn create_descriptor_sets(
         }
     }
 
-    descriptor_sets
+    Ok(descriptor_sets)
 }
 
-pub fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+pub fn create_descriptor_set_layout(
+    device: &ash::Device,
+) -> Result<vk::DescriptorSetLayout, VkPlaygroundError> {
     let ubo_layout_bindings = [vk::DescriptorSetLayoutBinding {
         binding: 0,
         descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
@@ -675,15 +442,15 @@ pub fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLa
     unsafe {
         device
             .create_descriptor_set_layout(&ubo_layout_create_info, None)
-            .expect("Failed to create Descriptor Set Layout!")
+            .vk_context("vkCreateDescriptorSetLayout", "Failed to create Descriptor Set Layout!")
     }
 }
 
 pub fn create_uniform_buffers(
     device: &ash::Device,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allocator: &Allocator,
     swapchain_image_count: usize,
-) -> (Vec<vk::Buffer>, Vec<vk::DeviceMemory>) {
+) -> Result<(Vec<vk::Buffer>, Vec<Allocation>), VkPlaygroundError> {
     let buffer_size = ::std::mem::size_of::<UniformBufferObject>();
 
     let mut uniform_buffers = vec![];
@@ -695,13 +462,13 @@ pub fn create_uniform_buffers(
             buffer_size as u64,
             vk::BufferUsageFlags::UNIFORM_BUFFER,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-            device_memory_properties,
-        );
+            allocator,
+        )?;
         uniform_buffers.push(uniform_buffer);
         uniform_buffers_memory.push(uniform_buffer_memory);
     }
 
-    (uniform_buffers, uniform_buffers_memory)
+    Ok((uniform_buffers, uniform_buffers_memory))
 }
 
 pub fn create_image(
@@ -714,8 +481,8 @@ pub fn create_image(
     tiling: vk::ImageTiling,
     usage: vk::ImageUsageFlags,
     required_memory_properties: vk::MemoryPropertyFlags,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
-) -> (vk::Image, vk::DeviceMemory) {
+    allocator: &Allocator,
+) -> Result<(vk::Image, Allocation), VkPlaygroundError> {
     let image_create_info = vk::ImageCreateInfo {
         s_type: vk::StructureType::IMAGE_CREATE_INFO,
         p_next: ptr::null(),
@@ -741,34 +508,17 @@ pub fn create_image(
     let texture_image = unsafe {
         device
             .create_image(&image_create_info, None)
-            .expect("Failed to create Texture Image!")
-    };
-
-    let image_memory_requirement = unsafe { device.get_image_memory_requirements(texture_image) };
-    let memory_allocate_info = vk::MemoryAllocateInfo {
-        s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
-        p_next: ptr::null(),
-        allocation_size: image_memory_requirement.size,
-        memory_type_index: find_memory_type(
-            image_memory_requirement.memory_type_bits,
-            required_memory_properties,
-            device_memory_properties,
-        ),
+            .vk_context("vkCreateImage", "Failed to create Texture Image!")?
     };
 
-    let texture_image_memory = unsafe {
-        device
-            .allocate_memory(&memory_allocate_info, None)
-            .expect("Failed to allocate Texture Image memory!")
-    };
-
-    unsafe {
-        device
-            .bind_image_memory(texture_image, texture_image_memory, 0)
-            .expect("Failed to bind image memory!");
-    }
+    let texture_image_memory = allocator.allocate_image_memory(
+        device,
+        texture_image,
+        tiling,
+        required_memory_properties,
+    )?;
 
-    (texture_image, texture_image_memory)
+    Ok((texture_image, texture_image_memory))
 }
 
 pub fn transition_image_layout(
@@ -780,8 +530,8 @@ pub fn transition_image_layout(
     old_layout: vk::ImageLayout,
     new_layout: vk::ImageLayout,
     mip_levels: u32,
-) {
-    let command_buffer = begin_single_time_command(device, command_pool);
+) -> Result<(), VkPlaygroundError> {
+    let command_buffer = begin_single_time_command(device, command_pool)?;
 
     let src_access_mask;
     let dst_access_mask;
@@ -811,7 +561,10 @@ pub fn transition_image_layout(
         source_stage = vk::PipelineStageFlags::TOP_OF_PIPE;
         destination_stage = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
     } else {
-        panic!("Unsupported layout transition!")
+        return Err(VkPlaygroundError::unsupported(
+            "transition_image_layout",
+            format!("Unsupported layout transition {:?} -> {:?}!", old_layout, new_layout),
+        ));
     }
 
     let image_barriers = [vk::ImageMemoryBarrier {
@@ -845,15 +598,15 @@ pub fn transition_image_layout(
         );
     }
 
-    end_single_time_command(device, command_pool, submit_queue, command_buffer);
+    end_single_time_command(device, command_pool, submit_queue, command_buffer)
 }
 
 pub fn create_image_views(
     device: &ash::Device,
     surface_format: vk::Format,
     images: &Vec<vk::Image>,
-) -> Vec<vk::ImageView> {
-    let swapchain_imageviews: Vec<vk::ImageView> = images
+) -> Result<Vec<vk::ImageView>, VkPlaygroundError> {
+    let swapchain_imageviews = images
         .iter()
         .map(|&image| {
             create_image_view(
@@ -864,9 +617,9 @@ pub fn create_image_views(
                 1,
             )
         })
-        .collect();
+        .collect::<Result<Vec<vk::ImageView>, VkPlaygroundError>>()?;
 
-    swapchain_imageviews
+    Ok(swapchain_imageviews)
 }
 
 pub fn create_image_view(
@@ -875,7 +628,7 @@ pub fn create_image_view(
     format: vk::Format,
     aspect_flags: vk::ImageAspectFlags,
     mip_levels: u32,
-) -> vk::ImageView {
+) -> Result<vk::ImageView, VkPlaygroundError> {
     let imageview_create_info = vk::ImageViewCreateInfo {
         s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
         p_next: ptr::null(),
@@ -901,7 +654,7 @@ pub fn create_image_view(
     unsafe {
         device
             .create_image_view(&imageview_create_info, None)
-            .expect("Failed to create Image View!")
+            .vk_context("vkCreateImageView", "Failed to create Image View!")
     }
 }
 
@@ -909,7 +662,7 @@ pub fn create_texture_image_view(
     device: &ash::Device,
     texture_image: vk::Image,
     mip_levels: u32,
-) -> vk::ImageView {
+) -> Result<vk::ImageView, VkPlaygroundError> {
     create_image_view(
         device,
         texture_image,
@@ -919,7 +672,7 @@ pub fn create_texture_image_view(
     )
 }
 
-pub fn create_texture_sampler(device: &ash::Device) -> vk::Sampler {
+pub fn create_texture_sampler(device: &ash::Device) -> Result<vk::Sampler, VkPlaygroundError> {
     let sampler_create_info = vk::SamplerCreateInfo {
         s_type: vk::StructureType::SAMPLER_CREATE_INFO,
         p_next: ptr::null(),
@@ -944,7 +697,7 @@ pub fn create_texture_sampler(device: &ash::Device) -> vk::Sampler {
     unsafe {
         device
             .create_sampler(&sampler_create_info, None)
-            .expect("Failed to create Sampler!")
+            .vk_context("vkCreateSampler", "Failed to create Sampler!")
     }
 }
 
@@ -952,10 +705,14 @@ pub fn create_texture_image(
     device: &ash::Device,
     command_pool: vk::CommandPool,
     submit_queue: vk::Queue,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allocator: &Allocator,
     image_path: &Path,
-) -> (vk::Image, vk::DeviceMemory) {
-    let mut image_object = image::open(image_path).unwrap(); // this function is slow in debug mode.
+) -> Result<(vk::Image, Allocation), VkPlaygroundError> {
+    // this function is slow in debug mode.
+    let mut image_object = image::open(image_path).map_err(|source| VkPlaygroundError::Image {
+        path: image_path.to_path_buf(),
+        source,
+    })?;
     image_object = image_object.flipv();
     let (image_width, image_height) = (image_object.width(), image_object.height());
     let image_data = match &image_object {
@@ -970,7 +727,10 @@ pub fn create_texture_image(
         (::std::mem::size_of::<u8>() as u32 * image_width * image_height * 4) as vk::DeviceSize;
 
     if image_size <= 0 {
-        panic!("Failed to load texture image!")
+        return Err(VkPlaygroundError::unsupported(
+            "create_texture_image",
+            format!("{} is empty", image_path.display()),
+        ));
     }
 
     let (staging_buffer, staging_buffer_memory) = create_buffer(
@@ -978,23 +738,10 @@ pub fn create_texture_image(
         image_size,
         vk::BufferUsageFlags::TRANSFER_SRC,
         vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        device_memory_properties,
-    );
-
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                staging_buffer_memory,
-                0,
-                image_size,
-                vk::MemoryMapFlags::empty(),
-            )
-            .expect("Failed to Map Memory") as *mut u8;
-
-        data_ptr.copy_from_nonoverlapping(image_data.as_ptr(), image_data.len());
+        allocator,
+    )?;
 
-        device.unmap_memory(staging_buffer_memory);
-    }
+    staging_buffer_memory.write(&image_data);
 
     let (texture_image, texture_image_memory) = create_image(
         device,
@@ -1006,8 +753,8 @@ pub fn create_texture_image(
         vk::ImageTiling::OPTIMAL,
         vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        device_memory_properties,
-    );
+        allocator,
+    )?;
 
     transition_image_layout(
         device,
@@ -1018,7 +765,7 @@ pub fn create_texture_image(
         vk::ImageLayout::UNDEFINED,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
         1,
-    );
+    )?;
 
     copy_buffer_to_image(
         device,
@@ -1028,7 +775,7 @@ pub fn create_texture_image(
         texture_image,
         image_width,
         image_height,
-    );
+    )?;
 
     transition_image_layout(
         device,
@@ -1039,14 +786,13 @@ pub fn create_texture_image(
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
         vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
         1,
-    );
+    )?;
 
     unsafe {
         device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
     }
 
-    (texture_image, texture_image_memory)
+    Ok((texture_image, texture_image_memory))
 }
 
 pub fn create_depth_resources(
@@ -1056,10 +802,10 @@ pub fn create_depth_resources(
     _command_pool: vk::CommandPool,
     _submit_queue: vk::Queue,
     swapchain_extent: vk::Extent2D,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allocator: &Allocator,
     msaa_samples: vk::SampleCountFlags,
-) -> (vk::Image, vk::ImageView, vk::DeviceMemory) {
-    let depth_format = find_depth_format(instance, physical_device);
+) -> Result<(vk::Image, vk::ImageView, Allocation), VkPlaygroundError> {
+    let depth_format = find_depth_format(instance, physical_device)?;
     let (depth_image, depth_image_memory) = create_image(
         device,
         swapchain_extent.width,
@@ -1070,17 +816,17 @@ pub fn create_depth_resources(
         vk::ImageTiling::OPTIMAL,
         vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        device_memory_properties,
-    );
+        allocator,
+    )?;
     let depth_image_view = create_image_view(
         device,
         depth_image,
         depth_format,
         vk::ImageAspectFlags::DEPTH,
         1,
-    );
+    )?;
 
-    (depth_image, depth_image_view, depth_image_memory)
+    Ok((depth_image, depth_image_view, depth_image_memory))
 }
 
 pub fn generate_mipmaps(
@@ -1091,8 +837,8 @@ pub fn generate_mipmaps(
     tex_width: u32,
     tex_height: u32,
     mip_levels: u32,
-) {
-    let command_buffer = begin_single_time_command(device, command_pool);
+) -> Result<(), VkPlaygroundError> {
+    let command_buffer = begin_single_time_command(device, command_pool)?;
 
     let mut image_barrier = vk::ImageMemoryBarrier {
         s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
@@ -1217,5 +963,5 @@ pub fn generate_mipmaps(
         );
     }
 
-    end_single_time_command(device, command_pool, submit_queue, command_buffer);
+    end_single_time_command(device, command_pool, submit_queue, command_buffer)
 }
\ No newline at end of file
@@ -4,14 +4,20 @@ use std::ptr;
 
 use super::*;
 
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+
+// `uniform_type` is UNIFORM_BUFFER, or UNIFORM_BUFFER_DYNAMIC for a
+// transform uniform in a `UniformRing`
+
 pub fn create_descriptor_pool(
     device: &ash::Device,
     swapchain_images_size: usize,
-) -> vk::DescriptorPool {
+    uniform_type: vk::DescriptorType,
+) -> Result<vk::DescriptorPool, VkPlaygroundError> {
     let pool_sizes = [
         vk::DescriptorPoolSize {
             // transform descriptor ppol
-            ty: vk::DescriptorType::UNIFORM_BUFFER,
+            ty: uniform_type,
             descriptor_count: swapchain_images_size as u32, 
         },
         vk::DescriptorPoolSize {
@@ -33,7 +39,7 @@ pub fn create_descriptor_pool(
     unsafe {
         device
             .create_descriptor_pool(&descriptor_pool_create_info, None)
-            .expect("Failed to create descriptor pool!")
+            .vk_context("vkCreateDescriptorPool", "Failed to create descriptor pool!")
     }
 }
 
@@ -44,33 +50,30 @@ pub fn create_descriptor_sets(
     uniform_buffers: &Vec<vk::Buffer>,
     texture_image_view: vk::ImageView,
     texture_sampler: vk::Sampler,
-    swapchain_images_size: usize,
-) -> Vec<vk::DescriptorSet> {
-
-    let mut layouts: Vec<vk::DescriptorSetLayout> = vec![];
-    for _ in 0..swapchain_images_size {
-        layouts.push(descriptor_set_layout);
-    }
+    uniform_type: vk::DescriptorType,
+) -> Result<Vec<vk::DescriptorSet>, VkPlaygroundError> {
+    // a set for each uniform buffer
+    let layouts = vec![descriptor_set_layout; uniform_buffers.len()];
 
     let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfo {
         s_type: vk::StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
         p_next: ptr::null(),
         descriptor_pool,
-        descriptor_set_count: swapchain_images_size as u32,
+        descriptor_set_count: layouts.len() as u32,
         p_set_layouts: layouts.as_ptr(),
     };
 
     let descriptor_sets = unsafe {
         device
             .allocate_descriptor_sets(&descriptor_set_allocate_info)
-            .expect("Failed to allocate descriptor sets")
+            .vk_context("vkAllocateDescriptorSets", "Failed to allocate descriptor sets")?
     };
 
     for (i, &descriptor_set) in descriptor_sets.iter().enumerate() {
         let descriptor_buffer_infos = [vk::DescriptorBufferInfo {
             buffer: uniform_buffers[i],
             offset: 0,
-            range: ::std::mem::size_of::<UniformBufferObject>() as u64,
+            range: ::std::mem::size_of::<CameraUniformBufferObject>() as u64,
         }];
 
         let descriptor_image_infos = [vk::DescriptorImageInfo {
@@ -88,7 +91,7 @@ pub fn create_descriptor_sets(
                 dst_binding: 0,
                 dst_array_element: 0,
                 descriptor_count: 1,
-                descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+                descriptor_type: uniform_type,
                 p_image_info: ptr::null(),
                 p_buffer_info: descriptor_buffer_infos.as_ptr(),
                 p_texel_buffer_view: ptr::null(),
@@ -113,16 +116,18 @@ pub fn create_descriptor_sets(
         }
     }
 
-    descriptor_sets
-
+    Ok(descriptor_sets)
 }
 
-pub fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+pub fn create_descriptor_set_layout(
+    device: &ash::Device,
+    uniform_type: vk::DescriptorType,
+) -> Result<vk::DescriptorSetLayout, VkPlaygroundError> {
     let ubo_layout_bindings = [
         vk::DescriptorSetLayoutBinding {
             // transform uniform
             binding: 0,
-            descriptor_type: vk::DescriptorType::UNIFORM_BUFFER,
+            descriptor_type: uniform_type,
             descriptor_count: 1,
             stage_flags: vk::ShaderStageFlags::VERTEX,
             p_immutable_samplers: ptr::null(),
@@ -148,6 +153,6 @@ pub fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLa
     unsafe {
         device
             .create_descriptor_set_layout(&ubo_layout_create_info, None)
-            .expect("Failed to create descriptor set layout")
+            .vk_context("vkCreateDescriptorSetLayout", "Failed to create descriptor set layout")
     }
 }
\ No newline at end of file
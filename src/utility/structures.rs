@@ -7,7 +7,7 @@ use memoffset::offset_of;
 // such as queues, swapchains, etc.
 
 pub struct DeviceExtension {
-    pub names: [&'static str; 1],
+    pub names: &'static [&'static str],
 }
 
 pub struct SurfaceStuff {
@@ -24,6 +24,7 @@ pub struct SwapChainStuff {
     pub swapchain_images: Vec<vk::Image>,
     pub swapchain_format: vk::Format,
     pub swapchain_extent: vk::Extent2D,
+    pub swapchain_usage: vk::ImageUsageFlags,
 }
 
 pub struct SwapChainSupportDetail {
@@ -35,6 +36,10 @@ pub struct SwapChainSupportDetail {
 pub struct QueueFamilyIndices {
     pub graphics_family: Option<u32>,
     pub present_family: Option<u32>,
+    // a compute-only family when there is one, else the graphics family
+    pub compute_family: Option<u32>,
+    // a transfer-only family when there is one, else the graphics family
+    pub transfer_family: Option<u32>,
 }
 
 impl QueueFamilyIndices {
@@ -42,12 +47,19 @@ impl QueueFamilyIndices {
        QueueFamilyIndices {
            graphics_family: None,
            present_family: None,
+           compute_family: None,
+           transfer_family: None,
        } 
     }
 
     pub fn is_complete(&self) -> bool {
         self.graphics_family.is_some() && self.present_family.is_some()
     }
+
+    // headless rendering never presents, so a graphics queue is enough
+    pub fn is_headless_complete(&self) -> bool {
+        self.graphics_family.is_some()
+    }
 }
 
 pub struct SyncObjects {
@@ -64,6 +76,21 @@ pub struct UniformBufferObject {
     pub proj: Matrix4<f32>,
 }
 
+/// `UniformBufferObject` without the model matrix, for shaders such as the
+/// cube's that take it per object from `ModelPushConstants` instead.
+#[repr(C)]
+#[derive(Clone, Debug, Copy)]
+pub struct CameraUniformBufferObject {
+    pub view: Matrix4<f32>,
+    pub proj: Matrix4<f32>,
+}
+
+#[repr(C)]
+#[derive(Clone, Debug, Copy)]
+pub struct ModelPushConstants {
+    pub model: Matrix4<f32>,
+}
+
 #[repr(C)]
 #[derive(Clone, Debug, Copy)]
 pub struct VertexV1 {
@@ -145,6 +172,105 @@ impl VertexV3 {
     }
 }
 
+/// A vertex of a loaded model, see `obj::load_obj`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshVertex {
+    pub pos: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2],
+}
+
+impl MeshVertex {
+    pub fn get_binding_descriptions() -> [vk::VertexInputBindingDescription; 1] {
+        [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: std::mem::size_of::<Self>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }]
+    }
+
+    pub fn get_attribute_descriptions() -> [vk::VertexInputAttributeDescription; 3] {
+        [
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 0,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: offset_of!(Self, pos) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 1,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: offset_of!(Self, normal) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 2,
+                format: vk::Format::R32G32_SFLOAT,
+                offset: offset_of!(Self, tex_coord) as u32,
+            },
+        ]
+    }
+}
+
+/// A vertex of a glTF mesh, see `gltf::load_gltf`. The tangent's w is the
+/// handedness of the bitangent, +1 or -1.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PbrVertex {
+    pub pos: [f32; 3],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 4],
+    pub tex_coord: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl PbrVertex {
+    pub fn get_binding_descriptions() -> [vk::VertexInputBindingDescription; 1] {
+        [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: std::mem::size_of::<Self>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }]
+    }
+
+    pub fn get_attribute_descriptions() -> [vk::VertexInputAttributeDescription; 5] {
+        [
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 0,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: offset_of!(Self, pos) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 1,
+                format: vk::Format::R32G32B32_SFLOAT,
+                offset: offset_of!(Self, normal) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 2,
+                format: vk::Format::R32G32B32A32_SFLOAT,
+                offset: offset_of!(Self, tangent) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 3,
+                format: vk::Format::R32G32_SFLOAT,
+                offset: offset_of!(Self, tex_coord) as u32,
+            },
+            vk::VertexInputAttributeDescription {
+                binding: 0,
+                location: 4,
+                format: vk::Format::R32G32B32A32_SFLOAT,
+                offset: offset_of!(Self, color) as u32,
+            },
+        ]
+    }
+}
+
 pub const RECT_VERTICES_DATA: [VertexV1; 4] = [
     VertexV1 {
         pos: [-0.5, -0.5],
@@ -1,7 +1,10 @@
 use std::ffi::CStr;
+use std::fs;
 use std::os::raw::c_char;
 use std::path::Path;
 
+use crate::utility::error::VkPlaygroundError;
+
 /// Helper function to convert [c_char; SIZE] to string
 pub fn vk_to_string(raw_string_array: &[c_char]) -> String {
     let raw_string = unsafe {
@@ -9,19 +12,13 @@ pub fn vk_to_string(raw_string_array: &[c_char]) -> String {
         CStr::from_ptr(pointer)
     };
 
-    raw_string
-        .to_str()
-        .expect("Failed to convert to Vulkan raw string")
-        .to_owned()
+    // names from the driver are ASCII, anything else shouldn't be fatal
+    raw_string.to_string_lossy().into_owned()
 }
 
-pub fn read_shader_code(shader_path: &Path) -> Vec<u8> {
-    use std::fs::File;
-    use std::io::Read;
-
-    let spv_file = 
-        File::open(shader_path).expect(&format!("Failed to find spv file at {:?}", shader_path));
-    let bytes_code: Vec<u8> = spv_file.bytes().filter_map(|byte| byte.ok()).collect();
-    
-    bytes_code
-}
\ No newline at end of file
+pub fn read_shader_code(shader_path: &Path) -> Result<Vec<u8>, VkPlaygroundError> {
+    fs::read(shader_path).map_err(|source| VkPlaygroundError::Io {
+        path: shader_path.to_path_buf(),
+        source,
+    })
+}
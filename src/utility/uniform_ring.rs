@@ -0,0 +1,110 @@
+use ash::version::InstanceV1_0;
+use ash::vk;
+
+use crate::utility::allocator::{Allocation, Allocator};
+use crate::utility::error::VkPlaygroundError;
+use crate::utility::handles::Owned;
+use crate::utility::vkstuff;
+
+// Uniforms for many objects out of one buffer, instead of a small buffer per
+// object per swapchain image. The buffer stays mapped and is split into a
+// region per frame in flight, and each frame's uniforms are packed into its
+// region at minUniformBufferOffsetAlignment granularity. A descriptor of type
+// UNIFORM_BUFFER_DYNAMIC pointing at the start of the buffer then reaches
+// each one through the dynamic offset `push` returns, e.g.
+//
+//     ring.begin_frame(frame.index);
+//     let offset = ring.push(&ubo);
+//     ...
+//     device.cmd_bind_descriptor_sets(cmd, GRAPHICS, layout, 0, &[set], &[offset]);
+
+pub struct UniformRing {
+    buffer: Owned<vk::Buffer>,
+    memory: Allocation,
+
+    alignment: vk::DeviceSize,
+    frame_capacity: vk::DeviceSize,
+    frame_count: usize,
+    // where the current frame's region starts, and how much of it is used
+    frame_start: vk::DeviceSize,
+    frame_used: vk::DeviceSize,
+}
+
+impl UniformRing {
+    /// A ring with room for `frame_capacity` bytes of uniforms, before
+    /// alignment padding, in each of `frame_count` frames in flight.
+    pub fn new(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &ash::Device,
+        allocator: &Allocator,
+        frame_capacity: vk::DeviceSize,
+        frame_count: usize,
+    ) -> Result<UniformRing, VkPlaygroundError> {
+        let limits = unsafe { instance.get_physical_device_properties(physical_device).limits };
+        let alignment = limits.min_uniform_buffer_offset_alignment.max(1);
+        let frame_capacity = align_up(frame_capacity.max(1), alignment);
+
+        let (buffer, memory) = vkstuff::vkbuffer::create_buffer(
+            device,
+            frame_capacity * frame_count as vk::DeviceSize,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            allocator,
+        )?;
+
+        Ok(UniformRing {
+            buffer: Owned::new(device, buffer),
+            memory,
+
+            alignment,
+            frame_capacity,
+            frame_count,
+            frame_start: 0,
+            frame_used: 0,
+        })
+    }
+
+    /// The buffer to point the dynamic uniform descriptors at, with an
+    /// offset of 0.
+    pub fn buffer(&self) -> vk::Buffer {
+        *self.buffer
+    }
+
+    /// Starts filling the region of `frame_index`. Anything pushed for it
+    /// before is overwritten, so the frame's previous submit has to have
+    /// finished.
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        assert!(
+            frame_index < self.frame_count,
+            "Frame {} of a uniform ring with {} frames",
+            frame_index,
+            self.frame_count
+        );
+
+        self.frame_start = self.frame_capacity * frame_index as vk::DeviceSize;
+        self.frame_used = 0;
+    }
+
+    /// Copies `value` into the current frame's region and returns the
+    /// dynamic offset to bind it with.
+    pub fn push<T: Copy>(&mut self, value: &T) -> u32 {
+        let size = std::mem::size_of::<T>() as vk::DeviceSize;
+        assert!(
+            self.frame_used + size <= self.frame_capacity,
+            "Uniform ring is full, {} of {} bytes are used this frame",
+            self.frame_used,
+            self.frame_capacity
+        );
+
+        let offset = self.frame_start + self.frame_used;
+        self.memory.write_at(offset, std::slice::from_ref(value));
+        self.frame_used = align_up(self.frame_used + size, self.alignment);
+
+        offset as u32
+    }
+}
+
+fn align_up(size: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    size.div_ceil(alignment) * alignment
+}
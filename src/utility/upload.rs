@@ -0,0 +1,676 @@
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use std::path::Path;
+use std::ptr;
+
+use crate::utility::allocator::{Allocation, Allocator};
+use crate::utility::compute::Access;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::handles::Owned;
+use crate::utility::vkstuff;
+
+// Uploads that don't stall the graphics queue. `vkbuffer::copy_buffer` and
+// friends submit every copy on its own and wait for the queue to go idle,
+// which is fine at startup but stops rendering dead when a model is loaded
+// mid-frame. An `Uploader` instead records any number of copies into one
+// command buffer and submits them together on the transfer queue, a
+// transfer-only family if the device has one, e.g.
+//
+//     let mut uploader = Uploader::new(device, context.upload_queues())?;
+//     let (vertex_buffer, memory) = uploader.upload_buffer(allocator, usage, &vertices, after)?;
+//     let (texture, texture_memory) = uploader.upload_texture(allocator, path)?;
+//     let upload = uploader.submit()?;
+//     ...
+//     if uploader.is_complete(upload)? { /* start drawing with them */ }
+//
+// A resource made on one queue family belongs to it, so when the transfer
+// family is a different one each resource is released by the transfer queue
+// and acquired by the graphics queue, in a small command buffer that waits on
+// a semaphore from the copies. Everything recorded after `submit` on the
+// graphics queue is ordered after that, so the results can be drawn from
+// straight away; the GPU waits for the copies rather than the CPU. The fence
+// of each batch tells when its staging buffers can be freed, which happens
+// in `poll`.
+
+/// The queues uploads are copied on and handed over to, see
+/// `RenderContext::upload_queues`.
+#[derive(Debug, Clone, Copy)]
+pub struct UploadQueues {
+    pub transfer_family: u32,
+    pub transfer_queue: vk::Queue,
+    pub graphics_family: u32,
+    pub graphics_queue: vk::Queue,
+}
+
+impl UploadQueues {
+    /// Whether uploads change queue family on their way to the graphics
+    /// queue.
+    pub fn is_transfer_dedicated(&self) -> bool {
+        self.transfer_family != self.graphics_family
+    }
+}
+
+/// A submitted batch of uploads, from `Uploader::submit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UploadId(u64);
+
+// the uploads recorded since the last submit
+struct Batch {
+    transfer_command_buffer: vk::CommandBuffer,
+    // recorded on the graphics queue once the copies are done, empty when
+    // the transfer and graphics families are the same
+    buffer_acquires: Vec<vk::BufferMemoryBarrier>,
+    image_acquires: Vec<vk::ImageMemoryBarrier>,
+    acquire_stages: vk::PipelineStageFlags,
+    staging_buffers: Vec<(Owned<vk::Buffer>, Allocation)>,
+}
+
+// a submitted batch, until its fence signals
+struct InFlight {
+    id: UploadId,
+    fence: Owned<vk::Fence>,
+    // from the copies to the acquiring command buffer, null without one
+    _semaphore: Owned<vk::Semaphore>,
+    // (pool, command buffer) pairs to free once finished
+    command_buffers: Vec<(vk::CommandPool, vk::CommandBuffer)>,
+    _staging_buffers: Vec<(Owned<vk::Buffer>, Allocation)>,
+}
+
+pub struct Uploader {
+    batch: Option<Batch>,
+    in_flight: Vec<InFlight>,
+    next_id: u64,
+
+    // the acquiring command buffers come from a pool of the graphics family
+    transfer_pool: Owned<vk::CommandPool>,
+    acquire_pool: Owned<vk::CommandPool>,
+    queues: UploadQueues,
+    device: ash::Device,
+}
+
+impl Uploader {
+    pub fn new(device: &ash::Device, queues: UploadQueues) -> Result<Uploader, VkPlaygroundError> {
+        let transfer_pool = Owned::new(
+            device,
+            create_command_pool(device, queues.transfer_family)?,
+        );
+        let acquire_pool = Owned::new(
+            device,
+            create_command_pool(device, queues.graphics_family)?,
+        );
+
+        Ok(Uploader {
+            batch: None,
+            in_flight: vec![],
+            next_id: 0,
+
+            transfer_pool,
+            acquire_pool,
+            queues,
+            device: device.clone(),
+        })
+    }
+
+    /// A device local buffer for `usage` holding `data`, ready for the
+    /// `after` use on the graphics queue once the batch is submitted.
+    pub fn upload_buffer<T: Copy>(
+        &mut self,
+        allocator: &Allocator,
+        usage: vk::BufferUsageFlags,
+        data: &[T],
+        after: Access,
+    ) -> Result<(vk::Buffer, Allocation), VkPlaygroundError> {
+        // a zero sized buffer isn't valid Vulkan
+        if data.is_empty() {
+            return Err(VkPlaygroundError::unsupported(
+                "Uploader::upload_buffer",
+                "there's no data to upload",
+            ));
+        }
+        let buffer_size = std::mem::size_of_val(data) as vk::DeviceSize;
+        let staging_buffer = self.create_staging_buffer(allocator, data)?;
+
+        let (buffer, buffer_memory) = vkstuff::vkbuffer::create_buffer(
+            &self.device,
+            buffer_size,
+            vk::BufferUsageFlags::TRANSFER_DST | usage,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            allocator,
+        )?;
+        // destroyed if the batch can't be begun
+        let buffer = Owned::new(&self.device, buffer);
+
+        let is_transfer_dedicated = self.queues.is_transfer_dedicated();
+        let (transfer_family, graphics_family) = self.queue_families();
+        let command_buffer = self.batch()?.transfer_command_buffer;
+        let copy_regions = [vk::BufferCopy {
+            src_offset: 0,
+            dst_offset: 0,
+            size: buffer_size,
+        }];
+
+        let barrier = vk::BufferMemoryBarrier {
+            s_type: vk::StructureType::BUFFER_MEMORY_BARRIER,
+            p_next: ptr::null(),
+            src_access_mask: Access::TransferWrite.access_mask(),
+            dst_access_mask: after.access_mask(),
+            src_queue_family_index: transfer_family,
+            dst_queue_family_index: graphics_family,
+            buffer: *buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+        };
+        // the release half only makes the copy available, the acquire half
+        // makes it visible to `after`
+        let release = vk::BufferMemoryBarrier {
+            dst_access_mask: vk::AccessFlags::empty(),
+            ..barrier
+        };
+        let acquire = vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::empty(),
+            ..barrier
+        };
+
+        unsafe {
+            self.device.cmd_copy_buffer(
+                command_buffer,
+                *staging_buffer.0,
+                *buffer,
+                &copy_regions,
+            );
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                release_stage(is_transfer_dedicated, after),
+                vk::DependencyFlags::empty(),
+                &[],
+                &[if is_transfer_dedicated { release } else { barrier }],
+                &[],
+            );
+        }
+
+        let batch = self.batch.as_mut().unwrap();
+        if is_transfer_dedicated {
+            batch.buffer_acquires.push(acquire);
+            batch.acquire_stages |= after.stage();
+        }
+        batch.staging_buffers.push(staging_buffer);
+
+        Ok((buffer.into_raw(), buffer_memory))
+    }
+
+    /// A 2D `format` image with `pixels` tightly packed and a single mip
+    /// level, in the layout for the `after` use on the graphics queue once
+    /// the batch is submitted.
+    pub fn upload_image(
+        &mut self,
+        allocator: &Allocator,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        pixels: &[u8],
+        after: Access,
+    ) -> Result<(vk::Image, Allocation), VkPlaygroundError> {
+        if pixels.is_empty() {
+            return Err(VkPlaygroundError::unsupported(
+                "Uploader::upload_image",
+                "there are no pixels to upload",
+            ));
+        }
+        let staging_buffer = self.create_staging_buffer(allocator, pixels)?;
+
+        let (image, image_memory) = vkstuff::vkimage::create_image(
+            &self.device,
+            extent.width,
+            extent.height,
+            1,
+            vk::SampleCountFlags::TYPE_1,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            allocator,
+        )?;
+        let image = Owned::new(&self.device, image);
+
+        let is_transfer_dedicated = self.queues.is_transfer_dedicated();
+        let (transfer_family, graphics_family) = self.queue_families();
+        let command_buffer = self.batch()?.transfer_command_buffer;
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let to_transfer_dst = vk::ImageMemoryBarrier {
+            s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+            p_next: ptr::null(),
+            src_access_mask: Access::Nothing.access_mask(),
+            dst_access_mask: Access::TransferWrite.access_mask(),
+            old_layout: Access::Nothing.image_layout(),
+            new_layout: Access::TransferWrite.image_layout(),
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: *image,
+            subresource_range,
+        };
+        let buffer_image_regions = [vk::BufferImageCopy {
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_extent: vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            },
+            buffer_offset: 0,
+            buffer_image_height: 0,
+            buffer_row_length: 0,
+            image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        }];
+
+        // both halves of an ownership transfer do the same layout transition
+        let barrier = vk::ImageMemoryBarrier {
+            src_access_mask: Access::TransferWrite.access_mask(),
+            dst_access_mask: after.access_mask(),
+            old_layout: Access::TransferWrite.image_layout(),
+            new_layout: after.image_layout(),
+            src_queue_family_index: transfer_family,
+            dst_queue_family_index: graphics_family,
+            ..to_transfer_dst
+        };
+        let release = vk::ImageMemoryBarrier {
+            dst_access_mask: vk::AccessFlags::empty(),
+            ..barrier
+        };
+        let acquire = vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::empty(),
+            ..barrier
+        };
+
+        unsafe {
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                Access::Nothing.stage(),
+                Access::TransferWrite.stage(),
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer_dst],
+            );
+            self.device.cmd_copy_buffer_to_image(
+                command_buffer,
+                *staging_buffer.0,
+                *image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &buffer_image_regions,
+            );
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                release_stage(is_transfer_dedicated, after),
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[if is_transfer_dedicated { release } else { barrier }],
+            );
+        }
+
+        let batch = self.batch.as_mut().unwrap();
+        if is_transfer_dedicated {
+            batch.image_acquires.push(acquire);
+            batch.acquire_stages |= after.stage();
+        }
+        batch.staging_buffers.push(staging_buffer);
+
+        Ok((image.into_raw(), image_memory))
+    }
+
+    /// The image at `image_path` as an sRGB texture, ready to be sampled in
+    /// fragment shaders.
+    pub fn upload_texture(
+        &mut self,
+        allocator: &Allocator,
+        image_path: &Path,
+    ) -> Result<(vk::Image, Allocation), VkPlaygroundError> {
+        let (width, height, pixels) = vkstuff::vkimage::load_texture_pixels(image_path)?;
+
+        self.upload_image(
+            allocator,
+            vk::Extent2D { width, height },
+            vk::Format::R8G8B8A8_SRGB,
+            &pixels,
+            Access::FragmentShaderRead,
+        )
+    }
+
+    /// Submits everything uploaded since the last submit without waiting
+    /// for it. Commands submitted to the graphics queue afterwards can use
+    /// the results; `is_complete` says when the copies have actually
+    /// finished. With nothing to submit, the returned upload is complete.
+    pub fn submit(&mut self) -> Result<UploadId, VkPlaygroundError> {
+        let id = UploadId(self.next_id);
+        self.next_id += 1;
+
+        let batch = match self.batch.take() {
+            Some(batch) => batch,
+            None => return Ok(id),
+        };
+
+        let device = &self.device;
+        let fence = Owned::new(device, create_fence(device)?);
+        let mut semaphore = Owned::null(device);
+        let mut command_buffers = vec![(*self.transfer_pool, batch.transfer_command_buffer)];
+
+        unsafe {
+            device
+                .end_command_buffer(batch.transfer_command_buffer)
+                .vk_context("vkEndCommandBuffer", "Failed to record Command Buffer at Ending!")?;
+        }
+
+        if self.queues.is_transfer_dedicated() {
+            let semaphore_create_info = vk::SemaphoreCreateInfo {
+                s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
+                p_next: ptr::null(),
+                flags: vk::SemaphoreCreateFlags::empty(),
+            };
+            semaphore = Owned::new(device, unsafe {
+                device
+                    .create_semaphore(&semaphore_create_info, None)
+                    .vk_context("vkCreateSemaphore", "Failed to create Semaphore Object!")?
+            });
+
+            submit(
+                device,
+                self.queues.transfer_queue,
+                batch.transfer_command_buffer,
+                &[],
+                &[],
+                &[*semaphore],
+                vk::Fence::null(),
+            )?;
+
+            let acquire_command_buffer = begin_command_buffer(device, *self.acquire_pool)?;
+            command_buffers.push((*self.acquire_pool, acquire_command_buffer));
+            unsafe {
+                device.cmd_pipeline_barrier(
+                    acquire_command_buffer,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    batch.acquire_stages,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &batch.buffer_acquires,
+                    &batch.image_acquires,
+                );
+                device
+                    .end_command_buffer(acquire_command_buffer)
+                    .vk_context("vkEndCommandBuffer", "Failed to record Command Buffer at Ending!")?;
+            }
+
+            submit(
+                device,
+                self.queues.graphics_queue,
+                acquire_command_buffer,
+                &[*semaphore],
+                &[vk::PipelineStageFlags::ALL_COMMANDS],
+                &[],
+                *fence,
+            )?;
+        } else {
+            submit(
+                device,
+                self.queues.transfer_queue,
+                batch.transfer_command_buffer,
+                &[],
+                &[],
+                &[],
+                *fence,
+            )?;
+        }
+
+        self.in_flight.push(InFlight {
+            id,
+            fence,
+            _semaphore: semaphore,
+            command_buffers,
+            _staging_buffers: batch.staging_buffers,
+        });
+
+        Ok(id)
+    }
+
+    /// Frees the staging buffers and command buffers of every batch that
+    /// has finished.
+    pub fn poll(&mut self) -> Result<(), VkPlaygroundError> {
+        let mut finished = vec![];
+        for (i, in_flight) in self.in_flight.iter().enumerate() {
+            // NOT_READY is how an unsignaled fence is reported
+            match unsafe { self.device.get_fence_status(*in_flight.fence) } {
+                Ok(()) => finished.push(i),
+                Err(vk::Result::NOT_READY) => {}
+                Err(result) => {
+                    return Err(result).vk_context(
+                        "vkGetFenceStatus",
+                        "Failed to get the status of an upload",
+                    );
+                }
+            }
+        }
+
+        for i in finished.into_iter().rev() {
+            let in_flight = self.in_flight.remove(i);
+            self.free_command_buffers(&in_flight);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the copies of `upload` have finished, after a `poll`.
+    pub fn is_complete(&mut self, upload: UploadId) -> Result<bool, VkPlaygroundError> {
+        self.poll()?;
+
+        Ok(!self.in_flight.iter().any(|in_flight| in_flight.id == upload))
+    }
+
+    /// Blocks until `upload` has finished.
+    pub fn wait(&mut self, upload: UploadId) -> Result<(), VkPlaygroundError> {
+        if let Some(in_flight) = self.in_flight.iter().find(|in_flight| in_flight.id == upload) {
+            unsafe {
+                self.device
+                    .wait_for_fences(&[*in_flight.fence], true, u64::MAX)
+                    .vk_context("vkWaitForFences", "Failed to wait for an upload")?;
+            }
+        }
+
+        self.poll()
+    }
+
+    /// Throws away everything uploaded since the last `submit` without
+    /// running it, for when a batch can't be finished. The resources it was
+    /// uploading into are never filled.
+    pub fn discard(&mut self) {
+        if let Some(batch) = self.batch.take() {
+            unsafe {
+                self.device
+                    .free_command_buffers(*self.transfer_pool, &[batch.transfer_command_buffer]);
+            }
+        }
+    }
+
+    /// Whether anything has been uploaded since the last `submit`.
+    pub fn has_unsubmitted(&self) -> bool {
+        self.batch.is_some()
+    }
+
+    fn queue_families(&self) -> (u32, u32) {
+        if self.queues.is_transfer_dedicated() {
+            (self.queues.transfer_family, self.queues.graphics_family)
+        } else {
+            (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+        }
+    }
+
+    // the batch being recorded, begun by the first upload after a submit
+    fn batch(&mut self) -> Result<&mut Batch, VkPlaygroundError> {
+        if self.batch.is_none() {
+            self.batch = Some(Batch {
+                transfer_command_buffer: begin_command_buffer(&self.device, *self.transfer_pool)?,
+                buffer_acquires: vec![],
+                image_acquires: vec![],
+                acquire_stages: vk::PipelineStageFlags::empty(),
+                staging_buffers: vec![],
+            });
+        }
+
+        Ok(self.batch.as_mut().unwrap())
+    }
+
+    fn create_staging_buffer<T: Copy>(
+        &self,
+        allocator: &Allocator,
+        data: &[T],
+    ) -> Result<(Owned<vk::Buffer>, Allocation), VkPlaygroundError> {
+        let (staging_buffer, staging_buffer_memory) = vkstuff::vkbuffer::create_buffer(
+            &self.device,
+            std::mem::size_of_val(data) as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            allocator,
+        )?;
+        staging_buffer_memory.write(data);
+
+        Ok((Owned::new(&self.device, staging_buffer), staging_buffer_memory))
+    }
+
+    fn free_command_buffers(&self, in_flight: &InFlight) {
+        for &(pool, command_buffer) in in_flight.command_buffers.iter() {
+            unsafe {
+                self.device.free_command_buffers(pool, &[command_buffer]);
+            }
+        }
+    }
+}
+
+impl Drop for Uploader {
+    fn drop(&mut self) {
+        // the staging buffers can't go while the GPU still copies from them.
+        // A batch that was never submitted goes with its pool.
+        let fences: Vec<vk::Fence> =
+            self.in_flight.iter().map(|in_flight| *in_flight.fence).collect();
+        if !fences.is_empty() {
+            unsafe {
+                let _ = self.device.wait_for_fences(&fences, true, u64::MAX);
+            }
+        }
+    }
+}
+
+// Where the transfer queue's barrier after a copy waits: nowhere for a
+// release, whose acquire on the graphics queue does the waiting, otherwise
+// the `after` use itself.
+fn release_stage(is_transfer_dedicated: bool, after: Access) -> vk::PipelineStageFlags {
+    if is_transfer_dedicated {
+        vk::PipelineStageFlags::BOTTOM_OF_PIPE
+    } else {
+        after.stage()
+    }
+}
+
+fn create_command_pool(
+    device: &ash::Device,
+    queue_family_index: u32,
+) -> Result<vk::CommandPool, VkPlaygroundError> {
+    let command_pool_create_info = vk::CommandPoolCreateInfo {
+        s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::CommandPoolCreateFlags::TRANSIENT,
+        queue_family_index,
+    };
+
+    unsafe {
+        device
+            .create_command_pool(&command_pool_create_info, None)
+            .vk_context("vkCreateCommandPool", "Failed to create Command Pool!")
+    }
+}
+
+fn create_fence(device: &ash::Device) -> Result<vk::Fence, VkPlaygroundError> {
+    let fence_create_info = vk::FenceCreateInfo {
+        s_type: vk::StructureType::FENCE_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::FenceCreateFlags::empty(),
+    };
+
+    unsafe {
+        device
+            .create_fence(&fence_create_info, None)
+            .vk_context("vkCreateFence", "Failed to create Fence Object!")
+    }
+}
+
+fn begin_command_buffer(
+    device: &ash::Device,
+    command_pool: vk::CommandPool,
+) -> Result<vk::CommandBuffer, VkPlaygroundError> {
+    let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
+        s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
+        p_next: ptr::null(),
+        command_buffer_count: 1,
+        command_pool,
+        level: vk::CommandBufferLevel::PRIMARY,
+    };
+    let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+        s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+        p_next: ptr::null(),
+        p_inheritance_info: ptr::null(),
+        flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+    };
+
+    unsafe {
+        let command_buffer = device
+            .allocate_command_buffers(&command_buffer_allocate_info)
+            .vk_context("vkAllocateCommandBuffers", "Failed to allocate Command Buffers!")?[0];
+        device
+            .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+            .vk_context(
+                "vkBeginCommandBuffer",
+                "Failed to begin recording Command Buffer at beginning!",
+            )?;
+
+        Ok(command_buffer)
+    }
+}
+
+fn submit(
+    device: &ash::Device,
+    queue: vk::Queue,
+    command_buffer: vk::CommandBuffer,
+    wait_semaphores: &[vk::Semaphore],
+    wait_stages: &[vk::PipelineStageFlags],
+    signal_semaphores: &[vk::Semaphore],
+    fence: vk::Fence,
+) -> Result<(), VkPlaygroundError> {
+    let command_buffers = [command_buffer];
+
+    let submit_infos = [vk::SubmitInfo {
+        s_type: vk::StructureType::SUBMIT_INFO,
+        p_next: ptr::null(),
+        wait_semaphore_count: wait_semaphores.len() as u32,
+        p_wait_semaphores: wait_semaphores.as_ptr(),
+        p_wait_dst_stage_mask: wait_stages.as_ptr(),
+        command_buffer_count: command_buffers.len() as u32,
+        p_command_buffers: command_buffers.as_ptr(),
+        signal_semaphore_count: signal_semaphores.len() as u32,
+        p_signal_semaphores: signal_semaphores.as_ptr(),
+    }];
+
+    unsafe {
+        device
+            .queue_submit(queue, &submit_infos, fence)
+            .vk_context("vkQueueSubmit", "Failed to submit uploads!")
+    }
+}
@@ -9,8 +9,10 @@ use std::os::raw::c_void;
 use std::path::Path;
 use std::ptr;
 
+use crate::utility::allocator::{Allocation, Allocator};
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
 use crate::utility::platforms;
 use crate::utility::structures::*;
 
@@ -19,8 +21,8 @@ pub fn create_buffer(
     size: vk::DeviceSize,
     usage: vk::BufferUsageFlags,
     required_memory_properties: vk::MemoryPropertyFlags,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
-) -> (vk::Buffer, vk::DeviceMemory) {
+    allocator: &Allocator,
+) -> Result<(vk::Buffer, Allocation), VkPlaygroundError> {
 
 
     let buffer_create_info = vk::BufferCreateInfo {
@@ -37,36 +39,13 @@ pub fn create_buffer(
     let buffer = unsafe {
         device
             .create_buffer(&buffer_create_info, None)
-            .expect("Failed to create Vertex Buffer")
+            .vk_context("vkCreateBuffer", "Failed to create Vertex Buffer")?
     };
 
-    let mem_requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
-    let memory_type = find_memory_type(
-        mem_requirements.memory_type_bits,
-        required_memory_properties,
-        device_memory_properties,
-    );
+    let buffer_memory =
+        allocator.allocate_buffer_memory(device, buffer, required_memory_properties)?;
 
-    let allocate_info = vk::MemoryAllocateInfo {
-        s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
-        p_next: ptr::null(),
-        allocation_size: mem_requirements.size,
-        memory_type_index: memory_type,
-    };
-
-    let buffer_memory = unsafe {
-        device
-            .allocate_memory(&allocate_info, None)
-            .expect("Failed to allocate vertex buffer memory!")
-    };
-
-    unsafe {
-        device
-            .bind_buffer_memory(buffer, buffer_memory, 0)
-            .expect("Failed to bind Buffer");
-    }
-
-    (buffer, buffer_memory)
+    Ok((buffer, buffer_memory))
 }
 
 pub fn copy_buffer(
@@ -76,8 +55,8 @@ pub fn copy_buffer(
     src_buffer: vk::Buffer,
     dst_buffer: vk::Buffer,
     size: vk::DeviceSize,
-) {
-    let command_buffer = begin_single_time_command(device, command_pool);
+) -> Result<(), VkPlaygroundError> {
+    let command_buffer = begin_single_time_command(device, command_pool)?;
 
     let copy_regions = [vk::BufferCopy {
         src_offset: 0,
@@ -89,13 +68,12 @@ pub fn copy_buffer(
         device.cmd_copy_buffer(command_buffer, src_buffer, dst_buffer, &copy_regions);
     }
 
-    end_single_time_command(device, command_pool, submit_queue, command_buffer);
-
+    end_single_time_command(device, command_pool, submit_queue, command_buffer)
 }
 pub fn begin_single_time_command(
     device: &ash::Device,
     command_pool: vk::CommandPool,
-) -> vk::CommandBuffer {
+) -> Result<vk::CommandBuffer, VkPlaygroundError> {
     let command_buffer_allocate_info = vk::CommandBufferAllocateInfo {
         s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
         p_next: ptr::null(),
@@ -107,7 +85,7 @@ pub fn begin_single_time_command(
     let command_buffer = unsafe {
         device
             .allocate_command_buffers(&command_buffer_allocate_info)
-            .expect("Failed to allocate Command Buffers!")
+            .vk_context("vkAllocateCommandBuffers", "Failed to allocate Command Buffers!")?
     }[0];
 
     let command_buffer_begin_info = vk::CommandBufferBeginInfo {
@@ -120,10 +98,13 @@ pub fn begin_single_time_command(
     unsafe {
         device
             .begin_command_buffer(command_buffer, &command_buffer_begin_info)
-            .expect("Failed to begin recording Command Buffer at beginning!");
+            .vk_context(
+                "vkBeginCommandBuffer",
+                "Failed to begin recording Command Buffer at beginning!",
+            )?;
     }
 
-    command_buffer
+    Ok(command_buffer)
 }
 
 pub fn end_single_time_command(
@@ -131,11 +112,11 @@ pub fn end_single_time_command(
     command_pool: vk::CommandPool,
     submit_queue: vk::Queue,
     command_buffer: vk::CommandBuffer,
-) {
+) -> Result<(), VkPlaygroundError> {
     unsafe {
         device
             .end_command_buffer(command_buffer)
-            .expect("Failed to record Command Buffer at Ending!");
+            .vk_context("vkEndCommandBuffer", "Failed to record Command Buffer at Ending!")?;
     }
 
     let buffers_to_submit = [command_buffer];
@@ -155,29 +136,32 @@ pub fn end_single_time_command(
     unsafe {
         device
             .queue_submit(submit_queue, &sumbit_infos, vk::Fence::null())
-            .expect("Failed to Queue Submit!");
+            .vk_context("vkQueueSubmit", "Failed to Queue Submit!")?;
         device
             .queue_wait_idle(submit_queue)
-            .expect("Failed to wait Queue idle!");
+            .vk_context("vkQueueWaitIdle", "Failed to wait Queue idle!")?;
         device.free_command_buffers(command_pool, &buffers_to_submit);
     }
 
+    Ok(())
 }
 
 pub fn find_memory_type(
     type_filter: u32,
     required_properties: vk::MemoryPropertyFlags,
     mem_properties: &vk::PhysicalDeviceMemoryProperties
-) -> u32 {
+) -> Result<u32, VkPlaygroundError> {
     for (i, memory_type) in mem_properties.memory_types.iter().enumerate() {
         if (type_filter & (1 << i)) > 0 && memory_type.property_flags.contains(required_properties)
         {
-            return i as u32;
+            return Ok(i as u32);
         }
     }
 
-    panic!("Failed to find suitable memory type!")
-
+    Err(VkPlaygroundError::unsupported(
+        "find_memory_type",
+        format!("Failed to find suitable memory type with {:?}!", required_properties),
+    ))
 }
 
 pub fn has_stencil_component(format: vk::Format) -> bool {
@@ -192,8 +176,8 @@ pub fn copy_buffer_to_image(
     image: vk::Image,
     width: u32,
     height: u32,
-) {
-    let command_buffer = begin_single_time_command(device, command_pool);
+) -> Result<(), VkPlaygroundError> {
+    let command_buffer = begin_single_time_command(device, command_pool)?;
 
     let buffer_image_regions = [vk::BufferImageCopy {
         image_subresource: vk::ImageSubresourceLayers {
@@ -223,73 +207,54 @@ pub fn copy_buffer_to_image(
         );
     }
 
-    end_single_time_command(device, command_pool, submit_queue, command_buffer);
+    end_single_time_command(device, command_pool, submit_queue, command_buffer)
 }
 
 pub fn create_vertex_buffer<T>(
     device: &ash::Device,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allocator: &Allocator,
     command_pool: vk::CommandPool,
     submit_queue: vk::Queue,
     data: &[T],
-) -> (vk::Buffer, vk::DeviceMemory) {
-    let buffer_size = ::std::mem::size_of_val(data) as vk::DeviceSize;
-
-    let (staging_buffer, staging_buffer_memory) = create_buffer(
-        device,
-        buffer_size,
-        vk::BufferUsageFlags::TRANSFER_SRC,
-        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        &device_memory_properties,
-    );
-
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                staging_buffer_memory,
-                0,
-                buffer_size,
-                vk::MemoryMapFlags::empty(),
-            )
-            .expect("Failed to Map Memory") as *mut T;
-
-        data_ptr.copy_from_nonoverlapping(data.as_ptr(), data.len());
-
-        device.unmap_memory(staging_buffer_memory);
-    }
-
-    let (vertex_buffer, vertex_buffer_memory) = create_buffer(
-        device,
-        buffer_size,
-        vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::VERTEX_BUFFER,
-        vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        &device_memory_properties,
-    );
-
-    copy_buffer(
+) -> Result<(vk::Buffer, Allocation), VkPlaygroundError> {
+    create_device_local_buffer(
         device,
-        submit_queue,
+        allocator,
         command_pool,
-        staging_buffer,
-        vertex_buffer,
-        buffer_size,
-    );
-
-    unsafe {
-        device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
-    }
-
-    (vertex_buffer, vertex_buffer_memory)
+        submit_queue,
+        vk::BufferUsageFlags::VERTEX_BUFFER,
+        data,
+    )
 }
 
 pub fn create_index_buffer(
     device: &ash::Device,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allocator: &Allocator,
     command_pool: vk::CommandPool,
     submit_queue: vk::Queue,
     data: &[u32],
-) -> (vk::Buffer, vk::DeviceMemory) {
+) -> Result<(vk::Buffer, Allocation), VkPlaygroundError> {
+    create_device_local_buffer(
+        device,
+        allocator,
+        command_pool,
+        submit_queue,
+        vk::BufferUsageFlags::INDEX_BUFFER,
+        data,
+    )
+}
+
+/// A device local buffer for `usage` holding `data`, copied in through a
+/// staging buffer. E.g. STORAGE_BUFFER | VERTEX_BUFFER for particles that a
+/// compute shader moves and the vertex shader then draws.
+pub fn create_device_local_buffer<T>(
+    device: &ash::Device,
+    allocator: &Allocator,
+    command_pool: vk::CommandPool,
+    submit_queue: vk::Queue,
+    usage: vk::BufferUsageFlags,
+    data: &[T],
+) -> Result<(vk::Buffer, Allocation), VkPlaygroundError> {
     let buffer_size = ::std::mem::size_of_val(data) as vk::DeviceSize;
 
     let (staging_buffer, staging_buffer_memory) = create_buffer(
@@ -297,55 +262,41 @@ pub fn create_index_buffer(
         buffer_size,
         vk::BufferUsageFlags::TRANSFER_SRC,
         vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        &device_memory_properties,
-    );
+        allocator,
+    )?;
 
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                staging_buffer_memory,
-                0,
-                buffer_size,
-                vk::MemoryMapFlags::empty(),
-            )
-            .expect("Failed to Map Memory") as *mut u32;
-
-        data_ptr.copy_from_nonoverlapping(data.as_ptr(), data.len());
-
-        device.unmap_memory(staging_buffer_memory);
-    }
+    staging_buffer_memory.write(data);
 
-    let (index_buffer, index_buffer_memory) = create_buffer(
+    let (buffer, buffer_memory) = create_buffer(
         device,
         buffer_size,
-        vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::INDEX_BUFFER,
+        vk::BufferUsageFlags::TRANSFER_DST | usage,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        &device_memory_properties,
-    );
+        allocator,
+    )?;
 
     copy_buffer(
         device,
         submit_queue,
         command_pool,
         staging_buffer,
-        index_buffer,
+        buffer,
         buffer_size,
-    );
+    )?;
 
     unsafe {
         device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
     }
 
-    (index_buffer, index_buffer_memory)
+    Ok((buffer, buffer_memory))
 }
 
 pub fn create_uniform_buffers(
     device: &ash::Device,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allocator: &Allocator,
     swapchain_image_count: usize,
-) -> (Vec<vk::Buffer>, Vec<vk::DeviceMemory>) {
-    let buffer_size = ::std::mem::size_of::<UniformBufferObject>();
+) -> Result<(Vec<vk::Buffer>, Vec<Allocation>), VkPlaygroundError> {
+    let buffer_size = ::std::mem::size_of::<CameraUniformBufferObject>();
 
     let mut uniform_buffers = vec![];
     let mut uniform_buffers_memory = vec![];
@@ -356,12 +307,12 @@ pub fn create_uniform_buffers(
             buffer_size as u64,
             vk::BufferUsageFlags::UNIFORM_BUFFER,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-            device_memory_properties,
-        );
+            allocator,
+        )?;
         uniform_buffers.push(uniform_buffer);
         uniform_buffers_memory.push(uniform_buffer_memory);
     }
 
-    (uniform_buffers, uniform_buffers_memory)
+    Ok((uniform_buffers, uniform_buffers_memory))
 }
 
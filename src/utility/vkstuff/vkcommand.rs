@@ -11,6 +11,7 @@ use std::ptr;
 
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
 use crate::utility::platforms;
 use crate::utility::tools;
 use crate::utility::structures::*;
@@ -19,7 +20,7 @@ use crate::utility::vkstuff::vkswapchain::*;
 pub fn create_command_pool(
     device: &ash::Device,
     queue_families: &QueueFamilyIndices,
-) -> vk::CommandPool {
+) -> Result<vk::CommandPool, VkPlaygroundError> {
     let command_pool_create_info = vk::CommandPoolCreateInfo {
         s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
         p_next: ptr::null(),
@@ -30,6 +31,6 @@ pub fn create_command_pool(
     unsafe {
         device
             .create_command_pool(&command_pool_create_info, None)
-            .expect("Failed to create Command Pool!")
+            .vk_context("vkCreateCommandPool", "Failed to create Command Pool!")
     }
 }
\ No newline at end of file
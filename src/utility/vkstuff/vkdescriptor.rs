@@ -11,7 +11,9 @@ use std::ptr;
 
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
 use crate::utility::platforms;
+use crate::utility::reflection::DescriptorBinding;
 use crate::utility::structures::*;
 
 use super::*;
@@ -21,7 +23,7 @@ use super::*;
 pub fn create_descriptor_pool(
     device: &ash::Device,
     swapchain_images_size: usize,
-) -> vk::DescriptorPool {
+) -> Result<vk::DescriptorPool, VkPlaygroundError> {
     let pool_sizes = [
         vk::DescriptorPoolSize {
             // transform descriptor ppol
@@ -47,7 +49,7 @@ pub fn create_descriptor_pool(
     unsafe {
         device
             .create_descriptor_pool(&descriptor_pool_create_info, None)
-            .expect("Failed to create descriptor pool!")
+            .vk_context("vkCreateDescriptorPool", "Failed to create descriptor pool!")
     }
 }
 
@@ -59,7 +61,7 @@ pub fn create_descriptor_sets(
     texture_image_view: vk::ImageView,
     texture_sampler: vk::Sampler,
     swapchain_images_size: usize,
-) -> Vec<vk::DescriptorSet> {
+) -> Result<Vec<vk::DescriptorSet>, VkPlaygroundError> {
 
     let mut layouts: Vec<vk::DescriptorSetLayout> = vec![];
     for _ in 0..swapchain_images_size {
@@ -77,14 +79,14 @@ pub fn create_descriptor_sets(
     let descriptor_sets = unsafe {
         device
             .allocate_descriptor_sets(&descriptor_set_allocate_info)
-            .expect("Failed to allocate descriptor sets")
+            .vk_context("vkAllocateDescriptorSets", "Failed to allocate descriptor sets")?
     };
 
     for (i, &descriptor_set) in descriptor_sets.iter().enumerate() {
         let descriptor_buffer_infos = [vk::DescriptorBufferInfo {
             buffer: uniform_buffers[i],
             offset: 0,
-            range: ::std::mem::size_of::<UniformBufferObject>() as u64,
+            range: ::std::mem::size_of::<CameraUniformBufferObject>() as u64,
         }];
 
         let descriptor_image_infos = [vk::DescriptorImageInfo {
@@ -127,11 +129,12 @@ pub fn create_descriptor_sets(
         }
     }
 
-    descriptor_sets
-
+    Ok(descriptor_sets)
 }
 
-pub fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+pub fn create_descriptor_set_layout(
+    device: &ash::Device,
+) -> Result<vk::DescriptorSetLayout, VkPlaygroundError> {
     let ubo_layout_bindings = [
         vk::DescriptorSetLayoutBinding {
             // transform uniform
@@ -162,6 +165,130 @@ pub fn create_descriptor_set_layout(device: &ash::Device) -> vk::DescriptorSetLa
     unsafe {
         device
             .create_descriptor_set_layout(&ubo_layout_create_info, None)
-            .expect("Failed to create descriptor set layout")
+            .vk_context("vkCreateDescriptorSetLayout", "Failed to create descriptor set layout")
+    }
+}
+
+/// A pool with room for `set_count` sets of the reflected `bindings`, e.g.
+/// from `reflection::merge_descriptor_bindings`, whatever their types.
+pub fn create_descriptor_pool_for_bindings(
+    device: &ash::Device,
+    bindings: &[DescriptorBinding],
+    set_count: usize,
+) -> Result<vk::DescriptorPool, VkPlaygroundError> {
+    let mut pool_sizes: Vec<vk::DescriptorPoolSize> = vec![];
+    for binding in bindings.iter() {
+        let descriptor_count = binding.descriptor_count * set_count as u32;
+        match pool_sizes.iter_mut().find(|size| size.ty == binding.descriptor_type) {
+            Some(pool_size) => pool_size.descriptor_count += descriptor_count,
+            None => pool_sizes.push(vk::DescriptorPoolSize {
+                ty: binding.descriptor_type,
+                descriptor_count,
+            }),
+        }
+    }
+
+    let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo {
+        s_type: vk::StructureType::DESCRIPTOR_POOL_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::DescriptorPoolCreateFlags::empty(),
+        max_sets: set_count as u32,
+        pool_size_count: pool_sizes.len() as u32,
+        p_pool_sizes: pool_sizes.as_ptr(),
+    };
+
+    unsafe {
+        device
+            .create_descriptor_pool(&descriptor_pool_create_info, None)
+            .vk_context("vkCreateDescriptorPool", "Failed to create descriptor pool!")
+    }
+}
+
+/// `set_count` sets of `descriptor_set_layout`, still to be written.
+pub fn allocate_descriptor_sets(
+    device: &ash::Device,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    set_count: usize,
+) -> Result<Vec<vk::DescriptorSet>, VkPlaygroundError> {
+    let layouts = vec![descriptor_set_layout; set_count];
+
+    let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfo {
+        s_type: vk::StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
+        p_next: ptr::null(),
+        descriptor_pool,
+        descriptor_set_count: layouts.len() as u32,
+        p_set_layouts: layouts.as_ptr(),
+    };
+
+    unsafe {
+        device
+            .allocate_descriptor_sets(&descriptor_set_allocate_info)
+            .vk_context("vkAllocateDescriptorSets", "Failed to allocate descriptor sets")
     }
-}
\ No newline at end of file
+}
+
+/// Points the STORAGE_BUFFER at `binding` of `descriptor_set` at the whole
+/// of `buffer`.
+pub fn write_storage_buffer(
+    device: &ash::Device,
+    descriptor_set: vk::DescriptorSet,
+    binding: u32,
+    buffer: vk::Buffer,
+) {
+    let descriptor_buffer_infos = [vk::DescriptorBufferInfo {
+        buffer,
+        offset: 0,
+        range: vk::WHOLE_SIZE,
+    }];
+
+    let descriptor_write_sets = [vk::WriteDescriptorSet {
+        s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
+        p_next: ptr::null(),
+        dst_set: descriptor_set,
+        dst_binding: binding,
+        dst_array_element: 0,
+        descriptor_count: 1,
+        descriptor_type: vk::DescriptorType::STORAGE_BUFFER,
+        p_image_info: ptr::null(),
+        p_buffer_info: descriptor_buffer_infos.as_ptr(),
+        p_texel_buffer_view: ptr::null(),
+    }];
+
+    unsafe {
+        device.update_descriptor_sets(&descriptor_write_sets, &[]);
+    }
+}
+
+/// Points the STORAGE_IMAGE at `binding` of `descriptor_set` at
+/// `image_view`. Storage images are only accessed in the GENERAL layout, see
+/// `compute::image_barrier` for getting them there.
+pub fn write_storage_image(
+    device: &ash::Device,
+    descriptor_set: vk::DescriptorSet,
+    binding: u32,
+    image_view: vk::ImageView,
+) {
+    let descriptor_image_infos = [vk::DescriptorImageInfo {
+        sampler: vk::Sampler::null(),
+        image_view,
+        image_layout: vk::ImageLayout::GENERAL,
+    }];
+
+    let descriptor_write_sets = [vk::WriteDescriptorSet {
+        s_type: vk::StructureType::WRITE_DESCRIPTOR_SET,
+        p_next: ptr::null(),
+        dst_set: descriptor_set,
+        dst_binding: binding,
+        dst_array_element: 0,
+        descriptor_count: 1,
+        descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+        p_image_info: descriptor_image_infos.as_ptr(),
+        p_buffer_info: ptr::null(),
+        p_texel_buffer_view: ptr::null(),
+    }];
+
+    unsafe {
+        device.update_descriptor_sets(&descriptor_write_sets, &[]);
+    }
+}
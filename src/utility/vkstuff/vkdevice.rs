@@ -3,92 +3,167 @@ use ash::version::EntryV1_0;
 use ash::version::InstanceV1_0;
 use ash::vk;
 
-use std::ffi::CString;
 use std::os::raw::c_char;
 use std::os::raw::c_void;
 use std::path::Path;
 use std::ptr;
 
 use crate::utility::constants::*;
-use crate::utility::debug;
-use crate::utility::platforms;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::extensions::{self, EnabledExtensions, ExtensionConfig};
+use crate::utility::gpu::{self, GpuInfo, GpuSelector};
 use crate::utility::tools;
+use crate::utility::platforms;
 use crate::utility::structures::*;
 use crate::utility::vkstuff::vkswapchain::*;
 
+// Picks the GPU `selector` asks for, or else the suitable one with the best
+// score, see utility::gpu.
 pub fn pick_physical_device(
     instance: &ash::Instance,
-    surface_stuff: &SurfaceStuff,
-    required_device_extensions: &DeviceExtension
-) -> vk::PhysicalDevice {
+    surface_stuff: Option<&SurfaceStuff>,
+    config: &ExtensionConfig,
+    selector: Option<&GpuSelector>,
+) -> Result<vk::PhysicalDevice, VkPlaygroundError> {
+    let gpus = describe_physical_devices(instance, surface_stuff, config)?;
+
+    gpu::choose(&gpus, selector).map(|gpu| gpu.physical_device)
+}
+
+/// Every physical device with its properties, score and, if it can't be
+/// used, the reason why.
+pub fn describe_physical_devices(
+    instance: &ash::Instance,
+    surface_stuff: Option<&SurfaceStuff>,
+    config: &ExtensionConfig,
+) -> Result<Vec<GpuInfo>, VkPlaygroundError> {
     let physical_devices = unsafe {
         instance
             .enumerate_physical_devices()
-            .expect("Failed to enumerate Physical Devices!")
+            .vk_context("vkEnumeratePhysicalDevices", "Failed to enumerate Physical Devices!")?
     };
 
-    let result = physical_devices.iter().find(|physical_device| {
-        let is_suitable = is_physical_device_suitable(
-            instance,
-            **physical_device,
-            surface_stuff,
-            required_device_extensions,
-        );
-
-        is_suitable
-    });
-
-    match result {
-        Some(p_physical_device) => *p_physical_device,
-        None => panic!("Failed to find a suitable GPU"),
+    let mut gpus = vec![];
+    for (index, &physical_device) in physical_devices.iter().enumerate() {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let features = unsafe { instance.get_physical_device_features(physical_device) };
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let device_local_memory = gpu::device_local_memory(&memory_properties);
+
+        gpus.push(GpuInfo {
+            index,
+            physical_device,
+            name: tools::vk_to_string(&properties.device_name),
+            device_type: properties.device_type,
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            api_version: properties.api_version,
+            driver_version: properties.driver_version,
+            device_local_memory,
+            score: gpu::score(&properties, &features, device_local_memory),
+            rejection: find_rejection_reason(instance, physical_device, surface_stuff, config)?,
+        });
     }
+
+    Ok(gpus)
 }
 
 pub fn is_physical_device_suitable(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-    surface_stuff: &SurfaceStuff,
-    required_device_extensions: &DeviceExtension,
-) -> bool {
+    surface_stuff: Option<&SurfaceStuff>,
+    config: &ExtensionConfig,
+) -> Result<bool, VkPlaygroundError> {
+    let rejection = find_rejection_reason(instance, physical_device, surface_stuff, config)?;
 
-    let device_features = unsafe {instance.get_physical_device_features(physical_device)};
+    Ok(rejection.is_none())
+}
+
+// With no surface (headless rendering) only a graphics queue is needed and
+// there's no swapchain support to check.
+fn find_rejection_reason(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+    surface_stuff: Option<&SurfaceStuff>,
+    config: &ExtensionConfig,
+) -> Result<Option<String>, VkPlaygroundError> {
+    let device_features = unsafe { instance.get_physical_device_features(physical_device) };
+    if device_features.sampler_anisotropy != vk::TRUE {
+        return Ok(Some("no sampler anisotropy".to_owned()));
+    }
+
+    let missing_extensions = config.missing_device_extensions(instance, physical_device)?;
+    if !missing_extensions.is_empty() {
+        return Ok(Some(format!(
+            "missing device extensions {}",
+            missing_extensions.join(", ")
+        )));
+    }
 
     let indices = find_queue_family(instance, physical_device, surface_stuff);
+    if indices.graphics_family.is_none() {
+        return Ok(Some("no graphics queue".to_owned()));
+    }
 
-    let is_queue_family_supported = indices.is_complete();
+    if let Some(surface_stuff) = surface_stuff {
+        if indices.present_family.is_none() {
+            return Ok(Some("no queue can present to the surface".to_owned()));
+        }
 
-    let is_device_extension_supported = 
-        check_device_extension_support(instance, physical_device, required_device_extensions);
+        let swapchain_support = query_swapchain_support(physical_device, surface_stuff)?;
+        if swapchain_support.formats.is_empty() || swapchain_support.present_modes.is_empty() {
+            return Ok(Some("no surface formats or present modes".to_owned()));
+        }
+    }
 
-    let is_swapchain_supported = if is_device_extension_supported {
-        let swapchain_support = query_swapchain_support(physical_device, surface_stuff);
-        !swapchain_support.formats.is_empty() && !swapchain_support.present_modes.is_empty()
-    } else {
-        false
-    };
+    Ok(None)
+}
 
-    let is_support_sampler_anisotropy = device_features.sampler_anisotropy == 1;
+/// The features `create_logical_device` enables: sampler anisotropy, which
+/// every demo needs, plus whichever of the optional features `PipelineBuilder`
+/// checks for the device supports.
+pub fn device_features(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+) -> vk::PhysicalDeviceFeatures {
+    let supported = unsafe { instance.get_physical_device_features(physical_device) };
 
-    return is_queue_family_supported
-        && is_device_extension_supported
-        && is_swapchain_supported
-        && is_support_sampler_anisotropy;
-    
+    vk::PhysicalDeviceFeatures {
+        sampler_anisotropy: vk::TRUE,
+        geometry_shader: supported.geometry_shader,
+        tessellation_shader: supported.tessellation_shader,
+        fill_mode_non_solid: supported.fill_mode_non_solid,
+        wide_lines: supported.wide_lines,
+        depth_clamp: supported.depth_clamp,
+        depth_bias_clamp: supported.depth_bias_clamp,
+        independent_blend: supported.independent_blend,
+        dual_src_blend: supported.dual_src_blend,
+        logic_op: supported.logic_op,
+        ..Default::default()
+    }
 }
 
 pub fn create_logical_device(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-    validation: &debug::ValidationInfo,
-    device_extensions: &DeviceExtension,
-    surface_stuff: &SurfaceStuff,
-) -> (ash::Device, QueueFamilyIndices) {
+    enabled: &EnabledExtensions,
+    surface_stuff: Option<&SurfaceStuff>,
+) -> Result<(ash::Device, QueueFamilyIndices), VkPlaygroundError> {
     let indices = find_queue_family(instance, physical_device, surface_stuff);
 
     use std::collections::HashSet;
     let mut unique_queue_families = HashSet::new();
     unique_queue_families.insert(indices.graphics_family.unwrap());
-    unique_queue_families.insert(indices.present_family.unwrap());
+    if let Some(present_family) = indices.present_family {
+        unique_queue_families.insert(present_family);
+    }
+    if let Some(compute_family) = indices.compute_family {
+        unique_queue_families.insert(compute_family);
+    }
+    if let Some(transfer_family) = indices.transfer_family {
+        unique_queue_families.insert(transfer_family);
+    }
 
     let queue_priorities = [1.0_f32];
     let mut queue_create_infos = vec![];
@@ -104,22 +179,18 @@ pub fn create_logical_device(
         queue_create_infos.push(queue_create_info);
     }
 
-    let physical_device_features = vk::PhysicalDeviceFeatures {
-        sampler_anisotropy: vk::TRUE,
-        ..Default::default()
-    };
+    let physical_device_features = device_features(instance, physical_device);
 
-    let required_validation_layer_raw_names: Vec<CString> = validation
-        .required_validation_layers
+    let layer_raw_names = extensions::to_raw_names(&enabled.layers);
+    let enable_layer_names: Vec<*const c_char> = layer_raw_names
         .iter()
-        .map(|layer_name| CString::new(*layer_name).unwrap())
+        .map(|layer_name| layer_name.as_ptr())
         .collect();
-    let enable_layer_names: Vec<*const c_char> = required_validation_layer_raw_names
+    let extension_raw_names = extensions::to_raw_names(&enabled.device_extensions);
+    let enable_extension_names: Vec<*const c_char> = extension_raw_names
         .iter()
-        .map(|layer_name| layer_name.as_ptr())
+        .map(|extension_name| extension_name.as_ptr())
         .collect();
-    
-    let enable_extension_names = device_extensions.get_extensions_raw_names();
 
     let device_create_info = vk::DeviceCreateInfo {
         s_type: vk::StructureType::DEVICE_CREATE_INFO,
@@ -127,16 +198,8 @@ pub fn create_logical_device(
         flags: vk::DeviceCreateFlags::empty(),
         queue_create_info_count: queue_create_infos.len() as u32,
         p_queue_create_infos: queue_create_infos.as_ptr(),
-        enabled_layer_count: if validation.is_enable {
-            enable_layer_names.len()
-        } else {
-            0
-        } as u32,
-        pp_enabled_layer_names: if validation.is_enable {
-            enable_layer_names.as_ptr()
-        } else {
-            ptr::null()
-        },
+        enabled_layer_count: enable_layer_names.len() as u32,
+        pp_enabled_layer_names: enable_layer_names.as_ptr(),
         enabled_extension_count: enable_extension_names.len() as u32,
         pp_enabled_extension_names: enable_extension_names.as_ptr(),
         p_enabled_features: &physical_device_features,
@@ -145,17 +208,17 @@ pub fn create_logical_device(
     let device: ash::Device = unsafe {
         instance
             .create_device(physical_device, &device_create_info, None)
-            .expect("Failed to create logical device!")
+            .vk_context("vkCreateDevice", "Failed to create logical device!")?
     };
 
-    (device, indices)
+    Ok((device, indices))
 }
 
 
 pub fn find_queue_family(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-    surface_stuff: &SurfaceStuff
+    surface_stuff: Option<&SurfaceStuff>
 ) -> QueueFamilyIndices {
     let queue_families = 
         unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
@@ -164,64 +227,66 @@ pub fn find_queue_family(
 
     let mut index = 0;
     for queue_family in queue_families.iter() {
+        // every device with graphics has a family that also does compute,
+        // which lets compute dispatches go in the frame's command buffer
         if queue_family.queue_count > 0
-            && queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+            && queue_family
+                .queue_flags
+                .contains(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
             {
                 queue_family_indices.graphics_family = Some(index);
             }
         
-        let is_present_support = unsafe {
-            surface_stuff
-                .surface_loader
-                .get_physical_device_surface_support(
-                    physical_device,
-                    index as u32,
-                    surface_stuff.surface,
-                )
-        };
-
-        if queue_family.queue_count > 0 && is_present_support {
-            queue_family_indices.present_family = Some(index);
-        }
+        if let Some(surface_stuff) = surface_stuff {
+            let is_present_support = unsafe {
+                surface_stuff
+                    .surface_loader
+                    .get_physical_device_surface_support(
+                        physical_device,
+                        index as u32,
+                        surface_stuff.surface,
+                    )
+            };
+
+            if queue_family.queue_count > 0 && is_present_support {
+                queue_family_indices.present_family = Some(index);
+            }
 
-        if queue_family_indices.is_complete() {
+            if queue_family_indices.is_complete() {
+                break;
+            }
+        } else if queue_family_indices.is_headless_complete() {
             break;
         }
 
         index += 1;
     }
 
+    // a compute-only family can run alongside graphics, otherwise compute
+    // shares the graphics family
+    queue_family_indices.compute_family = queue_families
+        .iter()
+        .position(|queue_family| {
+            queue_family.queue_count > 0
+                && queue_family.queue_flags.contains(vk::QueueFlags::COMPUTE)
+                && !queue_family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+        })
+        .map(|index| index as u32)
+        .or(queue_family_indices.graphics_family);
+
+    // a transfer-only family is usually a DMA engine that copies alongside
+    // rendering, otherwise uploads go through the graphics family
+    queue_family_indices.transfer_family = queue_families
+        .iter()
+        .position(|queue_family| {
+            queue_family.queue_count > 0
+                && queue_family.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                && !queue_family
+                    .queue_flags
+                    .intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+        })
+        .map(|index| index as u32)
+        .or(queue_family_indices.graphics_family);
+
     queue_family_indices
 }
-
-pub fn check_device_extension_support(
-    instance: &ash::Instance,
-    physical_device: vk::PhysicalDevice,
-    device_extensions: &DeviceExtension,
-) -> bool {
-    let available_extensions = unsafe {
-        instance
-            .enumerate_device_extension_properties(physical_device)
-            .expect("Failed to get device extension properties")
-    };
-
-    let mut available_extension_names = vec![];
-
-    for extension in available_extensions.iter() {
-        let extension_name = tools::vk_to_string(&extension.extension_name);
-
-        available_extension_names.push(extension_name);
-    }
-
-    use std::collections::HashSet;
-    let mut required_extensions = HashSet::new();
-    for extension in device_extensions.names.iter() {
-        required_extensions.insert(extension.to_string());
-    }
-
-    for extension_name in available_extension_names.iter() {
-        required_extensions.remove(extension_name);
-    }
-
-    return required_extensions.is_empty();
-}
\ No newline at end of file
@@ -11,13 +11,14 @@ use std::ptr;
 
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::VkPlaygroundError;
 use crate::utility::platforms;
 use crate::utility::structures::*;
 
 pub fn find_depth_format(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
-) -> vk::Format {
+) -> Result<vk::Format, VkPlaygroundError> {
     find_supported_format(
         instance,
         physical_device,
@@ -38,21 +39,27 @@ pub fn find_supported_format(
     candidate_formats: &[vk::Format],
     tiling: vk::ImageTiling,
     features: vk::FormatFeatureFlags,
-) -> vk::Format {
+) -> Result<vk::Format, VkPlaygroundError> {
     for &format in candidate_formats.iter() {
         let format_properties =
             unsafe { instance.get_physical_device_format_properties(physical_device, format) };
         if tiling == vk::ImageTiling::LINEAR
             && format_properties.linear_tiling_features.contains(features)
         {
-            return format.clone();
+            return Ok(format.clone());
         } else if tiling == vk::ImageTiling::OPTIMAL
             && format_properties.optimal_tiling_features.contains(features)
         {
-            return format.clone();
+            return Ok(format.clone());
         }
     }
 
-    panic!("Failed to find supported format!")
+    Err(VkPlaygroundError::unsupported(
+        "find_supported_format",
+        format!(
+            "None of {:?} support {:?} with {:?} tiling",
+            candidate_formats, features, tiling
+        ),
+    ))
 
 }
\ No newline at end of file
@@ -9,8 +9,10 @@ use std::os::raw::c_void;
 use std::path::Path;
 use std::ptr;
 
+use crate::utility::allocator::{Allocation, Allocator};
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
 use crate::utility::platforms;
 use crate::utility::structures::*;
 
@@ -33,8 +35,8 @@ pub fn create_image(
     tiling: vk::ImageTiling,
     usage: vk::ImageUsageFlags,
     required_memory_properties: vk::MemoryPropertyFlags,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
-) -> (vk::Image, vk::DeviceMemory) {
+    allocator: &Allocator,
+) -> Result<(vk::Image, Allocation), VkPlaygroundError> {
     let image_create_info = vk::ImageCreateInfo {
         s_type: vk::StructureType::IMAGE_CREATE_INFO,
         p_next: ptr::null(),
@@ -60,34 +62,17 @@ pub fn create_image(
     let texture_image = unsafe {
         device
             .create_image(&image_create_info, None)
-            .expect("Failed to create Texture Image!")
+            .vk_context("vkCreateImage", "Failed to create Texture Image!")?
     };
 
-    let image_memory_requirement = unsafe { device.get_image_memory_requirements(texture_image) };
-    let memory_allocate_info = vk::MemoryAllocateInfo {
-        s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
-        p_next: ptr::null(),
-        allocation_size: image_memory_requirement.size,
-        memory_type_index: find_memory_type(
-            image_memory_requirement.memory_type_bits,
-            required_memory_properties,
-            device_memory_properties,
-        ),
-    };
-
-    let texture_image_memory = unsafe {
-        device
-            .allocate_memory(&memory_allocate_info, None)
-            .expect("Failed to allocate Texture Image memory!")
-    };
-
-    unsafe {
-        device
-            .bind_image_memory(texture_image, texture_image_memory, 0)
-            .expect("Failed to bind image memory!");
-    }
+    let texture_image_memory = allocator.allocate_image_memory(
+        device,
+        texture_image,
+        tiling,
+        required_memory_properties,
+    )?;
 
-    (texture_image, texture_image_memory)
+    Ok((texture_image, texture_image_memory))
 }
 
 pub fn transition_image_layout(
@@ -99,8 +84,8 @@ pub fn transition_image_layout(
     old_layout: vk::ImageLayout,
     new_layout: vk::ImageLayout,
     mip_levels: u32,
-) {
-    let command_buffer = begin_single_time_command(device, command_pool);
+) -> Result<(), VkPlaygroundError> {
+    let command_buffer = begin_single_time_command(device, command_pool)?;
 
     let src_access_mask;
     let dst_access_mask;
@@ -130,7 +115,10 @@ pub fn transition_image_layout(
         source_stage = vk::PipelineStageFlags::TOP_OF_PIPE;
         destination_stage = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
     } else {
-        panic!("Unsupported layout transition!")
+        return Err(VkPlaygroundError::unsupported(
+            "transition_image_layout",
+            format!("Unsupported layout transition {:?} -> {:?}!", old_layout, new_layout),
+        ));
     }
 
     let image_barriers = [vk::ImageMemoryBarrier {
@@ -164,15 +152,15 @@ pub fn transition_image_layout(
         );
     }
 
-    end_single_time_command(device, command_pool, submit_queue, command_buffer);
+    end_single_time_command(device, command_pool, submit_queue, command_buffer)
 }
 
 pub fn create_image_views(
     device: &ash::Device,
     surface_format: vk::Format,
     images: &Vec<vk::Image>,
-) -> Vec<vk::ImageView> {
-    let swapchain_imageviews: Vec<vk::ImageView> = images
+) -> Result<Vec<vk::ImageView>, VkPlaygroundError> {
+    let swapchain_imageviews = images
         .iter()
         .map(|&image| {
             create_image_view(
@@ -183,9 +171,9 @@ pub fn create_image_views(
                 1,
             )
         })
-        .collect();
+        .collect::<Result<Vec<vk::ImageView>, VkPlaygroundError>>()?;
 
-    swapchain_imageviews
+    Ok(swapchain_imageviews)
 }
 
 pub fn create_image_view(
@@ -194,7 +182,7 @@ pub fn create_image_view(
     format: vk::Format,
     aspect_flags: vk::ImageAspectFlags,
     mip_levels: u32,
-) -> vk::ImageView {
+) -> Result<vk::ImageView, VkPlaygroundError> {
     let imageview_create_info = vk::ImageViewCreateInfo {
         s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
         p_next: ptr::null(),
@@ -220,7 +208,7 @@ pub fn create_image_view(
     unsafe {
         device
             .create_image_view(&imageview_create_info, None)
-            .expect("Failed to create Image View!")
+            .vk_context("vkCreateImageView", "Failed to create Image View!")
     }
 }
 
@@ -228,7 +216,7 @@ pub fn create_texture_image_view(
     device: &ash::Device,
     texture_image: vk::Image,
     mip_levels: u32,
-) -> vk::ImageView {
+) -> Result<vk::ImageView, VkPlaygroundError> {
     create_image_view(
         device,
         texture_image,
@@ -238,7 +226,7 @@ pub fn create_texture_image_view(
     )
 }
 
-pub fn create_texture_sampler(device: &ash::Device) -> vk::Sampler {
+pub fn create_texture_sampler(device: &ash::Device) -> Result<vk::Sampler, VkPlaygroundError> {
     let sampler_create_info = vk::SamplerCreateInfo {
         s_type: vk::StructureType::SAMPLER_CREATE_INFO,
         p_next: ptr::null(),
@@ -263,18 +251,18 @@ pub fn create_texture_sampler(device: &ash::Device) -> vk::Sampler {
     unsafe {
         device
             .create_sampler(&sampler_create_info, None)
-            .expect("Failed to create Sampler!")
+            .vk_context("vkCreateSampler", "Failed to create Sampler!")
     }
 }
 
-pub fn create_texture_image(
-    device: &ash::Device,
-    command_pool: vk::CommandPool,
-    submit_queue: vk::Queue,
-    device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
-    image_path: &Path,
-) -> (vk::Image, vk::DeviceMemory) {
-    let mut image_object = image::open(image_path).unwrap(); // this function is slow in debug mode.
+/// The pixels of the image at `image_path` as 8 bit RGBA, flipped so the
+/// first row is the bottom one, with the width and height.
+pub fn load_texture_pixels(image_path: &Path) -> Result<(u32, u32, Vec<u8>), VkPlaygroundError> {
+    // this function is slow in debug mode.
+    let mut image_object = image::open(image_path).map_err(|source| VkPlaygroundError::Image {
+        path: image_path.to_path_buf(),
+        source,
+    })?;
     image_object = image_object.flipv();
     let (image_width, image_height) = (image_object.width(), image_object.height());
     let image_data = match &image_object {
@@ -289,31 +277,34 @@ pub fn create_texture_image(
         (::std::mem::size_of::<u8>() as u32 * image_width * image_height * 4) as vk::DeviceSize;
 
     if image_size <= 0 {
-        panic!("Failed to load texture image!")
+        return Err(VkPlaygroundError::unsupported(
+            "create_texture_image",
+            format!("{} is empty", image_path.display()),
+        ));
     }
 
+    Ok((image_width, image_height, image_data))
+}
+
+pub fn create_texture_image(
+    device: &ash::Device,
+    command_pool: vk::CommandPool,
+    submit_queue: vk::Queue,
+    allocator: &Allocator,
+    image_path: &Path,
+) -> Result<(vk::Image, Allocation), VkPlaygroundError> {
+    let (image_width, image_height, image_data) = load_texture_pixels(image_path)?;
+    let image_size = image_data.len() as vk::DeviceSize;
+
     let (staging_buffer, staging_buffer_memory) = create_buffer(
         device,
         image_size,
         vk::BufferUsageFlags::TRANSFER_SRC,
         vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        device_memory_properties,
-    );
+        allocator,
+    )?;
 
-    unsafe {
-        let data_ptr = device
-            .map_memory(
-                staging_buffer_memory,
-                0,
-                image_size,
-                vk::MemoryMapFlags::empty(),
-            )
-            .expect("Failed to Map Memory") as *mut u8;
-
-        data_ptr.copy_from_nonoverlapping(image_data.as_ptr(), image_data.len());
-
-        device.unmap_memory(staging_buffer_memory);
-    }
+    staging_buffer_memory.write(&image_data);
 
     let (texture_image, texture_image_memory) = create_image(
         device,
@@ -325,8 +316,8 @@ pub fn create_texture_image(
         vk::ImageTiling::OPTIMAL,
         vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        device_memory_properties,
-    );
+        allocator,
+    )?;
 
     transition_image_layout(
         device,
@@ -337,7 +328,7 @@ pub fn create_texture_image(
         vk::ImageLayout::UNDEFINED,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
         1,
-    );
+    )?;
 
     copy_buffer_to_image(
         device,
@@ -347,7 +338,7 @@ pub fn create_texture_image(
         texture_image,
         image_width,
         image_height,
-    );
+    )?;
 
     transition_image_layout(
         device,
@@ -358,12 +349,11 @@ pub fn create_texture_image(
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
         vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
         1,
-    );
+    )?;
 
     unsafe {
         device.destroy_buffer(staging_buffer, None);
-        device.free_memory(staging_buffer_memory, None);
     }
 
-    (texture_image, texture_image_memory)
+    Ok((texture_image, texture_image_memory))
 }
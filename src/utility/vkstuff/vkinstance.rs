@@ -11,20 +11,22 @@ use std::ptr;
 
 use crate::utility::constants::*;
 use crate::utility::debug;
-use crate::utility::platforms;
-use crate::utility::structures::*;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::extensions::{self, EnabledExtensions, ExtensionConfig};
 
+/// Loads the Vulkan library, the first step before creating an instance.
+pub fn load_entry() -> Result<ash::Entry, VkPlaygroundError> {
+    ash::Entry::new().map_err(|source| VkPlaygroundError::Loader { source })
+}
+
+// Enables whatever `config` selects out of what the loader supports, and
+// chains a debug messenger for instance creation when debug utils is on.
 pub fn create_instance(
     entry: &ash::Entry,
     window_title: &str,
-    is_enable_debug: bool,
-    required_validation_layers: &Vec<&str>,
-) -> ash::Instance {
-    if is_enable_debug
-        && debug::check_validation_layer_support(entry, required_validation_layers) == false
-    {
-        panic!("Validation layers requested, but not available!");
-    }
+    config: &ExtensionConfig,
+) -> Result<(ash::Instance, EnabledExtensions), VkPlaygroundError> {
+    let enabled = config.select_instance(entry)?;
 
     let app_name = CString::new(window_title).unwrap();
     let engine_name = CString::new("Vulkan Engine").unwrap();
@@ -41,22 +43,21 @@ pub fn create_instance(
     // this creates info used to debug issues in vk::createInstance
     let debug_utils_create_info = debug::populate_debug_messenger_create_info();
 
-    // VK_EXT debug report has been requested here
-    let extension_names = platforms::required_extension_names();
-
-    let required_validation_layer_raw_names: Vec<CString> = required_validation_layers
+    let extension_raw_names = extensions::to_raw_names(&enabled.instance_extensions);
+    let extension_names: Vec<*const c_char> = extension_raw_names
         .iter()
-        .map(|layer_name| CString::new(*layer_name).unwrap())
+        .map(|extension_name| extension_name.as_ptr())
         .collect();
-    let layer_names: Vec<*const i8> = required_validation_layer_raw_names
+    let layer_raw_names = extensions::to_raw_names(&enabled.layers);
+    let layer_names: Vec<*const c_char> = layer_raw_names
         .iter()
         .map(|layer_name| layer_name.as_ptr())
-        .collect(); 
+        .collect();
 
     // fill out instance info
     let create_info = vk::InstanceCreateInfo {
         s_type: vk::StructureType::INSTANCE_CREATE_INFO,
-        p_next: if VALIDATION.is_enable {
+        p_next: if enabled.has_debug_utils() {
             &debug_utils_create_info as *const vk::DebugUtilsMessengerCreateInfoEXT
                     as *const c_void
         } else {
@@ -64,16 +65,8 @@ pub fn create_instance(
         },
         flags: vk::InstanceCreateFlags::empty(),
         p_application_info: &app_info,
-        pp_enabled_layer_names: if is_enable_debug {
-            layer_names.as_ptr()
-        } else {
-            ptr::null()
-        },
-        enabled_layer_count: if is_enable_debug {
-            layer_names.len()
-        } else {
-            0
-        } as u32,
+        pp_enabled_layer_names: layer_names.as_ptr(),
+        enabled_layer_count: layer_names.len() as u32,
         pp_enabled_extension_names: extension_names.as_ptr(),
         enabled_extension_count: extension_names.len() as u32,
     };
@@ -81,8 +74,8 @@ pub fn create_instance(
     let instance: ash::Instance = unsafe {
         entry
             .create_instance(&create_info, None)
-            .expect("Failed to create instance!")
+            .vk_context("vkCreateInstance", "Failed to create instance!")?
     };
 
-    instance
-}
\ No newline at end of file
+    Ok((instance, enabled))
+}
@@ -11,10 +11,14 @@ use std::ptr;
 
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
 use crate::utility::platforms;
 use crate::utility::structures::*;
 
-pub fn create_sync_objects(device: &ash::Device, max_frame_in_flight: usize) -> SyncObjects {
+pub fn create_sync_objects(
+    device: &ash::Device,
+    max_frame_in_flight: usize,
+) -> Result<SyncObjects, VkPlaygroundError> {
     let mut sync_objects = SyncObjects {
         image_available_semaphores: vec![],
         render_finished_semaphores: vec![],
@@ -37,13 +41,13 @@ pub fn create_sync_objects(device: &ash::Device, max_frame_in_flight: usize) ->
         unsafe {
             let image_available_semaphore = device
                 .create_semaphore(&semaphore_create_info, None)
-                .expect("Failed to create Semaphore Object!");
+                .vk_context("vkCreateSemaphore", "Failed to create Semaphore Object!")?;
             let render_finished_semaphore = device
                 .create_semaphore(&semaphore_create_info, None)
-                .expect("Failed to create Semaphore Object!");
+                .vk_context("vkCreateSemaphore", "Failed to create Semaphore Object!")?;
             let inflight_fence = device
                 .create_fence(&fence_create_info, None)
-                .expect("Failed to create Fence Object!");
+                .vk_context("vkCreateFence", "Failed to create Fence Object!")?;
 
             sync_objects
                 .image_available_semaphores
@@ -55,5 +59,5 @@ pub fn create_sync_objects(device: &ash::Device, max_frame_in_flight: usize) ->
         }
     }
 
-    sync_objects
+    Ok(sync_objects)
 }
\ No newline at end of file
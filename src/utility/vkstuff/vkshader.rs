@@ -11,15 +11,16 @@ use std::ptr;
 
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
+use crate::utility::shader_compiler;
 use crate::utility::platforms;
 use crate::utility::structures::*;
 
 pub fn create_shader_module(
     device: &ash::Device,
-    spirv_path: &str) -> vk::ShaderModule {
-    // read the spirv file
-    let spirv_u8 = std::fs::read(spirv_path)
-        .unwrap_or_else(|_| panic!("Failed to read spirv file at {}!"));
+    spirv_path: &str) -> Result<vk::ShaderModule, VkPlaygroundError> {
+    // read the spirv file, or compile it first if it's GLSL
+    let spirv_u8 = shader_compiler::load_spirv(Path::new(spirv_path))?;
     
     // create shader module
     let spirv_u32 = {
@@ -40,8 +41,8 @@ pub fn create_shader_module(
     let vk_shader_module = unsafe {
         device
             .create_shader_module(&create_info, None)
-            .expect("Failed to create shader module!")
+            .vk_context("vkCreateShaderModule", "Failed to create shader module!")?
     };
 
-    vk_shader_module
+    Ok(vk_shader_module)
 }
\ No newline at end of file
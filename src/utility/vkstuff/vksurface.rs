@@ -11,6 +11,7 @@ use std::ptr;
 
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
 use crate::utility::platforms;
 use crate::utility::structures::*;
 
@@ -20,18 +21,19 @@ pub fn create_surface(
     window: &winit::window::Window,
     screen_width: u32,
     screen_height: u32,
-)   -> SurfaceStuff {
+) -> Result<SurfaceStuff, VkPlaygroundError> {
 
     let surface = unsafe {
-        platforms::create_surface(entry, instance, window).expect("Failed to create surface!")
+        platforms::create_surface(entry, instance, window)
+            .vk_context("vkCreateSurfaceKHR", "Failed to create surface!")?
     };
 
     let surface_loader = ash::extensions::khr::Surface::new(entry, instance);
 
-    SurfaceStuff {
+    Ok(SurfaceStuff {
         surface_loader,
         surface,
         screen_width,
         screen_height,
-    }
+    })
 }
\ No newline at end of file
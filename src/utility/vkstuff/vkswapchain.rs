@@ -11,34 +11,45 @@ use std::ptr;
 
 use crate::utility::constants::*;
 use crate::utility::debug;
+use crate::utility::error::{VkPlaygroundError, VkResultExt};
 use crate::utility::platforms;
+use crate::utility::screenshot;
 use crate::utility::structures::*;
 
 
 pub fn query_swapchain_support(
     physical_device: vk::PhysicalDevice,
     surface_stuff: &SurfaceStuff
-) -> SwapChainSupportDetail {
+) -> Result<SwapChainSupportDetail, VkPlaygroundError> {
 
     unsafe {
         let capabilities = surface_stuff
             .surface_loader
             .get_physical_device_surface_capabilities(physical_device, surface_stuff.surface)
-            .expect("Failed to query for surface capabilities");
+            .vk_context(
+                "vkGetPhysicalDeviceSurfaceCapabilitiesKHR",
+                "Failed to query for surface capabilities",
+            )?;
         let formats = surface_stuff
             .surface_loader
             .get_physical_device_surface_formats(physical_device, surface_stuff.surface)
-            .expect("Failed to queryt for surface formats");
+            .vk_context(
+                "vkGetPhysicalDeviceSurfaceFormatsKHR",
+                "Failed to queryt for surface formats",
+            )?;
         let present_modes = surface_stuff
             .surface_loader
             .get_physical_device_surface_present_modes(physical_device, surface_stuff.surface)
-            .expect("Failed to query for surface present mode");
+            .vk_context(
+                "vkGetPhysicalDeviceSurfacePresentModesKHR",
+                "Failed to query for surface present mode",
+            )?;
 
-        SwapChainSupportDetail {
+        Ok(SwapChainSupportDetail {
             capabilities,
             formats,
             present_modes,
-        }
+        })
     }
 }
 
@@ -49,9 +60,9 @@ pub fn create_swapchain(
     window: &winit::window::Window,
     surface_stuff: &SurfaceStuff,
     queue_family: &QueueFamilyIndices,
-) -> SwapChainStuff {
+) -> Result<SwapChainStuff, VkPlaygroundError> {
 
-    let swapchain_support = query_swapchain_support(physical_device, surface_stuff);
+    let swapchain_support = query_swapchain_support(physical_device, surface_stuff)?;
 
     let surface_format = choose_swapchain_format(&swapchain_support.formats);
     let present_mode = choose_swapchain_present_mode(&swapchain_support.present_modes);
@@ -78,6 +89,7 @@ pub fn create_swapchain(
             (vk::SharingMode::EXCLUSIVE, 0, vec![])
         };
     
+    let image_usage = screenshot::swapchain_image_usage(&swapchain_support.capabilities);
     let swapchain_create_info = vk::SwapchainCreateInfoKHR {
         s_type: vk::StructureType::SWAPCHAIN_CREATE_INFO_KHR,
         p_next: ptr::null(),
@@ -87,7 +99,7 @@ pub fn create_swapchain(
         image_color_space: surface_format.color_space,
         image_format: surface_format.format,
         image_extent: extent,
-        image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        image_usage,
         image_sharing_mode,
         p_queue_family_indices: queue_family_indices.as_ptr(),
         queue_family_index_count,
@@ -103,22 +115,23 @@ pub fn create_swapchain(
     let swapchain = unsafe {
         swapchain_loader
             .create_swapchain(&swapchain_create_info, None)
-            .expect("Failed to create swapchain")
+            .vk_context("vkCreateSwapchainKHR", "Failed to create swapchain")?
     };
 
     let swapchain_images = unsafe {
         swapchain_loader
             .get_swapchain_images(swapchain)
-            .expect("Failed to get swapchain images")
+            .vk_context("vkGetSwapchainImagesKHR", "Failed to get swapchain images")?
     };
 
-    SwapChainStuff {
+    Ok(SwapChainStuff {
         swapchain_loader,
         swapchain,
         swapchain_format: surface_format.format,
         swapchain_extent: extent,
+        swapchain_usage: image_usage,
         swapchain_images
-    }
+    })
 }
 
 pub fn choose_swapchain_format(
@@ -1,6 +1,10 @@
 use winit::event::{Event, VirtualKeyCode, ElementState, KeyboardInput, WindowEvent};
 use winit::event_loop::{EventLoop, ControlFlow};
 
+use super::error::{self, VkPlaygroundError};
+use super::recording::{FrameRecorder, RecordingOptions};
+use super::screenshot::{self, CapturedFrame, SCREENSHOT_KEY};
+
 
 // constants
 const IS_PAINT_FPS_COUNTER: bool = true;
@@ -10,21 +14,32 @@ pub fn init_window(
     title: &str,
     width: u32,
     height: u32
-) -> winit::window::Window {
+) -> Result<winit::window::Window, VkPlaygroundError> {
     winit::window::WindowBuilder::new() 
     .with_title(title)
     .with_inner_size(winit::dpi::LogicalSize::new(width, height))
     .build(event_loop)
-    .expect("Failed to create window!")
+    .map_err(|source| VkPlaygroundError::Window { source })
 }
 
 pub trait VulkanApp {
-    fn draw_frame(&mut self, delta_time: f32);
-    fn recreate_swapchain(&mut self);
-    fn cleanup_swapchain(&self);
-    fn wait_device_idle(&self);
+    fn draw_frame(&mut self, delta_time: f32) -> Result<(), VkPlaygroundError>;
+    fn recreate_swapchain(&mut self) -> Result<(), VkPlaygroundError>;
+    fn cleanup_swapchain(&mut self);
+    fn wait_device_idle(&self) -> Result<(), VkPlaygroundError>;
     fn resize_framebuffer(&mut self);
     fn window_ref(&self) -> &winit::window::Window;
+    // copy the next presented frame back to the host, see utility::screenshot
+    fn request_capture(&mut self);
+    fn take_captured_frame(&mut self) -> Option<CapturedFrame>;
+}
+
+// a device that can't be waited on is lost, and all that's left to do with
+// the error is report it
+fn wait_device_idle_or_report<A: VulkanApp>(vulkan_app: &A) {
+    if let Err(err) = vulkan_app.wait_device_idle() {
+        eprintln!("{}", err);
+    }
 }
 
 pub struct ProgramProc {
@@ -43,6 +58,11 @@ impl ProgramProc {
     pub fn main_loop<A: 'static + VulkanApp>(self, mut vulkan_app: A) {
 
         let mut tick_counter = super::fps_limiter::FPSLimiter::new();
+        // with --record every frame is captured and the clock is fixed
+        let mut recorder = RecordingOptions::from_args()
+            .and_then(|options| options.map(FrameRecorder::new).transpose())
+            .unwrap_or_else(|err| error::exit_with(err));
+        let mut is_screenshot_requested = false;
 
         self.event_loop.run(move |event, _, control_flow| {
 
@@ -52,7 +72,7 @@ impl ProgramProc {
                     // handle closing
                     match event {
                         | WindowEvent::CloseRequested => {
-                            vulkan_app.wait_device_idle();
+                            wait_device_idle_or_report(&vulkan_app);
                             *control_flow = ControlFlow::Exit
                         },
                         // handle keyboard input
@@ -64,9 +84,13 @@ impl ProgramProc {
                                     // someone's pressed escape!
                                     match (virtual_keycode, state) {
                                         | (Some(VirtualKeyCode::Escape), ElementState::Pressed) => {
-                                            vulkan_app.wait_device_idle();
+                                            wait_device_idle_or_report(&vulkan_app);
                                             *control_flow = ControlFlow::Exit
                                         },
+                                        | (Some(SCREENSHOT_KEY), ElementState::Pressed) => {
+                                            is_screenshot_requested = true;
+                                            vulkan_app.request_capture();
+                                        },
                                         | _ => {},
                                     }
                                 },
@@ -74,7 +98,7 @@ impl ProgramProc {
                             // end of handling key cases
                         }, 
                          | WindowEvent::Resized(_new_size) => {
-                            vulkan_app.wait_device_idle();
+                            wait_device_idle_or_report(&vulkan_app);
                             vulkan_app.resize_framebuffer();
                          },
                         | _ => {},
@@ -84,8 +108,41 @@ impl ProgramProc {
                     vulkan_app.window_ref().request_redraw();
                 },
                 | Event::RedrawRequested(_window_id) => {
-                    let delta_time = tick_counter.delta_time();
-                    vulkan_app.draw_frame(delta_time);
+                    let delta_time = match &recorder {
+                        | Some(recorder) => {
+                            vulkan_app.request_capture();
+                            recorder.delta_time()
+                        },
+                        | None => tick_counter.delta_time(),
+                    };
+                    if let Err(err) = vulkan_app.draw_frame(delta_time) {
+                        eprintln!("Failed to draw a frame: {}", err);
+                        wait_device_idle_or_report(&vulkan_app);
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+
+                    // nothing is captured when the swapchain was out of date
+                    if let Some(frame) = vulkan_app.take_captured_frame() {
+                        if is_screenshot_requested {
+                            is_screenshot_requested = false;
+                            if let Err(err) = screenshot::save_screenshot(&frame) {
+                                eprintln!("Failed to save screenshot: {}", err);
+                            }
+                        }
+                        if let Some(recorder) = &mut recorder {
+                            if let Err(err) = recorder.write_frame(&frame) {
+                                eprintln!("Failed to record a frame: {}", err);
+                                wait_device_idle_or_report(&vulkan_app);
+                                *control_flow = ControlFlow::Exit;
+                                return;
+                            }
+                            if recorder.is_finished() {
+                                wait_device_idle_or_report(&vulkan_app);
+                                *control_flow = ControlFlow::Exit
+                            }
+                        }
+                    }
 
                     if IS_PAINT_FPS_COUNTER {
                         print!("FPS: {}\r", tick_counter.fps());
@@ -94,7 +151,7 @@ impl ProgramProc {
                     tick_counter.tick_frame();
                 },
                 | Event::LoopDestroyed => {
-                    vulkan_app.wait_device_idle();
+                    wait_device_idle_or_report(&vulkan_app);
                 },
                 _ => (),
             }
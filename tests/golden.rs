@@ -0,0 +1,165 @@
+// Golden image regression tests: each demo renders a fixed number of frames
+// headless with a fixed delta time, and the result is compared against a
+// reference PNG in tests/golden/.
+//
+// They need a Vulkan device, so they're ignored by default and run with
+// `cargo test --test golden -- --ignored`, where a missing device fails
+// them. Run with VK_PLAYGROUND_BLESS=1 to (re)write the references from the
+// current output.
+
+use vk_playground::utility::{golden, headless, pipeline_cache, shader_compiler};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const BLESS_ENV_VAR: &str = "VK_PLAYGROUND_BLESS";
+// per channel, enough to absorb rounding differences between drivers
+const CHANNEL_TOLERANCE: u8 = 2;
+const DELTA_TIME: f32 = 1.0 / 60.0;
+
+struct GoldenCase {
+    binary: &'static str,
+    binary_path: &'static str,
+    frame_count: u32,
+}
+
+fn reference_path(case: &GoldenCase) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.png", case.binary))
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+fn render(case: &GoldenCase, output_path: &Path) {
+    let status = Command::new(case.binary_path)
+        .args(&["--headless", "--frames"])
+        .arg(case.frame_count.to_string())
+        .arg("--delta-time")
+        .arg(DELTA_TIME.to_string())
+        .arg("--output")
+        .arg(output_path)
+        // the demos load shaders and textures relative to the crate root
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        // and would otherwise keep their caches there too
+        .env(pipeline_cache::PIPELINE_CACHE_ENV_VAR, output_dir().join("pipeline-cache"))
+        .env(shader_compiler::SHADER_CACHE_ENV_VAR, output_dir().join("shader-cache"))
+        .status()
+        .unwrap_or_else(|err| panic!("Failed to run {}: {}", case.binary, err));
+
+    assert!(status.success(), "{} exited with {}", case.binary, status);
+}
+
+fn check_golden(case: GoldenCase) {
+    assert!(
+        headless::is_vulkan_available(),
+        "No Vulkan device available to render {}",
+        case.binary
+    );
+
+    let output_dir = output_dir();
+    fs::create_dir_all(&output_dir).expect("Failed to create output directory");
+    let output_path = output_dir.join(format!("{}.png", case.binary));
+    render(&case, &output_path);
+
+    let reference_path = reference_path(&case);
+    if env::var_os(BLESS_ENV_VAR).is_some() {
+        fs::create_dir_all(reference_path.parent().unwrap())
+            .expect("Failed to create reference directory");
+        fs::copy(&output_path, &reference_path).expect("Failed to write reference image");
+        eprintln!("Blessed {}", reference_path.display());
+        return;
+    }
+
+    if !reference_path.exists() {
+        panic!(
+            "Missing reference image {}, run with {}=1 to create it",
+            reference_path.display(),
+            BLESS_ENV_VAR
+        );
+    }
+
+    let (expected_extent, expected) =
+        golden::load_png(&reference_path).expect("Failed to load reference image");
+    let (actual_extent, actual) =
+        golden::load_png(&output_path).expect("Failed to load rendered image");
+    assert_eq!(
+        (expected_extent.width, expected_extent.height),
+        (actual_extent.width, actual_extent.height),
+        "{} rendered at a different size than its reference",
+        case.binary
+    );
+
+    let comparison = golden::compare_images(&expected, &actual, CHANNEL_TOLERANCE);
+    if !comparison.is_match() {
+        let diff_path = output_dir.join(format!("{}.diff.png", case.binary));
+        headless::save_png(&diff_path, actual_extent, &comparison.diff_pixels)
+            .unwrap_or_else(|err| panic!("{}", err));
+
+        panic!(
+            "{} differs from {}: {} pixels off by up to {}, see {} and {}",
+            case.binary,
+            reference_path.display(),
+            comparison.mismatched_pixels,
+            comparison.max_channel_difference,
+            output_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+#[ignore = "needs a Vulkan device"]
+fn triangle() {
+    check_golden(GoldenCase {
+        binary: "triangle",
+        binary_path: env!("CARGO_BIN_EXE_triangle"),
+        frame_count: 1,
+    });
+}
+
+#[test]
+#[ignore = "needs a Vulkan device"]
+fn texture_map() {
+    check_golden(GoldenCase {
+        binary: "texture_map",
+        binary_path: env!("CARGO_BIN_EXE_texture_map"),
+        frame_count: 1,
+    });
+}
+
+#[test]
+#[ignore = "needs a Vulkan device"]
+fn depth() {
+    check_golden(GoldenCase {
+        binary: "depth",
+        binary_path: env!("CARGO_BIN_EXE_depth"),
+        frame_count: 1,
+    });
+}
+
+#[test]
+#[ignore = "needs a Vulkan device"]
+fn cube() {
+    check_golden(GoldenCase {
+        binary: "cube",
+        binary_path: env!("CARGO_BIN_EXE_cube"),
+        frame_count: 1,
+    });
+}
+
+// half a second of spinning, so the model matrix is actually exercised
+#[test]
+#[ignore = "needs a Vulkan device"]
+fn spinny_cube() {
+    check_golden(GoldenCase {
+        binary: "spinny_cube",
+        binary_path: env!("CARGO_BIN_EXE_spinny_cube"),
+        frame_count: 30,
+    });
+}